@@ -0,0 +1,167 @@
+//! What the app is busy with, for the status bar at the bottom of the window. Kept outside of the
+//! GUI state so it can be updated from the threads doing the work, like the queue's downloads.
+//!
+//! More than one thing can be going on at once (like the queue downloading a few albums), in which
+//! case the one started last is shown.
+
+use once_cell::sync::Lazy;
+use std::{
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        RwLock,
+    },
+};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Activity {
+    #[default]
+    Idle,
+    /// With the site's name, like "Discogs"
+    Scraping(&'static str),
+    /// Looking for another upload of a track
+    Searching,
+    Downloading {
+        /// Like "Artist - Album"
+        album: String,
+        /// Tracks that have been saved or have failed
+        done: usize,
+        total: usize,
+    },
+    /// A download's waiting for space to be freed up before starting its next track
+    DiskFull {
+        /// The dir that's short on space
+        path: PathBuf,
+        /// Bytes left in it
+        available: u64,
+    },
+}
+
+impl Activity {
+    #[must_use]
+    pub fn is_idle(&self) -> bool {
+        *self == Self::Idle
+    }
+
+    #[must_use]
+    pub fn is_disk_full(&self) -> bool {
+        matches!(self, Self::DiskFull { .. })
+    }
+
+    /// Counts another track as done, if it's downloading
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::activity::Activity;
+    ///
+    /// let mut activity = Activity::Downloading {
+    ///     album: String::from("Artist - Album"),
+    ///     done: 4,
+    ///     total: 12,
+    /// };
+    /// activity.finish_track();
+    /// assert_eq!(activity.to_string(), "Downloading Artist - Album: 5/12 tracks done (41%)");
+    /// ```
+    pub fn finish_track(&mut self) {
+        if let Self::Downloading { done, total, .. } = self {
+            *done = (*done + 1).min(*total);
+        }
+    }
+}
+
+impl Display for Activity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Idle => f.write_str("Idle"),
+            Self::Scraping(site) => write!(f, "Scraping {site}…"),
+            Self::Searching => f.write_str("Searching Youtube…"),
+            Self::Downloading { album, done, total } => write!(
+                f,
+                "Downloading {album}: {done}/{total} tracks done ({}%)",
+                (done * 100).checked_div(*total).unwrap_or(0)
+            ),
+            Self::DiskFull { path, available } => write!(
+                f,
+                r#"Paused: the disk is nearly full ({} MB left in "{}"), free up some space to carry on"#,
+                available / 1_000_000,
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Everything going on, oldest first, by the ID of its [`ActivityGuard`]
+static RUNNING: Lazy<RwLock<Vec<(u64, Activity)>>> = Lazy::new(RwLock::default);
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+
+/// The activity started last that's still going, or [`Activity::Idle`]
+#[must_use]
+pub fn current() -> Activity {
+    RUNNING
+        .read()
+        .ok()
+        .and_then(|running| running.last().map(|(_, activity)| activity.clone()))
+        .unwrap_or_default()
+}
+
+/// Shows `activity` until the guard is dropped, when it goes back to whatever else is still going
+/// on
+pub fn start(activity: Activity) -> ActivityGuard {
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    if let Ok(mut running) = RUNNING.write() {
+        running.push((id, activity));
+    }
+    ActivityGuard(id)
+}
+
+/// From [`start`]
+#[derive(Debug)]
+#[must_use = "the activity is cleared as soon as it's dropped"]
+pub struct ActivityGuard(u64);
+
+impl ActivityGuard {
+    /// Counts another track of the download as done
+    pub fn finish_track(&self) {
+        if let Ok(mut running) = RUNNING.write() {
+            if let Some((_, activity)) = running.iter_mut().find(|(id, _)| *id == self.0) {
+                activity.finish_track();
+            }
+        }
+    }
+}
+
+impl Drop for ActivityGuard {
+    fn drop(&mut self) {
+        if let Ok(mut running) = RUNNING.write() {
+            running.retain(|(id, _)| *id != self.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn overlapping() {
+        let downloading = |album: &str| Activity::Downloading {
+            album: album.to_string(),
+            done: 0,
+            total: 2,
+        };
+        let first = start(downloading("First"));
+        let second = start(downloading("Second"));
+        first.finish_track();
+        assert_eq!(current(), downloading("Second"));
+
+        // the first one's still going once the second finishes
+        drop(second);
+        assert_eq!(
+            current().to_string(),
+            "Downloading First: 1/2 tracks done (50%)"
+        );
+        drop(first);
+        assert!(current().is_idle());
+    }
+}
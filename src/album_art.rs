@@ -0,0 +1,820 @@
+use crate::{
+    ids::VideoId,
+    network,
+    scraping::{scrape_discogs, search_itunes, search_release_groups},
+    utils::is_test_server,
+};
+use bytes::Bytes;
+use reqwest::{
+    blocking::Response,
+    header::{HeaderValue, CONTENT_LENGTH, CONTENT_TYPE},
+    StatusCode,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::Display,
+    fs,
+    io::{self, Read},
+    panic,
+    path::Path,
+    process::Command,
+    thread,
+};
+use tempdir::TempDir;
+use thiserror::Error;
+use url::Url;
+
+#[derive(Debug, Error)]
+pub enum AlbumArtError {
+    #[error("invalid URL: {0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("unsupported URL scheme \"{0}\"")]
+    UnsupportedScheme(String),
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("server responded with {0}")]
+    BadStatus(StatusCode),
+    #[error("not an image (content type {})", .0.as_deref().unwrap_or("unknown"))]
+    NotAnImage(Option<String>),
+    #[error("couldn't get the cover from the Discogs page: {0}")]
+    DiscogsError(String),
+    #[error("image is only {0} bytes")]
+    TooSmall(usize),
+    #[error("downloaded file isn't a JPEG, PNG, GIF, WebP or BMP image")]
+    UnrecognizedImage,
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("there isn't an upscaling command or API set")]
+    UpscaleNotConfigured,
+    #[error("upscaling command failed: {0}")]
+    UpscaleFailed(String),
+}
+
+/// Anything smaller than this is a placeholder or an empty response rather than an actual cover
+pub const MIN_IMAGE_SIZE: usize = 128;
+
+/// Set under `[art_upscale]` in the config, for upscaling small album covers with an external
+/// tool
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UpscaleSettings {
+    /// Run with `{input}` and `{output}` replaced by the paths of the cover and where to write
+    /// the upscaled one, like `realesrgan-ncnn-vulkan -i {input} -o {output} -s 4`
+    pub command: Option<String>,
+    /// Sent the cover in a POST request, and responds with the upscaled one. Only used if there
+    /// isn't a `command`.
+    pub api_url: Option<String>,
+    /// Covers with a side shorter than this many pixels are upscaled
+    pub min_size: u32,
+}
+
+impl Default for UpscaleSettings {
+    fn default() -> Self {
+        Self {
+            command: None,
+            api_url: None,
+            min_size: 500,
+        }
+    }
+}
+
+impl UpscaleSettings {
+    /// Whether there's a command or API to upscale with
+    #[must_use]
+    pub fn is_configured(&self) -> bool {
+        let set = |s: &Option<String>| s.as_deref().map_or(false, |s| !s.trim().is_empty());
+        set(&self.command) || set(&self.api_url)
+    }
+
+    /// Whether `img` should be upscaled: there's something to do it with and it's smaller than
+    /// [`UpscaleSettings::min_size`]. Images whose size can't be read are left alone.
+    #[must_use]
+    pub fn wants(&self, img: &[u8]) -> bool {
+        self.is_configured()
+            && image_dimensions(img).map_or(false, |(w, h)| w.min(h) < self.min_size)
+    }
+}
+
+/// What an image is, for showing it before and after upscaling
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageInfo {
+    pub content_type: &'static str,
+    pub bytes: usize,
+    /// Width and height, if the header could be read
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl ImageInfo {
+    /// # Errors
+    /// If [`verify_image`] fails
+    pub fn of(img: &[u8]) -> Result<Self, AlbumArtError> {
+        Ok(Self {
+            content_type: verify_image(img)?,
+            bytes: img.len(),
+            dimensions: image_dimensions(img),
+        })
+    }
+}
+
+impl Display for ImageInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.content_type)?;
+        if let Some((width, height)) = self.dimensions {
+            write!(f, ", {width}×{height}")?;
+        }
+        write!(f, ", {} KB", self.bytes.div_ceil(1024))
+    }
+}
+
+/// The cover before and after upscaling, for comparing them before downloading
+#[derive(Debug, Clone)]
+pub struct UpscalePreview {
+    /// The cover's URL, so it isn't used if the URL's changed since
+    pub url: String,
+    pub before: ImageInfo,
+    /// The upscaled cover and its MIME type, or why it couldn't be upscaled
+    pub after: Result<(Bytes, &'static str), String>,
+}
+
+impl UpscalePreview {
+    /// Downloads the cover and upscales it with `settings`. The upscaling failing isn't an
+    /// error, so it can be shown next to the original.
+    ///
+    /// # Errors
+    /// If the cover can't be downloaded or isn't an image
+    pub fn new(url: &str, settings: &UpscaleSettings) -> Result<Self, AlbumArtError> {
+        let (img, _) = download_image(url)?;
+        Ok(Self {
+            url: url.to_string(),
+            before: ImageInfo::of(&img)?,
+            after: upscale(&img, settings).map_err(|err| err.to_string()),
+        })
+    }
+
+    /// What the upscaled cover is, if it worked
+    #[must_use]
+    pub fn after_info(&self) -> Option<ImageInfo> {
+        let (img, _) = self.after.as_ref().ok()?;
+        ImageInfo::of(img).ok()
+    }
+}
+
+/// Where a [`CoverCandidate`] was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CoverSource {
+    /// The Discogs, Musicbrainz or Bandcamp release the tags came from
+    Release,
+    /// The first video's thumbnail
+    Youtube,
+    Itunes,
+    CoverArtArchive,
+}
+
+impl Display for CoverSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Release => "The release",
+            Self::Youtube => "Youtube thumbnail",
+            Self::Itunes => "iTunes",
+            Self::CoverArtArchive => "Cover Art Archive",
+        })
+    }
+}
+
+/// A cover that could be used for the album. Enough of it has been downloaded to make sure it's an
+/// image and to see how big it is, along with a smaller version to show.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CoverCandidate {
+    pub source: CoverSource,
+    /// What it's the cover of, like "Artist - Album", since searches can find other albums
+    pub title: String,
+    pub url: String,
+    pub info: ImageInfo,
+    /// `None` if it couldn't be downloaded
+    pub thumbnail: Option<Bytes>,
+}
+
+/// A cover that's been found but not downloaded yet
+struct FoundCover {
+    source: CoverSource,
+    title: String,
+    url: String,
+    /// A smaller version of it, or `None` if there isn't one and the cover itself is shown
+    thumbnail_url: Option<String>,
+}
+
+impl FoundCover {
+    /// Downloads the thumbnail and as much of the cover as it takes to size it, once there's a
+    /// free [`NETWORK`](crate::NETWORK) permit
+    fn fetch(self) -> Option<CoverCandidate> {
+        let _permit = crate::NETWORK.acquire();
+        let fetched = match &self.thumbnail_url {
+            None => {
+                download_image(&self.url).and_then(|(img, _)| Ok((ImageInfo::of(&img)?, Some(img))))
+            }
+            Some(thumbnail_url) => probe_image(&self.url).map(|info| {
+                let thumbnail = download_image(thumbnail_url)
+                    .map_err(|err| log::info!("couldn't get the thumbnail {thumbnail_url}: {err}"))
+                    .ok()
+                    .map(|(img, _)| img);
+                (info, thumbnail)
+            }),
+        };
+        match fetched {
+            Ok((info, thumbnail)) => Some(CoverCandidate {
+                source: self.source,
+                title: self.title,
+                url: self.url,
+                info,
+                thumbnail,
+            }),
+            Err(err) => {
+                log::info!("not offering the cover at {}: {err}", self.url);
+                None
+            }
+        }
+    }
+}
+
+/// What's known about the album to look for covers with
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CoverQuery<'a> {
+    pub artist: &'a str,
+    pub album: &'a str,
+    /// The cover the release came with
+    pub release_image: Option<&'a str>,
+    /// The first video in the playlist
+    pub video: Option<&'a VideoId>,
+}
+
+/// How many of each search's results are offered
+const COVERS_PER_SEARCH: usize = 3;
+
+/// Gathers covers from the release, the playlist's first video, and searches of iTunes and the
+/// Cover Art Archive. The ones that can't be downloaded or aren't images are left out, as are
+/// searches that fail.
+///
+/// The downloads share the [`NETWORK`](crate::NETWORK) limit with the tracks, and only the
+/// thumbnails are downloaded whole, except for the release's cover which doesn't have one.
+#[must_use]
+pub fn find_covers(query: &CoverQuery<'_>) -> Vec<CoverCandidate> {
+    let title = format!("{} - {}", query.artist, query.album);
+    let mut found: Vec<FoundCover> = Vec::new();
+    if let Some(url) = query.release_image.filter(|url| !url.trim().is_empty()) {
+        found.push(FoundCover {
+            source: CoverSource::Release,
+            title: title.clone(),
+            url: url.to_string(),
+            thumbnail_url: None,
+        });
+    }
+    if let Some(video) = query.video {
+        let thumbnail =
+            |name: &str| format!("https://i.ytimg.com/vi/{}/{name}.jpg", video.as_str());
+        found.push(FoundCover {
+            source: CoverSource::Youtube,
+            title: title.clone(),
+            url: thumbnail("maxresdefault"),
+            thumbnail_url: Some(thumbnail("mqdefault")),
+        });
+    }
+    if !query.album.trim().is_empty() {
+        match search_itunes(query.artist, query.album) {
+            Ok(albums) => found.extend(albums.iter().take(COVERS_PER_SEARCH).filter_map(|album| {
+                Some(FoundCover {
+                    source: CoverSource::Itunes,
+                    title: format!("{} - {}", album.artist_name, album.collection_name),
+                    url: album.artwork_url(1000)?,
+                    thumbnail_url: album.artwork_url(THUMBNAIL_SIZE),
+                })
+            })),
+            Err(err) => log::warn!("couldn't search iTunes for covers: {err}"),
+        }
+        match search_release_groups(query.artist, query.album) {
+            Ok(groups) => found.extend(groups.iter().take(COVERS_PER_SEARCH).map(|group| {
+                FoundCover {
+                    source: CoverSource::CoverArtArchive,
+                    title: format!("{} - {}", group.artist, group.title),
+                    url: group.cover_url(),
+                    // the archive's thumbnails are 250, 500 or 1200 pixels
+                    thumbnail_url: Some(format!("{}-{THUMBNAIL_SIZE}", group.cover_url())),
+                }
+            })),
+            Err(err) => log::warn!("couldn't search the Cover Art Archive for covers: {err}"),
+        }
+    }
+    let mut urls = Vec::new();
+    found.retain(|cover| {
+        let is_new = !urls.contains(&cover.url);
+        urls.push(cover.url.clone());
+        is_new
+    });
+
+    thread::scope(|scope| {
+        let fetching: Vec<_> = found
+            .into_iter()
+            .map(|cover| scope.spawn(|| cover.fetch()))
+            .collect();
+        fetching
+            .into_iter()
+            .filter_map(|fetch| fetch.join().unwrap_or_else(|err| panic::resume_unwind(err)))
+            .collect()
+    })
+}
+
+/// How big the thumbnails of the covers found by searching are asked to be
+const THUMBNAIL_SIZE: u32 = 250;
+
+/// How much of a cover [`probe_image`] reads looking for its dimensions before giving up on them
+const PROBE_LIMIT: usize = 512 * 1024;
+
+/// Finds out what the image at `url` is and how big it is, downloading only as much of it as that
+/// takes. The rest is only read to count it if the server didn't say how big it is.
+fn probe_image(url: &str) -> Result<ImageInfo, AlbumArtError> {
+    let url = normalize_url(url)?;
+    let mut resp = network::download(url.as_str())?;
+    if !resp.status().is_success() {
+        return Err(AlbumArtError::BadStatus(resp.status()));
+    }
+
+    let length = resp.content_length();
+    let start = read_image_header(&mut resp)?;
+    let mut info = ImageInfo::of(&start)?;
+    info.bytes = match length {
+        Some(length) => usize::try_from(length).unwrap_or(usize::MAX),
+        None => start.len() + usize::try_from(io::copy(&mut resp, &mut io::sink())?).unwrap_or(0),
+    };
+    Ok(info)
+}
+
+/// Reads the start of an image, up to where [`image_dimensions`] can find its size (or to
+/// [`PROBE_LIMIT`] if it can't)
+fn read_image_header(mut reader: impl Read) -> io::Result<Vec<u8>> {
+    let mut img = Vec::new();
+    let mut chunk = [0; 8 * 1024];
+    while img.len() < PROBE_LIMIT {
+        let read = reader.read(&mut chunk)?;
+        if read == 0 {
+            break;
+        }
+        img.extend_from_slice(&chunk[..read]);
+        if img.len() >= MIN_IMAGE_SIZE && image_dimensions(&img).is_some() {
+            break;
+        }
+    }
+    Ok(img)
+}
+
+/// An album art URL that's been checked to point at an image
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckedImage {
+    pub url: Url,
+    pub content_type: String,
+    /// Size in bytes, if the server said
+    pub size: Option<u64>,
+}
+
+impl Display for CheckedImage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.content_type)?;
+        if let Some(size) = self.size {
+            write!(f, ", {} KB", size.div_ceil(1024))?;
+        }
+        Ok(())
+    }
+}
+
+/// Parses an album art URL, upgrading `http` to `https` (except for the
+/// [test server](crate::utils::is_test_server)).
+///
+/// # Errors
+/// - If it isn't a valid URL
+/// - If it isn't an `http` or `https` URL
+pub fn normalize_url(url: &str) -> Result<Url, AlbumArtError> {
+    let mut url = Url::parse(url.trim())?;
+    match url.scheme() {
+        "https" => {}
+        "http" if is_test_server(&url) => {}
+        "http" => {
+            // can't fail, http and https are both "special" schemes
+            let _ = url.set_scheme("https");
+        }
+        scheme => return Err(AlbumArtError::UnsupportedScheme(scheme.to_string())),
+    }
+    Ok(url)
+}
+
+/// Makes sure the URL actually points at an image with a head request.
+///
+/// If it's a Discogs release page rather than an image, it uses the cover from that release.
+///
+/// # Errors
+/// - If [`normalize_url`] fails
+/// - If the request fails or the server doesn't respond with a success
+/// - If the response isn't an image
+pub fn check_url(url: &str) -> Result<CheckedImage, AlbumArtError> {
+    check(url, true)
+}
+
+fn check(url: &str, follow_discogs: bool) -> Result<CheckedImage, AlbumArtError> {
+    let url = normalize_url(url)?;
+    let resp = head_or_get(&url)?;
+
+    match content_type(&resp) {
+        Some(content_type) if content_type.starts_with("image/") => Ok(CheckedImage {
+            size: resp
+                .headers()
+                .get(CONTENT_LENGTH)
+                .and_then(|len| len.to_str().ok()?.parse().ok()),
+            url,
+            content_type,
+        }),
+        Some(content_type)
+            if follow_discogs && content_type.starts_with("text/html") && is_discogs(&url) =>
+        {
+            let album = scrape_discogs(url.as_str())
+                .map_err(|err| AlbumArtError::DiscogsError(err.to_string()))?;
+            check(&album.album_data.image, false)
+        }
+        content_type => Err(AlbumArtError::NotAnImage(content_type)),
+    }
+}
+
+/// Downloads the album art, making sure it's actually an image.
+///
+/// Returns the image and its MIME type (from the file itself, since the content type header isn't
+/// always right).
+///
+/// # Errors
+/// - If [`normalize_url`] fails
+/// - If the request fails or the server doesn't respond with a success
+/// - If [`verify_image`] fails
+pub fn download_image(url: &str) -> Result<(Bytes, &'static str), AlbumArtError> {
+    let url = normalize_url(url)?;
+    let resp = network::download(url.as_str())?;
+    if !resp.status().is_success() {
+        return Err(AlbumArtError::BadStatus(resp.status()));
+    }
+
+    let img = resp.bytes()?;
+    let mime_type = verify_image(&img)?;
+    Ok((img, mime_type))
+}
+
+/// Checks the file is big enough and starts with the magic bytes of a supported image format.
+///
+/// Returns its MIME type.
+///
+/// # Errors
+/// - If it's smaller than [`MIN_IMAGE_SIZE`]
+/// - If it isn't a JPEG, PNG, GIF, WebP or BMP
+///
+/// # Examples
+/// ```
+/// # use ytmdl::album_art::{verify_image, AlbumArtError};
+/// let mut png = b"\x89PNG\r\n\x1a\n".to_vec();
+/// png.resize(1024, 0);
+/// assert_eq!(verify_image(&png).unwrap(), "image/png");
+///
+/// let mut html = b"<!DOCTYPE html><html>".to_vec();
+/// html.resize(1024, b' ');
+/// assert!(matches!(verify_image(&html), Err(AlbumArtError::UnrecognizedImage)));
+///
+/// assert!(matches!(verify_image(b""), Err(AlbumArtError::TooSmall(0))));
+/// ```
+pub fn verify_image(img: &[u8]) -> Result<&'static str, AlbumArtError> {
+    if img.len() < MIN_IMAGE_SIZE {
+        return Err(AlbumArtError::TooSmall(img.len()));
+    }
+
+    if img.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        Ok("image/jpeg")
+    } else if img.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Ok("image/png")
+    } else if img.starts_with(b"GIF87a") || img.starts_with(b"GIF89a") {
+        Ok("image/gif")
+    } else if img.starts_with(b"RIFF") && img.get(8..12) == Some(b"WEBP") {
+        Ok("image/webp")
+    } else if img.starts_with(b"BM") {
+        Ok("image/bmp")
+    } else {
+        Err(AlbumArtError::UnrecognizedImage)
+    }
+}
+
+/// The width and height of a JPEG, PNG, GIF, WebP or BMP image from its header
+///
+/// # Examples
+/// ```
+/// use ytmdl::album_art::image_dimensions;
+///
+/// let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+/// png.extend_from_slice(&300u32.to_be_bytes());
+/// png.extend_from_slice(&200u32.to_be_bytes());
+/// assert_eq!(image_dimensions(&png), Some((300, 200)));
+/// assert_eq!(image_dimensions(b"not an image"), None);
+/// ```
+#[must_use]
+pub fn image_dimensions(img: &[u8]) -> Option<(u32, u32)> {
+    let le_u16 = |at: usize| Some(u16::from_le_bytes(img.get(at..at + 2)?.try_into().ok()?));
+    let be_u16 = |at: usize| Some(u16::from_be_bytes(img.get(at..at + 2)?.try_into().ok()?));
+    let be_u32 = |at: usize| Some(u32::from_be_bytes(img.get(at..at + 4)?.try_into().ok()?));
+    let le_u32 = |at: usize| Some(u32::from_le_bytes(img.get(at..at + 4)?.try_into().ok()?));
+    let le_u24 = |at: usize| {
+        Some(u32::from_le_bytes([
+            *img.get(at)?,
+            *img.get(at + 1)?,
+            *img.get(at + 2)?,
+            0,
+        ]))
+    };
+
+    if img.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some((be_u32(16)?, be_u32(20)?))
+    } else if img.starts_with(b"GIF87a") || img.starts_with(b"GIF89a") {
+        Some((u32::from(le_u16(6)?), u32::from(le_u16(8)?)))
+    } else if img.starts_with(b"BM") {
+        // negative heights are images stored top to bottom
+        Some((le_u32(18)?, le_u32(22)?.cast_signed().unsigned_abs()))
+    } else if img.starts_with(b"RIFF") && img.get(8..12) == Some(b"WEBP") {
+        match img.get(12..16)? {
+            b"VP8 " => Some((
+                u32::from(le_u16(26)? & 0x3FFF),
+                u32::from(le_u16(28)? & 0x3FFF),
+            )),
+            b"VP8L" => {
+                let bits = le_u32(21)?;
+                Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+            }
+            b"VP8X" => Some((le_u24(24)? + 1, le_u24(27)? + 1)),
+            _ => None,
+        }
+    } else if img.starts_with(&[0xFF, 0xD8]) {
+        // the size is in the first start of frame segment
+        let mut at = 2;
+        loop {
+            if *img.get(at)? != 0xFF {
+                return None;
+            }
+            let marker = *img.get(at + 1)?;
+            let is_start_of_frame =
+                matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_start_of_frame {
+                return Some((u32::from(be_u16(at + 7)?), u32::from(be_u16(at + 5)?)));
+            }
+            at += 2 + usize::from(be_u16(at + 2)?);
+        }
+    } else {
+        None
+    }
+}
+
+/// Upscales the cover with the settings' command, or its API if there isn't a command.
+///
+/// Returns the upscaled image and its MIME type.
+///
+/// # Errors
+/// - If there's no command or API set
+/// - If the command fails or can't be run, or the API request fails
+/// - If what comes back isn't an image
+pub fn upscale(
+    img: &[u8],
+    settings: &UpscaleSettings,
+) -> Result<(Bytes, &'static str), AlbumArtError> {
+    let command = settings.command.as_deref().filter(|c| !c.trim().is_empty());
+    let api_url = settings.api_url.as_deref().filter(|u| !u.trim().is_empty());
+    let upscaled: Bytes = if let Some(command) = command {
+        let dir = TempDir::new("ytmdl-upscale")?;
+        let extension = verify_image(img)?.trim_start_matches("image/");
+        let input = dir.path().join(format!("cover.{extension}"));
+        let output = dir.path().join("upscaled.png");
+        fs::write(&input, img)?;
+        run_upscale_command(command, &input, &output)?;
+        fs::read(&output)?.into()
+    } else if let Some(api_url) = api_url {
+        let content_type = verify_image(img)?;
+        let resp = network::post(api_url.trim(), img.to_vec(), content_type)?;
+        resp.bytes()?
+    } else {
+        return Err(AlbumArtError::UpscaleNotConfigured);
+    };
+    let content_type = verify_image(&upscaled)?;
+    Ok((upscaled, content_type))
+}
+
+/// Runs `command` (split on spaces) with `{input}` and `{output}` filled in
+fn run_upscale_command(command: &str, input: &Path, output: &Path) -> Result<(), AlbumArtError> {
+    let input = input.to_string_lossy();
+    let output = output.to_string_lossy();
+    let mut args = command
+        .split_whitespace()
+        .map(|arg| arg.replace("{input}", &input).replace("{output}", &output));
+    let program = args.next().ok_or(AlbumArtError::UpscaleNotConfigured)?;
+    let result = Command::new(&program).args(args).output()?;
+    if result.status.success() {
+        Ok(())
+    } else {
+        Err(AlbumArtError::UpscaleFailed(
+            String::from_utf8_lossy(&result.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Some image hosts don't allow head requests, so it falls back to a get (without reading the body)
+fn head_or_get(url: &Url) -> Result<Response, AlbumArtError> {
+    let mut resp = network::head(url.as_str())?;
+    if resp.status() == StatusCode::METHOD_NOT_ALLOWED {
+        resp = network::download(url.as_str())?;
+    }
+
+    if resp.status().is_success() {
+        Ok(resp)
+    } else {
+        Err(AlbumArtError::BadStatus(resp.status()))
+    }
+}
+
+fn content_type(resp: &Response) -> Option<String> {
+    resp.headers()
+        .get(CONTENT_TYPE)
+        .map(HeaderValue::to_str)
+        .and_then(Result::ok)
+        .map(str::to_ascii_lowercase)
+}
+
+fn is_discogs(url: &Url) -> bool {
+    url.host_str().map_or(false, |host| {
+        host == "discogs.com" || host.ends_with(".discogs.com")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize() {
+        assert_eq!(
+            normalize_url("http://i.discogs.com/cover.jpg")
+                .unwrap()
+                .as_str(),
+            "https://i.discogs.com/cover.jpg"
+        );
+        assert_eq!(
+            normalize_url("  https://i.discogs.com/cover.jpg ")
+                .unwrap()
+                .as_str(),
+            "https://i.discogs.com/cover.jpg"
+        );
+        // only the fixture server is left on http
+        let local = normalize_url("http://127.0.0.1:8080/cover.jpg").unwrap();
+        let scheme = if cfg!(feature = "test-support") {
+            "http"
+        } else {
+            "https"
+        };
+        assert_eq!(local.scheme(), scheme);
+        assert!(matches!(
+            normalize_url("ftp://example.com/cover.jpg"),
+            Err(AlbumArtError::UnsupportedScheme(_))
+        ));
+        assert!(matches!(
+            normalize_url("cover.jpg"),
+            Err(AlbumArtError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn image_formats() {
+        let padded = |magic: &[u8]| {
+            let mut img = magic.to_vec();
+            img.resize(MIN_IMAGE_SIZE, 0);
+            img
+        };
+
+        assert_eq!(
+            verify_image(&padded(&[0xFF, 0xD8, 0xFF, 0xE0])).unwrap(),
+            "image/jpeg"
+        );
+        assert_eq!(verify_image(&padded(b"GIF89a")).unwrap(), "image/gif");
+        assert_eq!(
+            verify_image(&padded(b"RIFF\0\0\0\0WEBPVP8 ")).unwrap(),
+            "image/webp"
+        );
+        assert!(matches!(
+            verify_image(&padded(b"RIFF\0\0\0\0WAVEfmt ")),
+            Err(AlbumArtError::UnrecognizedImage)
+        ));
+        assert!(matches!(
+            verify_image(&[0xFF, 0xD8, 0xFF, 0xE0]),
+            Err(AlbumArtError::TooSmall(4))
+        ));
+    }
+
+    #[test]
+    fn dimensions() {
+        let jpeg = [
+            &[0xFF, 0xD8][..],
+            // an APP0 segment to skip over, then the start of frame
+            &[0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00],
+            &[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x01, 0x2C, 0x01, 0x90],
+        ]
+        .concat();
+        assert_eq!(image_dimensions(&jpeg), Some((400, 300)));
+        assert_eq!(image_dimensions(&jpeg[..8]), None);
+
+        let gif = [&b"GIF89a"[..], &[0x40, 0x01, 0xF0, 0x00]].concat();
+        assert_eq!(image_dimensions(&gif), Some((320, 240)));
+
+        let mut bmp = b"BM".to_vec();
+        bmp.resize(18, 0);
+        bmp.extend_from_slice(&250u32.to_le_bytes());
+        bmp.extend_from_slice(&(-100i32).to_le_bytes());
+        assert_eq!(image_dimensions(&bmp), Some((250, 100)));
+
+        let mut webp = b"RIFF\0\0\0\0WEBPVP8X".to_vec();
+        webp.resize(24, 0);
+        webp.extend_from_slice(&[0xFF, 0x01, 0x00, 0x7F, 0x00, 0x00]);
+        assert_eq!(image_dimensions(&webp), Some((512, 128)));
+    }
+
+    #[test]
+    fn image_header() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&300u32.to_be_bytes());
+        png.extend_from_slice(&800u32.to_be_bytes());
+        png.resize(64 * 1024, 0);
+        let start = read_image_header(png.as_slice()).unwrap();
+        assert!(start.len() < png.len());
+        assert_eq!(image_dimensions(&start), Some((300, 800)));
+
+        // without a size it reads up to the limit
+        let unknown = vec![0; PROBE_LIMIT * 2];
+        assert_eq!(
+            read_image_header(unknown.as_slice()).unwrap().len(),
+            PROBE_LIMIT
+        );
+        assert_eq!(read_image_header(&b"short"[..]).unwrap(), b"short");
+    }
+
+    #[test]
+    fn upscale_settings() {
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&300u32.to_be_bytes());
+        png.extend_from_slice(&800u32.to_be_bytes());
+
+        let mut settings = UpscaleSettings::default();
+        assert!(!settings.wants(&png));
+        settings.api_url = Some(String::from("  "));
+        assert!(!settings.is_configured());
+        settings.command = Some(String::from("upscale {input} {output}"));
+        assert!(settings.wants(&png));
+        settings.min_size = 300;
+        assert!(!settings.wants(&png));
+        assert!(!settings.wants(b"not an image"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn upscale_command() {
+        let mut img = [
+            &[0xFF, 0xD8][..],
+            &[0xFF, 0xC0, 0x00, 0x11, 0x08, 0x00, 0x10, 0x00, 0x10],
+        ]
+        .concat();
+        img.resize(MIN_IMAGE_SIZE, 0);
+        let settings = UpscaleSettings {
+            command: Some(String::from("cp {input} {output}")),
+            ..UpscaleSettings::default()
+        };
+        let (upscaled, content_type) = upscale(&img, &settings).unwrap();
+        assert_eq!(upscaled, img);
+        assert_eq!(content_type, "image/jpeg");
+
+        let failing = UpscaleSettings {
+            command: Some(String::from("false {input}")),
+            ..UpscaleSettings::default()
+        };
+        assert!(matches!(
+            upscale(&img, &failing),
+            Err(AlbumArtError::UpscaleFailed(_))
+        ));
+        assert!(matches!(
+            upscale(&img, &UpscaleSettings::default()),
+            Err(AlbumArtError::UpscaleNotConfigured)
+        ));
+    }
+
+    #[test]
+    fn discogs_hosts() {
+        assert!(is_discogs(
+            &Url::parse("https://www.discogs.com/release/1").unwrap()
+        ));
+        assert!(is_discogs(
+            &Url::parse("https://i.discogs.com/cover.jpg").unwrap()
+        ));
+        assert!(!is_discogs(
+            &Url::parse("https://notdiscogs.com/cover.jpg").unwrap()
+        ));
+    }
+}
@@ -0,0 +1,388 @@
+//! Optional audio analysis for DJ-oriented tags, run on each track after it's downloaded.
+
+use id3::{frame::ExtendedText, Tag, TagLike};
+use serde::{Deserialize, Serialize};
+use std::{
+    f32::consts::PI,
+    fmt::{self, Display, Formatter},
+};
+
+/// Sample rate tracks are decoded at for analysis; plenty for finding beats
+pub const SAMPLE_RATE: u32 = 11025;
+/// Samples between each point of the onset envelope (~86 per second)
+const HOP: usize = 128;
+const MIN_BPM: f32 = 60.0;
+const MAX_BPM: f32 = 200.0;
+/// Beats are only really reliable with at least this many seconds of audio
+const MIN_SECONDS: usize = 10;
+/// Samples in each window the key is worked out from
+const KEY_WINDOW: usize = 4096;
+/// MIDI notes checked for the key: C2 to B6
+const KEY_NOTES: std::ops::Range<u8> = 36..96;
+
+/// Krumhansl-Kessler key profiles, starting at the tonic
+const MAJOR_PROFILE: [f32; 12] = [
+    6.35, 2.23, 3.48, 2.33, 4.38, 4.09, 2.52, 5.19, 2.39, 3.66, 2.29, 2.88,
+];
+const MINOR_PROFILE: [f32; 12] = [
+    6.33, 2.68, 3.52, 5.38, 2.60, 3.53, 2.54, 4.75, 3.98, 2.69, 3.34, 3.17,
+];
+
+/// Which analyses to run, set in the config
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AnalysisSettings {
+    /// Detect the tempo and write it to TBPM
+    pub bpm: bool,
+    /// Detect the key and write it to TKEY, and in Camelot notation to a TXXX frame
+    pub key: bool,
+}
+
+impl AnalysisSettings {
+    /// Whether any analysis is turned on, i.e. whether the track needs decoding
+    #[must_use]
+    pub fn any(self) -> bool {
+        self.bpm || self.key
+    }
+}
+
+/// What was found out about a track
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TrackAnalysis {
+    pub bpm: Option<f32>,
+    pub key: Option<Key>,
+}
+
+impl TrackAnalysis {
+    /// Runs the analyses turned on in the settings on mono samples at [`SAMPLE_RATE`]
+    #[must_use]
+    pub fn new(samples: &[f32], settings: AnalysisSettings) -> Self {
+        Self {
+            bpm: settings
+                .bpm
+                .then(|| detect_bpm(samples, SAMPLE_RATE))
+                .flatten(),
+            key: settings
+                .key
+                .then(|| detect_key(samples, SAMPLE_RATE))
+                .flatten(),
+        }
+    }
+
+    pub fn apply_to_tag(&self, tag: &mut Tag) {
+        if let Some(bpm) = self.bpm {
+            tag.set_text("TBPM", format!("{bpm:.0}"));
+        }
+        if let Some(key) = self.key {
+            tag.set_text("TKEY", key.to_string());
+            tag.add_frame(ExtendedText {
+                description: String::from("CAMELOT"),
+                value: key.camelot(),
+            });
+        }
+    }
+}
+
+/// A musical key
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Key {
+    /// Pitch class of the tonic, 0 is C
+    pub tonic: u8,
+    pub minor: bool,
+}
+
+impl Key {
+    /// Position on the Camelot wheel, e.g. `8B` for C major and `8A` for A minor
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::analysis::Key;
+    ///
+    /// assert_eq!(Key { tonic: 0, minor: false }.camelot(), "8B");
+    /// assert_eq!(Key { tonic: 9, minor: true }.camelot(), "8A");
+    /// assert_eq!(Key { tonic: 11, minor: false }.camelot(), "1B");
+    /// ```
+    #[must_use]
+    pub fn camelot(self) -> String {
+        // going round the wheel is going up in fifths, and minor keys line up with their relative major
+        let offset = if self.minor { 5 } else { 8 };
+        let number = match (7 * u32::from(self.tonic) + offset) % 12 {
+            0 => 12,
+            n => n,
+        };
+        format!("{number}{}", if self.minor { 'A' } else { 'B' })
+    }
+}
+
+/// In the format used by TKEY, e.g. `Eb` or `F#m`
+impl Display for Key {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        const MAJOR: [&str; 12] = [
+            "C", "Db", "D", "Eb", "E", "F", "F#", "G", "Ab", "A", "Bb", "B",
+        ];
+        const MINOR: [&str; 12] = [
+            "Cm", "C#m", "Dm", "Ebm", "Em", "Fm", "F#m", "Gm", "G#m", "Am", "Bbm", "Bm",
+        ];
+        let names = if self.minor { MINOR } else { MAJOR };
+        f.write_str(names[usize::from(self.tonic % 12)])
+    }
+}
+
+/// Estimates the key of some mono audio.
+///
+/// Adds up how loud each pitch class is across the track, then finds the major or minor key
+/// profile that fits that best.
+///
+/// Returns `None` if the audio is too short or silent.
+#[must_use]
+#[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+pub fn detect_key(samples: &[f32], sample_rate: u32) -> Option<Key> {
+    if samples.len() < MIN_SECONDS * sample_rate as usize {
+        return None;
+    }
+
+    let window: Vec<f32> = (0..KEY_WINDOW)
+        .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / KEY_WINDOW as f32).cos())
+        .collect();
+    let coefficients: Vec<(u8, f32)> = KEY_NOTES
+        .map(|note| {
+            let frequency = 440.0 * 2_f32.powf((f32::from(note) - 69.0) / 12.0);
+            (
+                note % 12,
+                2.0 * (2.0 * PI * frequency / sample_rate as f32).cos(),
+            )
+        })
+        .collect();
+
+    let mut chroma = [0.0_f32; 12];
+    let mut frame = vec![0.0; KEY_WINDOW];
+    for chunk in samples.chunks_exact(KEY_WINDOW) {
+        for ((windowed, sample), w) in frame.iter_mut().zip(chunk).zip(&window) {
+            *windowed = sample * w;
+        }
+
+        let mut frame_chroma = [0.0_f32; 12];
+        for &(pitch_class, coefficient) in &coefficients {
+            frame_chroma[usize::from(pitch_class)] += goertzel_power(&frame, coefficient).sqrt();
+        }
+        // each window counts the same however loud it is
+        let total: f32 = frame_chroma.iter().sum();
+        if total > f32::EPSILON {
+            for (c, f) in chroma.iter_mut().zip(frame_chroma) {
+                *c += f / total;
+            }
+        }
+    }
+    if chroma.iter().all(|c| *c <= f32::EPSILON) {
+        return None;
+    }
+
+    let mut best = None;
+    for tonic in 0..12 {
+        for (minor, profile) in [(false, &MAJOR_PROFILE), (true, &MINOR_PROFILE)] {
+            let rotated: Vec<f32> = (0..12).map(|i| chroma[(i + tonic) % 12]).collect();
+            let score = correlation(&rotated, profile);
+            if best.map_or(true, |(best_score, _)| score > best_score) {
+                best = Some((
+                    score,
+                    Key {
+                        tonic: tonic as u8,
+                        minor,
+                    },
+                ));
+            }
+        }
+    }
+    best.map(|(_, key)| key)
+}
+
+/// Power of a single frequency in the samples, where `coefficient` is `2cos(2πf/sample_rate)`
+fn goertzel_power(samples: &[f32], coefficient: f32) -> f32 {
+    let (mut s1, mut s2) = (0.0, 0.0);
+    for sample in samples {
+        let s0 = sample + coefficient * s1 - s2;
+        s2 = s1;
+        s1 = s0;
+    }
+    s1 * s1 + s2 * s2 - coefficient * s1 * s2
+}
+
+/// Pearson correlation
+#[allow(clippy::cast_precision_loss)]
+fn correlation(a: &[f32], b: &[f32]) -> f32 {
+    let mean = |v: &[f32]| v.iter().sum::<f32>() / v.len() as f32;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let (mut covariance, mut var_a, mut var_b) = (0.0, 0.0, 0.0);
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        var_a += (x - mean_a).powi(2);
+        var_b += (y - mean_b).powi(2);
+    }
+    covariance / (var_a * var_b).sqrt().max(f32::EPSILON)
+}
+
+/// Estimates the tempo of some mono audio.
+///
+/// Works out how much louder each short window is than the last (where notes start), then finds
+/// the beat length that lines those up best. Tempos near 120 are slightly preferred so it doesn't
+/// pick double or half time.
+///
+/// Returns `None` if the audio is too short or silent.
+#[must_use]
+#[allow(
+    clippy::cast_precision_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss
+)]
+pub fn detect_bpm(samples: &[f32], sample_rate: u32) -> Option<f32> {
+    let sample_rate = sample_rate as usize;
+    if samples.len() < MIN_SECONDS * sample_rate {
+        return None;
+    }
+
+    let envelope = onset_envelope(samples);
+    let frames_per_second = sample_rate as f32 / HOP as f32;
+    let lag_for = |bpm: f32| frames_per_second * 60.0 / bpm;
+    let min_lag = lag_for(MAX_BPM).floor() as usize;
+    let max_lag = (lag_for(MIN_BPM).ceil() as usize).min(envelope.len() / 2);
+    if min_lag == 0 || min_lag >= max_lag {
+        return None;
+    }
+
+    let autocorrelation = |lag: usize| -> f32 {
+        envelope
+            .iter()
+            .zip(&envelope[lag..])
+            .map(|(a, b)| a * b)
+            .sum::<f32>()
+            / (envelope.len() - lag) as f32
+    };
+    let scores: Vec<f32> = (min_lag..=max_lag + 1).map(autocorrelation).collect();
+
+    let weighted = |i: usize| {
+        let bpm = frames_per_second * 60.0 / (min_lag + i) as f32;
+        // log-gaussian centred on 120 BPM, about an octave wide
+        let octaves = (bpm / 120.0).log2();
+        scores[i] * (-0.5 * (octaves / 0.9).powi(2)).exp()
+    };
+    let best = (1..scores.len() - 1).max_by(|&a, &b| weighted(a).total_cmp(&weighted(b)))?;
+    if scores[best] <= 0.0 {
+        return None;
+    }
+
+    // parabolic interpolation between neighbouring lags for sub-frame precision
+    let (prev, here, next) = (scores[best - 1], scores[best], scores[best + 1]);
+    let denominator = prev - 2.0 * here + next;
+    let offset = if denominator.abs() > f32::EPSILON {
+        (0.5 * (prev - next) / denominator).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    let lag = (min_lag + best) as f32 + offset;
+
+    Some(frames_per_second * 60.0 / lag)
+}
+
+/// How much louder (RMS) each window is than the last, with the average taken away
+#[allow(clippy::cast_precision_loss)]
+fn onset_envelope(samples: &[f32]) -> Vec<f32> {
+    let energies: Vec<f32> = samples
+        .chunks_exact(HOP)
+        .map(|window| (window.iter().map(|s| s * s).sum::<f32>() / HOP as f32).sqrt())
+        .collect();
+
+    let mut envelope: Vec<f32> = energies
+        .windows(2)
+        .map(|pair| (pair[1] - pair[0]).max(0.0))
+        .collect();
+    let mean = envelope.iter().sum::<f32>() / envelope.len().max(1) as f32;
+    for value in &mut envelope {
+        *value -= mean;
+    }
+    envelope
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Short decaying noise bursts on every beat, with quieter ones on the off-beats
+    #[allow(clippy::cast_precision_loss)]
+    fn click_track(bpm: f32, seconds: usize) -> Vec<f32> {
+        let sample_rate = SAMPLE_RATE as usize;
+        let beat = sample_rate as f32 * 60.0 / bpm;
+        let mut samples = vec![0.0; seconds * sample_rate];
+        let mut noise = 12345_u32;
+        for (i, sample) in samples.iter_mut().enumerate() {
+            noise = noise.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            let white = (noise >> 16) as f32 / 32768.0 - 1.0;
+            let since_beat = (i as f32 % beat) / sample_rate as f32;
+            let since_half = ((i as f32 + beat / 2.0) % beat) / sample_rate as f32;
+            *sample = white * ((-since_beat * 40.0).exp() + 0.3 * (-since_half * 40.0).exp());
+        }
+        samples
+    }
+
+    #[test]
+    fn detects_tempo() {
+        for bpm in [90.0, 128.0, 174.0] {
+            let detected = detect_bpm(&click_track(bpm, 30), SAMPLE_RATE).unwrap();
+            assert!(
+                (detected - bpm).abs() < 1.5,
+                "expected {bpm}, got {detected}"
+            );
+        }
+    }
+
+    /// Sine waves at the given MIDI notes, all at once
+    #[allow(clippy::cast_precision_loss)]
+    fn chord(notes: &[u8], seconds: usize) -> Vec<f32> {
+        (0..seconds * SAMPLE_RATE as usize)
+            .map(|i| {
+                let t = i as f32 / SAMPLE_RATE as f32;
+                notes
+                    .iter()
+                    .map(|&note| {
+                        let frequency = 440.0 * 2_f32.powf((f32::from(note) - 69.0) / 12.0);
+                        (2.0 * PI * frequency * t).sin()
+                    })
+                    .sum::<f32>()
+                    / notes.len() as f32
+            })
+            .collect()
+    }
+
+    #[test]
+    fn detects_key() {
+        // C E G
+        let key = detect_key(&chord(&[60, 64, 67], 12), SAMPLE_RATE).unwrap();
+        assert_eq!(
+            key,
+            Key {
+                tonic: 0,
+                minor: false
+            }
+        );
+        assert_eq!(key.to_string(), "C");
+
+        // A C E
+        let key = detect_key(&chord(&[57, 60, 64], 12), SAMPLE_RATE).unwrap();
+        assert_eq!(
+            key,
+            Key {
+                tonic: 9,
+                minor: true
+            }
+        );
+        assert_eq!(key.to_string(), "Am");
+        assert_eq!(key.camelot(), "8A");
+    }
+
+    #[test]
+    fn nothing_to_detect() {
+        assert_eq!(detect_bpm(&[0.0; 1000], SAMPLE_RATE), None);
+        let silence = vec![0.0; 30 * SAMPLE_RATE as usize];
+        assert_eq!(detect_bpm(&silence, SAMPLE_RATE), None);
+        assert_eq!(detect_key(&silence, SAMPLE_RATE), None);
+    }
+}
@@ -0,0 +1,281 @@
+//! Adding many albums to the queue at once from a text or CSV file of links, one album per line:
+//! a Youtube playlist, optionally followed by the Discogs, Musicbrainz or Bandcamp release to take
+//! its tags from
+
+use crate::{
+    gui::view_modifying_data::StateModifyingData,
+    jobs::Job,
+    queue,
+    urls::{classify, UrlKind},
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+use thiserror::Error;
+
+/// Why a line couldn't be queued
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum LineError {
+    #[error("no Youtube link")]
+    NoYoutube,
+    #[error("more than one Youtube link")]
+    SeveralYoutube,
+    #[error("more than one release link")]
+    SeveralReleases,
+    #[error(r#""{0}" isn't a Youtube or release link"#)]
+    NotALink(String),
+    #[error("same playlist as line {0}")]
+    Duplicate(usize),
+    #[error("couldn't scrape it: {0}")]
+    Scrape(String),
+}
+
+/// A line that can be queued
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BatchEntry {
+    /// 1-based, as it'd be shown in an editor
+    pub line: usize,
+    pub youtube: String,
+    pub metadata: Option<String>,
+}
+
+/// A line that can't be queued, and why
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InvalidLine {
+    /// 1-based, as it'd be shown in an editor
+    pub line: usize,
+    pub text: String,
+    pub error: LineError,
+}
+
+impl Display for InvalidLine {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {} ({})", self.line, self.error, self.text)
+    }
+}
+
+/// A file of links, split into the lines that can be queued and the ones that can't
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UrlList {
+    pub entries: Vec<BatchEntry>,
+    pub invalid: Vec<InvalidLine>,
+}
+
+/// How adding a [`UrlList`] to the queue went
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BatchReport {
+    pub queued: usize,
+    /// In the order they're in the file
+    pub invalid: Vec<InvalidLine>,
+}
+
+impl Display for BatchReport {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "Added {} albums to the queue", self.queued)?;
+        if !self.invalid.is_empty() {
+            write!(f, "; {} lines skipped:", self.invalid.len())?;
+            for line in &self.invalid {
+                write!(f, "\n{line}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Splits each line of the file into its links. They can be separated by commas, semicolons, tabs
+/// or spaces, and may be quoted like a spreadsheet exports them. Blank lines, lines starting with
+/// `#` and a header line (like `youtube,discogs`) are skipped.
+///
+/// # Examples
+/// ```
+/// use ytmdl::batch::{parse_url_list, LineError};
+///
+/// let list = parse_url_list(
+///     "youtube,discogs\n\
+///      https://www.youtube.com/playlist?list=OLAK5uy_abc,\"https://www.discogs.com/release/1\"\n\
+///      ## just the playlist\n\
+///      https://www.youtube.com/playlist?list=OLAK5uy_def\n\
+///      https://www.discogs.com/release/2\n",
+/// );
+/// assert_eq!(list.entries.len(), 2);
+/// assert_eq!(
+///     list.entries[0].metadata.as_deref(),
+///     Some("https://www.discogs.com/release/1")
+/// );
+/// assert_eq!(list.entries[1].line, 4);
+/// assert_eq!(list.invalid[0].line, 5);
+/// assert_eq!(list.invalid[0].error, LineError::NoYoutube);
+/// ```
+#[must_use]
+pub fn parse_url_list(text: &str) -> UrlList {
+    let mut list = UrlList::default();
+    let mut playlists: Vec<(UrlKind, usize)> = Vec::new();
+    let mut first = true;
+
+    for (i, text) in text.lines().enumerate() {
+        let line = i + 1;
+        let trimmed = text.trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let fields: Vec<&str> = trimmed
+            .split(|c: char| c == ',' || c == ';' || c.is_whitespace())
+            .map(|field| field.trim_matches(|c| c == '"' || c == '\''))
+            .filter(|field| !field.is_empty())
+            .collect();
+        let is_header = first && fields.iter().all(|field| !looks_like_link(field));
+        first = false;
+        if is_header {
+            continue;
+        }
+
+        let invalid = |error| InvalidLine {
+            line,
+            text: trimmed.to_string(),
+            error,
+        };
+        match parse_line(&fields) {
+            Ok((youtube, kind, metadata)) => {
+                if let Some((_, other_line)) = playlists.iter().find(|(other, _)| *other == kind) {
+                    list.invalid
+                        .push(invalid(LineError::Duplicate(*other_line)));
+                } else {
+                    playlists.push((kind, line));
+                    list.entries.push(BatchEntry {
+                        line,
+                        youtube,
+                        metadata,
+                    });
+                }
+            }
+            Err(err) => list.invalid.push(invalid(err)),
+        }
+    }
+    list
+}
+
+/// The Youtube link (and what it is, to spot duplicates) and the release link, if there is one
+fn parse_line(fields: &[&str]) -> Result<(String, UrlKind, Option<String>), LineError> {
+    let mut youtube = None;
+    let mut metadata = None;
+    for field in fields {
+        let kind = classify(field);
+        if kind.is_youtube() {
+            if youtube.replace((field.to_string(), kind)).is_some() {
+                return Err(LineError::SeveralYoutube);
+            }
+        } else if kind.is_metadata() {
+            if metadata.replace(field.to_string()).is_some() {
+                return Err(LineError::SeveralReleases);
+            }
+        } else {
+            return Err(LineError::NotALink(field.to_string()));
+        }
+    }
+    let (youtube, kind) = youtube.ok_or(LineError::NoYoutube)?;
+    Ok((youtube, kind, metadata))
+}
+
+/// Whether a field is meant to be a link, rather than a column name
+fn looks_like_link(field: &str) -> bool {
+    field.contains('/') || field.contains('.')
+}
+
+/// Reads and parses a file of links with [`parse_url_list`]
+///
+/// # Errors
+/// If the file can't be read
+pub fn read_url_list(path: &Path) -> Result<UrlList, io::Error> {
+    Ok(parse_url_list(&fs::read_to_string(path)?))
+}
+
+impl UrlList {
+    /// Scrapes each album like the "Scrape" button does and adds it to the queue. Albums whose
+    /// playlist can't be scraped are added to the report with the lines that weren't valid.
+    #[must_use]
+    pub fn queue(self) -> BatchReport {
+        let mut report = BatchReport {
+            queued: 0,
+            invalid: self.invalid,
+        };
+        for entry in self.entries {
+            match StateModifyingData::from_links(
+                entry.youtube.clone(),
+                entry.metadata.as_deref().unwrap_or_default(),
+            ) {
+                Ok(state) => {
+                    log::info!("adding line {} of the list to the queue", entry.line);
+                    queue::add(Job::new(&state));
+                    report.queued += 1;
+                }
+                Err(err) => {
+                    log::warn!("couldn't scrape line {} of the list: {err}", entry.line);
+                    report.invalid.push(InvalidLine {
+                        line: entry.line,
+                        text: entry.youtube,
+                        error: LineError::Scrape(err.to_string()),
+                    });
+                }
+            }
+        }
+        report.invalid.sort_by_key(|line| line.line);
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invalid_lines() {
+        let list = parse_url_list(
+            "https://music.youtube.com/playlist?list=OLAK5uy_abc\thttps://artist.bandcamp.com/album/a\n\
+             \n\
+             https://www.youtube.com/playlist?list=OLAK5uy_abc\n\
+             https://youtu.be/dQw4w9WgXcQ https://youtu.be/oHg5SJYRHA0\n\
+             youtube.com/playlist?list=PLxyz; https://www.discogs.com/release/1; https://musicbrainz.org/release/b84ee12a-09ef-421b-82de-0441a926375b\n\
+             https://www.youtube.com/playlist?list=PLxyz, not-a-link\n\
+             'https://youtu.be/dQw4w9WgXcQ'\n",
+        );
+        assert_eq!(list.entries.len(), 2);
+        assert_eq!(list.entries[0].line, 1);
+        assert_eq!(
+            list.entries[0].metadata.as_deref(),
+            Some("https://artist.bandcamp.com/album/a")
+        );
+        assert_eq!(list.entries[1].youtube, "https://youtu.be/dQw4w9WgXcQ");
+        assert_eq!(list.entries[1].metadata, None);
+
+        let errors: Vec<(usize, LineError)> = list
+            .invalid
+            .into_iter()
+            .map(|line| (line.line, line.error))
+            .collect();
+        assert_eq!(
+            errors,
+            [
+                (3, LineError::Duplicate(1)),
+                (4, LineError::SeveralYoutube),
+                (5, LineError::SeveralReleases),
+                (6, LineError::NotALink(String::from("not-a-link"))),
+            ]
+        );
+    }
+
+    #[test]
+    fn header_only_on_first_line() {
+        let list = parse_url_list("Playlist\tRelease\nsomething else\n");
+        assert!(list.entries.is_empty());
+        assert_eq!(
+            list.invalid,
+            [InvalidLine {
+                line: 2,
+                text: String::from("something else"),
+                error: LineError::NotALink(String::from("something")),
+            }]
+        );
+    }
+}
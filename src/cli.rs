@@ -0,0 +1,266 @@
+//! Downloading an album without the GUI, for machines without a display:
+//! `ytmdl download --youtube <url> [--discogs <url>] [--out <dir>] [--profile <name>]`
+//!
+//! It goes through the same steps as the GUI, without changing anything on the modify screen.
+
+use crate::{
+    config,
+    deps::check_dependencies,
+    gui::{
+        view_modifying_data::{ImageStatus, StateModifyingData},
+        view_results::StateResults,
+    },
+    scraping::ScrapeYoutubePlaylistError,
+};
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("{0} needs a value")]
+    MissingValue(String),
+    #[error(r#"unknown argument "{0}""#)]
+    UnknownArgument(String),
+    #[error("--youtube is required")]
+    MissingYoutube,
+    #[error(r#"profile "{0}" doesn't exist"#)]
+    UnknownProfile(String),
+    #[error("{0}")]
+    ScrapeError(#[from] ScrapeYoutubePlaylistError),
+}
+
+pub const USAGE: &str = "\
+Usage:
+  ytmdl [--portable]
+      Opens the app
+  ytmdl download --youtube <url> [--discogs <url>] [--out <dir>] [--profile <name>] [--portable]
+      Downloads an album without opening the app
+
+Options for download:
+  --youtube <url>   Youtube playlist, or comma-separated list of videos
+  --discogs <url>   Discogs, MusicBrainz or Bandcamp release (or an album folder) to get the
+                    tags from
+  --out <dir>       Where to save the files, instead of the profile's directory
+  --profile <name>  Profile from config.toml to use, instead of the default one
+";
+
+/// The options for `ytmdl download`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DownloadArgs {
+    pub youtube: String,
+    /// A Discogs, Musicbrainz or Bandcamp release, or an album folder
+    pub metadata: Option<String>,
+    pub out_dir: Option<PathBuf>,
+    pub profile: Option<String>,
+}
+
+/// What the command line asks for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliCommand {
+    /// No subcommand, so the GUI opens as usual
+    Gui,
+    Help,
+    Download(DownloadArgs),
+}
+
+impl CliCommand {
+    /// Parses the arguments after the program name. `--portable` is allowed anywhere, since it's
+    /// handled by [`paths::init`](crate::paths::init).
+    ///
+    /// Anything that doesn't start with a subcommand opens the GUI, like it always has.
+    ///
+    /// # Errors
+    /// If `download` is given arguments it doesn't know, is missing a value, or doesn't have
+    /// `--youtube`
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::cli::{CliCommand, DownloadArgs};
+    ///
+    /// let args = ["download", "--youtube", "https://youtu.be/abc", "--out=/music"].map(String::from);
+    /// let CliCommand::Download(download) = CliCommand::parse(args).unwrap() else {
+    ///     panic!("not a download");
+    /// };
+    /// assert_eq!(download.youtube, "https://youtu.be/abc");
+    /// assert_eq!(download.out_dir.unwrap().to_str(), Some("/music"));
+    /// ```
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut args = args.into_iter().filter(|arg| arg != "--portable");
+        match args.next().as_deref() {
+            Some("download") => parse_download(args).map(Self::Download),
+            Some("help" | "--help" | "-h") => Ok(Self::Help),
+            _ => Ok(Self::Gui),
+        }
+    }
+}
+
+fn parse_download(mut args: impl Iterator<Item = String>) -> Result<DownloadArgs, CliError> {
+    let mut download = DownloadArgs::default();
+    while let Some(arg) = args.next() {
+        // both "--out dir" and "--out=dir"
+        let (name, value) = match arg.split_once('=') {
+            Some((name, value)) => (name.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        let value = || {
+            value
+                .or_else(|| args.next())
+                .filter(|value| !value.trim().is_empty())
+                .ok_or_else(|| CliError::MissingValue(name.clone()))
+        };
+        match name.as_str() {
+            "--youtube" => download.youtube = value()?,
+            "--discogs" => download.metadata = Some(value()?),
+            "--out" => download.out_dir = Some(PathBuf::from(value()?)),
+            "--profile" => download.profile = Some(value()?),
+            _ => return Err(CliError::UnknownArgument(name)),
+        }
+    }
+    if download.youtube.trim().is_empty() {
+        return Err(CliError::MissingYoutube);
+    }
+    Ok(download)
+}
+
+/// Scrapes and downloads the album, printing what it's doing and what was saved. Progress for
+/// each track is logged as it would be with the GUI.
+///
+/// Returns whether every track was saved.
+///
+/// # Errors
+/// - If the profile doesn't exist
+/// - If there isn't a release and the playlist can't be scraped
+pub fn download(args: &DownloadArgs) -> Result<bool, CliError> {
+    let profile = match &args.profile {
+        Some(name) => Some(
+            config::current()
+                .profiles()
+                .into_iter()
+                .find(|profile| &profile.name == name)
+                .ok_or_else(|| CliError::UnknownProfile(name.clone()))?,
+        ),
+        None => None,
+    };
+
+    // it's still tried, since an outdated one often works
+    for problem in check_dependencies().problems() {
+        println!("{problem}");
+    }
+    println!("Scraping the album...");
+    let mut state = StateModifyingData::from_links(
+        args.youtube.clone(),
+        args.metadata.as_deref().unwrap_or_default(),
+    )?;
+    if let Some(profile) = profile {
+        state.profile = profile;
+    }
+    if let Some(out_dir) = &args.out_dir {
+        state.profile.out_dir = Some(out_dir.clone());
+    }
+    // there's no one to ask to fix it, so it goes without
+    state.check_image();
+    if let ImageStatus::Invalid(err) = &state.image_status {
+        println!("Not using the album cover: {err}");
+        state.album_data.image.clear();
+    }
+
+    let album = format!("{} - {}", state.album_data.artist, state.album_data.name);
+    println!(
+        "Downloading {album} ({} tracks)...",
+        state.tracks_to_download()
+    );
+    let result = crate::download_album(&state);
+    let results = StateResults::new(album, result.as_ref());
+
+    for file in &results.files {
+        println!("Saved {}: {}", file.title, file.path.display());
+        match &file.probe {
+            Ok(probe) if probe.is_low_bitrate() => println!("  {probe} (low bitrate)"),
+            Ok(probe) => println!("  {probe}"),
+            Err(err) => println!("  couldn't read it: {err}"),
+        }
+        if let Some(format) = &file.source_format {
+            println!("  downloaded as {format}");
+        }
+    }
+    if results.already_saved > 0 {
+        println!(
+            "Skipped {} tracks that were already downloaded",
+            results.already_saved
+        );
+    }
+    for failure in &results.failures {
+        println!("Failed: {}", failure.message);
+        if let Some(hint) = failure.hint {
+            println!("  Try: {hint}");
+        }
+    }
+    Ok(result.is_ok())
+}
+
+/// [`download`], as the process's exit code: 0 if everything was saved, 1 if anything failed
+#[must_use]
+pub fn run_download(args: &DownloadArgs) -> i32 {
+    match download(args) {
+        Ok(true) => 0,
+        Ok(false) => 1,
+        Err(err) => {
+            eprintln!("{err}");
+            1
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<CliCommand, CliError> {
+        CliCommand::parse(args.iter().map(|arg| (*arg).to_string()))
+    }
+
+    #[test]
+    fn parses_download() {
+        assert_eq!(
+            parse(&[
+                "--portable",
+                "download",
+                "--youtube",
+                "https://youtube.com/playlist?list=OLAK5uy_abc",
+                "--discogs",
+                "https://www.discogs.com/release/1",
+                "--profile=Phone",
+            ])
+            .unwrap(),
+            CliCommand::Download(DownloadArgs {
+                youtube: String::from("https://youtube.com/playlist?list=OLAK5uy_abc"),
+                metadata: Some(String::from("https://www.discogs.com/release/1")),
+                out_dir: None,
+                profile: Some(String::from("Phone")),
+            })
+        );
+    }
+
+    #[test]
+    fn bad_arguments() {
+        assert!(matches!(
+            parse(&["download", "--discogs", "https://www.discogs.com/release/1"]),
+            Err(CliError::MissingYoutube)
+        ));
+        assert!(matches!(
+            parse(&["download", "--youtube"]),
+            Err(CliError::MissingValue(name)) if name == "--youtube"
+        ));
+        assert!(matches!(
+            parse(&["download", "--youtube", "x", "--format", "flac"]),
+            Err(CliError::UnknownArgument(name)) if name == "--format"
+        ));
+    }
+
+    #[test]
+    fn gui_by_default() {
+        assert_eq!(parse(&[]).unwrap(), CliCommand::Gui);
+        assert_eq!(parse(&["--portable"]).unwrap(), CliCommand::Gui);
+        assert_eq!(parse(&["--help"]).unwrap(), CliCommand::Help);
+    }
+}
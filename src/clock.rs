@@ -0,0 +1,95 @@
+//! The current date and time, behind a trait so tests can use a fixed time
+
+use chrono::{DateTime, Datelike, Local, NaiveDate, SecondsFormat, TimeZone, Utc};
+
+pub trait Clock {
+    fn now(&self) -> DateTime<Utc>;
+
+    /// The date where the user is. [`Clock::now`]'s date in UTC unless overridden.
+    fn today(&self) -> NaiveDate {
+        self.now().date_naive()
+    }
+
+    fn year(&self) -> i32 {
+        self.today().year()
+    }
+
+    /// Seconds since the Unix epoch, or 0 if it's somehow before then
+    fn unix_time(&self) -> u64 {
+        u64::try_from(self.now().timestamp()).unwrap_or(0)
+    }
+}
+
+/// The real time, with [`Clock::today`] in the local timezone
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+
+    fn today(&self) -> NaiveDate {
+        Local::now().date_naive()
+    }
+}
+
+/// Always the same time, for tests
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl FixedClock {
+    /// # Panics
+    /// If `secs` is out of range for a date
+    #[must_use]
+    pub fn at_unix_time(secs: i64) -> Self {
+        Self(
+            Utc.timestamp_opt(secs, 0)
+                .single()
+                .expect("timestamp out of range"),
+        )
+    }
+}
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
+}
+
+/// A Unix timestamp as an RFC 3339 date and time in UTC
+///
+/// # Examples
+/// ```
+/// use ytmdl::clock::format_unix_time;
+///
+/// assert_eq!(format_unix_time(1_700_000_000), "2023-11-14T22:13:20Z");
+/// ```
+#[must_use]
+pub fn format_unix_time(secs: u64) -> String {
+    i64::try_from(secs)
+        .ok()
+        .and_then(|secs| Utc.timestamp_opt(secs, 0).single())
+        .map_or_else(String::new, |time| {
+            time.to_rfc3339_opts(SecondsFormat::Secs, true)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn year_around_new_year() {
+        // 2023-12-31T23:59:59Z and a second later
+        assert_eq!(FixedClock::at_unix_time(1_704_067_199).year(), 2023);
+        assert_eq!(FixedClock::at_unix_time(1_704_067_200).year(), 2024);
+        // leap day
+        let leap_day = FixedClock::at_unix_time(1_709_164_800);
+        assert_eq!(
+            leap_day.today(),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert_eq!(leap_day.unix_time(), 1_709_164_800);
+    }
+}
@@ -0,0 +1,243 @@
+//! Comparing what the release says about an album with what its Youtube playlist says, so where
+//! they disagree can be settled one field at a time instead of the release always winning
+
+use crate::{
+    gui::view_modifying_data::StateModifyingData,
+    parsing::{format_duration, parse_duration},
+    scraping::{AlbumMetadata, Confidence},
+};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Track lengths closer than this are the same, since Youtube rounds to the second and releases
+/// often do too
+const LENGTH_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Which source a field is taken from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Pick {
+    /// The Discogs, Musicbrainz or Bandcamp release, which is what the fields start as
+    #[default]
+    Release,
+    Youtube,
+}
+
+/// A field the sources disagree on. Tracks are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictField {
+    AlbumName,
+    Artist,
+    Year,
+    TrackName(usize),
+    TrackLength(usize),
+}
+
+impl Display for ConflictField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlbumName => f.write_str("Album name"),
+            Self::Artist => f.write_str("Artist"),
+            Self::Year => f.write_str("Year"),
+            Self::TrackName(i) => write!(f, "Track {}", i + 1),
+            Self::TrackLength(i) => write!(f, "Track {}'s length", i + 1),
+        }
+    }
+}
+
+/// What each source has for a field, and which one's being used
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Conflict {
+    pub field: ConflictField,
+    pub release: String,
+    pub youtube: String,
+    pub picked: Pick,
+}
+
+impl Conflict {
+    fn new(field: ConflictField, release: &str, youtube: &str) -> Self {
+        Self {
+            field,
+            release: release.to_string(),
+            youtube: youtube.to_string(),
+            picked: Pick::Release,
+        }
+    }
+
+    /// Sets the field to what `pick` has for it
+    pub fn pick(&mut self, pick: Pick, state: &mut StateModifyingData) {
+        self.picked = pick;
+        let value = match pick {
+            Pick::Release => self.release.clone(),
+            Pick::Youtube => self.youtube.clone(),
+        };
+        match self.field {
+            ConflictField::AlbumName => {
+                state.album_data.name = value;
+                state.confidence.title = Confidence::Edited;
+            }
+            ConflictField::Artist => {
+                state.album_data.artist = value;
+                state.confidence.artist = Confidence::Edited;
+            }
+            ConflictField::Year => {
+                if let Ok(year) = value.parse() {
+                    state.album_data.year = year;
+                    state.confidence.year = Confidence::Edited;
+                }
+            }
+            ConflictField::TrackName(i) => {
+                if let Some(track) = state.track_data.get_mut(i) {
+                    track.name = value;
+                    track.confidence = Confidence::Edited;
+                }
+            }
+            ConflictField::TrackLength(i) => {
+                if let Some(track) = state.track_data.get_mut(i) {
+                    track.length = parse_duration(&value);
+                }
+            }
+        }
+    }
+}
+
+/// Where `youtube` (from [`AlbumMetadata::from`] the playlist) disagrees with `release`, skipping
+/// anything either of them doesn't have. `video_for_track` gives which of the playlist's videos
+/// each of the release's tracks is downloaded from, since they might not be in the same order.
+///
+/// # Examples
+/// ```
+/// use ytmdl::{
+///     compare::{compare, ConflictField},
+///     scraping::{AlbumMetadata, TrackMetadata},
+/// };
+///
+/// let track = |title: &str| TrackMetadata {
+///     title: title.to_string(),
+///     ..TrackMetadata::default()
+/// };
+/// let release = AlbumMetadata {
+///     title: String::from("Album"),
+///     tracks: vec![track("One"), track("Two")],
+///     ..AlbumMetadata::default()
+/// };
+/// let youtube = AlbumMetadata {
+///     title: String::from("ALBUM"),
+///     tracks: vec![track("Two (Official Audio)"), track("One")],
+///     ..AlbumMetadata::default()
+/// };
+///
+/// let conflicts = compare(&release, &youtube, |i| Some(1 - i));
+/// assert_eq!(conflicts.len(), 1);
+/// assert_eq!(conflicts[0].field, ConflictField::TrackName(1));
+/// assert_eq!(conflicts[0].youtube, "Two (Official Audio)");
+/// ```
+#[must_use]
+pub fn compare(
+    release: &AlbumMetadata,
+    youtube: &AlbumMetadata,
+    video_for_track: impl Fn(usize) -> Option<usize>,
+) -> Vec<Conflict> {
+    let text = |field: ConflictField, release: &str, youtube: &str| {
+        let (release, youtube) = (release.trim(), youtube.trim());
+        (!release.is_empty() && !youtube.is_empty() && !release.eq_ignore_ascii_case(youtube))
+            .then(|| Conflict::new(field, release, youtube))
+    };
+
+    let mut conflicts: Vec<Conflict> = [
+        text(ConflictField::AlbumName, &release.title, &youtube.title),
+        text(ConflictField::Artist, &release.artist, &youtube.artist),
+        release
+            .year()
+            .zip(youtube.year())
+            .filter(|(release, youtube)| release != youtube)
+            .map(|(release, youtube)| {
+                Conflict::new(
+                    ConflictField::Year,
+                    &release.to_string(),
+                    &youtube.to_string(),
+                )
+            }),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    for (i, track) in release.tracks.iter().enumerate() {
+        let Some(video) = video_for_track(i).and_then(|video| youtube.tracks.get(video)) else {
+            continue;
+        };
+        conflicts.extend(text(
+            ConflictField::TrackName(i),
+            &track.title,
+            &video.title,
+        ));
+        if let (Some(release), Some(youtube)) = (track.length, video.length) {
+            if release.abs_diff(youtube) > LENGTH_TOLERANCE {
+                conflicts.push(Conflict::new(
+                    ConflictField::TrackLength(i),
+                    &format_duration(release),
+                    &format_duration(youtube),
+                ));
+            }
+        }
+    }
+    conflicts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gui::view_modifying_data::TrackData, scraping::TrackMetadata};
+
+    fn track(title: &str, secs: u64) -> TrackMetadata {
+        TrackMetadata {
+            title: title.to_string(),
+            artist: None,
+            length: Some(Duration::from_secs(secs)),
+        }
+    }
+
+    #[test]
+    fn pick_either_side() {
+        let release = AlbumMetadata {
+            title: String::from("Album"),
+            artist: String::from("Band"),
+            year: Some(2019),
+            tracks: vec![track("One", 200), track("Two", 185)],
+            ..AlbumMetadata::default()
+        };
+        let youtube = AlbumMetadata {
+            title: String::from("Album (Remastered)"),
+            artist: String::from("band"),
+            tracks: vec![track("One", 201), track("Two (Live)", 410)],
+            ..AlbumMetadata::default()
+        };
+        let mut conflicts = compare(&release, &youtube, Some);
+        let fields: Vec<ConflictField> = conflicts.iter().map(|c| c.field).collect();
+        assert_eq!(
+            fields,
+            [
+                ConflictField::AlbumName,
+                ConflictField::TrackName(1),
+                ConflictField::TrackLength(1)
+            ]
+        );
+
+        let mut state = StateModifyingData {
+            track_data: vec![TrackData::new("One"), TrackData::new("Two")],
+            ..StateModifyingData::default()
+        };
+        state.album_data.name = String::from("Album");
+        conflicts[1].pick(Pick::Youtube, &mut state);
+        conflicts[2].pick(Pick::Youtube, &mut state);
+        assert_eq!(state.track_data[1].name, "Two (Live)");
+        assert_eq!(state.track_data[1].length, Some(Duration::from_secs(410)));
+        assert_eq!(conflicts[1].picked, Pick::Youtube);
+
+        conflicts[1].pick(Pick::Release, &mut state);
+        assert_eq!(state.track_data[1].name, "Two");
+        assert_eq!(state.album_data.name, "Album");
+    }
+}
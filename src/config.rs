@@ -0,0 +1,1037 @@
+use crate::{
+    album_art::UpscaleSettings,
+    analysis::AnalysisSettings,
+    network::NetworkSettings,
+    non_music::NonMusicRules,
+    paths,
+    postprocess::{PostProcessSettings, PostStep},
+    report::EncodeSettings,
+    romanize::RomanizeSettings,
+    scraping::LyricsSettings,
+    titles::TitleRules,
+    utils::{ascii_file_name, fill_template},
+    watch::WatchSettings,
+};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::RwLock,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    DeserializeError(#[from] toml::de::Error),
+    #[error("{0}")]
+    SerializeError(#[from] toml::ser::Error),
+    #[error("couldn't determine the config directory")]
+    NoConfigDir,
+    #[error("the config's version isn't a version: {0}")]
+    BadVersion(String),
+    #[error("the config file is from a newer version of ytmdl ({0}), so it wasn't overwritten")]
+    NewerVersion(u32),
+}
+
+/// The version of the config file's layout. When a setting is renamed or moved, this goes up by
+/// one and a migration is added to [`MIGRATIONS`], so older files keep their settings.
+pub const CONFIG_VERSION: u32 = 1;
+
+/// The migration at each index changes a config file from that version to the next one
+const MIGRATIONS: &[fn(&mut toml::Table)] = &[
+    // files from before there were versions, which are already laid out like version 1
+    |_| {},
+];
+
+/// The audio format the final files are saved as
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Mp3,
+    Flac,
+    Opus,
+    /// AAC in an MP4 container
+    M4a,
+    /// Uncompressed, tagged with ID3 in an `ID3 ` chunk
+    Aiff,
+    /// Uncompressed, with just the basic tags in a `LIST`/`INFO` chunk and no cover
+    Wav,
+}
+
+impl OutputFormat {
+    pub const ALL: &'static [Self] = &[
+        Self::Mp3,
+        Self::Flac,
+        Self::Opus,
+        Self::M4a,
+        Self::Aiff,
+        Self::Wav,
+    ];
+
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Mp3 => "mp3",
+            Self::Flac => "flac",
+            Self::Opus => "opus",
+            Self::M4a => "m4a",
+            Self::Aiff => "aiff",
+            Self::Wav => "wav",
+        }
+    }
+
+    /// The ffmpeg encoder it's converted with
+    #[must_use]
+    pub fn encoder(self) -> &'static str {
+        match self {
+            Self::Mp3 => "libmp3lame",
+            Self::Flac => "flac",
+            Self::Opus => "libopus",
+            Self::M4a => "aac",
+            // 16-bit, like a CD
+            Self::Aiff => "pcm_s16be",
+            Self::Wav => "pcm_s16le",
+        }
+    }
+
+    /// Lossless formats ignore [`Profile::bitrate`] and [`Profile::vbr_quality`]
+    #[must_use]
+    pub fn is_lossless(self) -> bool {
+        matches!(self, Self::Flac | Self::Aiff | Self::Wav)
+    }
+
+    /// Whether it's tagged with ID3, otherwise ffmpeg writes the tags (see [`crate::tagging`])
+    #[must_use]
+    pub fn uses_id3(self) -> bool {
+        matches!(self, Self::Mp3 | Self::Aiff)
+    }
+
+    /// Whether it can have the cover embedded at all
+    #[must_use]
+    pub fn has_cover(self) -> bool {
+        self != Self::Wav
+    }
+
+    /// Whether ffmpeg attaches the cover as a picture stream, rather than it going in the ID3 tag
+    /// or (for Opus) a comment
+    #[must_use]
+    pub fn attaches_cover(self) -> bool {
+        matches!(self, Self::Flac | Self::M4a)
+    }
+}
+
+impl Display for OutputFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Mp3 => "MP3",
+            Self::Flac => "FLAC",
+            Self::Opus => "Opus",
+            Self::M4a => "M4A (AAC)",
+            Self::Aiff => "AIFF",
+            Self::Wav => "WAV (no cover, basic tags)",
+        })
+    }
+}
+
+/// Which version of ID3 tags to write
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagVersion {
+    Id3v23,
+    #[default]
+    Id3v24,
+}
+
+impl From<TagVersion> for id3::Version {
+    fn from(version: TagVersion) -> Self {
+        match version {
+            TagVersion::Id3v23 => Self::Id3v23,
+            TagVersion::Id3v24 => Self::Id3v24,
+        }
+    }
+}
+
+/// A choice between a constant bitrate and a VBR level, for picking one in the GUI
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quality {
+    /// Leave it up to ffmpeg
+    Default,
+    /// In kbps
+    Bitrate(u32),
+    /// LAME's `-q:a` level
+    Vbr(u8),
+}
+
+impl Quality {
+    pub const WORST_VBR: u8 = 9;
+
+    /// What's worth offering for `format`
+    #[must_use]
+    pub fn choices(format: OutputFormat) -> Vec<Self> {
+        let mut choices = vec![Self::Default];
+        if format.is_lossless() {
+            return choices;
+        }
+        if format == OutputFormat::Mp3 {
+            choices.extend([Self::Vbr(0), Self::Vbr(2), Self::Vbr(5)]);
+        }
+        choices.extend([320, 256, 192, 128, 96].map(Self::Bitrate));
+        choices
+    }
+}
+
+impl Display for Quality {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => f.write_str("Default"),
+            Self::Bitrate(bitrate) => write!(f, "{bitrate} kbps"),
+            Self::Vbr(0) => f.write_str("VBR V0 (best)"),
+            Self::Vbr(quality) => write!(f, "VBR V{quality}"),
+        }
+    }
+}
+
+/// A named set of output settings that can be picked per download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Profile {
+    pub name: String,
+    pub format: OutputFormat,
+    /// In kbps, `None` leaves it up to ffmpeg
+    pub bitrate: Option<u32>,
+    /// LAME's VBR level for MP3s (ffmpeg's `-q:a`), from 0 (best) to 9 (smallest), used instead of
+    /// the bitrate
+    pub vbr_quality: Option<u8>,
+    /// See [`Profile::file_name`] for the placeholders
+    pub naming_template: String,
+    /// Falls back to `YTMDL_OUT_DIR` if not set
+    pub out_dir: Option<PathBuf>,
+    /// Names of other profiles to also output each track as, from the same download
+    pub also: Vec<String>,
+    /// Also write a [`TrackInfo`](crate::report::TrackInfo) sidecar next to each track
+    pub info_json: bool,
+    /// Older players can't read ID3v2.4
+    pub tag_version: TagVersion,
+    /// Make file names ASCII with [`ascii_file_name`]
+    pub ascii_file_names: bool,
+    /// Also save the album art as `folder.jpg` in the output dir
+    pub folder_jpg: bool,
+    /// In KB; bigger album art is shrunk (and made a JPEG) before it's embedded
+    pub max_art_kb: Option<u32>,
+    /// In pixels; album art with a bigger width or height is scaled down (and made a JPEG)
+    /// before it's embedded. [`Profile::DEFAULT_MAX_ART_PX`] unless it's set, and 0 embeds it at
+    /// any size (see [`Profile::art_px_limit`]).
+    pub max_art_px: Option<u32>,
+    /// Steps to run on each track as it's converted, in order (see [`crate::postprocess`])
+    pub post_process: Vec<PostStep>,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        Self {
+            name: String::from("Default"),
+            format: OutputFormat::default(),
+            bitrate: None,
+            vbr_quality: None,
+            naming_template: String::from(Self::DEFAULT_NAMING_TEMPLATE),
+            out_dir: None,
+            also: Vec::new(),
+            info_json: false,
+            tag_version: TagVersion::default(),
+            ascii_file_names: false,
+            folder_jpg: false,
+            max_art_kb: None,
+            max_art_px: Some(Self::DEFAULT_MAX_ART_PX),
+            post_process: vec![PostStep::Analyze],
+        }
+    }
+}
+
+impl Profile {
+    pub const DEFAULT_NAMING_TEMPLATE: &'static str = "{artist} - {album} - {title}";
+    pub const LEGACY_DEVICE: &'static str = "Older devices (iPod)";
+    /// Big enough for any player's cover view, while a 3000px PNG from a release page would add
+    /// megabytes to every track
+    pub const DEFAULT_MAX_ART_PX: u32 = 1000;
+
+    /// Bundled profile for iPods and other older devices, which struggle with ID3v2.4, non-ASCII
+    /// file names and big album art, and look for a `folder.jpg`
+    #[must_use]
+    pub fn legacy_device() -> Self {
+        Self {
+            name: String::from(Self::LEGACY_DEVICE),
+            bitrate: Some(192),
+            naming_template: String::from("{track} {title}"),
+            tag_version: TagVersion::Id3v23,
+            ascii_file_names: true,
+            folder_jpg: true,
+            max_art_kb: Some(300),
+            ..Self::default()
+        }
+    }
+
+    /// [`Profile::max_art_px`], or `None` if album art shouldn't be scaled down at all
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::config::Profile;
+    ///
+    /// assert_eq!(Profile::default().art_px_limit(), Some(Profile::DEFAULT_MAX_ART_PX));
+    /// let any_size = Profile {
+    ///     max_art_px: Some(0),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(any_size.art_px_limit(), None);
+    /// ```
+    #[must_use]
+    pub fn art_px_limit(&self) -> Option<u32> {
+        self.max_art_px.filter(|px| *px > 0)
+    }
+
+    /// Fills in the naming template (`{artist}`, `{album}`, `{track}` and `{title}`),
+    /// and adds the extension for the format.
+    ///
+    /// Doesn't sanitize the name.
+    ///
+    /// # Examples
+    /// ```
+    /// let profile = ytmdl::config::Profile {
+    ///     naming_template: String::from("{track}. {title}"),
+    ///     ..Default::default()
+    /// };
+    /// assert_eq!(profile.file_name("Artist", "Album", 3, "Song"), "03. Song.mp3");
+    /// ```
+    #[must_use]
+    pub fn file_name(&self, artist: &str, album: &str, track: usize, title: &str) -> String {
+        let track = format!("{track:02}");
+        let mut name = fill_template(
+            &self.naming_template,
+            &[
+                ("artist", artist),
+                ("album", album),
+                ("track", &track),
+                ("title", title),
+            ],
+        );
+        if self.ascii_file_names {
+            name = ascii_file_name(&name).into_owned();
+        }
+        name.push('.');
+        name.push_str(self.format.extension());
+        name
+    }
+
+    /// How tracks are encoded: with [`Profile::vbr_quality`] or [`Profile::bitrate`], or if
+    /// neither is set `YTMDL_VBR_QUALITY` or `YTMDL_BITRATE`. Only MP3s can be VBR, and lossless
+    /// formats don't have either.
+    #[must_use]
+    pub fn encode_settings(&self) -> EncodeSettings {
+        fn from_env<T: FromStr>(name: &str) -> Option<T> {
+            env::var(name).ok()?.trim().parse().ok()
+        }
+
+        let (mut bitrate, mut vbr_quality) = (self.bitrate, self.vbr_quality);
+        if bitrate.is_none() && vbr_quality.is_none() {
+            bitrate = from_env("YTMDL_BITRATE").filter(|&kbps| kbps > 0);
+            vbr_quality = from_env("YTMDL_VBR_QUALITY");
+        }
+        if self.format.is_lossless() {
+            bitrate = None;
+            vbr_quality = None;
+        } else if self.format != OutputFormat::Mp3 {
+            vbr_quality = None;
+        } else if vbr_quality.is_some() {
+            bitrate = None;
+        }
+        EncodeSettings {
+            format: self.format,
+            bitrate,
+            vbr_quality: vbr_quality.map(|quality| quality.min(Quality::WORST_VBR)),
+        }
+    }
+
+    /// [`Profile::bitrate`] and [`Profile::vbr_quality`] as one setting
+    #[must_use]
+    pub fn quality(&self) -> Quality {
+        match (self.vbr_quality, self.bitrate) {
+            (Some(quality), _) => Quality::Vbr(quality),
+            (None, Some(bitrate)) => Quality::Bitrate(bitrate),
+            (None, None) => Quality::Default,
+        }
+    }
+
+    pub fn set_quality(&mut self, quality: Quality) {
+        (self.vbr_quality, self.bitrate) = match quality {
+            Quality::Default => (None, None),
+            Quality::Bitrate(bitrate) => (None, Some(bitrate)),
+            Quality::Vbr(quality) => (Some(quality), None),
+        };
+    }
+
+    /// Where the files go: [`Profile::out_dir`], then `YTMDL_OUT_DIR`, then `ytmdl` in the working
+    /// directory
+    #[must_use]
+    pub fn resolved_out_dir(&self) -> PathBuf {
+        self.out_dir.clone().unwrap_or_else(|| {
+            env::var("YTMDL_OUT_DIR").map_or_else(
+                |_| {
+                    let mut p = env::current_dir().unwrap_or_default();
+                    p.push("ytmdl");
+                    p
+                },
+                PathBuf::from,
+            )
+        })
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.name)
+    }
+}
+
+/// How big the GUI is drawn
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct UiSettings {
+    /// Percentage to scale the whole GUI by, on top of the system's display scaling
+    pub scale: u16,
+    /// Default text size in pixels, before scaling (only applied on startup)
+    pub text_size: u16,
+    /// High-contrast theme with bigger buttons and text inputs
+    pub accessible: bool,
+}
+
+impl Default for UiSettings {
+    fn default() -> Self {
+        Self {
+            scale: 100,
+            text_size: 16,
+            accessible: false,
+        }
+    }
+}
+
+impl UiSettings {
+    pub const SCALE_RANGE: RangeInclusive<u16> = 50..=300;
+    pub const TEXT_SIZE_RANGE: RangeInclusive<u16> = 10..=40;
+
+    #[must_use]
+    pub fn scale_factor(self) -> f64 {
+        f64::from(
+            self.scale
+                .clamp(*Self::SCALE_RANGE.start(), *Self::SCALE_RANGE.end()),
+        ) / 100.0
+    }
+
+    #[must_use]
+    pub fn text_size(self) -> f32 {
+        f32::from(
+            self.text_size
+                .clamp(*Self::TEXT_SIZE_RANGE.start(), *Self::TEXT_SIZE_RANGE.end()),
+        )
+    }
+}
+
+/// Downloading with aria2c, which opens several connections at once so it isn't slowed down by ISPs
+/// that throttle single connections to Youtube
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DownloaderSettings {
+    /// Have yt-dlp download with aria2c, if it's installed
+    pub aria2c: bool,
+    /// How many connections aria2c opens for each track
+    pub connections: u8,
+    /// yt-dlp's `-f` format selection, tried from left to right. Empty leaves it up to yt-dlp.
+    pub format: String,
+}
+
+impl Default for DownloaderSettings {
+    fn default() -> Self {
+        Self {
+            aria2c: false,
+            connections: 8,
+            format: String::from(Self::DEFAULT_FORMAT),
+        }
+    }
+}
+
+impl DownloaderSettings {
+    /// aria2c's own limit
+    pub const CONNECTIONS_RANGE: RangeInclusive<u8> = 1..=16;
+
+    /// Youtube's Opus audio (format 251), then its AAC audio (140), then whatever's the best
+    /// audio, then the best video with audio if there isn't any audio on its own
+    pub const DEFAULT_FORMAT: &'static str = "251/140/bestaudio/best";
+
+    /// The `-f` argument to give yt-dlp, which is empty if the format's left up to yt-dlp
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::config::DownloaderSettings;
+    ///
+    /// assert_eq!(
+    ///     DownloaderSettings::default().format_args(),
+    ///     ["-f", "251/140/bestaudio/best"]
+    /// );
+    /// let settings = DownloaderSettings { format: String::from(" "), ..Default::default() };
+    /// assert!(settings.format_args().is_empty());
+    /// ```
+    #[must_use]
+    pub fn format_args(&self) -> Vec<String> {
+        match self.format.trim() {
+            "" => Vec::new(),
+            format => vec![String::from("-f"), format.to_string()],
+        }
+    }
+
+    /// The arguments to give yt-dlp, which are empty without aria2c
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::config::DownloaderSettings;
+    ///
+    /// let settings = DownloaderSettings { aria2c: true, connections: 4, ..Default::default() };
+    /// assert_eq!(
+    ///     settings.ytdlp_args(),
+    ///     ["--downloader", "aria2c", "--downloader-args", "aria2c:-x 4 -s 4 -k 1M"]
+    /// );
+    /// assert!(DownloaderSettings::default().ytdlp_args().is_empty());
+    /// ```
+    #[must_use]
+    pub fn ytdlp_args(&self) -> Vec<String> {
+        if !self.aria2c {
+            return Vec::new();
+        }
+        let connections = self.connections.clamp(
+            *Self::CONNECTIONS_RANGE.start(),
+            *Self::CONNECTIONS_RANGE.end(),
+        );
+        vec![
+            String::from("--downloader"),
+            String::from("aria2c"),
+            String::from("--downloader-args"),
+            format!("aria2c:-x {connections} -s {connections} -k 1M"),
+        ]
+    }
+}
+
+static CURRENT: Lazy<RwLock<Config>> = Lazy::new(|| {
+    RwLock::new(Config::load().unwrap_or_else(|err| {
+        log::error!("couldn't load config: {err}");
+        Config::default()
+    }))
+});
+
+/// The config as it was loaded at startup or last saved
+#[must_use]
+pub fn current() -> Config {
+    CURRENT
+        .read()
+        .map(|config| config.clone())
+        .unwrap_or_default()
+}
+
+/// Settings saved to `config.toml` in [`paths::config_dir`] (e.g. `~/.config/ytmdl/`)
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The [`CONFIG_VERSION`] the file was saved with, which is 0 if it's from before there were
+    /// versions
+    pub version: u32,
+    /// Where the final files go; overridden by `YTMDL_OUT_DIR`
+    pub out_dir: Option<PathBuf>,
+    pub format: OutputFormat,
+    /// See [`Profile::file_name`]; like `out_dir` and `format`, only used without any `profiles`
+    pub naming_template: Option<String>,
+    /// Skip tracks that are already in the output dir rather than replacing them; overridden by
+    /// `YTMDL_OVERWRITE`
+    pub keep_existing_files: bool,
+    /// Only skip a track that's already in the output dir if its title and track number tags are
+    /// the track's, replacing it otherwise
+    pub verify_existing_files: bool,
+    /// How many tracks are analyzed, converted and tagged at once, 0 for one per CPU core (only
+    /// applied on startup)
+    pub threads: u8,
+    /// How many tracks are downloaded at once, separately from `threads`, 0 for one per CPU core
+    /// (only applied on startup)
+    pub download_threads: u8,
+    /// How many albums the queue downloads at once, 0 or 1 for one at a time (only applied on
+    /// startup). They share the `threads` and `download_threads` between them.
+    pub albums_at_once: u8,
+    pub profiles: Vec<Profile>,
+    /// Name of the profile that's selected by default
+    pub default_profile: Option<String>,
+    pub ui: UiSettings,
+    pub analysis: AnalysisSettings,
+    pub post_process: PostProcessSettings,
+    pub watch: WatchSettings,
+    /// Applied to the track titles on the modify screen
+    pub titles: TitleRules,
+    /// Which playlist videos aren't songs, so their tracks start out unticked
+    pub non_music: NonMusicRules,
+    pub romanize: RomanizeSettings,
+    /// Upscales covers that are too small
+    pub art_upscale: UpscaleSettings,
+    pub lyrics: LyricsSettings,
+    pub downloader: DownloaderSettings,
+    /// A proxy and certificates for networks that need them
+    pub network: NetworkSettings,
+    /// Personal access token for browsing the user's Discogs collection and wantlist
+    pub discogs_token: Option<String>,
+    /// Don't look on Github for a newer version at startup
+    pub skip_update_check: bool,
+}
+
+impl Config {
+    /// Where the config file is (or would be)
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        let mut path = paths::config_dir()?;
+        path.push("config.toml");
+        Some(path)
+    }
+
+    /// Whether the config file has been written yet (i.e. if it's not the first run)
+    #[must_use]
+    pub fn exists() -> bool {
+        Self::path().map_or(false, |path| path.exists())
+    }
+
+    /// Loads the config file, or the default config if there isn't one yet.
+    ///
+    /// A file from an older version is migrated to [`CONFIG_VERSION`] and saved again, after
+    /// it's copied to `config.v<version>.toml` next to it in case anything goes wrong.
+    ///
+    /// # Errors
+    /// - If the file exists but can't be read
+    /// - If the file isn't valid TOML, or its version isn't a number
+    /// - If it needs migrating and it can't be backed up or saved
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::load_from(&Self::path().ok_or(ConfigError::NoConfigDir)?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self, ConfigError> {
+        let mut table: toml::Table = match fs::read_to_string(path) {
+            Ok(s) => toml::from_str(&s)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err.into()),
+        };
+        let version = migrate(&mut table)?;
+        let config: Self = table.try_into()?;
+        if version < CONFIG_VERSION {
+            log::info!("migrating the config from version {version} to {CONFIG_VERSION}");
+            fs::copy(path, path.with_file_name(format!("config.v{version}.toml")))?;
+            config.write(path)?;
+        }
+        Ok(config)
+    }
+
+    /// The configured profiles, or just one made from [`Config::out_dir`], [`Config::format`] and
+    /// [`Config::naming_template`] if there aren't any, followed by [`Profile::legacy_device`]
+    /// unless one's been configured with its name.
+    #[must_use]
+    pub fn profiles(&self) -> Vec<Profile> {
+        let mut profiles = if self.profiles.is_empty() {
+            let mut profile = Profile {
+                format: self.format,
+                out_dir: self.out_dir.clone(),
+                ..Profile::default()
+            };
+            if let Some(template) = self.naming_template.as_deref().map(str::trim) {
+                if !template.is_empty() {
+                    profile.naming_template = template.to_string();
+                }
+            }
+            vec![profile]
+        } else {
+            self.profiles.clone()
+        };
+        if !profiles.iter().any(|p| p.name == Profile::LEGACY_DEVICE) {
+            profiles.push(Profile {
+                out_dir: self.out_dir.clone(),
+                ..Profile::legacy_device()
+            });
+        }
+        profiles
+    }
+
+    /// The profile named by [`Config::default_profile`], or the first one
+    #[must_use]
+    pub fn default_profile(&self) -> Profile {
+        let mut profiles = self.profiles();
+        let i = self
+            .default_profile
+            .as_ref()
+            .and_then(|name| profiles.iter().position(|p| &p.name == name))
+            .unwrap_or_default();
+        profiles.swap_remove(i)
+    }
+
+    /// The given profile, followed by all the profiles in its [`Profile::also`]
+    #[must_use]
+    pub fn outputs_for(&self, profile: &Profile) -> Vec<Profile> {
+        let profiles = self.profiles();
+        let mut outputs = vec![profile.clone()];
+        for name in &profile.also {
+            if outputs.iter().any(|p| &p.name == name) {
+                continue;
+            }
+            if let Some(other) = profiles.iter().find(|p| &p.name == name) {
+                outputs.push(other.clone());
+            } else {
+                log::warn!(r#"profile "{name}" doesn't exist"#);
+            }
+        }
+        outputs
+    }
+
+    /// Writes the config file, creating its directory if needed, and makes it the [`current`] one.
+    ///
+    /// # Errors
+    /// - If the config dir can't be determined or created
+    /// - If the file there is from a newer [`CONFIG_VERSION`], whose settings would be lost
+    /// - If the file can't be written
+    pub fn save(&self) -> Result<(), ConfigError> {
+        let path = Self::path().ok_or(ConfigError::NoConfigDir)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        self.write(&path)?;
+        if let Ok(mut current) = CURRENT.write() {
+            *current = self.clone();
+        }
+        Ok(())
+    }
+
+    /// Writes the file as the current [`CONFIG_VERSION`], whatever version it was loaded as, unless
+    /// the file that's there is from a newer version
+    fn write(&self, path: &Path) -> Result<(), ConfigError> {
+        let saved_version = fs::read_to_string(path)
+            .ok()
+            .and_then(|saved| toml::from_str::<toml::Table>(&saved).ok())
+            .and_then(|table| table.get("version")?.as_integer())
+            .and_then(|version| u32::try_from(version).ok());
+        if let Some(version) = saved_version.filter(|version| *version > CONFIG_VERSION) {
+            return Err(ConfigError::NewerVersion(version));
+        }
+        let config = Self {
+            version: CONFIG_VERSION,
+            ..self.clone()
+        };
+        fs::write(path, toml::to_string_pretty(&config)?)?;
+        Ok(())
+    }
+
+    /// Whether files already in the output dir are replaced: `YTMDL_OVERWRITE` if it's set,
+    /// otherwise the opposite of [`Config::keep_existing_files`]
+    #[must_use]
+    pub fn overwrite(&self) -> bool {
+        env::var("YTMDL_OVERWRITE").map_or(!self.keep_existing_files, |v| v.as_str() == "true")
+    }
+
+    /// Sets the env vars the rest of the app reads from, without overriding any that are already set.
+    pub fn apply_to_env(&self) {
+        if let Some(out_dir) = &self.out_dir {
+            if env::var_os("YTMDL_OUT_DIR").is_none() {
+                env::set_var("YTMDL_OUT_DIR", out_dir);
+            }
+        }
+    }
+}
+
+/// Runs the [`MIGRATIONS`] a config file's table needs to be [`CONFIG_VERSION`], returning the
+/// version it was. A file from a newer version is left as it is, since there's no knowing what's
+/// changed; anything this version doesn't know about in it is ignored.
+///
+/// # Errors
+/// If the version isn't a number
+fn migrate(table: &mut toml::Table) -> Result<u32, ConfigError> {
+    let version = match table.get("version") {
+        None => 0,
+        Some(toml::Value::Integer(version)) => {
+            u32::try_from(*version).map_err(|_| ConfigError::BadVersion(version.to_string()))?
+        }
+        Some(version) => return Err(ConfigError::BadVersion(version.to_string())),
+    };
+    if version > CONFIG_VERSION {
+        log::warn!("the config is from a newer version of ytmdl ({version}), so some settings may be ignored");
+        return Ok(version);
+    }
+    for migration in &MIGRATIONS[version as usize..] {
+        migration(table);
+    }
+    table.insert(
+        String::from("version"),
+        toml::Value::Integer(CONFIG_VERSION.into()),
+    );
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    #[test]
+    fn round_trip() {
+        let config = Config {
+            version: CONFIG_VERSION,
+            out_dir: Some(PathBuf::from("/music/ytmdl")),
+            format: OutputFormat::Mp3,
+            naming_template: Some(String::from("{track} {title}")),
+            keep_existing_files: true,
+            verify_existing_files: true,
+            threads: 4,
+            download_threads: 8,
+            albums_at_once: 2,
+            profiles: vec![Profile {
+                name: String::from("Phone"),
+                bitrate: Some(128),
+                naming_template: String::from("{artist} - {title}"),
+                out_dir: Some(PathBuf::from("/sync")),
+                post_process: vec![PostStep::TrimSilence, PostStep::ReplayGain],
+                ..Profile::default()
+            }],
+            default_profile: Some(String::from("Phone")),
+            ui: UiSettings {
+                scale: 150,
+                text_size: 20,
+                accessible: true,
+            },
+            analysis: AnalysisSettings {
+                bpm: true,
+                key: true,
+            },
+            post_process: PostProcessSettings {
+                loudness: -16,
+                silence_threshold: -60,
+            },
+            watch: WatchSettings {
+                urls: vec![String::from("https://www.youtube.com/@artist")],
+                interval_hours: 6,
+            },
+            titles: TitleRules {
+                feat_brackets: crate::titles::BracketStyle::Round,
+                standardize_feat: true,
+                ..TitleRules::default()
+            },
+            non_music: NonMusicRules {
+                keywords: vec![String::from("Teaser"), String::from("Highlight Medley")],
+                min_secs: 30,
+                ..NonMusicRules::default()
+            },
+            romanize: RomanizeSettings {
+                file_names: true,
+                tags: false,
+                command: Some(String::from("kakasi -i utf8 -o utf8 -Ja -Ha -Ka -s")),
+            },
+            lyrics: LyricsSettings {
+                embed: true,
+                synced: true,
+                lrc_file: true,
+                api_url: Some(String::from("http://localhost:3000/api")),
+            },
+            art_upscale: UpscaleSettings {
+                command: Some(String::from(
+                    "realesrgan-ncnn-vulkan -i {input} -o {output}",
+                )),
+                api_url: None,
+                min_size: 600,
+            },
+            downloader: DownloaderSettings {
+                aria2c: true,
+                connections: 16,
+                format: String::from("bestaudio[ext=m4a]/bestaudio"),
+            },
+            network: NetworkSettings {
+                proxy: Some(String::from("http://proxy.example.com:8080")),
+                no_proxy: Some(String::from("localhost")),
+                ca_certificates: Some(PathBuf::from("/etc/ssl/company.pem")),
+            },
+            discogs_token: Some(String::from("abc123")),
+            skip_update_check: true,
+        };
+        let s = toml::to_string_pretty(&config).unwrap();
+        assert_eq!(toml::from_str::<Config>(&s).unwrap(), config);
+    }
+
+    #[test]
+    fn migrations() {
+        assert_eq!(MIGRATIONS.len(), CONFIG_VERSION as usize);
+
+        let dir = TempDir::new("ytmdl-config").unwrap();
+        let path = dir.path().join("config.toml");
+        let old = "format = \"flac\"\nkeep_existing_files = true\n";
+        fs::write(&path, old).unwrap();
+
+        let config = Config::load_from(&path).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.format, OutputFormat::Flac);
+        assert!(config.keep_existing_files);
+        // the old file's kept, and the new one's saved as the current version
+        assert_eq!(
+            fs::read_to_string(dir.path().join("config.v0.toml")).unwrap(),
+            old
+        );
+        let saved = fs::read_to_string(&path).unwrap();
+        assert!(saved.starts_with(&format!("version = {CONFIG_VERSION}\n")));
+        assert_eq!(Config::load_from(&path).unwrap(), config);
+
+        let mut newer: toml::Table = toml::from_str("version = 99\nfuture = true").unwrap();
+        assert_eq!(migrate(&mut newer).unwrap(), 99);
+        assert_eq!(newer["version"].as_integer(), Some(99));
+        // and isn't saved over, which would lose what's new in it
+        fs::write(&path, "version = 99\nfuture = true\n").unwrap();
+        assert!(matches!(
+            config.write(&path),
+            Err(ConfigError::NewerVersion(99))
+        ));
+        assert!(fs::read_to_string(&path).unwrap().contains("future"));
+        let mut bad: toml::Table = toml::from_str(r#"version = "one""#).unwrap();
+        assert!(matches!(migrate(&mut bad), Err(ConfigError::BadVersion(_))));
+        let mut negative: toml::Table = toml::from_str("version = -1").unwrap();
+        assert!(matches!(
+            migrate(&mut negative),
+            Err(ConfigError::BadVersion(_))
+        ));
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config: Config = toml::from_str("").unwrap();
+        assert_eq!(config, Config::default());
+
+        let config: Config = toml::from_str(r#"format = "mp3""#).unwrap();
+        assert_eq!(config.out_dir, None);
+        assert_eq!(config.format, OutputFormat::Mp3);
+
+        let config: Config = toml::from_str(r#"format = "m4a""#).unwrap();
+        assert_eq!(config.format, OutputFormat::M4a);
+        assert_eq!(
+            config.default_profile().file_name("A", "B", 1, "C"),
+            "A - B - C.m4a"
+        );
+    }
+
+    #[test]
+    fn profiles() {
+        let config: Config = toml::from_str(
+            r#"
+            out_dir = "/music"
+            default_profile = "Archive"
+
+            [[profiles]]
+            name = "Phone"
+            bitrate = 128
+
+            [[profiles]]
+            name = "Archive"
+            out_dir = "/nas/music"
+            "#,
+        )
+        .unwrap();
+        // plus the bundled one
+        assert_eq!(config.profiles().len(), 3);
+        assert_eq!(config.profiles()[2].name, Profile::LEGACY_DEVICE);
+        assert_eq!(
+            config.profiles()[0].naming_template,
+            Profile::DEFAULT_NAMING_TEMPLATE
+        );
+        let default = config.default_profile();
+        assert_eq!(default.name, "Archive");
+        assert_eq!(default.out_dir, Some(PathBuf::from("/nas/music")));
+
+        // falls back to a profile made from the top-level settings
+        let config: Config = toml::from_str(r#"out_dir = "/music""#).unwrap();
+        let default = config.default_profile();
+        assert_eq!(default.name, "Default");
+        assert_eq!(default.out_dir, Some(PathBuf::from("/music")));
+        assert_eq!(default.naming_template, Profile::DEFAULT_NAMING_TEMPLATE);
+        let config: Config = toml::from_str(r#"naming_template = "{title}""#).unwrap();
+        assert_eq!(config.default_profile().naming_template, "{title}");
+    }
+
+    #[test]
+    fn legacy_device() {
+        let profile = Profile::legacy_device();
+        assert_eq!(
+            profile.file_name("Sigur Rós", "Takk...", 4, "Sæglópur"),
+            "04 Saeglopur.mp3"
+        );
+        assert_eq!(
+            id3::Version::from(profile.tag_version),
+            id3::Version::Id3v23
+        );
+
+        let config: Config = toml::from_str(
+            r#"
+            [[profiles]]
+            name = "Older devices (iPod)"
+            tag_version = "id3v24"
+            "#,
+        )
+        .unwrap();
+        // a configured one replaces the bundled one
+        assert_eq!(config.profiles().len(), 1);
+        assert_eq!(config.profiles()[0].tag_version, TagVersion::Id3v24);
+    }
+
+    #[test]
+    fn encode_settings() {
+        let mut profile = Profile {
+            bitrate: Some(192),
+            vbr_quality: Some(12),
+            ..Profile::default()
+        };
+        // VBR wins, up to the smallest level
+        assert_eq!(profile.encode_settings().bitrate, None);
+        assert_eq!(profile.encode_settings().vbr_quality, Some(9));
+
+        profile.format = OutputFormat::Opus;
+        assert_eq!(profile.encode_settings().bitrate, Some(192));
+        assert_eq!(profile.encode_settings().vbr_quality, None);
+
+        profile.format = OutputFormat::Flac;
+        assert!(profile.encode_settings().ffmpeg_args().is_empty());
+
+        profile.set_quality(Quality::Vbr(2));
+        assert_eq!(profile.quality(), Quality::Vbr(2));
+        assert_eq!(profile.bitrate, None);
+        assert!(Quality::choices(OutputFormat::Mp3).contains(&Quality::Vbr(2)));
+        assert!(!Quality::choices(OutputFormat::M4a).contains(&Quality::Vbr(2)));
+    }
+
+    #[test]
+    fn outputs_for() {
+        let config: Config = toml::from_str(
+            r#"
+            [[profiles]]
+            name = "Car"
+            also = ["Archive", "Car", "Missing"]
+
+            [[profiles]]
+            name = "Archive"
+            out_dir = "/nas/music"
+            "#,
+        )
+        .unwrap();
+        let car = config.default_profile();
+        let outputs: Vec<_> = config
+            .outputs_for(&car)
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(outputs, ["Car", "Archive"]);
+    }
+}
@@ -0,0 +1,236 @@
+//! Checking yt-dlp and ffmpeg are installed (and new enough) before anything is downloaded with
+//! them, since otherwise every track fails with an error that doesn't say why
+
+use crate::clock::{Clock, SystemClock};
+use chrono::NaiveDate;
+use once_cell::sync::Lazy;
+use std::{ffi::OsStr, process::Command, sync::RwLock};
+
+/// yt-dlp releases older than this tend to be broken by changes on Youtube's end
+const YTDLP_MAX_AGE_DAYS: i64 = 180;
+/// The oldest ffmpeg that has all the encoders and filters that are used
+const MIN_FFMPEG_MAJOR: u32 = 4;
+
+/// Runs `<program> <version_arg>` and returns the first line of its output,
+/// or `None` if it couldn't be run (most likely because it's not installed).
+///
+/// # Examples
+/// ```
+/// assert_eq!(ytmdl::deps::tool_version("surely-not-a-real-program", "--version"), None);
+/// ```
+#[must_use]
+pub fn tool_version(program: impl AsRef<OsStr>, version_arg: &str) -> Option<String> {
+    let output = Command::new(program).arg(version_arg).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .next()
+        .map(|line| line.trim().to_string())
+}
+
+/// Whether a tool was found, with its version
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ToolStatus {
+    Missing,
+    /// Installed, but old enough that it's likely to fail
+    Outdated(String),
+    Found(String),
+}
+
+impl ToolStatus {
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Found(_))
+    }
+}
+
+/// What [`check_dependencies`] found
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DependencyReport {
+    pub ytdlp: ToolStatus,
+    pub ffmpeg: ToolStatus,
+}
+
+impl DependencyReport {
+    /// Works out whether each tool is outdated from its version, as `<tool> --version` (or
+    /// `-version`) prints it
+    ///
+    /// # Examples
+    /// ```
+    /// use chrono::NaiveDate;
+    /// use ytmdl::deps::{DependencyReport, ToolStatus};
+    ///
+    /// let today = NaiveDate::from_ymd_opt(2023, 12, 1).unwrap();
+    /// let report = DependencyReport::new(
+    ///     Some("2023.11.16"),
+    ///     Some("ffmpeg version 3.4.8-0ubuntu0.2 Copyright (c) 2000-2020"),
+    ///     today,
+    /// );
+    /// assert_eq!(report.ytdlp, ToolStatus::Found(String::from("2023.11.16")));
+    /// assert_eq!(report.ffmpeg, ToolStatus::Outdated(String::from("3.4.8-0ubuntu0.2")));
+    /// ```
+    #[must_use]
+    pub fn new(ytdlp: Option<&str>, ffmpeg: Option<&str>, today: NaiveDate) -> Self {
+        let ytdlp = match ytdlp {
+            Some(version) if ytdlp_is_outdated(version, today) => {
+                ToolStatus::Outdated(version.to_string())
+            }
+            Some(version) => ToolStatus::Found(version.to_string()),
+            None => ToolStatus::Missing,
+        };
+        let ffmpeg = match ffmpeg.map(ffmpeg_version) {
+            Some(version) if ffmpeg_is_outdated(&version) => ToolStatus::Outdated(version),
+            Some(version) => ToolStatus::Found(version),
+            None => ToolStatus::Missing,
+        };
+        Self { ytdlp, ffmpeg }
+    }
+
+    /// Whether both tools are installed and new enough
+    #[must_use]
+    pub fn is_ok(&self) -> bool {
+        self.ytdlp.is_ok() && self.ffmpeg.is_ok()
+    }
+
+    /// A line for each tool, saying what was found and what to do if it's missing or outdated
+    #[must_use]
+    pub fn lines(&self) -> [String; 2] {
+        let ytdlp = match &self.ytdlp {
+            ToolStatus::Found(version) => format!("✔ yt-dlp found: {version}"),
+            ToolStatus::Outdated(version) => format!(
+                "✘ yt-dlp {version} is out of date, and Youtube often breaks older versions; \
+                 update it with yt-dlp -U (or however you installed it)"
+            ),
+            ToolStatus::Missing => String::from(
+                "✘ yt-dlp not found; download it from https://github.com/yt-dlp/yt-dlp/releases \
+                 and put it on your PATH, or have ytmdl download its own copy",
+            ),
+        };
+        let ffmpeg = match &self.ffmpeg {
+            ToolStatus::Found(version) => format!("✔ ffmpeg found: {version}"),
+            ToolStatus::Outdated(version) => format!(
+                "✘ ffmpeg {version} is older than {MIN_FFMPEG_MAJOR}.0, so converting may fail; \
+                 install a newer one from https://ffmpeg.org/"
+            ),
+            ToolStatus::Missing => String::from(
+                "✘ ffmpeg not found; install it from https://ffmpeg.org/ and put it on your PATH",
+            ),
+        };
+        [ytdlp, ffmpeg]
+    }
+
+    /// [`DependencyReport::lines`], just for the tools that are missing or outdated
+    #[must_use]
+    pub fn problems(&self) -> Vec<String> {
+        let [ytdlp, ffmpeg] = self.lines();
+        [(&self.ytdlp, ytdlp), (&self.ffmpeg, ffmpeg)]
+            .into_iter()
+            .filter(|(status, _)| !status.is_ok())
+            .map(|(_, line)| line)
+            .collect()
+    }
+}
+
+/// yt-dlp versions are the date they were released, like `2023.11.16` (nightlies have the time
+/// after it). Ones that can't be read aren't outdated.
+fn ytdlp_is_outdated(version: &str, today: NaiveDate) -> bool {
+    let mut parts = version.trim().split('.');
+    let mut next = || parts.next().and_then(|part| part.parse().ok());
+    let released = match (next(), next(), next()) {
+        (Some(year), Some(month), Some(day)) => i32::try_from(year)
+            .ok()
+            .and_then(|year| NaiveDate::from_ymd_opt(year, month, day)),
+        _ => None,
+    };
+    released.is_some_and(|released| (today - released).num_days() > YTDLP_MAX_AGE_DAYS)
+}
+
+/// The version out of ffmpeg's first line, like `ffmpeg version 6.0 Copyright (c) ...`
+fn ffmpeg_version(line: &str) -> String {
+    line.split_whitespace()
+        .skip_while(|word| *word != "version")
+        .nth(1)
+        .unwrap_or(line)
+        .to_string()
+}
+
+/// Builds from git (like `N-112345-g...`) and ones that can't be read aren't outdated
+fn ffmpeg_is_outdated(version: &str) -> bool {
+    let version = version.strip_prefix('n').unwrap_or(version);
+    let major: String = version.chars().take_while(char::is_ascii_digit).collect();
+    major
+        .parse::<u32>()
+        .is_ok_and(|major| major < MIN_FFMPEG_MAJOR)
+}
+
+/// Runs yt-dlp and ffmpeg to see if they're installed and new enough
+#[must_use]
+pub fn check_dependencies() -> DependencyReport {
+    DependencyReport::new(
+        tool_version(crate::ytdlp::program(), "--version").as_deref(),
+        tool_version("ffmpeg", "-version").as_deref(),
+        SystemClock.today(),
+    )
+}
+
+/// The last [`check_dependencies`], unless it's been dismissed. Kept outside of the GUI state so
+/// it stays when switching screens.
+static LAST_REPORT: Lazy<RwLock<Option<DependencyReport>>> = Lazy::new(RwLock::default);
+
+#[must_use]
+pub fn last_report() -> Option<DependencyReport> {
+    LAST_REPORT.read().ok().and_then(|report| report.clone())
+}
+
+/// `None` to dismiss it
+pub fn set_report(report: Option<DependencyReport>) {
+    if let Ok(mut last) = LAST_REPORT.write() {
+        *last = report;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn ytdlp_age() {
+        let today = date(2024, 6, 1);
+        assert!(!ytdlp_is_outdated("2024.03.10", today));
+        assert!(!ytdlp_is_outdated("2024.05.27.232946", today));
+        assert!(ytdlp_is_outdated("2023.07.06", today));
+        assert!(!ytdlp_is_outdated("not a date", today));
+    }
+
+    #[test]
+    fn ffmpeg_versions() {
+        assert_eq!(
+            ffmpeg_version("ffmpeg version n6.1.1 Copyright (c) 2000-2023 the FFmpeg developers"),
+            "n6.1.1"
+        );
+        assert!(!ffmpeg_is_outdated("n6.1.1"));
+        assert!(!ffmpeg_is_outdated("4.4.2-0ubuntu0.22.04.1"));
+        assert!(!ffmpeg_is_outdated("N-112345-gabcdef0123"));
+        assert!(ffmpeg_is_outdated("2.8.17"));
+    }
+
+    #[test]
+    fn problems() {
+        let report = DependencyReport::new(
+            None,
+            Some("ffmpeg version 6.0 Copyright (c) 2000-2023"),
+            date(2024, 1, 1),
+        );
+        assert!(!report.is_ok());
+        assert_eq!(report.ffmpeg, ToolStatus::Found(String::from("6.0")));
+        let problems = report.problems();
+        assert_eq!(problems.len(), 1);
+        assert!(problems[0].starts_with("✘ yt-dlp not found"));
+    }
+}
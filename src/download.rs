@@ -1,140 +1,1098 @@
 use crate::{
+    activity::{self, Activity, ActivityGuard},
+    album_art::{download_image, image_dimensions, upscale, verify_image},
+    clock::{Clock, SystemClock},
+    config::{self, OutputFormat, Profile, TagVersion},
+    deps::tool_version,
+    diagnostics,
+    full_album::Section,
     gui::view_modifying_data::StateModifyingData,
-    scraping::{scrape_playlist, scrape_youtube},
-    utils::{music_to_www, sanitize_file_name, SendableRawPointer},
+    history,
+    ids::VideoId,
+    jobs::{Job, JobState},
+    network, paths, playlist,
+    postprocess::Pipeline,
+    report::{
+        find_tracks_with_info, sha256_file, DownloadReport, SourceFormat, StageTimings, TrackInfo,
+        TrackInfoError, TrackReport,
+    },
+    romanize::RomanizeSettings,
+    scraping::{
+        self, fetch_lyrics, parse_lrc, read_tags, scrape_playlist, scrape_youtube, LyricsQuery,
+    },
+    sources::Channel,
+    tagging, threading,
+    utils::{sanitize_file_name, serialize_display, sort_name, SendableRawPointer},
+    versions::LengthMismatch,
 };
 use bytes::Bytes;
 use id3::{
-    frame::{Picture, PictureType},
+    frame::{
+        ExtendedText, Lyrics, Picture, PictureType, Popularimeter, SynchronisedLyrics,
+        SynchronisedLyricsType, TimestampFormat,
+    },
     Tag, TagLike,
 };
-use rayon::prelude::{IntoParallelIterator, ParallelIterator};
-use reqwest::header::{HeaderValue, CONTENT_TYPE};
+use once_cell::sync::{Lazy, OnceCell};
+use serde::Serialize;
 use std::{
-    env, fs,
+    collections::HashMap,
+    env,
+    fmt::{self, Display, Formatter},
+    fs,
+    io::{self, Read},
     path::{Path, PathBuf},
-    process::Command,
-    time::Instant,
+    process::{Command, Output, Stdio},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Condvar, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
 };
 use tempdir::TempDir;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Error, Serialize)]
 pub enum DownloadError {
     #[error("{0}")]
     ScrapeYoutubeError(#[from] crate::scraping::ScrapeYoutubeError),
     #[error("{0}")]
-    IoError(#[from] std::io::Error),
+    IoError(
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        std::io::Error,
+    ),
+    /// The source, and what yt-dlp printed to stderr
     #[error("ytdlp error when downloading {0}")]
-    YtdlpError(String),
+    YtdlpError(String, String),
+    /// The track, and what ffmpeg printed to stderr
     #[error("ffmpeg error converting {0}")]
-    FfmpegError(String),
+    FfmpegError(String, String),
+    /// The track, and libav's error
+    #[cfg(feature = "libav")]
+    #[error("couldn't convert {0}: {1}")]
+    TranscodeError(String, String),
     #[error("some error with the temp dir")]
     TmpDirError,
     #[error("{0}")]
-    Id3Error(#[from] id3::Error),
-    #[error("{0:?}")]
-    MultipleErrors(Vec<Self>),
+    Id3Error(
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        id3::Error,
+    ),
+    #[error("{0} timed out")]
+    Timeout(String),
+    #[error(
+        r#"not enough free disk space in "{}" ({} MB left); free some up and try again"#,
+        .path.display(),
+        .available / 1_000_000
+    )]
+    DiskFull { path: PathBuf, available: u64 },
+    #[error(r#"can't save anything in "{}": {reason}"#, .path.display())]
+    OutDirNotWritable { path: PathBuf, reason: String },
+    /// Some of the tracks failed, with the report of the ones that didn't as well
+    #[error("{}", display_track_errors(&.0.failed))]
+    TracksFailed(Box<DownloadReport>),
+    #[error("track {} isn't in the playlist and doesn't have its own source", .0 + 1)]
+    MissingSource(usize),
+    #[error("{0}")]
+    TrackInfoError(
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        TrackInfoError,
+    ),
+    #[error(r#""{0}" isn't a link that can be downloaded"#)]
+    InvalidSource(String),
+    #[error("the download was cancelled")]
+    Cancelled,
+}
+
+impl DownloadError {
+    /// Whether trying again has a decent chance of working
+    #[must_use]
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::Timeout(_) | Self::DiskFull { .. } => true,
+            Self::TracksFailed(report) => report.failed.iter().all(|err| err.source.is_retryable()),
+            _ => false,
+        }
+    }
+
+    /// A short suggestion of what to do about it, if there's something the user can do
+    #[must_use]
+    pub fn hint(&self) -> Option<&'static str> {
+        match self {
+            Self::YtdlpError(_, stderr) if stderr.contains("HTTP Error 403") => {
+                Some("update yt-dlp (yt-dlp -U), since YouTube often breaks older versions")
+            }
+            Self::YtdlpError(_, stderr)
+                if [
+                    "Video unavailable",
+                    "Private video",
+                    "This video is not available",
+                    "members-only",
+                ]
+                .iter()
+                .any(|message| stderr.contains(message)) =>
+            {
+                Some("the video is unavailable, so give the track another source link")
+            }
+            Self::YtdlpError(..) => Some("check the link works, and try updating yt-dlp"),
+            Self::FfmpegError(_, stderr)
+                if ["Unknown encoder", "Encoder not found", "Decoder not found"]
+                    .iter()
+                    .any(|message| stderr.contains(message)) =>
+            {
+                Some("ffmpeg is missing a codec, so install a full build of ffmpeg")
+            }
+            #[cfg(feature = "libav")]
+            Self::TranscodeError(_, err) if err.contains("built without") => {
+                Some("build ytmdl against a full build of FFmpeg's libraries")
+            }
+            Self::DiskFull { .. } => Some("check free space, then resume the download"),
+            Self::OutDirNotWritable { .. } => {
+                Some("pick another folder to save in, or check this one's permissions")
+            }
+            Self::IoError(err)
+            | Self::Id3Error(id3::Error {
+                kind: id3::ErrorKind::Io(err),
+                ..
+            }) => io_hint(err),
+            Self::Timeout(_) => Some("check your connection, or raise the timeouts"),
+            Self::MissingSource(_) => Some("give the track its own source link"),
+            // only if every track has the same one
+            Self::TracksFailed(report) => {
+                let mut hints = report.failed.iter().map(|err| err.source.hint());
+                let first = hints.next()??;
+                hints.all(|hint| hint == Some(first)).then_some(first)
+            }
+            _ => None,
+        }
+    }
+
+    /// Each failed track's error separately, or just this error if it wasn't down to tracks
+    #[must_use]
+    pub fn failures(&self) -> Vec<String> {
+        match self {
+            Self::TracksFailed(report) => report.failed.iter().map(ToString::to_string).collect(),
+            err => vec![err.to_string()],
+        }
+    }
+}
+
+fn io_hint(err: &io::Error) -> Option<&'static str> {
+    // ENOSPC, or ERROR_HANDLE_DISK_FULL and ERROR_DISK_FULL on Windows
+    let out_of_space: &[i32] = if cfg!(windows) { &[39, 112] } else { &[28] };
+    if err.kind() == io::ErrorKind::PermissionDenied {
+        Some("check the output folder can be written to and the files aren't read-only")
+    } else if err
+        .raw_os_error()
+        .map_or(false, |code| out_of_space.contains(&code))
+    {
+        Some("check free space, then resume the download")
+    } else {
+        None
+    }
+}
+
+/// What the program printed to stderr, for working out what went wrong
+pub(crate) fn stderr(output: &Output) -> String {
+    String::from_utf8_lossy(&output.stderr).trim().to_string()
+}
+
+fn display_track_errors(errors: &[TrackError]) -> String {
+    errors
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Which step of processing a track was at
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TrackStage {
+    Download,
+    Convert,
+    Tag,
+    Move,
+}
+
+impl Display for TrackStage {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Download => "downloading",
+            Self::Convert => "converting",
+            Self::Tag => "tagging",
+            Self::Move => "moving",
+        })
+    }
+}
+
+/// Where a single track is downloaded from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrackSource {
+    Youtube(VideoId),
+    /// Any other URL yt-dlp supports, like Soundcloud or Bandcamp
+    Url(String),
+}
+
+impl TrackSource {
+    /// Youtube video links (or IDs) become [`TrackSource::Youtube`], and any other http(s) link is
+    /// left for yt-dlp to work out.
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::TrackSource;
+    ///
+    /// assert_eq!(
+    ///     TrackSource::parse("https://youtu.be/dQw4w9WgXcQ"),
+    ///     Some(TrackSource::Youtube("dQw4w9WgXcQ".parse().unwrap())),
+    /// );
+    /// assert_eq!(
+    ///     TrackSource::parse("https://soundcloud.com/artist/song"),
+    ///     Some(TrackSource::Url("https://soundcloud.com/artist/song".into())),
+    /// );
+    /// assert_eq!(TrackSource::parse("not a link"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let s = s.trim();
+        if let Some(id) = playlist::parse_video_id(s) {
+            Some(Self::Youtube(id))
+        } else if s.starts_with("https://") || s.starts_with("http://") {
+            Some(Self::Url(s.to_string()))
+        } else {
+            None
+        }
+    }
+
+    /// What's passed to yt-dlp
+    #[must_use]
+    pub fn url(&self) -> String {
+        match self {
+            Self::Youtube(id) => id.url(),
+            Self::Url(url) => url.clone(),
+        }
+    }
+}
+
+/// The video ID for Youtube, otherwise the URL
+impl Display for TrackSource {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Youtube(id) => f.write_str(id.as_str()),
+            Self::Url(url) => f.write_str(url),
+        }
+    }
+}
+
+/// An error for a specific track, including which track and what it was doing at the time
+#[derive(Debug, Error, Serialize)]
+#[error(r#"track {} ("{title}", id "{video_id}") failed while {stage}: {source}"#, .index + 1)]
+pub struct TrackError {
+    /// 0-based index into the track list
+    pub index: usize,
+    /// The [`TrackSource`], so the URL if it's not from Youtube
+    pub video_id: String,
+    pub title: String,
+    pub stage: TrackStage,
+    pub source: Box<DownloadError>,
+}
+
+/// How long things are allowed to take before they're killed.
+///
+/// Set via `YTMDL_DOWNLOAD_TIMEOUT`, `YTMDL_CONVERT_TIMEOUT` and `YTMDL_ALBUM_TIMEOUT` (in seconds),
+/// where `0` means no timeout.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Timeouts {
+    /// Per yt-dlp call
+    pub download: Option<Duration>,
+    /// Per ffmpeg call
+    pub convert: Option<Duration>,
+    /// For the whole album; tracks still running when this passes are killed
+    pub album: Option<Duration>,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Self {
+            download: Some(Duration::from_secs(600)),
+            convert: Some(Duration::from_secs(300)),
+            album: None,
+        }
+    }
+}
+
+impl Timeouts {
+    #[must_use]
+    pub fn from_env() -> Self {
+        fn var(key: &str, default: Option<Duration>) -> Option<Duration> {
+            match env::var(key).ok().map(|v| v.parse::<u64>()) {
+                Some(Ok(0)) => None,
+                Some(Ok(secs)) => Some(Duration::from_secs(secs)),
+                Some(Err(err)) => {
+                    log::warn!("invalid value for {key}: {err}");
+                    default
+                }
+                None => default,
+            }
+        }
+
+        let default = Self::default();
+        Self {
+            download: var("YTMDL_DOWNLOAD_TIMEOUT", default.download),
+            convert: var("YTMDL_CONVERT_TIMEOUT", default.convert),
+            album: var("YTMDL_ALBUM_TIMEOUT", default.album),
+        }
+    }
+}
+
+/// Shared with a running download to stop it early.
+///
+/// Tracks check it between yt-dlp and ffmpeg calls, so whatever's already running gets to finish.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    stop_starting: Arc<AtomicBool>,
+}
+
+impl CancellationToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+        // so tracks waiting for disk space stop now
+        check_disk_space_now();
+    }
+
+    /// Gentler than [`CancellationToken::cancel`]: tracks that have started carry on to the end,
+    /// and the rest are cancelled before they start
+    pub fn stop_starting(&self) {
+        self.stop_starting.store(true, Ordering::Relaxed);
+        check_disk_space_now();
+    }
+
+    #[must_use]
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// # Errors
+    /// [`DownloadError::Cancelled`] if it's been cancelled
+    pub fn check(&self) -> Result<(), DownloadError> {
+        if self.is_cancelled() {
+            Err(DownloadError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+
+    /// [`CancellationToken::check`] for a track that hasn't started yet
+    ///
+    /// # Errors
+    /// [`DownloadError::Cancelled`] if it's been cancelled or told to stop starting tracks
+    pub fn check_start(&self) -> Result<(), DownloadError> {
+        if self.stop_starting.load(Ordering::Relaxed) {
+            Err(DownloadError::Cancelled)
+        } else {
+            self.check()
+        }
+    }
+}
+
+/// The earlier of the stage's timeout (starting now) and the album's deadline
+pub(crate) fn stage_deadline(
+    timeout: Option<Duration>,
+    album_deadline: Option<Instant>,
+) -> Option<Instant> {
+    let stage_deadline = timeout.map(|timeout| Instant::now() + timeout);
+    match (stage_deadline, album_deadline) {
+        (Some(stage), Some(album)) => Some(stage.min(album)),
+        (stage, album) => stage.or(album),
+    }
+}
+
+/// Like [`Command::output`], but kills the process if it's still running at the deadline
+pub(crate) fn output_with_deadline(
+    command: &mut Command,
+    deadline: Option<Instant>,
+    what: &str,
+) -> Result<Output, DownloadError> {
+    const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+    fn drain(mut pipe: impl Read + Send + 'static) -> JoinHandle<Vec<u8>> {
+        thread::spawn(move || {
+            let mut buf = Vec::new();
+            let _ = pipe.read_to_end(&mut buf);
+            buf
+        })
+    }
+
+    let Some(deadline) = deadline else {
+        return Ok(command.output()?);
+    };
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    // read the pipes on other threads so the process can't get stuck on a full pipe
+    let stdout = child.stdout.take().map(drain);
+    let stderr = child.stderr.take().map(drain);
+
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() >= deadline {
+            log::warn!("{what} timed out, killing it");
+            let _ = child.kill();
+            child.wait()?;
+            return Err(DownloadError::Timeout(what.to_string()));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+
+    let join = |handle: Option<JoinHandle<Vec<u8>>>| {
+        handle
+            .and_then(|handle| handle.join().ok())
+            .unwrap_or_default()
+    };
+    Ok(Output {
+        status,
+        stdout: join(stdout),
+        stderr: join(stderr),
+    })
+}
+
+/// Free space (in bytes) that the temp and output dirs need before starting a track.
+///
+/// Set via `YTMDL_MIN_FREE_SPACE` (in MB), defaults to 500 MB.
+fn min_free_space() -> u64 {
+    const DEFAULT_MB: u64 = 500;
+
+    let mb = env::var("YTMDL_MIN_FREE_SPACE").map_or(DEFAULT_MB, |v| {
+        v.parse().unwrap_or_else(|err| {
+            log::warn!("invalid value for YTMDL_MIN_FREE_SPACE: {err}");
+            DEFAULT_MB
+        })
+    });
+    mb * 1_000_000
+}
+
+/// Notified by [`check_disk_space_now`] to wake the tracks waiting for space
+static SPACE_FREED: (Mutex<()>, Condvar) = (Mutex::new(()), Condvar::new());
+
+/// Has tracks that are waiting for space to be freed up check again straight away, rather than
+/// on their next poll, like once some files have been cleaned up
+pub fn check_disk_space_now() {
+    SPACE_FREED.1.notify_all();
+}
+
+/// Waits until all the given dirs have at least `min` bytes free, showing
+/// [`Activity::DiskFull`] while it does.
+///
+/// If space doesn't get freed up within 10 minutes (or before the deadline), returns
+/// [`DownloadError::DiskFull`], or [`DownloadError::Cancelled`] if `cancel` is cancelled (or told
+/// to stop starting tracks) first.
+fn wait_for_disk_space(
+    dirs: &[&Path],
+    min: u64,
+    deadline: Option<Instant>,
+    cancel: &CancellationToken,
+) -> Result<(), DownloadError> {
+    const POLL_INTERVAL: Duration = Duration::from_secs(5);
+    const MAX_WAIT: Duration = Duration::from_secs(600);
+
+    let deadline = stage_deadline(Some(MAX_WAIT), deadline);
+    let mut paused = None;
+
+    loop {
+        let mut low = None;
+        for dir in dirs {
+            let available = fs2::available_space(dir)?;
+            if available < min {
+                low = Some((*dir, available));
+                break;
+            }
+        }
+
+        let Some((path, available)) = low else {
+            return Ok(());
+        };
+        cancel.check_start()?;
+        if deadline.map_or(false, |deadline| Instant::now() >= deadline) {
+            return Err(DownloadError::DiskFull {
+                path: path.to_path_buf(),
+                available,
+            });
+        }
+        if paused.is_none() {
+            log::warn!(
+                r#"Disk is nearly full ({} MB left in "{}"), pausing until some space is freed up"#,
+                available / 1_000_000,
+                path.display()
+            );
+            paused = Some(activity::start(Activity::DiskFull {
+                path: path.to_path_buf(),
+                available,
+            }));
+        }
+        let waiting = SPACE_FREED.0.lock().unwrap_or_else(PoisonError::into_inner);
+        drop(SPACE_FREED.1.wait_timeout(waiting, POLL_INTERVAL));
+    }
 }
 
-/// Actually downloads all the tracks, converts them to mp3 and applies ID3 tags
+/// Actually downloads all the tracks, converts them to each profile's format and tags them
 ///
 /// # Errors
 /// - If it can't determine the temp dir or output dir, or if either are invalid
-/// - If [`get_ids`] fails
+/// - If [`get_sources`] fails
 /// - If it can't generate the output file name of a track (using the yt-dlp CLI tool)
 /// - If the yt-dlp CLI tool fails to download a track
-/// - If ffmpeg fails to convert the file to an mp3
-/// - If the ID3 tags fail being written to the file
+/// - If ffmpeg fails to convert the file
+/// - If the tags fail being written to the file
 /// - If the file can't be moved from the temp directory to the actual output
-pub fn download_album(state: &StateModifyingData) -> Result<(), DownloadError> {
+/// - If any of the above take longer than the [`Timeouts`] allow
+/// - If the disk stays full for too long (tracks wait for space to be freed up before starting)
+///
+/// The download is saved as a [`Job`] as it goes, which is deleted once it's finished, so it can be
+/// resumed if it fails or the app crashes.
+pub fn download_album(state: &StateModifyingData) -> Result<DownloadReport, DownloadError> {
+    download_album_cancellable(state, &CancellationToken::default())
+}
+
+/// [`download_album`], but it stops with [`DownloadError::Cancelled`] once `cancel` is cancelled.
+///
+/// The job is kept (like any other failure) so the rest of the album can be resumed later.
+///
+/// # Errors
+/// See [`download_album`]
+pub fn download_album_cancellable(
+    state: &StateModifyingData,
+    cancel: &CancellationToken,
+) -> Result<DownloadReport, DownloadError> {
+    let job = Job::new(state);
+    job.save_or_warn();
+    let job = Mutex::new(job);
+    let activity = activity::start(Activity::Downloading {
+        album: format!("{} - {}", state.album_data.artist, state.album_data.name),
+        done: 0,
+        total: state.tracks_to_download(),
+    });
+
+    let result = download_album_as_job(state, &job, &activity, cancel);
+
+    let mut job = job.into_inner().unwrap_or_else(PoisonError::into_inner);
+    diagnostics::record_download(&result);
+    match &result {
+        Ok(report) => {
+            job.complete();
+            let channels: Vec<Channel> = report
+                .tracks
+                .iter()
+                .filter_map(|track| track.channel.clone())
+                .collect();
+            history::record(state, Vec::new(), &channels);
+        }
+        Err(err) => {
+            job.fail(err.to_string());
+            // the tracks that were saved still count towards their channels
+            let channels: Vec<Channel> = match err {
+                DownloadError::TracksFailed(report) => report
+                    .tracks
+                    .iter()
+                    .filter_map(|track| track.channel.clone())
+                    .collect(),
+                _ => Vec::new(),
+            };
+            history::record(state, err.failures(), &channels);
+        }
+    }
+    result
+}
+
+/// Moves the job along, ignoring if another thread panicked while holding it
+fn advance(job: &Mutex<Job>, state: JobState) {
+    job.lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .advance(state);
+}
+
+fn download_album_as_job(
+    state: &StateModifyingData,
+    job: &Mutex<Job>,
+    activity: &ActivityGuard,
+    cancel: &CancellationToken,
+) -> Result<DownloadReport, DownloadError> {
     let started = Instant::now();
+    let started_at = SystemClock.now();
+    let timeouts = Timeouts::from_env();
+    let min_free_space = min_free_space();
+    let album_deadline = timeouts.album.map(|timeout| started + timeout);
 
-    let (tmp_dir, out_dir) = where_dirs()?;
+    let config = config::current();
+    let profiles = config.outputs_for(&state.profile);
+    let (work_dir, out_dirs) = where_dirs(&state.youtube_url, &profiles)?;
     let tmp_dir =
-        SendableRawPointer::new(tmp_dir.path().to_str().ok_or(DownloadError::TmpDirError)?);
-    let out_dir = SendableRawPointer::new(out_dir.as_path());
-    let ids = get_ids(state.youtube_url.as_str())?;
-    let num_tracks = ids.len();
-    let (img, content_type) = get_image(state);
-    let img = img.as_deref().map(SendableRawPointer::new);
-    let content_type = content_type.as_deref().map(SendableRawPointer::new);
+        SendableRawPointer::new(work_dir.path().to_str().ok_or(DownloadError::TmpDirError)?);
+    let outputs: Vec<(Profile, PathBuf)> = profiles.into_iter().zip(out_dirs).collect();
+    advance(job, JobState::Scraping);
+    let sources = get_sources(state)?;
+    let full_videos = full_videos(state, &sources);
+    cancel.check()?;
+    let num_tracks = state.track_data.len();
+    let num_downloads = sources.len();
+    let image = get_image(state);
+    let arts: Vec<OutputArt> = outputs
+        .iter()
+        .enumerate()
+        .map(|(n, (profile, out_dir))| {
+            let art = art_for_profile(image.as_ref(), profile, work_dir.path());
+            if profile.folder_jpg {
+                write_folder_jpg(out_dir, art.as_ref());
+            }
+            OutputArt::new(art, profile, n, work_dir.path())
+        })
+        .collect();
+    // only the outputs' copies are needed from here, which are often the same one
+    drop(image);
+    let pipelines: Vec<Pipeline> = outputs
+        .iter()
+        .map(|(profile, _)| Pipeline::for_profile(profile, &config))
+        .collect();
+    let outputs = SendableRawPointer::new(outputs.as_slice());
+    let arts = SendableRawPointer::new(arts.as_slice());
+    let pipelines = SendableRawPointer::new(pipelines.as_slice());
     let state = state.into();
 
-    let errors: Vec<DownloadError> = crate::POOL.install(|| {
-        ids.into_iter()
-            .enumerate()
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .filter_map(|(i, id)| {
-                // SAFETY: none of the raw pointers sent here will be invalidated because all the
-                // tasks are joined before the memory is deallocated
-                unsafe {
-                    handle_track(
-                        state,
-                        i,
-                        num_tracks,
-                        id,
-                        tmp_dir,
-                        out_dir,
-                        img,
-                        content_type,
-                    )
-                }
-                .err()
-            })
-            .collect()
-    });
+    advance(job, JobState::Downloading);
+    let results: Vec<Result<TrackReport, TrackError>> =
+        threading::run_tracks(sources, |(i, source)| {
+            // SAFETY: none of the raw pointers sent here will be invalidated because all the
+            // tasks are joined before the memory is deallocated
+            let result = unsafe {
+                handle_track(
+                    state,
+                    i,
+                    num_tracks,
+                    source,
+                    tmp_dir,
+                    outputs,
+                    arts,
+                    pipelines,
+                    timeouts,
+                    album_deadline,
+                    min_free_space,
+                    &full_videos,
+                    job,
+                    cancel,
+                )
+            };
+            activity.finish_track();
+            result
+        });
 
-    log::info!("Finished in {}s", started.elapsed().as_secs());
+    let mut report = DownloadReport {
+        started: started_at,
+        ..DownloadReport::default()
+    };
+    for result in results {
+        match result {
+            Ok(track) => report.tracks.push(track),
+            Err(err) => report.failed.push(err),
+        }
+    }
+    report.elapsed = started.elapsed();
 
-    if errors.is_empty() {
-        Ok(())
+    log::info!("Finished in {}s", report.elapsed.as_secs());
+    log::info!("Total time per stage: {}", report.total_timings());
+    let mismatches = report.length_mismatches();
+    if !mismatches.is_empty() {
+        log::warn!(
+            "{} track(s) might be a different version of the song, by their length",
+            mismatches.len()
+        );
+    }
+
+    let skipped = report
+        .failed
+        .iter()
+        .any(|err| matches!(*err.source, DownloadError::Cancelled));
+    if cancel.is_cancelled() || skipped {
+        log::info!(
+            "Cancelled after finishing {}/{num_downloads} tracks",
+            report.tracks.len()
+        );
+        Err(DownloadError::Cancelled)
+    } else if report.failed.is_empty() {
+        work_dir.finish();
+        Ok(report)
     } else {
-        Err(DownloadError::MultipleErrors(errors))
+        Err(DownloadError::TracksFailed(Box::new(report)))
     }
 }
 
-/// This downloads the file, sets its id3 tags, moves it to correct dir
+/// This downloads the file, then for each output converts it, post-processes it, sets its id3 tags
+/// and moves it to the correct dir
 ///
 /// # Safety
 /// The arguments passed as [`SendableRawPointer`]s must be valid for the duration of the function.
-#[allow(clippy::too_many_arguments, clippy::needless_pass_by_value)]
+#[allow(
+    clippy::too_many_arguments,
+    clippy::too_many_lines,
+    clippy::needless_pass_by_value
+)]
 unsafe fn handle_track(
     state: SendableRawPointer<StateModifyingData>,
     i: usize,
     num_tracks: usize,
-    id: String,
+    source: TrackSource,
     tmp_dir: SendableRawPointer<str>,
-    out_dir: SendableRawPointer<Path>,
-    img: Option<SendableRawPointer<[u8]>>,
-    content_type: Option<SendableRawPointer<str>>,
-) -> Result<(), DownloadError> {
+    outputs: SendableRawPointer<[(Profile, PathBuf)]>,
+    arts: SendableRawPointer<[OutputArt]>,
+    pipelines: SendableRawPointer<[Pipeline]>,
+    timeouts: Timeouts,
+    album_deadline: Option<Instant>,
+    min_free_space: u64,
+    full_videos: &FullVideos,
+    job: &Mutex<Job>,
+    cancel: &CancellationToken,
+) -> Result<TrackReport, TrackError> {
     // SAFETY: these .get calls aren't guaranteed to be safe
     let state = state.get();
     let tmp_dir = tmp_dir.get();
-    let out_dir = out_dir.get();
-    let img = img.as_ref().map(|i| i.get());
-    let content_type = content_type.as_ref().map(|ct| ct.get());
+    let outputs = outputs.get();
+    let arts = arts.get();
+    let pipelines = pipelines.get();
     // SAFETY: everything after here should be safe (assuming the above are valid)
 
-    // download from youtube
-    let path = generate_path_name(i, num_tracks, &id, tmp_dir)?;
-    dl_from_yt(i, &id, &path, tmp_dir)?;
+    let id = source.to_string();
+
+    let fail = |stage, source: DownloadError| TrackError {
+        index: i,
+        video_id: id.clone(),
+        title: state.track_data[i].name.clone(),
+        stage,
+        source: Box::new(source),
+    };
+
+    let mut timings = StageTimings::default();
+
+    // already saved by an earlier run that didn't finish, so there's nothing to do
+    let existing = check_outputs(state, i, outputs);
+    if existing.iter().all(|existing| *existing == Existing::Kept) {
+        log::info!("{}/{} is already downloaded; skipping", i + 1, num_tracks);
+        let title = state.track_data[i].name.clone();
+        return Ok(TrackReport::already_saved(i, id, title));
+    }
+
+    warn_if_no_art(state, i, outputs, arts);
+
+    // make sure there's room for it first
+    let mut dirs = vec![Path::new(tmp_dir)];
+    dirs.extend(outputs.iter().map(|(_, out_dir)| out_dir.as_path()));
+    wait_for_disk_space(&dirs, min_free_space, album_deadline, cancel)
+        .map_err(|err| fail(TrackStage::Download, err))?;
+
+    // download from youtube, unless it's been stopped while waiting its turn
+    let download_permit = crate::NETWORK.acquire();
+    cancel
+        .check_start()
+        .map_err(|err| fail(TrackStage::Download, err))?;
+    let stage_started = Instant::now();
+    log::info!(r#"Downloading {}/{}, "{}"..."#, i + 1, num_tracks, source);
+    let section = state.track_data.get(i).and_then(|track| track.section);
+    let downloaded = download_source(
+        i,
+        &source,
+        section,
+        tmp_dir,
+        full_videos,
+        timeouts,
+        album_deadline,
+    )
+    .map_err(|err| fail(TrackStage::Download, err))?;
+    let path = downloaded.path;
+    let length_mismatch = state.track_data[i]
+        .length
+        .zip(downloaded.length)
+        .and_then(|(track, video)| LengthMismatch::check(track, video));
+    if let Some(mismatch) = length_mismatch {
+        log::warn!(
+            "Track {} might be a different version of the song: {mismatch}",
+            i + 1
+        );
+    }
+    let lyrics = track_lyrics(state, i);
+    timings.download = stage_started.elapsed();
+    drop(download_permit);
+    let _convert_permit = crate::CPU.acquire();
+    cancel
+        .check()
+        .map_err(|err| fail(TrackStage::Convert, err))?;
+
+    advance(job, JobState::Converting);
+
+    let mut out_file_paths = Vec::with_capacity(outputs.len());
+    for (n, (profile, out_dir)) in outputs.iter().enumerate() {
+        // convert from webm or whatever to the output format
+        cancel
+            .check()
+            .map_err(|err| fail(TrackStage::Convert, err))?;
+        let stage_started = Instant::now();
+        let deadline = stage_deadline(timeouts.convert, album_deadline);
+        let mut tmp_file_path = PathBuf::from(tmp_dir);
+        tmp_file_path.push(format!("{i}-{n}.{}", profile.format.extension()));
+        let pipeline = &pipelines[n];
+        let filter = pipeline.filter();
+        convert_audio(
+            &path,
+            &tmp_file_path,
+            &id,
+            profile,
+            filter.as_deref(),
+            deadline,
+        )
+        .map_err(|err| fail(TrackStage::Convert, err))?;
+        timings.convert += stage_started.elapsed();
+
+        // post-process, which can add to the tags
+        let mut tag = generate_tags(state, i, lyrics.as_ref());
+        let deadline = stage_deadline(timeouts.convert, album_deadline);
+        for step in pipeline.run(&tmp_file_path, &id, &mut tag, deadline) {
+            timings.add_step(step);
+        }
+
+        // set tags. The track only gets its own copy of the output's cover for as long as it takes
+        // to write it.
+        let stage_started = Instant::now();
+        let deadline = stage_deadline(timeouts.convert, album_deadline);
+        if let Some((img, content_type)) = &arts[n].embedded {
+            tag.add_frame(cover_picture(img, content_type));
+        }
+        let cover = arts[n].file.as_deref();
+        write_tag_with_cover(&tag, cover, &tmp_file_path, profile, &id, deadline)
+            .map_err(|err| fail(TrackStage::Tag, err))?;
+        drop(tag);
+        timings.tag += stage_started.elapsed();
+
+        // copy to out dir
+        let stage_started = Instant::now();
+        let overwrite = config::current().overwrite() || existing[n] == Existing::Mismatched;
+        let out_file_path = move_to_out_dir(i, state, profile, &tmp_file_path, out_dir, overwrite)
+            .map_err(|err| fail(TrackStage::Move, err))?;
+        timings.move_to_out_dir += stage_started.elapsed();
+
+        if let Some(out_file_path) = &out_file_path {
+            write_sidecars(state, i, &source, profile, out_file_path, lyrics.as_ref());
+        }
+        out_file_paths.extend(out_file_path);
+    }
+
+    log::info!("Finished {}/{} ({timings})", i + 1, num_tracks);
+
+    Ok(TrackReport {
+        index: i,
+        video_id: id,
+        title: state.track_data[i].name.clone(),
+        timings,
+        outputs: out_file_paths,
+        source_format: downloaded.format,
+        channel: downloaded.channel,
+        length_mismatch,
+        already_saved: false,
+    })
+}
+
+/// Warns if there should be a cover but there isn't one for any of the outputs, unless none of
+/// them can have one
+fn warn_if_no_art(
+    state: &StateModifyingData,
+    i: usize,
+    outputs: &[(Profile, PathBuf)],
+    arts: &[OutputArt],
+) {
+    let can_have_art = outputs
+        .iter()
+        .any(|(profile, _)| profile.format.has_cover());
+    if can_have_art
+        && arts.iter().all(OutputArt::is_none)
+        && !state.album_data.image.trim().is_empty()
+    {
+        log::warn!(
+            "track {} ({}) will be saved without album art",
+            i + 1,
+            state.track_data[i].name
+        );
+    }
+}
+
+/// The track's lyrics if the config says to embed them, or `None` if they can't be found (which
+/// isn't a failure, since plenty of tracks don't have any)
+fn track_lyrics(state: &StateModifyingData, i: usize) -> Option<scraping::Lyrics> {
+    let settings = config::current().lyrics;
+    if !settings.embed {
+        return None;
+    }
+    let track = &state.track_data[i];
+    let duration = match track.section {
+        Some(Section {
+            start,
+            end: Some(end),
+        }) => Some(end.saturating_sub(start)),
+        Some(_) => None,
+        None => state
+            .track_video(i)
+            .and_then(|video| state.videos[video].duration),
+    };
+    let query = LyricsQuery {
+        artist: track.artist.as_deref().unwrap_or(&state.album_data.artist),
+        title: &track.name,
+        album: &state.album_data.name,
+        duration,
+    };
+    match fetch_lyrics(&query, &settings) {
+        Ok(lyrics) => Some(lyrics),
+        Err(err) => {
+            log::info!(r#"no lyrics for "{}": {err}"#, track.name);
+            None
+        }
+    }
+}
+
+/// What's already where a track would be saved
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Existing {
+    /// There isn't a file there, or it's going to be replaced anyway
+    Missing,
+    /// There's a file there that's kept, so the track doesn't need downloading for this output
+    Kept,
+    /// There's a file there, but its tags aren't this track's, so it's replaced
+    Mismatched,
+}
+
+/// [`check_existing`] for each output, with the config's settings
+fn check_outputs(
+    state: &StateModifyingData,
+    i: usize,
+    outputs: &[(Profile, PathBuf)],
+) -> Vec<Existing> {
+    let config = config::current();
+    outputs
+        .iter()
+        .map(|(profile, out_dir)| {
+            check_existing(
+                state,
+                i,
+                profile,
+                out_dir,
+                config.overwrite(),
+                config.verify_existing_files,
+            )
+        })
+        .collect()
+}
+
+/// Checks for the track's file in `out_dir` before downloading it. If `verify` is set, the file's
+/// title and track number have to match the track's, otherwise it's assumed to be the track.
+fn check_existing(
+    state: &StateModifyingData,
+    i: usize,
+    profile: &Profile,
+    out_dir: &Path,
+    overwrite: bool,
+    verify: bool,
+) -> Existing {
+    let path = out_file_path(i, state, profile, out_dir);
+    if overwrite || !path.exists() {
+        return Existing::Missing;
+    }
+    if !verify {
+        return Existing::Kept;
+    }
+
+    let expected = generate_tags(state, i, None);
+    let expected = (expected.title(), expected.track());
+    match existing_title_and_track(&path, profile.format) {
+        Some((title, track)) if (Some(title.as_str()), track) == expected => Existing::Kept,
+        found => {
+            log::warn!(
+                r#""{}" has the tags {found:?} rather than {expected:?}, so it'll be replaced"#,
+                path.display()
+            );
+            Existing::Mismatched
+        }
+    }
+}
+
+/// The title and track number in a file's tags, or `None` if there isn't a title or the tags can't
+/// be read
+fn existing_title_and_track(path: &Path, format: OutputFormat) -> Option<(String, Option<u32>)> {
+    if format.uses_id3() {
+        let tag = tagging::read_id3(path, format).ok()?;
+        Some((tag.title()?.to_string(), tag.track()))
+    } else {
+        let tags = read_tags(path).ok()?;
+        Some((tags.title()?.to_string(), tags.position().1))
+    }
+}
 
-    // convert from webm or whatever to mp3
-    let tmp_file_path = convert_to_mp3(&path, &id)?;
+/// Each track's own [`TrackData::source`](crate::gui::view_modifying_data::TrackData::source) if
+/// it has one, otherwise the video at the same position in the playlist, along with the track's
+/// index. Skipped tracks are left out.
+///
+/// The playlist isn't scraped if every track that's downloaded has its own source.
+fn get_sources(state: &StateModifyingData) -> Result<Vec<(usize, TrackSource)>, DownloadError> {
+    let overrides: Vec<Option<TrackSource>> = state
+        .track_data
+        .iter()
+        .map(|track| {
+            let source = track.source.as_deref()?;
+            let parsed = TrackSource::parse(source);
+            if parsed.is_none() {
+                log::warn!(r#""{source}" isn't a link, using the playlist instead"#);
+            }
+            parsed
+        })
+        .collect();
+    let skipped = |i: usize| state.track_data.get(i).is_some_and(|track| track.skip);
 
-    // set id3 tags
-    let tag = generate_tags(state, i, img, content_type);
-    tag.write_to_path(&tmp_file_path, id3::Version::Id3v24)?;
+    let has_own_sources = overrides
+        .iter()
+        .enumerate()
+        .all(|(i, source)| source.is_some() || skipped(i));
+    let ids = if !overrides.is_empty() && has_own_sources {
+        Vec::new()
+    } else {
+        get_ids(&state.youtube_url)?
+    };
 
-    // copy to out dir
-    move_to_out_dir(i, state, &tmp_file_path, out_dir)
+    let num_tracks = overrides
+        .iter()
+        .rposition(Option::is_some)
+        .map_or(0, |i| i + 1)
+        .max(ids.len());
+    (0..num_tracks)
+        .filter(|&i| !skipped(i))
+        .map(|i| {
+            overrides
+                .get(i)
+                .cloned()
+                .flatten()
+                .or_else(|| ids.get(i).cloned().map(TrackSource::Youtube))
+                .map(|source| (i, source))
+                .ok_or(DownloadError::MissingSource(i))
+        })
+        .collect()
 }
 
-fn get_ids(url: &str) -> Result<Vec<String>, DownloadError> {
-    let url = music_to_www(url);
+fn get_ids(url: &str) -> Result<Vec<VideoId>, DownloadError> {
+    if let Some(ids) = playlist::parse_video_list(url) {
+        return Ok(ids);
+    }
+
+    let url = playlist::page_url(url);
 
     log::debug!("scraping album data from YouTube...");
     match scrape_playlist(&url) {
@@ -160,165 +1118,933 @@ fn get_ids(url: &str) -> Result<Vec<String>, DownloadError> {
     Ok(scrape_youtube(&url)?.into_iter().map(|t| t.id).collect())
 }
 
-fn get_image(state: &StateModifyingData) -> (Option<Bytes>, Option<String>) {
-    let mut img = None;
-    let mut content_type = None;
+/// An image and its content type
+pub(crate) type Art = (Bytes, &'static str);
+
+/// Downloads the album art, or `None` if there isn't any or it isn't a valid image
+fn get_image(state: &StateModifyingData) -> Option<Art> {
+    if state.album_data.image.trim().is_empty() {
+        return None;
+    }
 
-    match reqwest::blocking::get(&state.album_data.image) {
-        Ok(resp) => {
-            content_type = resp
-                .headers()
-                .get(CONTENT_TYPE)
-                .map(HeaderValue::to_str)
-                .and_then(Result::ok)
-                .map(String::from);
-            img = resp.bytes().ok();
+    match download_image(&state.album_data.image) {
+        Ok(image) => Some(upscale_if_small(state, image)),
+        Err(err) => {
+            log::error!("error when downloading album art: {err}");
+            None
         }
-        Err(err) => log::error!("error when downloading album art: {err}"),
     }
+}
 
-    (img, content_type)
+/// The cover upscaled if it's smaller than the config's `[art_upscale]` size, using the one
+/// from the modify screen's preview if there is one. Falls back to the original if it can't be
+/// upscaled.
+fn upscale_if_small(state: &StateModifyingData, image: Art) -> Art {
+    let settings = config::current().art_upscale;
+    if state.album_data.keep_original_art || !settings.wants(&image.0) {
+        return image;
+    }
+    if let Some(upscaled) = state.previewed_upscale() {
+        return upscaled;
+    }
+    match upscale(&image.0, &settings) {
+        Ok(upscaled) => upscaled,
+        Err(err) => {
+            log::warn!("couldn't upscale the album art: {err}");
+            image
+        }
+    }
 }
 
-fn where_dirs() -> Result<(TempDir, PathBuf), DownloadError> {
-    // IMPORTANT: `TempDir` deleted dir on `drop`;
-    // moving in return so is fine but don't change to be PathBuf or String
-    let tmp_dir = TempDir::new("ytmdl")?;
-    let out_dir = env::var("YTMDL_OUT_DIR").map_or_else(
-        |_| {
-            let mut p = env::current_dir().unwrap_or_default();
-            p.push("ytmdl");
-            p
-        },
-        PathBuf::from,
-    );
-    fs::create_dir_all(out_dir.as_path())?;
-    Ok((tmp_dir, out_dir))
+/// The album art for one of the outputs, shared by all of its tracks. Each track's tag only gets
+/// its own copy of the image as it's written.
+#[derive(Debug)]
+pub(crate) struct OutputArt {
+    /// Put in the tag just before it's written, for ID3 and Opus
+    embedded: Option<Art>,
+    /// Written once for ffmpeg to embed in the other formats, rather than once per track
+    file: Option<PathBuf>,
 }
 
-fn generate_path_name(
-    i: usize,
-    num_tracks: usize,
-    id: &str,
-    tmp_dir: &str,
-) -> Result<String, DownloadError> {
-    // download from youtube
-    log::info!(r#"Downloading {}/{}, id "{}"..."#, i + 1, num_tracks, id);
-    let output = Command::new("yt-dlp")
-        .args([
-            "--audio-quality",
-            "0",
-            "--get-filename",
-            "-P",
-            tmp_dir,
-            "-o",
-            format!("{i}.%(ext)s").as_str(),
-            format!("https://youtu.be/{id}").as_str(),
-        ])
-        .output()?;
-    if !output.status.success() {
-        log::error!("{}", String::from_utf8_lossy(&output.stderr));
-        return Err(DownloadError::YtdlpError(id.to_string()));
-    }
-    let path = String::from_utf8_lossy(&output.stdout);
-    let path = path.trim_end();
-    Ok(path.to_string())
-}
-
-fn dl_from_yt(i: usize, id: &str, path: &str, tmp_dir: &str) -> Result<(), DownloadError> {
-    log::debug!("Downloading {} to {}", id, path);
-    let output = Command::new("yt-dlp")
-        .args([
-            "--audio-quality",
-            "0",
-            "-P",
-            tmp_dir,
-            "-o",
-            format!("{i}.%(ext)s").as_str(),
-            format!("https://youtu.be/{id}").as_str(),
-        ])
-        .output()?;
-    if !output.status.success() {
-        log::error!("{}", String::from_utf8_lossy(&output.stderr));
-        return Err(DownloadError::YtdlpError(id.to_string()));
+impl OutputArt {
+    /// The `n`th output's art, written to `tmp_dir` if ffmpeg embeds it. There's none for formats
+    /// that can't have a cover.
+    fn new(art: Option<Art>, profile: &Profile, n: usize, tmp_dir: &Path) -> Self {
+        if !profile.format.has_cover() {
+            return Self {
+                embedded: None,
+                file: None,
+            };
+        }
+        let Some((img, _)) = art.as_ref().filter(|_| profile.format.attaches_cover()) else {
+            return Self {
+                embedded: art,
+                file: None,
+            };
+        };
+        let path = tmp_dir.join(format!("art-{n}.cover"));
+        match fs::write(&path, img) {
+            Ok(()) => Self {
+                embedded: None,
+                file: Some(path),
+            },
+            Err(err) => {
+                log::warn!("couldn't write the album art for ffmpeg, so each track will: {err}");
+                Self {
+                    embedded: art,
+                    file: None,
+                }
+            }
+        }
     }
 
-    Ok(())
+    fn is_none(&self) -> bool {
+        self.embedded.is_none() && self.file.is_none()
+    }
 }
 
-fn convert_to_mp3(old_path: &str, id: &str) -> Result<PathBuf, DownloadError> {
-    let mut path = PathBuf::from(old_path);
-    if Path::new(old_path)
-        .extension()
-        .map_or(false, |ext| ext.eq_ignore_ascii_case("mp3"))
-    {
-        Ok(old_path.into())
-    } else {
-        path.set_extension("mp3");
-        log::debug!(
-            r#"Converting "{}" to "{}""#,
-            old_path,
-            path.to_string_lossy()
-        );
-        let output = Command::new("ffmpeg")
-            .args(["-i", old_path, path.to_string_lossy().as_ref()])
-            .output()?;
-        if output.status.success() {
-            Ok(path)
-        } else {
-            log::error!("{}", String::from_utf8_lossy(&output.stderr));
-            Err(DownloadError::FfmpegError(id.to_string()))
+/// The album art as it should be embedded for `profile`: scaled down to fit
+/// [`Profile::art_px_limit`] and [`Profile::max_art_kb`], and made a JPEG if it's shrunk or for
+/// [`Profile::folder_jpg`]. Falls back to the original if ffmpeg can't do it.
+pub(crate) fn art_for_profile(art: Option<&Art>, profile: &Profile, tmp_dir: &Path) -> Option<Art> {
+    let (img, content_type) = art?;
+    if !needs_shrinking(img, content_type, profile) {
+        return Some((img.clone(), content_type));
+    }
+
+    let max_size = profile.max_art_kb.map(|kb| kb as usize * 1000);
+    match shrink_art(img, max_size, profile.art_px_limit(), tmp_dir) {
+        Ok(jpeg) => Some((jpeg, "image/jpeg")),
+        Err(err) => {
+            log::warn!("couldn't shrink the album art: {err}");
+            Some((img.clone(), content_type))
         }
     }
 }
 
-#[allow(clippy::cast_possible_truncation)]
-fn generate_tags(
-    state: &StateModifyingData,
-    i: usize,
-    img: Option<&[u8]>,
-    content_type: Option<&str>,
-) -> Tag {
-    let mut tag = Tag::new();
-    tag.set_album(&state.album_data.name);
-    tag.set_year(state.album_data.year);
-    if let Some(dr) = state.album_data.released {
-        tag.set_date_released(dr);
-    }
-    tag.set_track((i + 1) as u32);
-    tag.set_total_tracks(state.track_data.len() as u32);
-    tag.set_artist(&state.album_data.artist);
-    tag.set_genre(&state.album_data.genre);
-    tag.set_title(&state.track_data[i].name);
-    if let (Some(content_type), Some(img)) = (content_type, img) {
-        tag.add_frame(Picture {
-            mime_type: content_type.to_string(),
-            picture_type: PictureType::CoverFront,
-            description: String::new(),
-            data: img.to_vec(),
+/// Whether the art is too big for `profile` in bytes or pixels, or needs to be a JPEG
+fn needs_shrinking(img: &[u8], content_type: &str, profile: &Profile) -> bool {
+    let too_big = profile
+        .max_art_kb
+        .map_or(false, |kb| img.len() > kb as usize * 1000);
+    let too_large = match (profile.art_px_limit(), image_dimensions(img)) {
+        (Some(max_px), Some((width, height))) => width.max(height) > max_px,
+        _ => false,
+    };
+    let needs_jpeg = profile.folder_jpg && content_type != "image/jpeg";
+    too_big || too_large || needs_jpeg
+}
+
+/// The sizes [`shrink_art`] tries scaling an image of `dimensions` down to, largest first. `None`
+/// keeps it at its own size, which is tried first if it isn't bigger than `max_px`, so an image
+/// that only needs to be a JPEG (or a bit smaller in bytes) isn't scaled for nothing.
+fn art_sizes(dimensions: Option<(u32, u32)>, max_px: Option<u32>) -> Vec<Option<u32>> {
+    const SIZES: &[u32] = &[1000, 800, 600, 500, 400, 300, 200];
+
+    let largest = dimensions.map(|(width, height)| width.max(height));
+    let fits = match (max_px, largest) {
+        (Some(max_px), Some(largest)) => largest <= max_px,
+        (Some(_), None) => false,
+        (None, _) => true,
+    };
+    let first = if fits { None } else { max_px };
+    let smaller = SIZES.iter().copied().filter(|size| {
+        max_px.map_or(true, |max_px| *size < max_px)
+            && largest.map_or(true, |largest| *size < largest)
+    });
+    std::iter::once(first).chain(smaller.map(Some)).collect()
+}
+
+/// Re-encodes the image as a JPEG with ffmpeg, no bigger than `max_px` on either side, scaling it
+/// down until it fits in `max_size` bytes (or it's as small as it goes)
+fn shrink_art(
+    img: &[u8],
+    max_size: Option<usize>,
+    max_px: Option<u32>,
+    tmp_dir: &Path,
+) -> Result<Bytes, DownloadError> {
+    let original = tmp_dir.join("art-original");
+    let shrunk = tmp_dir.join("art-shrunk.jpg");
+    fs::write(&original, img)?;
+    let mut jpeg = Vec::new();
+    for size in art_sizes(image_dimensions(img), max_px) {
+        let mut command = Command::new("ffmpeg");
+        command.args(["-y", "-v", "error", "-i"]).arg(&original);
+        if let Some(size) = size {
+            command.args([
+                "-vf",
+                &format!(
+                    "scale='min({size},iw)':'min({size},ih)':force_original_aspect_ratio=decrease"
+                ),
+            ]);
+        }
+        command.args(["-q:v", "3"]).arg(&shrunk);
+        let output = output_with_deadline(&mut command, None, "shrinking the album art")?;
+        if !output.status.success() {
+            return Err(DownloadError::FfmpegError(
+                String::from("the album art"),
+                stderr(&output),
+            ));
+        }
+        jpeg = fs::read(&shrunk)?;
+        if max_size.map_or(true, |max_size| jpeg.len() <= max_size) {
+            break;
+        }
+    }
+    let _ = fs::remove_file(&original);
+    let _ = fs::remove_file(&shrunk);
+    // so a broken cover isn't embedded instead of the original
+    if !matches!(verify_image(&jpeg), Ok("image/jpeg")) {
+        return Err(DownloadError::FfmpegError(
+            String::from("the album art"),
+            String::from("it didn't write a JPEG"),
+        ));
+    }
+    Ok(jpeg.into())
+}
+
+/// Saves the album art as `folder.jpg` in `out_dir` if it's a JPEG, which some devices show
+/// instead of embedded art
+pub(crate) fn write_folder_jpg(out_dir: &Path, art: Option<&Art>) {
+    if let Some((img, "image/jpeg")) = art {
+        if let Err(err) = fs::write(out_dir.join("folder.jpg"), img) {
+            log::warn!("couldn't write folder.jpg: {err}");
+        }
+    }
+}
+
+/// Where tracks are downloaded to and converted in before being moved to the output dir
+enum WorkDir {
+    /// Kept between runs so yt-dlp can resume partially downloaded files
+    Persistent(PathBuf),
+    /// Fallback for if there's no cache dir.
+    ///
+    /// IMPORTANT: `TempDir` deletes the dir on `drop`
+    Temp(TempDir),
+}
+
+impl WorkDir {
+    /// A dir in the cache dir specific to the album, so re-running the same album after it fails
+    /// picks up where it left off
+    fn new(youtube_url: &str) -> Result<Self, DownloadError> {
+        let key = playlist::album_key(youtube_url)
+            .map(|id| sanitize_file_name(&id).into_owned())
+            .filter(|id| !id.is_empty());
+
+        if let (Some(mut path), Some(key)) = (paths::cache_dir(), key) {
+            path.push("partial");
+            path.push(key);
+            fs::create_dir_all(&path)?;
+            Ok(Self::Persistent(path))
+        } else {
+            Ok(Self::Temp(TempDir::new("ytmdl")?))
+        }
+    }
+
+    fn path(&self) -> &Path {
+        match self {
+            Self::Persistent(path) => path,
+            Self::Temp(tmp_dir) => tmp_dir.path(),
+        }
+    }
+
+    /// Deletes the dir since there's nothing left to resume
+    fn finish(self) {
+        if let Self::Persistent(path) = self {
+            if let Err(err) = fs::remove_dir_all(&path) {
+                log::warn!(r#"couldn't delete "{}": {err}"#, path.display());
+            }
+        }
+    }
+}
+
+/// Makes sure the output dir for each profile exists and can be written to, then makes the work
+/// dir. That's checked before anything's downloaded, so it fails once rather than for every track.
+fn where_dirs(
+    youtube_url: &str,
+    profiles: &[Profile],
+) -> Result<(WorkDir, Vec<PathBuf>), DownloadError> {
+    let out_dirs = out_dirs(profiles)?;
+    let work_dir = WorkDir::new(youtube_url)?;
+    Ok((work_dir, out_dirs))
+}
+
+fn out_dirs(profiles: &[Profile]) -> Result<Vec<PathBuf>, DownloadError> {
+    profiles
+        .iter()
+        .map(|profile| {
+            let out_dir = profile.resolved_out_dir();
+            match paths::ensure_writable(&out_dir) {
+                Ok(()) => Ok(out_dir),
+                Err(err) => Err(DownloadError::OutDirNotWritable {
+                    path: out_dir,
+                    reason: err.to_string(),
+                }),
+            }
+        })
+        .collect()
+}
+
+/// What yt-dlp says about a source before downloading it, from [`generate_path_name`]
+#[derive(Debug, Clone)]
+struct SourceInfo {
+    /// Where it'll be downloaded to
+    path: String,
+    /// See [`DownloaderSettings::format`](config::DownloaderSettings::format)
+    format: Option<SourceFormat>,
+    channel: Option<Channel>,
+    /// The video's, or its section's once it's been downloaded
+    length: Option<Duration>,
+}
+
+/// yt-dlp `--print` template for the video's length in seconds, read by [`parse_length`]
+const LENGTH_TEMPLATE: &str = "%(duration)s";
+
+/// What's printed for [`LENGTH_TEMPLATE`], which is "NA" if yt-dlp doesn't know
+fn parse_length(printed: &str) -> Option<Duration> {
+    printed
+        .trim()
+        .parse::<f64>()
+        .ok()
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64)
+}
+
+/// yt-dlp `--print` template for everything in a [`SourceInfo`] but the path, as one tab-separated
+/// record read by [`parse_source_record`]
+fn source_record_template() -> String {
+    format!(
+        "{LENGTH_TEMPLATE}\t{}\t{}",
+        SourceFormat::TEMPLATE,
+        Channel::TEMPLATE
+    )
+}
+
+/// What's printed for [`source_record_template`]: the length, format and channel. Each field is
+/// found by where it is in the record, so one yt-dlp doesn't know (and prints as "NA") doesn't
+/// shift the others.
+fn parse_source_record(printed: &str) -> (Option<Duration>, Option<SourceFormat>, Option<Channel>) {
+    /// The record up to the end of the fields `template` prints, and the rest
+    fn split<'a>(record: &'a str, template: &str) -> (&'a str, &'a str) {
+        let fields = template.split('\t').count();
+        match record.match_indices('\t').nth(fields - 1) {
+            Some((at, _)) => (&record[..at], &record[at + 1..]),
+            None => (record, ""),
+        }
+    }
+    let printed = printed.trim_end_matches(['\r', '\n']);
+    let (length, rest) = split(printed, LENGTH_TEMPLATE);
+    let (format, channel) = split(rest, SourceFormat::TEMPLATE);
+    (
+        parse_length(length),
+        SourceFormat::parse(format),
+        Channel::parse(channel),
+    )
+}
+
+/// The path yt-dlp will download the source to (`stem` with the extension added), and the rest of
+/// its [`SourceInfo`]
+fn generate_path_name(
+    stem: &str,
+    source: &TrackSource,
+    tmp_dir: &str,
+    deadline: Option<Instant>,
+) -> Result<SourceInfo, DownloadError> {
+    let mut command = network::ytdlp();
+    command.args(config::current().downloader.format_args());
+    command.args([
+        "--print",
+        source_record_template().as_str(),
+        "--print",
+        "filename",
+        "-P",
+        tmp_dir,
+        "-o",
+        format!("{stem}.%(ext)s").as_str(),
+        source.url().as_str(),
+    ]);
+    let output = output_with_deadline(
+        &mut command,
+        deadline,
+        &format!("generating the file name for {source}"),
+    )?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(DownloadError::YtdlpError(
+            source.to_string(),
+            stderr(&output),
+        ));
+    }
+    // the record's printed first, then the file name
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let mut lines = stdout.lines();
+    let (length, format, channel) = parse_source_record(lines.next().unwrap_or_default());
+    let path = lines.next().unwrap_or_default().trim_end();
+    if let Some(format) = &format {
+        log::debug!("Downloading {source} as format {format}");
+    }
+    Ok(SourceInfo {
+        path: path.to_string(),
+        format,
+        channel,
+        length,
+    })
+}
+
+/// Whether aria2c can be found, checked the first time it's asked for
+static ARIA2C_FOUND: Lazy<bool> = Lazy::new(|| {
+    let found = tool_version("aria2c", "--version").is_some();
+    if !found {
+        log::warn!("aria2c isn't installed, so yt-dlp will download on its own");
+    }
+    found
+});
+
+/// Downloads the source to `stem` (with its extension) in `tmp_dir`, from [`generate_path_name`].
+/// Only downloads the `section` of the source if there is one, which yt-dlp cuts out with ffmpeg.
+///
+/// Downloads with aria2c if it's turned on in the [`DownloaderSettings`](config::DownloaderSettings)
+/// and installed.
+fn dl_from_yt(
+    stem: &str,
+    source: &TrackSource,
+    section: Option<Section>,
+    path: &str,
+    tmp_dir: &str,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    log::debug!("Downloading {} to {}", source, path);
+    let mut command = network::ytdlp();
+    let downloader = config::current().downloader;
+    command.args(downloader.format_args());
+    command.args([
+        "--continue",
+        "-P",
+        tmp_dir,
+        "-o",
+        format!("{stem}.%(ext)s").as_str(),
+        source.url().as_str(),
+    ]);
+    if let Some(section) = section {
+        command.args([
+            "--download-sections",
+            section.ytdlp_arg().as_str(),
+            "--force-keyframes-at-cuts",
+        ]);
+    }
+    if downloader.aria2c && *ARIA2C_FOUND {
+        command.args(downloader.ytdlp_args());
+    }
+    let output = output_with_deadline(&mut command, deadline, &format!("downloading {source}"))?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(DownloadError::YtdlpError(
+            source.to_string(),
+            stderr(&output),
+        ));
+    }
+
+    Ok(())
+}
+
+/// The videos more than one track is a section of, by their [`TrackSource`]. Each one's downloaded
+/// whole by the first of its tracks to need it, while the others wait, so the tracks can be cut out
+/// of it instead of yt-dlp downloading each one's section separately. `None` if that failed.
+type FullVideos = HashMap<String, OnceCell<Option<SourceInfo>>>;
+
+fn full_videos(state: &StateModifyingData, sources: &[(usize, TrackSource)]) -> FullVideos {
+    let mut sections: HashMap<String, usize> = HashMap::new();
+    for (i, source) in sources {
+        if state.track_data[*i].section.is_some() {
+            *sections.entry(source.to_string()).or_default() += 1;
+        }
+    }
+    sections
+        .into_iter()
+        .filter(|(_, count)| *count > 1)
+        .map(|(source, _)| (source, OnceCell::new()))
+        .collect()
+}
+
+/// Downloads the track to the work dir, with where it was saved and the rest of its
+/// [`SourceInfo`]. The length is the section's if it's a section.
+///
+/// A `section` of one of the [`FullVideos`] is cut out of the whole video with [`cut_section`],
+/// which is downloaded first if none of its other tracks have yet. If that fails (or it's the only
+/// section of its video) yt-dlp downloads just the section.
+fn download_source(
+    i: usize,
+    source: &TrackSource,
+    section: Option<Section>,
+    tmp_dir: &str,
+    full_videos: &FullVideos,
+    timeouts: Timeouts,
+    album_deadline: Option<Instant>,
+) -> Result<SourceInfo, DownloadError> {
+    let full_video = section.and_then(|section| {
+        let video = full_videos.get(&source.to_string())?.get_or_init(|| {
+            let deadline = stage_deadline(timeouts.download, album_deadline);
+            download_full_video(i, source, tmp_dir, deadline)
+                .map_err(|err| {
+                    log::warn!("couldn't download all of {source}, so downloading each section instead: {err}");
+                })
+                .ok()
+        });
+        Some((section, video.as_ref()?))
+    });
+    if let Some((section, video)) = full_video {
+        let deadline = stage_deadline(timeouts.convert, album_deadline);
+        let path = cut_section(i, source, &video.path, section, tmp_dir, deadline)?;
+        return Ok(SourceInfo {
+            path,
+            length: video.length.map(|length| section.length(length)),
+            ..video.clone()
         });
     }
-    tag.set_album_artist(&state.album_data.artist);
+
+    let stem = i.to_string();
+    let deadline = stage_deadline(timeouts.download, album_deadline);
+    let mut info = generate_path_name(&stem, source, tmp_dir, deadline)?;
+    let deadline = stage_deadline(timeouts.download, album_deadline);
+    dl_from_yt(&stem, source, section, &info.path, tmp_dir, deadline)?;
+    if let Some(section) = section {
+        info.length = info.length.map(|length| section.length(length));
+    }
+    Ok(info)
+}
+
+/// Downloads the whole of one of the [`FullVideos`], for track `i` (the first of its tracks to need
+/// it)
+fn download_full_video(
+    i: usize,
+    source: &TrackSource,
+    tmp_dir: &str,
+    deadline: Option<Instant>,
+) -> Result<SourceInfo, DownloadError> {
+    log::info!(r#"Downloading all of "{source}" to split it into tracks..."#);
+    let stem = format!("full-{i}");
+    let info = generate_path_name(&stem, source, tmp_dir, deadline)?;
+    dl_from_yt(&stem, source, None, &info.path, tmp_dir, deadline)?;
+    Ok(info)
+}
+
+/// Cuts track `i`'s `section` out of the whole video at `full_path` with ffmpeg, keeping the audio
+/// as it is since it's converted afterwards. The cuts are as close as they can be without
+/// re-encoding, which for audio is within a few milliseconds.
+fn cut_section(
+    i: usize,
+    source: &TrackSource,
+    full_path: &str,
+    section: Section,
+    tmp_dir: &str,
+    deadline: Option<Instant>,
+) -> Result<String, DownloadError> {
+    let extension = Path::new(full_path)
+        .extension()
+        .map(|ext| ext.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let path = Path::new(tmp_dir).join(format!("{i}.{extension}"));
+    log::debug!("Cutting {section} of {source} out to {}", path.display());
+
+    let mut command = Command::new("ffmpeg");
+    command.args(["-y", "-v", "error", "-ss"]);
+    command.arg(section.start.as_secs_f64().to_string());
+    if let Some(end) = section.end {
+        command.arg("-to").arg(end.as_secs_f64().to_string());
+    }
+    command.args(["-i", full_path, "-vn", "-c:a", "copy"]);
+    command.arg(&path);
+    let output = output_with_deadline(
+        &mut command,
+        deadline,
+        &format!("cutting {section} out of {source}"),
+    )?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(DownloadError::FfmpegError(
+            source.to_string(),
+            stderr(&output),
+        ));
+    }
+    path.into_os_string()
+        .into_string()
+        .map_err(|_| DownloadError::TmpDirError)
+}
+
+/// Converts the downloaded file to `profile`'s format and quality (see
+/// [`Profile::encode_settings`]) through the ffmpeg audio `filter` (from [`Pipeline::filter`]), or
+/// just copies it if it's already in that format, there's no filter and the quality is left up to
+/// ffmpeg.
+///
+/// Leaves the original file alone so it can be converted more than once.
+pub(crate) fn convert_audio(
+    old_path: &str,
+    new_path: &Path,
+    id: &str,
+    profile: &Profile,
+    filter: Option<&str>,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    let format = profile.format;
+    let quality_args = profile.encode_settings().ffmpeg_args();
+    let same_format = Path::new(old_path)
+        .extension()
+        .map_or(false, |ext| ext.eq_ignore_ascii_case(format.extension()));
+
+    if same_format && quality_args.is_empty() && filter.is_none() {
+        fs::copy(old_path, new_path)?;
+        Ok(())
+    } else {
+        log::debug!(
+            r#"Converting "{}" to "{}""#,
+            old_path,
+            new_path.to_string_lossy()
+        );
+        #[cfg(feature = "libav")]
+        {
+            crate::transcode::convert(
+                Path::new(old_path),
+                new_path,
+                &profile.encode_settings(),
+                filter,
+                deadline,
+            )
+            .map_err(|err| match err {
+                crate::transcode::TranscodeError::Timeout => {
+                    DownloadError::Timeout(format!("converting {id}"))
+                }
+                err => {
+                    log::error!("{err}");
+                    DownloadError::TranscodeError(id.to_string(), err.to_string())
+                }
+            })
+        }
+        #[cfg(not(feature = "libav"))]
+        {
+            let mut command = Command::new("ffmpeg");
+            // any video (like an embedded thumbnail) is dropped, since the cover's added when
+            // tagging
+            command.args(["-y", "-i", old_path, "-vn", "-c:a", format.encoder()]);
+            if let Some(filter) = filter {
+                command.args(["-af", filter]);
+            }
+            command.args(quality_args);
+            command.arg(new_path.as_os_str());
+            let output = output_with_deadline(&mut command, deadline, &format!("converting {id}"))?;
+            if output.status.success() {
+                Ok(())
+            } else {
+                log::error!("{}", String::from_utf8_lossy(&output.stderr));
+                Err(DownloadError::FfmpegError(id.to_string(), stderr(&output)))
+            }
+        }
+    }
+}
+
+/// Writes `tag` to a converted track. MP3s get it as ID3, and the other formats are rewritten by
+/// ffmpeg with it as [`tagging::ffmpeg_metadata`], plus the cover as an attached picture for the
+/// ones that can have one.
+pub(crate) fn write_tag(
+    tag: &Tag,
+    path: &Path,
+    profile: &Profile,
+    id: &str,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    write_tag_with_cover(tag, None, path, profile, id, deadline)
+}
+
+/// [`write_tag`], but ffmpeg embeds the image at `cover` (if it's given) instead of the tag's
+fn write_tag_with_cover(
+    tag: &Tag,
+    cover: Option<&Path>,
+    path: &Path,
+    profile: &Profile,
+    id: &str,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    if profile.format.uses_id3() {
+        return Ok(tagging::write_id3(
+            tag,
+            path,
+            profile.format,
+            profile.tag_version.into(),
+        )?);
+    }
+
+    let metadata = tagging::ffmpeg_metadata(tag, profile.format);
+    let metadata_path = path.with_extension("ffmetadata");
+    fs::write(&metadata_path, tagging::ffmetadata(&metadata))?;
+    // written next to the track if there isn't a shared one
+    let written_cover = match tag.pictures().next() {
+        Some(picture) if cover.is_none() && profile.format.attaches_cover() => {
+            let cover_path = path.with_extension("cover");
+            fs::write(&cover_path, &picture.data)?;
+            Some(cover_path)
+        }
+        _ => None,
+    };
+    let cover = cover.or(written_cover.as_deref());
+
+    let mut command = Command::new("ffmpeg");
+    command.args(["-y", "-f", "ffmetadata", "-i"]);
+    command.arg(&metadata_path);
+    if let Some(cover) = cover {
+        command.arg("-i").arg(cover);
+    }
+    // the audio goes last, after the metadata and cover
+    command.arg("-i").arg(path);
+    let audio = if cover.is_some() { 2 } else { 1 };
+    command.args(["-map", &format!("{audio}:a"), "-map_metadata", "0"]);
+    if cover.is_some() {
+        command.args(["-map", "1:v", "-disposition:v", "attached_pic"]);
+    }
+    if profile.format == OutputFormat::Opus {
+        // Ogg keeps the comments on the stream
+        command.args(["-map_metadata:s:a", "0:g"]);
+    }
+    let result = remux(command, path, id, deadline);
+
+    let _ = fs::remove_file(&metadata_path);
+    if let Some(written_cover) = &written_cover {
+        let _ = fs::remove_file(written_cover);
+    }
+    result
+}
+
+/// Copies the tags and cover from `from` to `to` with ffmpeg, which can read them from any format
+/// and converts them to `profile`'s
+fn copy_tags(
+    from: &Path,
+    to: &Path,
+    profile: &Profile,
+    id: &str,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    let mut command = Command::new("ffmpeg");
+    command.arg("-y").arg("-i").arg(from).arg("-i").arg(to);
+    command.args(["-map", "1:a", "-map_metadata", "0"]);
+    if profile.format.has_cover() && profile.format != OutputFormat::Opus {
+        command.args(["-map", "0:v?", "-disposition:v", "attached_pic"]);
+    }
+    if profile.format == OutputFormat::Aiff {
+        // ffmpeg leaves the ID3 chunk out of AIFFs unless it's asked for
+        command.args(["-write_id3v2", "1"]);
+    }
+    if profile.format.uses_id3() {
+        let version = match profile.tag_version {
+            TagVersion::Id3v23 => "3",
+            TagVersion::Id3v24 => "4",
+        };
+        command.args(["-id3v2_version", version]);
+    }
+    remux(command, to, id, deadline)
+}
+
+/// Runs `command` (an ffmpeg command that's missing its output) without re-encoding, into a new
+/// file that then replaces `path`
+fn remux(
+    mut command: Command,
+    path: &Path,
+    id: &str,
+    deadline: Option<Instant>,
+) -> Result<(), DownloadError> {
+    let mut remuxed = path.as_os_str().to_owned();
+    remuxed.push(".tagged.");
+    remuxed.push(path.extension().unwrap_or_default());
+    let remuxed = PathBuf::from(remuxed);
+
+    command.args(["-c", "copy"]).arg(&remuxed);
+    let output = output_with_deadline(&mut command, deadline, &format!("tagging {id}"))?;
+    if output.status.success() {
+        fs::rename(&remuxed, path)?;
+        Ok(())
+    } else {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        let _ = fs::remove_file(&remuxed);
+        Err(DownloadError::FfmpegError(id.to_string(), stderr(&output)))
+    }
+}
+
+/// The cover as an ID3 picture frame, which has its own copy of the image
+pub(crate) fn cover_picture(img: &[u8], content_type: &str) -> Picture {
+    Picture {
+        mime_type: content_type.to_string(),
+        picture_type: PictureType::CoverFront,
+        description: String::new(),
+        data: img.to_vec(),
+    }
+}
+
+/// The track's tags, without the cover, which is added as the tag's written so the tracks can
+/// share one copy of it until then
+#[allow(clippy::cast_possible_truncation)]
+fn generate_tags(state: &StateModifyingData, i: usize, lyrics: Option<&scraping::Lyrics>) -> Tag {
+    let mut tag = Tag::new();
+    tag.set_album(&state.album_data.name);
+    tag.set_year(state.album_data.year);
+    if let Some(dr) = state.album_data.released {
+        tag.set_date_released(dr);
+    }
+    let (number, total) = state.track_number(i);
+    tag.set_track(number as u32);
+    tag.set_total_tracks(total as u32);
+    tag.set_artist(
+        state.track_data[i]
+            .artist
+            .as_deref()
+            .unwrap_or(&state.album_data.artist),
+    );
+    tag.set_genre(&state.album_data.genre);
+    tag.set_title(&state.track_data[i].name);
+    set_sort_order_frames(&mut tag, state, i);
+    tag.set_album_artist(album_artist(state));
+    if let Some(stars) = state.album_data.rating {
+        tag.add_frame(Popularimeter {
+            user: String::from(POPM_USER),
+            rating: popm_rating(stars),
+            counter: 0,
+        });
+    }
+    if state.album_data.favorite {
+        tag.add_frame(ExtendedText {
+            description: String::from("FAVORITE"),
+            value: String::from("1"),
+        });
+    }
+    if let Some(lyrics) = lyrics {
+        add_lyrics_frames(&mut tag, lyrics, config::current().lyrics.synced);
+    }
+    romanize_tag(&mut tag, &config::current().romanize);
     tag
 }
 
+/// The lyrics as text (timestamped if `synced`), and as a SYLT frame if they're timestamped so
+/// players that support it can show each line as it's sung
+fn add_lyrics_frames(tag: &mut Tag, lyrics: &scraping::Lyrics, synced: bool) {
+    if let Some(text) = lyrics.text(synced) {
+        tag.add_frame(Lyrics {
+            lang: String::from("eng"),
+            description: String::new(),
+            text,
+        });
+    }
+    if let Some(lrc) = lyrics.lrc() {
+        tag.add_frame(SynchronisedLyrics {
+            lang: String::from("eng"),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: String::new(),
+            content: parse_lrc(lrc),
+        });
+    }
+}
+
+/// The names and their sort orders, romanized if the config says to
+fn romanize_tag(tag: &mut Tag, settings: &RomanizeSettings) {
+    if !settings.tags {
+        return;
+    }
+    for id in [
+        "TIT2", "TPE1", "TPE2", "TALB", "TSOP", "TSO2", "TSOA", "TSOT",
+    ] {
+        let romanized = tag
+            .get(id)
+            .and_then(|frame| frame.content().text())
+            .map(|text| settings.for_tag(text));
+        if let Some(romanized) = romanized {
+            tag.set_text(id, romanized);
+        }
+    }
+}
+
+/// Most players read a POPM frame from any user, but some only read Windows Media Player's
+const POPM_USER: &str = "Windows Media Player 9 Series";
+
+/// Converts stars out of [`AlbumData::MAX_RATING`](crate::gui::view_modifying_data::AlbumData::MAX_RATING)
+/// to POPM's 1-255, with the same steps as Windows Media Player so players show the same number of
+/// stars
+fn popm_rating(stars: u8) -> u8 {
+    match stars {
+        0 => 0,
+        1 => 1,
+        2 => 64,
+        3 => 128,
+        4 => 196,
+        _ => 255,
+    }
+}
+
+/// [`AlbumData::album_artist`](crate::gui::view_modifying_data::AlbumData::album_artist), falling
+/// back to the artist if it's empty
+fn album_artist(state: &StateModifyingData) -> &str {
+    let album_artist = state.album_data.album_artist.trim();
+    if album_artist.is_empty() {
+        &state.album_data.artist
+    } else {
+        album_artist
+    }
+}
+
+/// Sets the sort order frames (TSOP, TSO2, TSOA and TSOT) so players sort "The Beatles" under B.
+///
+/// Frames are only added when the sort name is different to the name.
+fn set_sort_order_frames(tag: &mut Tag, state: &StateModifyingData, i: usize) {
+    let artist_sort = state.album_data.artist_sort.trim();
+    let artist_sort = if artist_sort.is_empty() {
+        sort_name(&state.album_data.artist)
+    } else {
+        Some(artist_sort.to_string())
+    };
+    // a track-specific artist gets its own sort name
+    let artist_sort = match &state.track_data[i].artist {
+        Some(artist) => sort_name(artist),
+        None => artist_sort,
+    };
+
+    let frames = [
+        ("TSOP", artist_sort),
+        ("TSO2", sort_name(album_artist(state))),
+        ("TSOA", sort_name(&state.album_data.name)),
+        ("TSOT", sort_name(&state.track_data[i].name)),
+    ];
+    for (id, value) in frames {
+        if let Some(value) = value {
+            tag.set_text(id, value);
+        }
+    }
+}
+
+/// Where `profile` saves the track in `out_dir`
+fn out_file_path(
+    i: usize,
+    state: &StateModifyingData,
+    profile: &Profile,
+    out_dir: &Path,
+) -> PathBuf {
+    let romanize = config::current().romanize;
+    out_dir.join(
+        sanitize_file_name(&profile.file_name(
+            &romanize.for_file_name(&state.album_data.artist),
+            &romanize.for_file_name(&state.album_data.name),
+            state.track_number(i).0,
+            &romanize.for_file_name(&state.track_data[i].name),
+        ))
+        .as_ref(),
+    )
+}
+
+/// Where the track ended up, or `None` if there was already a file there that was kept rather
+/// than overwritten
 fn move_to_out_dir(
     i: usize,
     state: &StateModifyingData,
+    profile: &Profile,
     old_path: &Path,
     out_dir: &Path,
-) -> Result<(), DownloadError> {
-    let mut out_file_path = out_dir.to_path_buf();
-    out_file_path.push(
-        sanitize_file_name(
-            format!(
-                "{} - {} - {}.mp3",
-                state.album_data.artist, state.album_data.name, state.track_data[i].name
-            )
-            .as_str(),
-        )
-        .as_ref(),
-    );
+    overwrite: bool,
+) -> Result<Option<PathBuf>, DownloadError> {
+    let out_file_path = out_file_path(i, state, profile, out_dir);
     log::debug!(
         r#"Copying "{}" to "{}""#,
         old_path.to_string_lossy(),
@@ -328,7 +2054,7 @@ fn move_to_out_dir(
         log::warn!(r#""{}" doesn't exist"#, old_path.to_string_lossy());
     }
     if out_file_path.exists() {
-        if env::var("YTMDL_OVERWRITE").map_or(true, |v| v.as_str() == "true") {
+        if overwrite {
             log::debug!(r#"Removing existing "{}""#, out_file_path.to_string_lossy());
             fs::remove_file(out_file_path.as_path())?;
         } else {
@@ -337,12 +2063,652 @@ fn move_to_out_dir(
                 out_file_path.to_string_lossy()
             );
             fs::remove_file(old_path)?;
-            return Ok(());
+            return Ok(None);
         }
     }
-    fs::copy(old_path, out_file_path)?;
+    fs::copy(old_path, &out_file_path)?;
     log::debug!(r#"Deleting temp file"#);
     fs::remove_file(old_path)?;
 
-    Ok(())
+    Ok(Some(out_file_path))
+}
+
+/// Where a track is after [`upgrade_track`], or `None` if it didn't need upgrading
+pub type UpgradeResult = Result<Option<PathBuf>, DownloadError>;
+
+/// Re-downloads a track that has a [`TrackInfo`] sidecar using `profile`'s format and quality,
+/// keeping the tags (and art) it has now. The new file replaces the old one in one go, so there's
+/// never a half-written track.
+///
+/// Returns where it is now, which has a different extension if the format changed, or `None` if
+/// it was already made with these settings.
+///
+/// # Errors
+/// - If the sidecar can't be read, or its source isn't a link
+/// - If yt-dlp or ffmpeg fail
+/// - If the old tags can't be read or written to the new file
+/// - If the new file can't be moved into place
+pub fn upgrade_track(audio: &Path, profile: &Profile) -> UpgradeResult {
+    let mut info = TrackInfo::read(audio)?;
+    let encode = profile.encode_settings();
+    if info.encode == encode {
+        return Ok(None);
+    }
+    let source = TrackSource::parse(&info.source_url)
+        .ok_or_else(|| DownloadError::InvalidSource(info.source_url.clone()))?;
+
+    let timeouts = Timeouts::from_env();
+    let tmp_dir = TempDir::new("ytmdl-upgrade")?;
+    let tmp = tmp_dir.path().to_str().ok_or(DownloadError::TmpDirError)?;
+    log::info!(r#"Downloading "{source}"..."#);
+    let path = generate_path_name("0", &source, tmp, stage_deadline(timeouts.download, None))?.path;
+    let deadline = stage_deadline(timeouts.download, None);
+    dl_from_yt("0", &source, info.track.section, &path, tmp, deadline)?;
+    let converted = tmp_dir
+        .path()
+        .join(format!("upgraded.{}", profile.format.extension()));
+    let id = source.to_string();
+    let deadline = stage_deadline(timeouts.convert, None);
+    let filter = Pipeline::for_profile(profile, &config::current()).filter();
+    convert_audio(&path, &converted, &id, profile, filter.as_deref(), deadline)?;
+
+    // the tags on the file rather than from the sidecar, since they might have been edited since
+    let deadline = stage_deadline(timeouts.convert, None);
+    if info.encode.format.uses_id3() {
+        write_tag(
+            &tagging::read_id3(audio, info.encode.format)?,
+            &converted,
+            profile,
+            &id,
+            deadline,
+        )?;
+    } else {
+        copy_tags(audio, &converted, profile, &id, deadline)?;
+    }
+
+    let new_path = audio.with_extension(profile.format.extension());
+    replace_atomically(&converted, &new_path)?;
+    if new_path != audio {
+        fs::remove_file(audio)?;
+    }
+
+    info.encode = encode;
+    info.downloaded = SystemClock.now();
+    info.sha256 = sha256_file(&new_path)?;
+    info.size = fs::metadata(&new_path)?.len();
+    info.ytmdl_version = env!("CARGO_PKG_VERSION").to_string();
+    info.write(&new_path)?;
+    Ok(Some(new_path))
+}
+
+/// [`upgrade_track`]s every track in `dir` that has a sidecar, carrying on past any that fail
+///
+/// # Errors
+/// If `dir` can't be read
+pub fn upgrade_album(
+    dir: &Path,
+    profile: &Profile,
+) -> Result<Vec<(PathBuf, UpgradeResult)>, DownloadError> {
+    Ok(find_tracks_with_info(dir)?
+        .into_iter()
+        .map(|audio| {
+            log::info!(r#"Upgrading "{}""#, audio.display());
+            let result = upgrade_track(&audio, profile);
+            (audio, result)
+        })
+        .collect())
+}
+
+/// Copies `from` next to `to` first and then renames it over `to`, which is atomic as long as
+/// they're on the same filesystem
+fn replace_atomically(from: &Path, to: &Path) -> io::Result<()> {
+    let mut staging = to.as_os_str().to_owned();
+    staging.push(".upgrading");
+    let staging = PathBuf::from(staging);
+    fs::copy(from, &staging)?;
+    let result = fs::rename(&staging, to);
+    if result.is_err() {
+        let _ = fs::remove_file(&staging);
+    }
+    result
+}
+
+/// Writes the files the profile and config ask for next to the finished track. The track's fine
+/// without them, so failing to write them isn't a failure.
+fn write_sidecars(
+    state: &StateModifyingData,
+    i: usize,
+    source: &TrackSource,
+    profile: &Profile,
+    path: &Path,
+    lyrics: Option<&scraping::Lyrics>,
+) {
+    if profile.info_json {
+        if let Err(err) = write_track_info(state, i, source, profile, path) {
+            log::warn!("couldn't write the info for track {}: {err}", i + 1);
+        }
+    }
+    let lrc = lyrics.and_then(scraping::Lyrics::lrc);
+    if let (true, Some(lrc)) = (config::current().lyrics.lrc_file, lrc) {
+        if let Err(err) = fs::write(path.with_extension("lrc"), lrc) {
+            log::warn!("couldn't write the lyrics for track {}: {err}", i + 1);
+        }
+    }
+}
+
+/// Writes the [`TrackInfo`] sidecar next to the finished track
+fn write_track_info(
+    state: &StateModifyingData,
+    i: usize,
+    source: &TrackSource,
+    profile: &Profile,
+    path: &Path,
+) -> Result<(), TrackInfoError> {
+    TrackInfo {
+        source_url: source.url(),
+        youtube_url: state.youtube_url.clone(),
+        album: state.album_data.clone(),
+        track: state.track_data[i].clone(),
+        track_number: state.track_number(i).0,
+        tracks: state.track_number(i).1,
+        encode: profile.encode_settings(),
+        downloaded: SystemClock.now(),
+        sha256: sha256_file(path)?,
+        size: fs::metadata(path)?.len(),
+        ytmdl_version: env!("CARGO_PKG_VERSION").to_string(),
+    }
+    .write(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::view_modifying_data::TrackData;
+
+    #[test]
+    fn sort_order_frames() {
+        let mut state = StateModifyingData::default();
+        state.album_data.name = String::from("The Album");
+        state.album_data.artist = String::from("The Band");
+        state.track_data = vec![TrackData::new("Song"), TrackData::new("A Song")];
+        state.track_data[1].artist = Some(String::from("Guest"));
+
+        let tag = generate_tags(&state, 0, None);
+        assert_eq!(
+            tag.get("TSOP").and_then(|f| f.content().text()),
+            Some("Band, The")
+        );
+        assert_eq!(
+            tag.get("TSO2").and_then(|f| f.content().text()),
+            Some("Band, The")
+        );
+        assert_eq!(
+            tag.get("TSOA").and_then(|f| f.content().text()),
+            Some("Album, The")
+        );
+        assert!(tag.get("TSOT").is_none());
+
+        state.album_data.album_artist = String::from("Various Artists");
+        let tag = generate_tags(&state, 1, None);
+        assert_eq!(tag.artist(), Some("Guest"));
+        assert_eq!(tag.album_artist(), Some("Various Artists"));
+        assert!(tag.get("TSOP").is_none());
+        assert!(tag.get("TSO2").is_none());
+        assert_eq!(
+            tag.get("TSOT").and_then(|f| f.content().text()),
+            Some("Song, A")
+        );
+    }
+
+    #[test]
+    fn rating_and_favorite() {
+        let mut state = StateModifyingData {
+            track_data: vec![TrackData::new("Song")],
+            ..StateModifyingData::default()
+        };
+        let tag = generate_tags(&state, 0, None);
+        assert!(tag.get("POPM").is_none());
+        assert_eq!(tag.extended_texts().count(), 0);
+
+        state.album_data.rating = Some(4);
+        state.album_data.favorite = true;
+        let tag = generate_tags(&state, 0, None);
+        assert_eq!(
+            tag.get("POPM").and_then(|f| f.content().popularimeter()),
+            Some(&Popularimeter {
+                user: String::from(POPM_USER),
+                rating: 196,
+                counter: 0,
+            })
+        );
+        assert!(tag
+            .extended_texts()
+            .any(|t| t.description == "FAVORITE" && t.value == "1"));
+    }
+
+    #[test]
+    fn sources_without_playlist() {
+        let mut state = StateModifyingData {
+            track_data: vec![TrackData::new("A"), TrackData::new("B")],
+            ..Default::default()
+        };
+        state.track_data[0].source = Some(String::from("https://youtu.be/dQw4w9WgXcQ"));
+        state.track_data[1].source = Some(String::from("https://soundcloud.com/artist/song"));
+
+        // the playlist url is never used since every track has its own source
+        assert_eq!(
+            get_sources(&state).unwrap(),
+            [
+                (0, TrackSource::Youtube("dQw4w9WgXcQ".parse().unwrap())),
+                (
+                    1,
+                    TrackSource::Url(String::from("https://soundcloud.com/artist/song"))
+                ),
+            ]
+        );
+
+        // or every track that isn't skipped
+        state.track_data.push(TrackData {
+            skip: true,
+            ..TrackData::new("Bonus")
+        });
+        state.track_data[0].skip = true;
+        assert_eq!(
+            get_sources(&state).unwrap(),
+            [(
+                1,
+                TrackSource::Url(String::from("https://soundcloud.com/artist/song"))
+            )]
+        );
+    }
+
+    #[test]
+    fn skipped_track_numbers() {
+        let mut state = StateModifyingData {
+            track_data: vec![
+                TrackData::new("Intro"),
+                TrackData::new("Song"),
+                TrackData::new("Outro"),
+            ],
+            ..StateModifyingData::default()
+        };
+        state.track_data[0].skip = true;
+        let tag = generate_tags(&state, 2, None);
+        assert_eq!((tag.track(), tag.total_tracks()), (Some(3), Some(3)));
+
+        state.album_data.renumber_skipped = true;
+        let tag = generate_tags(&state, 2, None);
+        assert_eq!((tag.track(), tag.total_tracks()), (Some(2), Some(2)));
+    }
+
+    #[test]
+    fn art_shrinking() {
+        let png = |width: u32, height: u32| {
+            let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR".to_vec();
+            png.extend(width.to_be_bytes());
+            png.extend(height.to_be_bytes());
+            png.resize(2000, 0);
+            png
+        };
+        // limited to 1000px by default
+        let profile = Profile::default();
+        assert!(needs_shrinking(&png(3000, 3000), "image/png", &profile));
+        assert!(needs_shrinking(&png(800, 1200), "image/png", &profile));
+        assert!(!needs_shrinking(&png(1000, 1000), "image/png", &profile));
+        let any_size = Profile {
+            max_art_px: Some(0),
+            ..Profile::default()
+        };
+        assert!(!needs_shrinking(&png(3000, 3000), "image/png", &any_size));
+
+        let profile = Profile {
+            max_art_kb: Some(1),
+            ..Profile::default()
+        };
+        assert!(needs_shrinking(&png(500, 500), "image/png", &profile));
+        let profile = Profile {
+            folder_jpg: true,
+            ..Profile::default()
+        };
+        assert!(needs_shrinking(&png(500, 500), "image/png", &profile));
+        assert!(!needs_shrinking(&png(500, 500), "image/jpeg", &profile));
+
+        // only scaled if it's too large, and then never up
+        assert_eq!(
+            art_sizes(Some((3000, 3000)), Some(1000)),
+            [
+                Some(1000),
+                Some(800),
+                Some(600),
+                Some(500),
+                Some(400),
+                Some(300),
+                Some(200)
+            ]
+        );
+        assert_eq!(
+            art_sizes(Some((500, 450)), Some(1000)),
+            [None, Some(400), Some(300), Some(200)]
+        );
+        assert_eq!(art_sizes(Some((500, 450)), None)[0], None);
+        assert_eq!(art_sizes(None, Some(600))[..2], [Some(600), Some(500)]);
+    }
+
+    #[test]
+    fn art_shared_between_tracks() {
+        let dir = TempDir::new("ytmdl-output-art").unwrap();
+        let art = || Some((Bytes::from_static(b"cover"), "image/jpeg"));
+        let profile = |format| Profile {
+            format,
+            ..Profile::default()
+        };
+
+        let flac = OutputArt::new(art(), &profile(OutputFormat::Flac), 1, dir.path());
+        assert!(flac.embedded.is_none());
+        let file = flac.file.unwrap();
+        assert_eq!(fs::read(file).unwrap(), b"cover");
+
+        // these are put in each track's tag as it's written rather than by ffmpeg, and until then
+        // they all share the one image
+        for format in [OutputFormat::Mp3, OutputFormat::Opus, OutputFormat::Aiff] {
+            let art = art();
+            let output = OutputArt::new(art.clone(), &profile(format), 0, dir.path());
+            let (img, _) = output.embedded.unwrap();
+            assert_eq!(img.as_ptr(), art.unwrap().0.as_ptr());
+            assert!(output.file.is_none());
+        }
+        assert!(OutputArt::new(None, &profile(OutputFormat::Flac), 2, dir.path()).is_none());
+        assert!(OutputArt::new(art(), &profile(OutputFormat::Wav), 3, dir.path()).is_none());
+    }
+
+    #[test]
+    fn lyrics_frame() {
+        let state = StateModifyingData {
+            track_data: vec![TrackData::new("Song")],
+            ..StateModifyingData::default()
+        };
+        let lyrics = scraping::Lyrics {
+            plain_lyrics: Some(String::from("La la la")),
+            synced_lyrics: None,
+            instrumental: false,
+        };
+        let tag = generate_tags(&state, 0, Some(&lyrics));
+        let text: Vec<&str> = tag.lyrics().map(|lyrics| lyrics.text.as_str()).collect();
+        assert_eq!(text, ["La la la"]);
+        assert_eq!(tag.synchronised_lyrics().count(), 0);
+        assert_eq!(generate_tags(&state, 0, None).lyrics().count(), 0);
+
+        let lyrics = scraping::Lyrics {
+            synced_lyrics: Some(String::from("[00:01.00] La la\n[00:02.50] La")),
+            ..lyrics
+        };
+        let mut tag = Tag::new();
+        add_lyrics_frames(&mut tag, &lyrics, true);
+        let text: Vec<&str> = tag.lyrics().map(|lyrics| lyrics.text.as_str()).collect();
+        assert_eq!(text, ["[00:01.00] La la\n[00:02.50] La"]);
+        let synced: Vec<_> = tag.synchronised_lyrics().collect();
+        assert_eq!(synced.len(), 1);
+        assert_eq!(synced[0].timestamp_format, TimestampFormat::Ms);
+        assert_eq!(
+            synced[0].content,
+            [(1000, String::from("La la")), (2500, String::from("La"))]
+        );
+    }
+
+    #[test]
+    fn existing_files() {
+        let mut state = StateModifyingData::default();
+        state.album_data.name = String::from("Album");
+        state.album_data.artist = String::from("Band");
+        state.track_data = vec![TrackData::new("First"), TrackData::new("Second")];
+        let profile = Profile {
+            format: OutputFormat::Mp3,
+            ..Profile::default()
+        };
+        let out_dir = TempDir::new("ytmdl-existing").unwrap();
+        let check = |i, overwrite, verify| {
+            check_existing(&state, i, &profile, out_dir.path(), overwrite, verify)
+        };
+
+        assert_eq!(check(0, false, true), Existing::Missing);
+        let path = out_file_path(0, &state, &profile, out_dir.path());
+        fs::write(&path, b"").unwrap();
+        assert_eq!(check(0, false, false), Existing::Kept);
+        // there aren't any tags to check, like a file that was cut off
+        assert_eq!(check(0, false, true), Existing::Mismatched);
+        generate_tags(&state, 0, None)
+            .write_to_path(&path, id3::Version::Id3v24)
+            .unwrap();
+        assert_eq!(check(0, false, true), Existing::Kept);
+        assert_eq!(check(0, true, true), Existing::Missing);
+
+        // the second track's tags in the first track's file
+        generate_tags(&state, 1, None)
+            .write_to_path(&path, id3::Version::Id3v24)
+            .unwrap();
+        assert_eq!(check(0, false, true), Existing::Mismatched);
+    }
+
+    #[test]
+    #[cfg(unix)] // runs `echo`
+    fn output_with_deadline_finishes() {
+        let deadline = Some(Instant::now() + Duration::from_secs(10));
+        let output =
+            output_with_deadline(Command::new("echo").arg("hello"), deadline, "echo").unwrap();
+        assert!(output.status.success());
+        assert_eq!(output.stdout, b"hello\n");
+    }
+
+    #[test]
+    #[cfg(unix)] // runs `sleep`
+    fn output_with_deadline_kills() {
+        let started = Instant::now();
+        let deadline = Some(started + Duration::from_millis(200));
+        let err = output_with_deadline(Command::new("sleep").arg("10"), deadline, "sleeping")
+            .unwrap_err();
+        assert!(matches!(err, DownloadError::Timeout(_)));
+        assert!(err.is_retryable());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+
+    #[test]
+    fn disk_space() {
+        let dir = env::temp_dir();
+        let cancel = CancellationToken::default();
+        assert!(wait_for_disk_space(&[dir.as_path()], 0, None, &cancel).is_ok());
+
+        let err = wait_for_disk_space(&[dir.as_path()], u64::MAX, Some(Instant::now()), &cancel)
+            .unwrap_err();
+        assert!(matches!(err, DownloadError::DiskFull { ref path, .. } if path == &dir));
+        assert!(err.is_retryable());
+
+        // stops waiting once it's cancelled, rather than holding up closing the app
+        cancel.cancel();
+        let err = wait_for_disk_space(&[dir.as_path()], u64::MAX, None, &cancel).unwrap_err();
+        assert!(matches!(err, DownloadError::Cancelled));
+    }
+
+    #[test]
+    fn unwritable_out_dir() {
+        let dir = TempDir::new("ytmdl-out-dirs").unwrap();
+        let profile = |out_dir: PathBuf| Profile {
+            out_dir: Some(out_dir),
+            ..Profile::default()
+        };
+        let music = dir.path().join("music");
+        assert_eq!(
+            out_dirs(&[profile(music.clone())]).unwrap(),
+            vec![music.clone()]
+        );
+
+        // a folder can't be made inside a file
+        let not_a_dir = dir.path().join("file");
+        fs::write(&not_a_dir, "").unwrap();
+        let bad = not_a_dir.join("ytmdl");
+        let err = out_dirs(&[profile(music), profile(bad.clone())]).unwrap_err();
+        assert!(matches!(err, DownloadError::OutDirNotWritable { ref path, .. } if *path == bad));
+        assert!(err.hint().unwrap().contains("another folder"));
+        assert!(!err.is_retryable());
+    }
+
+    #[test]
+    fn upgrade_replaces_or_skips() {
+        let dir = TempDir::new("ytmdl-upgrade-test").unwrap();
+        let audio = dir.path().join("Song.mp3");
+        fs::write(&audio, b"old").unwrap();
+        let new = dir.path().join("new.mp3");
+        fs::write(&new, b"new").unwrap();
+        replace_atomically(&new, &audio).unwrap();
+        assert_eq!(fs::read(&audio).unwrap(), b"new");
+        assert!(!dir.path().join("Song.mp3.upgrading").exists());
+
+        let profile = Profile {
+            bitrate: Some(320),
+            ..Profile::default()
+        };
+        TrackInfo {
+            source_url: String::from("not a link"),
+            youtube_url: String::new(),
+            album: crate::gui::view_modifying_data::AlbumData::default(),
+            track: TrackData::new("Song"),
+            track_number: 1,
+            tracks: 1,
+            encode: profile.encode_settings(),
+            downloaded: SystemClock.now(),
+            sha256: String::new(),
+            size: 3,
+            ytmdl_version: String::new(),
+        }
+        .write(&audio)
+        .unwrap();
+        // already made with these settings
+        assert!(upgrade_track(&audio, &profile).unwrap().is_none());
+        let better = Profile {
+            bitrate: None,
+            ..profile
+        };
+        assert!(matches!(
+            upgrade_track(&audio, &better),
+            Err(DownloadError::InvalidSource(_))
+        ));
+    }
+
+    #[test]
+    fn hints() {
+        let ytdlp = |stderr: &str| DownloadError::YtdlpError(String::new(), stderr.to_string());
+        let hint = |err: &DownloadError| err.hint().unwrap_or_default();
+
+        assert!(hint(&ytdlp(
+            "ERROR: unable to download video data: HTTP Error 403: Forbidden"
+        ))
+        .contains("update yt-dlp"));
+        assert!(hint(&ytdlp("ERROR: [youtube] abc: Video unavailable")).contains("unavailable"));
+        assert!(hint(&ytdlp("ERROR: [youtube] abc: Private video")).contains("unavailable"));
+        assert!(hint(&ytdlp("something else")).contains("check the link"));
+
+        let ffmpeg = |stderr: &str| DownloadError::FfmpegError(String::new(), stderr.to_string());
+        assert!(hint(&ffmpeg("Unknown encoder 'libmp3lame'")).contains("codec"));
+        assert_eq!(
+            ffmpeg("Invalid data found when processing input").hint(),
+            None
+        );
+
+        let disk_full = DownloadError::DiskFull {
+            path: PathBuf::new(),
+            available: 0,
+        };
+        assert!(hint(&disk_full).contains("free space"));
+        let no_space = io::Error::from_raw_os_error(if cfg!(windows) { 112 } else { 28 });
+        assert!(hint(&no_space.into()).contains("free space"));
+
+        let forbidden = || io::Error::from(io::ErrorKind::PermissionDenied);
+        assert!(hint(&forbidden().into()).contains("read-only"));
+        let tag_error: DownloadError = id3::Error::from(forbidden()).into();
+        assert!(hint(&tag_error).contains("read-only"));
+        assert_eq!(DownloadError::TmpDirError.hint(), None);
+
+        let track = |source| TrackError {
+            index: 0,
+            video_id: String::new(),
+            title: String::new(),
+            stage: TrackStage::Download,
+            source: Box::new(source),
+        };
+        let failed = |failed| {
+            DownloadError::TracksFailed(Box::new(DownloadReport {
+                failed,
+                ..DownloadReport::default()
+            }))
+        };
+        let same = failed(vec![
+            track(ytdlp("HTTP Error 403")),
+            track(ytdlp("HTTP Error 403")),
+        ]);
+        assert!(hint(&same).contains("update yt-dlp"));
+        let different = failed(vec![
+            track(ytdlp("HTTP Error 403")),
+            track(DownloadError::MissingSource(1)),
+        ]);
+        assert_eq!(different.hint(), None);
+    }
+
+    #[test]
+    fn cancellation_is_shared() {
+        let cancel = CancellationToken::default();
+        let clone = cancel.clone();
+        assert!(clone.check().is_ok());
+        cancel.cancel();
+        assert!(clone.is_cancelled());
+        assert!(matches!(clone.check(), Err(DownloadError::Cancelled)));
+    }
+
+    #[test]
+    fn stage_deadline_picks_earliest() {
+        let now = Instant::now();
+        let album = now + Duration::from_secs(1);
+        assert_eq!(
+            stage_deadline(Some(Duration::from_secs(60)), Some(album)),
+            Some(album)
+        );
+        assert_eq!(stage_deadline(None, Some(album)), Some(album));
+        assert_eq!(stage_deadline(None, None), None);
+        assert!(stage_deadline(Some(Duration::from_secs(60)), None).unwrap() > album);
+    }
+
+    #[test]
+    fn printed_lengths() {
+        assert_eq!(parse_length("234\n"), Some(Duration::from_secs(234)));
+        assert_eq!(parse_length("70.5"), Some(Duration::from_millis(70_500)));
+        assert_eq!(parse_length("NA"), None);
+        assert_eq!(parse_length("0"), None);
+    }
+
+    #[test]
+    fn source_records() {
+        let (length, format, channel) =
+            parse_source_record("129.478\t251\topus\t129.478\tUCstub\tStub Channel\n");
+        assert_eq!(length, Some(Duration::from_millis(129_478)));
+        let format = format.unwrap();
+        assert_eq!(
+            (format.id.as_str(), format.codec.as_deref(), format.kbps),
+            ("251", Some("opus"), Some(129))
+        );
+        let channel = channel.unwrap();
+        assert_eq!(
+            (channel.id.as_str(), channel.name.as_str()),
+            ("UCstub", "Stub Channel")
+        );
+
+        // what yt-dlp doesn't know stays where it is
+        let (length, format, channel) = parse_source_record("NA\t140\tNA\tNA\tUCstub\tNA");
+        assert_eq!(length, None);
+        let format = format.unwrap();
+        assert_eq!(
+            (format.id.as_str(), format.codec, format.kbps),
+            ("140", None, None)
+        );
+        assert_eq!(channel.unwrap().name, "UCstub");
+
+        assert!(matches!(parse_source_record(""), (None, None, None)));
+    }
 }
@@ -0,0 +1,198 @@
+//! Copying downloaded albums to a device, like a DAP's SD card
+
+use crate::{
+    album_art::verify_image,
+    art_for_profile,
+    config::{self, OutputFormat, Profile},
+    convert_audio, cover_picture,
+    postprocess::Pipeline,
+    stage_deadline, tagging,
+    utils::sanitize_file_name,
+    write_folder_jpg, write_tag, DownloadError, Timeouts,
+};
+use bytes::Bytes;
+use id3::{Tag, TagLike};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+/// Where a track was exported to
+pub type ExportResult = Result<PathBuf, DownloadError>;
+
+/// Copies each track in `dir` to a folder with the same name in `mount_point`, carrying on past
+/// any that fail.
+///
+/// With a `profile`, each track is converted to its format and quality (only if they're
+/// different) and renamed with its naming template, using the track's tags, and its tags and art
+/// are made to fit the profile too. Otherwise they're copied as they are.
+///
+/// # Errors
+/// If `dir` can't be read or the folder can't be made on the device
+pub fn export_album(
+    dir: &Path,
+    mount_point: &Path,
+    profile: Option<&Profile>,
+) -> Result<Vec<(PathBuf, ExportResult)>, DownloadError> {
+    let tracks = find_audio(dir)?;
+    let out_dir = mount_point.join(dir.file_name().unwrap_or_default());
+    fs::create_dir_all(&out_dir)?;
+    let tmp_dir = TempDir::new("ytmdl-export")?;
+
+    Ok(tracks
+        .into_iter()
+        .enumerate()
+        .map(|(i, audio)| {
+            log::info!(r#"Exporting "{}""#, audio.display());
+            let result = match profile {
+                Some(profile) => transcode_track(&audio, &out_dir, profile, tmp_dir.path(), i),
+                None => copy_track(&audio, &out_dir),
+            };
+            (audio, result)
+        })
+        .collect())
+}
+
+/// The files in `dir` in any [`OutputFormat`], sorted by name
+fn find_audio(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut tracks = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_audio = path.extension().map_or(false, |ext| {
+            OutputFormat::ALL
+                .iter()
+                .any(|format| ext.eq_ignore_ascii_case(format.extension()))
+        });
+        if path.is_file() && is_audio {
+            tracks.push(path);
+        }
+    }
+    tracks.sort();
+    Ok(tracks)
+}
+
+fn copy_track(audio: &Path, out_dir: &Path) -> ExportResult {
+    let out_path = out_dir.join(audio.file_name().unwrap_or_default());
+    fs::copy(audio, &out_path)?;
+    Ok(out_path)
+}
+
+/// Converts into `tmp_dir` first so a half-converted file never ends up on the device.
+///
+/// Only MP3s' tags are rewritten for the profile; ffmpeg carries over the tags of other formats
+/// when converting them.
+///
+/// Also writes `folder.jpg` for [`Profile::folder_jpg`], which is the same for each track.
+fn transcode_track(
+    audio: &Path,
+    out_dir: &Path,
+    profile: &Profile,
+    tmp_dir: &Path,
+    i: usize,
+) -> ExportResult {
+    let format = OutputFormat::ALL
+        .iter()
+        .copied()
+        .find(|format| {
+            audio
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case(format.extension()))
+        })
+        .unwrap_or(OutputFormat::Mp3);
+    let tag = tagging::read_id3(audio, format).ok();
+    let file_name = export_file_name(audio, tag.as_ref(), profile);
+
+    let converted = tmp_dir.join(format!("{i}.{}", profile.format.extension()));
+    let timeouts = Timeouts::from_env();
+    let deadline = stage_deadline(timeouts.convert, None);
+    let filter = Pipeline::for_profile(profile, &config::current()).filter();
+    convert_audio(
+        &audio.to_string_lossy(),
+        &converted,
+        &file_name,
+        profile,
+        filter.as_deref(),
+        deadline,
+    )?;
+    if let Some(mut tag) = tag {
+        let art = tag
+            .pictures()
+            .next()
+            .map(|picture| Bytes::from(picture.data.clone()))
+            .and_then(|img| {
+                verify_image(&img)
+                    .ok()
+                    .map(|content_type| (img, content_type))
+            });
+        let art = art_for_profile(art.as_ref(), profile, tmp_dir);
+        if let Some((img, content_type)) = &art {
+            tag.remove_all_pictures();
+            tag.add_frame(cover_picture(img, content_type));
+        }
+        if profile.folder_jpg {
+            write_folder_jpg(out_dir, art.as_ref());
+        }
+        let deadline = stage_deadline(timeouts.convert, None);
+        write_tag(&tag, &converted, profile, &file_name, deadline)?;
+    }
+
+    let out_path = out_dir.join(sanitize_file_name(&file_name).as_ref());
+    fs::copy(&converted, &out_path)?;
+    fs::remove_file(&converted)?;
+    Ok(out_path)
+}
+
+/// [`Profile::file_name`] from the track's tags, falling back to its file name for the title
+fn export_file_name(audio: &Path, tag: Option<&Tag>, profile: &Profile) -> String {
+    let stem = audio
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let romanize = config::current().romanize;
+    profile.file_name(
+        &romanize.for_file_name(tag.and_then(TagLike::artist).unwrap_or_default()),
+        &romanize.for_file_name(tag.and_then(TagLike::album).unwrap_or_default()),
+        tag.and_then(TagLike::track).unwrap_or_default() as usize,
+        &romanize.for_file_name(tag.and_then(TagLike::title).unwrap_or(&stem)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn copies_and_renames() {
+        let dir = TempDir::new("ytmdl-export-test").unwrap();
+        let album = dir.path().join("Album");
+        fs::create_dir(&album).unwrap();
+        let audio = album.join("Artist - Album - Song.mp3");
+        fs::write(&audio, b"not really an mp3").unwrap();
+        fs::write(album.join("Artist - Album - Song.info.json"), b"{}").unwrap();
+        let mut tag = Tag::new();
+        tag.set_artist("Artist");
+        tag.set_album("Album");
+        tag.set_track(2);
+        tag.set_title("Song");
+        tag.write_to_path(&audio, id3::Version::Id3v24).unwrap();
+
+        let device = dir.path().join("device");
+        let copied = export_album(&album, &device, None).unwrap();
+        assert_eq!(copied.len(), 1);
+        assert_eq!(
+            copied[0].1.as_ref().unwrap(),
+            &device.join("Album").join("Artist - Album - Song.mp3")
+        );
+
+        // the same format without a bitrate doesn't need ffmpeg
+        let profile = Profile {
+            naming_template: String::from("{track} {title}"),
+            ..Profile::default()
+        };
+        let renamed = export_album(&album, &device, Some(&profile)).unwrap();
+        let out_path = renamed[0].1.as_ref().unwrap();
+        assert_eq!(out_path, &device.join("Album").join("02 Song.mp3"));
+        assert_eq!(Tag::read_from_path(out_path).unwrap().title(), Some("Song"));
+    }
+}
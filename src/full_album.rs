@@ -0,0 +1,242 @@
+use crate::{parsing::format_duration, scraping::YoutubeChapter};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// A playlist item needs to be at least this long to count as a full album upload
+pub const MIN_FULL_ALBUM_LENGTH: Duration = Duration::from_secs(10 * 60);
+
+/// How many times longer than the longest track a playlist item needs to be to count as a full
+/// album upload
+const FULL_ALBUM_RATIO: u32 = 2;
+
+/// Part of a video, for when one video has more than one track in it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Section {
+    pub start: Duration,
+    /// `None` for the rest of the video
+    pub end: Option<Duration>,
+}
+
+impl Section {
+    /// The value for yt-dlp's `--download-sections`
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use ytmdl::full_album::Section;
+    ///
+    /// let section = Section {
+    ///     start: Duration::from_millis(70_500),
+    ///     end: Some(Duration::from_secs(234)),
+    /// };
+    /// assert_eq!(section.ytdlp_arg(), "*70.5-234");
+    ///
+    /// let section = Section { start: Duration::from_secs(234), end: None };
+    /// assert_eq!(section.ytdlp_arg(), "*234-inf");
+    /// ```
+    #[must_use]
+    pub fn ytdlp_arg(&self) -> String {
+        let end = self
+            .end
+            .map_or_else(|| String::from("inf"), |end| end.as_secs_f64().to_string());
+        format!("*{}-{end}", self.start.as_secs_f64())
+    }
+
+    /// How long the section is, in a video that's `video` long
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use ytmdl::full_album::Section;
+    ///
+    /// let video = Duration::from_secs(600);
+    /// let section = Section {
+    ///     start: Duration::from_secs(70),
+    ///     end: Some(Duration::from_secs(234)),
+    /// };
+    /// assert_eq!(section.length(video), Duration::from_secs(164));
+    ///
+    /// let section = Section { start: Duration::from_secs(234), end: None };
+    /// assert_eq!(section.length(video), Duration::from_secs(366));
+    /// ```
+    #[must_use]
+    pub fn length(&self, video: Duration) -> Duration {
+        self.end
+            .unwrap_or(video)
+            .min(video)
+            .saturating_sub(self.start)
+    }
+}
+
+/// Like "1:10–3:54", or "3:54–end"
+impl Display for Section {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.end {
+            Some(end) => write!(
+                f,
+                "{}–{}",
+                format_duration(self.start),
+                format_duration(end)
+            ),
+            None => write!(f, "{}–end", format_duration(self.start)),
+        }
+    }
+}
+
+/// Finds the first playlist item that's much longer than any of the tracks, which is probably a
+/// whole album uploaded as one video.
+///
+/// Returns `None` if none of the tracks have a length to compare against.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::full_album::find_full_album;
+///
+/// let minutes = |m: u64| Some(Duration::from_secs(m * 60));
+/// let tracks = [minutes(3), minutes(4), minutes(5)];
+///
+/// assert_eq!(find_full_album(&[minutes(3), minutes(4), minutes(5)], &tracks), None);
+/// assert_eq!(find_full_album(&[minutes(3), None, minutes(40)], &tracks), Some(2));
+/// assert_eq!(find_full_album(&[minutes(40)], &[None, None]), None);
+/// ```
+#[must_use]
+pub fn find_full_album(
+    item_lengths: &[Option<Duration>],
+    track_lengths: &[Option<Duration>],
+) -> Option<usize> {
+    let longest_track = track_lengths.iter().flatten().max()?;
+    let min_length = (*longest_track * FULL_ALBUM_RATIO).max(MIN_FULL_ALBUM_LENGTH);
+    item_lengths
+        .iter()
+        .position(|length| length.is_some_and(|length| length >= min_length))
+}
+
+/// One section per chapter, or `None` if the number of chapters doesn't match the number of
+/// tracks (so they probably aren't one per track).
+#[must_use]
+pub fn sections_from_chapters(
+    chapters: &[YoutubeChapter],
+    num_tracks: usize,
+) -> Option<Vec<Section>> {
+    if chapters.len() != num_tracks || chapters.is_empty() {
+        return None;
+    }
+
+    Some(
+        chapters
+            .iter()
+            .enumerate()
+            .map(|(i, chapter)| Section {
+                start: Duration::from_secs_f64(chapter.start_time.max(0.0)),
+                // the last chapter goes to the end, in case it's a little off
+                end: (i + 1 < chapters.len())
+                    .then(|| Duration::from_secs_f64(chapter.end_time.max(0.0))),
+            })
+            .collect(),
+    )
+}
+
+/// Sections back to back, assuming the tracks are in order with no gaps between them (which is
+/// a guess, so chapters are better if the video has them).
+///
+/// Returns `None` if any track's length is missing.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::full_album::{sections_from_lengths, Section};
+///
+/// let sections = sections_from_lengths(&[
+///     Some(Duration::from_secs(70)),
+///     Some(Duration::from_secs(164)),
+/// ])
+/// .unwrap();
+/// assert_eq!(sections, [
+///     Section { start: Duration::ZERO, end: Some(Duration::from_secs(70)) },
+///     Section { start: Duration::from_secs(70), end: None },
+/// ]);
+///
+/// assert_eq!(sections_from_lengths(&[Some(Duration::from_secs(70)), None]), None);
+/// ```
+#[must_use]
+pub fn sections_from_lengths(track_lengths: &[Option<Duration>]) -> Option<Vec<Section>> {
+    let mut start = Duration::ZERO;
+    let mut sections = Vec::with_capacity(track_lengths.len());
+    for (i, length) in track_lengths.iter().enumerate() {
+        let end = start + (*length)?;
+        sections.push(Section {
+            start,
+            end: (i + 1 < track_lengths.len()).then_some(end),
+        });
+        start = end;
+    }
+    (!sections.is_empty()).then_some(sections)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(title: &str, start_time: f64, end_time: f64) -> YoutubeChapter {
+        YoutubeChapter {
+            title: title.to_string(),
+            start_time,
+            end_time,
+        }
+    }
+
+    #[test]
+    fn chapters_to_sections() {
+        let chapters = [
+            chapter("One", 0.0, 70.0),
+            chapter("Two", 70.0, 234.5),
+            chapter("Three", 234.5, 400.0),
+        ];
+        assert_eq!(
+            sections_from_chapters(&chapters, 3).unwrap(),
+            [
+                Section {
+                    start: Duration::ZERO,
+                    end: Some(Duration::from_secs(70)),
+                },
+                Section {
+                    start: Duration::from_secs(70),
+                    end: Some(Duration::from_millis(234_500)),
+                },
+                Section {
+                    start: Duration::from_millis(234_500),
+                    end: None,
+                },
+            ]
+        );
+        assert_eq!(sections_from_chapters(&chapters, 4), None);
+        assert_eq!(sections_from_chapters(&[], 0), None);
+    }
+
+    #[test]
+    fn short_albums_arent_full_albums() {
+        let track = Some(Duration::from_secs(60));
+        // three times as long as the longest track, but still under the minimum
+        assert_eq!(
+            find_full_album(&[Some(Duration::from_secs(180))], &[track, track]),
+            None
+        );
+        assert_eq!(
+            find_full_album(&[Some(MIN_FULL_ALBUM_LENGTH)], &[track]),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn section_display() {
+        let section = Section {
+            start: Duration::from_secs(70),
+            end: Some(Duration::from_secs(3723)),
+        };
+        assert_eq!(section.to_string(), "1:10–1:02:03");
+    }
+}
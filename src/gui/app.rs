@@ -1,21 +1,189 @@
 use super::{
-    message::Message, view_link_input::StateLinkInput, view_modifying_data::StateModifyingData,
+    background,
+    message::Message,
+    style,
+    view_discogs_collection::StateDiscogsCollection,
+    view_downloading::StateDownloading,
+    view_link_input::{self, StateLinkInput},
+    view_modifying_data::{
+        AlternativeSearch, ArtPreview, CoverSearch, ImageStatus, Rescrape, StateModifyingData,
+        TrackData,
+    },
+    view_onboarding::StateOnboarding,
+    view_results::StateResults,
+    view_settings::StateSettings,
     ModifyDataInputChange,
 };
-use crate::scraping::scrape_discogs;
-use iced::{Application, Command, Element, Theme};
+use crate::{
+    activity::{self, Activity},
+    album_art::{download_image, find_covers, CoverQuery, UpscalePreview},
+    config::{Config, Quality},
+    deps,
+    history::History,
+    jobs::Job,
+    parsing::parse_tracklist,
+    paths,
+    queue::{self, QueueStatus},
+    scraping::{metadata_site, scrape_chapters, Confidence},
+    updates,
+    utils::unix_time,
+    versions, watch,
+};
+use iced::{
+    clipboard, event,
+    keyboard::{self, KeyCode},
+    subscription,
+    widget::{column, container, focus_next, focus_previous, image, text_input},
+    window, Application, Command, Element, Event, Length, Subscription, Theme,
+};
+use std::{
+    env,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)] // there's only ever one of these
 pub enum App {
+    /// First-run screen that checks for the external tools and writes the initial config
+    Onboarding(StateOnboarding),
     /// Screen to give the link to the YouTube playlist and the Discogs page
     LinkInput(StateLinkInput),
     /// Page that lets a user modify the scraped data to fix errors
     ModifyingData(StateModifyingData),
+    /// Screen to change and save the config
+    Settings(StateSettings),
+    /// Picking a release from the user's Discogs collection or wantlist
+    DiscogsCollection(StateDiscogsCollection),
+    /// While the album downloads in the background
+    Downloading(StateDownloading),
+    /// What each file came out as after a download, and what failed
+    Results(StateResults),
 }
 
 impl Default for App {
     fn default() -> Self {
-        Self::LinkInput(StateLinkInput::default())
+        Self::LinkInput(StateLinkInput::new())
+    }
+}
+
+impl App {
+    /// Closes the window, but first saves the queue to offer to carry on with next time, and
+    /// cancels anything downloading and waits for the tracks in progress to stop cleanly
+    fn close(&mut self) -> Command<Message> {
+        if let Err(err) = queue::save_for_next_launch() {
+            log::error!("couldn't save the queue: {err}");
+        }
+        let downloading = if let Self::Downloading(state) = self {
+            state.cancel.cancel();
+            state.close_when_done = true;
+            true
+        } else {
+            false
+        };
+
+        if queue::cancel_in_flight() {
+            log::info!("Waiting for the queued download to stop before closing...");
+            background::perform(
+                "waiting for the queue to stop",
+                queue::wait_until_idle,
+                |()| Message::ReadyToClose,
+            )
+        } else if downloading {
+            log::info!("Waiting for the download to stop before closing...");
+            Command::none()
+        } else {
+            window::close()
+        }
+    }
+
+    /// Downloads the album in the background, showing how it's going
+    fn start_download(&mut self, mut state: StateModifyingData) -> Command<Message> {
+        // so retrying it carries on with this job, rather than leaving it interrupted
+        if state.job_id.is_none() {
+            state.job_id = Some(Job::new(&state).id);
+        }
+        let downloading = StateDownloading::new(state.clone());
+        let cancel = downloading.cancel.clone();
+        *self = Self::Downloading(downloading);
+        background::perform(
+            "the download",
+            move || crate::download_album_cancellable(&state, &cancel),
+            |result| match result {
+                Ok(report) => Message::DownloadFinished(Arc::new(report)),
+                Err(err) => Message::DownloadFailed(Arc::new(err)),
+            },
+        )
+    }
+
+    /// Opens `dir` in the file manager, just logging if it can't
+    fn open_folder(dir: &Path) {
+        if let Err(err) = paths::open_in_file_manager(dir) {
+            log::error!("couldn't open {}: {err}", dir.display());
+        }
+    }
+
+    /// Whether the window was closed while downloading, and the queue's finished too
+    fn ready_to_close(&self) -> bool {
+        match self {
+            Self::Downloading(state) => state.close_when_done && !queue::is_busy(),
+            _ => false,
+        }
+    }
+
+    /// Switches to the modify screen with `state`, loading what it needs from the saved history
+    /// and starting to download the cover
+    fn show_modifying_data(&mut self, mut state: StateModifyingData) -> Command<Message> {
+        state.load_flagged_sources();
+        *self = Self::ModifyingData(state);
+        self.load_art_preview()
+    }
+
+    /// Downloads the cover on the modify screen to show it, unless there isn't one, it's been
+    /// found not to be an image, or it's already shown
+    fn load_art_preview(&self) -> Command<Message> {
+        let Self::ModifyingData(state) = self else {
+            return Command::none();
+        };
+        let url = state.album_data.image.clone();
+        let shown = state
+            .art_preview
+            .as_ref()
+            .is_some_and(|preview| preview.url == url && preview.image.is_ok());
+        if url.trim().is_empty() || shown || matches!(state.image_status, ImageStatus::Invalid(_)) {
+            return Command::none();
+        }
+
+        background::perform(
+            "loading the cover",
+            move || {
+                let cover = download_image(&url)
+                    .map(|(img, _)| image::Handle::from_memory(img))
+                    .map_err(|err| err.to_string());
+                (url, cover)
+            },
+            |(url, cover)| Message::ArtPreviewLoaded(url, cover),
+        )
+    }
+
+    /// Runs yt-dlp and ffmpeg to see if they're installed, without waiting for them
+    fn check_dependencies() -> Command<Message> {
+        background::perform(
+            "checking the dependencies",
+            deps::check_dependencies,
+            Message::DependenciesChecked,
+        )
+    }
+
+    /// Queues anything new on the watched channels in the background, and sends
+    /// [`Message::WatchChecked`] when it's done
+    fn check_watched() -> Command<Message> {
+        background::perform(
+            "checking the watched channels",
+            || watch::queue_new_releases(&crate::config::current().watch),
+            Message::WatchChecked,
+        )
     }
 }
 
@@ -26,18 +194,465 @@ impl Application for App {
     type Flags = ();
 
     fn new(_flags: Self::Flags) -> (Self, Command<Self::Message>) {
-        (App::default(), Command::batch(vec![]))
+        if Config::exists() {
+            let mut commands = vec![
+                text_input::focus(view_link_input::youtube_link_id()),
+                Self::check_dependencies(),
+            ];
+            if !crate::config::current().skip_update_check {
+                commands.push(background::perform(
+                    "checking for updates",
+                    || {
+                        updates::check()
+                            .map_err(|err| log::warn!("couldn't check for updates: {err}"))
+                            .ok()
+                            .flatten()
+                    },
+                    Message::UpdateChecked,
+                ));
+            }
+            (App::default(), Command::batch(commands))
+        } else {
+            (
+                Self::Onboarding(StateOnboarding::default()),
+                Command::none(),
+            )
+        }
     }
 
     fn title(&self) -> String {
         "ytmdl".into()
     }
 
+    #[allow(clippy::too_many_lines)]
     fn update(&mut self, message: Self::Message) -> Command<Self::Message> {
         match message {
+            Message::FocusNext => return focus_next(),
+            Message::FocusPrevious => return focus_previous(),
+            Message::CloseRequested => return self.close(),
+            Message::UpdateChecked(release) => {
+                if let Some(release) = &release {
+                    log::info!("ytmdl {} is available", release.tag_name);
+                }
+                updates::set_available(release);
+            }
+            Message::CopyUpdateLink => {
+                if let Some(release) = updates::available() {
+                    return clipboard::write(release.html_url);
+                }
+            }
+            Message::DismissUpdate => updates::set_available(None),
+            Message::DependenciesChecked(report) => {
+                for problem in report.problems() {
+                    log::warn!("{problem}");
+                }
+                deps::set_report(Some(report));
+            }
+            Message::RecheckDependencies => return Self::check_dependencies(),
+            Message::DismissDependencies => deps::set_report(None),
+            Message::BackgroundPanicked(what) => log::error!("{what} panicked"),
+            Message::ReadyToClose => {
+                // otherwise it closes once the download stops
+                if !matches!(self, Self::Downloading(_)) {
+                    return window::close();
+                }
+            }
+            Message::OnboardingRecheckTools => {
+                if let Self::Onboarding(state) = self {
+                    state.check_tools();
+                } else {
+                    log::warn!(
+                        "Received `Message::OnboardingRecheckTools` when not in Onboarding state"
+                    );
+                }
+            }
+            Message::UpdateYtdlp => {
+                let downloading = Some(String::from("Downloading..."));
+                match self {
+                    Self::Settings(state) => state.ytdlp_status = downloading,
+                    Self::Onboarding(state) => state.ytdlp_status = downloading,
+                    _ => {
+                        log::warn!(
+                            "Received `Message::UpdateYtdlp` when not in Settings or Onboarding state"
+                        );
+                        return Command::none();
+                    }
+                }
+                return background::perform(
+                    "updating yt-dlp",
+                    || match crate::ytdlp::update() {
+                        Ok(update) => update.to_string(),
+                        Err(err) => {
+                            log::error!("couldn't download yt-dlp: {err}");
+                            format!("Couldn't download yt-dlp: {err}")
+                        }
+                    },
+                    Message::YtdlpUpdated,
+                );
+            }
+            Message::YtdlpUpdated(status) => {
+                match self {
+                    Self::Settings(state) => state.ytdlp_status = Some(status),
+                    Self::Onboarding(state) => {
+                        state.ytdlp_status = Some(status);
+                        state.check_tools();
+                    }
+                    _ => log::info!("{status}"),
+                }
+                return Self::check_dependencies();
+            }
+            Message::OnboardingOutDirChanged(new_text) => {
+                if let Self::Onboarding(state) = self {
+                    state.out_dir = new_text;
+                } else {
+                    log::warn!(
+                        "Received `Message::OnboardingOutDirChanged` when not in Onboarding state"
+                    );
+                }
+            }
+            Message::OnboardingFormatSelected(format) => {
+                if let Self::Onboarding(state) = self {
+                    state.format = format;
+                } else {
+                    log::warn!(
+                        "Received `Message::OnboardingFormatSelected` when not in Onboarding state"
+                    );
+                }
+            }
+            Message::OnboardingFinish => {
+                if let Self::Onboarding(state) = self {
+                    let config = Config {
+                        out_dir: Some(PathBuf::from(state.out_dir.trim()))
+                            .filter(|p| !p.as_os_str().is_empty()),
+                        format: state.format,
+                        ..crate::config::current()
+                    };
+                    if let Some(out_dir) = &config.out_dir {
+                        env::set_var("YTMDL_OUT_DIR", out_dir);
+                    }
+                    match config.save() {
+                        Ok(()) => *self = Self::LinkInput(StateLinkInput::new()),
+                        Err(err) => log::error!("couldn't save config: {err}"),
+                    }
+                } else {
+                    log::warn!("Received `Message::OnboardingFinish` when not in Onboarding state");
+                }
+            }
+            Message::OpenSettings => {
+                if let Self::LinkInput(_) = self {
+                    *self = Self::Settings(StateSettings::default());
+                } else {
+                    log::warn!("Received `Message::OpenSettings` when not in LinkInput state");
+                }
+            }
+            Message::SettingsOutDirChanged(out_dir) => {
+                if let Self::Settings(state) = self {
+                    state.config.out_dir =
+                        Some(PathBuf::from(out_dir)).filter(|p| !p.as_os_str().is_empty());
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsOutDirChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsFormatSelected(format) => {
+                if let Self::Settings(state) = self {
+                    state.config.format = format;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsFormatSelected` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsNamingTemplateChanged(template) => {
+                if let Self::Settings(state) = self {
+                    state.config.naming_template = Some(template).filter(|t| !t.is_empty());
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsNamingTemplateChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsKeepExistingToggled(keep) => {
+                if let Self::Settings(state) = self {
+                    state.config.keep_existing_files = keep;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsKeepExistingToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsLyricsToggled(embed) => {
+                if let Self::Settings(state) = self {
+                    state.config.lyrics.embed = embed;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsLyricsToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsSyncedLyricsToggled(synced) => {
+                if let Self::Settings(state) = self {
+                    state.config.lyrics.synced = synced;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsSyncedLyricsToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsLrcFileToggled(lrc_file) => {
+                if let Self::Settings(state) = self {
+                    state.config.lyrics.lrc_file = lrc_file;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsLrcFileToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsVerifyExistingToggled(verify) => {
+                if let Self::Settings(state) = self {
+                    state.config.verify_existing_files = verify;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsVerifyExistingToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsThreadsChanged(threads) => {
+                if let Self::Settings(state) = self {
+                    state.config.threads = threads;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsThreadsChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsDownloadThreadsChanged(threads) => {
+                if let Self::Settings(state) = self {
+                    state.config.download_threads = threads;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsDownloadThreadsChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsAlbumsAtOnceChanged(albums) => {
+                if let Self::Settings(state) = self {
+                    state.config.albums_at_once = albums;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsAlbumsAtOnceChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsScaleChanged(scale) => {
+                if let Self::Settings(state) = self {
+                    state.config.ui.scale = scale;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsScaleChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsTextSizeChanged(text_size) => {
+                if let Self::Settings(state) = self {
+                    state.config.ui.text_size = text_size;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsTextSizeChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsAccessibleToggled(accessible) => {
+                if let Self::Settings(state) = self {
+                    state.config.ui.accessible = accessible;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsAccessibleToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsBpmToggled(bpm) => {
+                if let Self::Settings(state) = self {
+                    state.config.analysis.bpm = bpm;
+                } else {
+                    log::warn!("Received `Message::SettingsBpmToggled` when not in Settings state");
+                }
+            }
+            Message::SettingsKeyToggled(key) => {
+                if let Self::Settings(state) = self {
+                    state.config.analysis.key = key;
+                } else {
+                    log::warn!("Received `Message::SettingsKeyToggled` when not in Settings state");
+                }
+            }
+            Message::SettingsWatchUrlChanged(url) => {
+                if let Self::Settings(state) = self {
+                    state.watch_url = url;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsWatchUrlChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsWatchAdd => {
+                if let Self::Settings(state) = self {
+                    let url = state.watch_url.trim();
+                    if !url.is_empty() && !state.config.watch.urls.iter().any(|u| u == url) {
+                        state.config.watch.urls.push(url.to_string());
+                    }
+                    state.watch_url.clear();
+                } else {
+                    log::warn!("Received `Message::SettingsWatchAdd` when not in Settings state");
+                }
+            }
+            Message::SettingsWatchRemove(i) => {
+                if let Self::Settings(state) = self {
+                    if i < state.config.watch.urls.len() {
+                        state.config.watch.urls.remove(i);
+                    }
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsWatchRemove` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsWatchIntervalChanged(hours) => {
+                if let Self::Settings(state) = self {
+                    state.config.watch.interval_hours = hours;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsWatchIntervalChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsDiscogsTokenChanged(token) => {
+                if let Self::Settings(state) = self {
+                    state.config.discogs_token = Some(token).filter(|t| !t.trim().is_empty());
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsDiscogsTokenChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsUpdateCheckToggled(check) => {
+                if let Self::Settings(state) = self {
+                    state.config.skip_update_check = !check;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsUpdateCheckToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsAria2cToggled(aria2c) => {
+                if let Self::Settings(state) = self {
+                    state.config.downloader.aria2c = aria2c;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsAria2cToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsConnectionsChanged(connections) => {
+                if let Self::Settings(state) = self {
+                    state.config.downloader.connections = connections;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsConnectionsChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsYtdlpFormatChanged(format) => {
+                if let Self::Settings(state) = self {
+                    state.config.downloader.format = format;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsYtdlpFormatChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsRomanizeFileNamesToggled(file_names) => {
+                if let Self::Settings(state) = self {
+                    state.config.romanize.file_names = file_names;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsRomanizeFileNamesToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsRomanizeTagsToggled(tags) => {
+                if let Self::Settings(state) = self {
+                    state.config.romanize.tags = tags;
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsRomanizeTagsToggled` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsRomanizeCommandChanged(command) => {
+                if let Self::Settings(state) = self {
+                    state.config.romanize.command =
+                        Some(command).filter(|command| !command.trim().is_empty());
+                } else {
+                    log::warn!(
+                        "Received `Message::SettingsRomanizeCommandChanged` when not in Settings state"
+                    );
+                }
+            }
+            Message::SettingsSave => {
+                if let Self::Settings(state) = self {
+                    // like onboarding, a new output dir takes over from the one set at startup, and
+                    // clearing it goes back to the default rather than the old one
+                    if state.config.out_dir != crate::config::current().out_dir {
+                        match &state.config.out_dir {
+                            Some(out_dir) => env::set_var("YTMDL_OUT_DIR", out_dir),
+                            None => env::remove_var("YTMDL_OUT_DIR"),
+                        }
+                    }
+                    match state.config.save() {
+                        Ok(()) => *self = Self::LinkInput(StateLinkInput::new()),
+                        Err(err) => log::error!("couldn't save config: {err}"),
+                    }
+                } else {
+                    log::warn!("Received `Message::SettingsSave` when not in Settings state");
+                }
+            }
+            Message::SettingsCancel => {
+                if let Self::Settings(_) = self {
+                    *self = Self::LinkInput(StateLinkInput::new());
+                } else {
+                    log::warn!("Received `Message::SettingsCancel` when not in Settings state");
+                }
+            }
+            Message::SaveDiagnostics => {
+                return if let Self::Settings(state) = self {
+                    state.diagnostics_status = Some(String::from("Saving..."));
+                    background::perform(
+                        "saving the diagnostics",
+                        || match crate::diagnostics::save_bundle() {
+                            Ok(path) => format!("Saved to {}", path.display()),
+                            Err(err) => {
+                                log::error!("couldn't save the diagnostics: {err}");
+                                format!("Couldn't save: {err}")
+                            }
+                        },
+                        Message::DiagnosticsSaved,
+                    )
+                } else {
+                    log::warn!("Received `Message::SaveDiagnostics` when not in Settings state");
+                    Command::none()
+                };
+            }
+            Message::DiagnosticsSaved(status) => {
+                if let Self::Settings(state) = self {
+                    state.diagnostics_status = Some(status);
+                } else {
+                    log::info!("{status}");
+                }
+            }
             Message::YoutubeLinkInputChanged(new_text) => {
                 if let Self::LinkInput(state) = self {
-                    state.youtube_link = new_text;
+                    state.set_youtube_link(new_text);
                 } else {
                     log::warn!(
                         "Received `Message::YoutubeLinkInputChanged` when not in LinkInput state"
@@ -46,66 +661,993 @@ impl Application for App {
             }
             Message::DiscogsLinkInputChanged(new_text) => {
                 if let Self::LinkInput(state) = self {
-                    state.discogs_link = new_text;
+                    state.set_discogs_link(new_text);
                 } else {
                     log::warn!(
                         "Received `Message::DiscogsLinkInputChanged` when not in LinkInput state"
                     );
                 }
             }
-            Message::SubmitLinks { youtube, discogs } => match scrape_discogs(discogs.as_str()) {
-                Ok(scraped_discogs) => {
-                    *self = Self::ModifyingData(StateModifyingData::new(youtube, &scraped_discogs));
+            Message::OpenDiscogsCollection => {
+                if let Self::LinkInput(state) = self {
+                    *self = Self::DiscogsCollection(StateDiscogsCollection::new(
+                        state.youtube_link.clone(),
+                        state.discogs_link.clone(),
+                    ));
+                } else {
+                    log::warn!(
+                        "Received `Message::OpenDiscogsCollection` when not in LinkInput state"
+                    );
                 }
-                Err(err) => {
-                    log::warn!("{err}");
-                    match StateModifyingData::new_without_discogs(youtube) {
-                        Ok(new_state) => *self = Self::ModifyingData(new_state),
-                        Err(err) => log::error!("{err}"),
-                    }
+            }
+            Message::DiscogsListSelected(list) => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.load(list, 1);
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscogsListSelected` when not in DiscogsCollection state"
+                    );
                 }
-            },
-            Message::ModifyDataInputChanged(change) => {
-                if let App::ModifyingData(data) = self {
-                    match change {
-                        ModifyDataInputChange::AlbumName(s) => data.album_data.name = s,
-                        ModifyDataInputChange::Artist(s) => data.album_data.artist = s,
-                        ModifyDataInputChange::Genre(s) => data.album_data.genre = s,
-                        ModifyDataInputChange::Year(s) => {
-                            if let Ok(y) = s.parse() {
-                                data.album_data.year = y;
-                            }
-                        }
-                        ModifyDataInputChange::Tracks { index, value } => {
-                            data.track_data[index].name = value;
-                        }
-                        ModifyDataInputChange::Image(s) => data.album_data.image = s,
-                    }
+            }
+            Message::DiscogsFilterChanged(filter) => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.filter = filter;
                 } else {
                     log::warn!(
-                        "Received `Message::ModifyDataInputChanged` when not in ModifyingData state"
+                        "Received `Message::DiscogsFilterChanged` when not in DiscogsCollection state"
                     );
                 }
             }
-            Message::Download => {
-                if let App::ModifyingData(state) = self {
-                    if let Err(err) = crate::download_album(state) {
-                        log::error!("{err}");
-                    }
-                    *self = Self::LinkInput(StateLinkInput::default());
+            Message::DiscogsPageChanged(page) => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.load(state.list, page);
                 } else {
-                    log::warn!("Received `Message::Download` when not in ModifyingData state");
+                    log::warn!(
+                        "Received `Message::DiscogsPageChanged` when not in DiscogsCollection state"
+                    );
                 }
             }
-        }
-
-        Command::none()
-    }
-
+            Message::DiscogsSearchChanged(search) => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.search = search;
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscogsSearchChanged` when not in DiscogsCollection state"
+                    );
+                }
+            }
+            Message::DiscogsSearch => {
+                return if let Self::DiscogsCollection(state) = self {
+                    state.search()
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscogsSearch` when not in DiscogsCollection state"
+                    );
+                    Command::none()
+                };
+            }
+            Message::DiscogsSearched(identifier, results) => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.searched(identifier, results);
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscogsSearched` when not in DiscogsCollection state"
+                    );
+                }
+            }
+            Message::ClearDiscogsSearch => {
+                if let Self::DiscogsCollection(state) = self {
+                    state.results = None;
+                } else {
+                    log::warn!(
+                        "Received `Message::ClearDiscogsSearch` when not in DiscogsCollection state"
+                    );
+                }
+            }
+            Message::DiscogsReleasePicked(url) => {
+                if let Self::DiscogsCollection(state) = self {
+                    let youtube_link = std::mem::take(&mut state.youtube_link);
+                    *self = Self::LinkInput(StateLinkInput {
+                        youtube_link,
+                        discogs_link: url,
+                        ..StateLinkInput::new()
+                    });
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscogsReleasePicked` when not in DiscogsCollection state"
+                    );
+                }
+            }
+            Message::CloseDiscogsCollection => {
+                if let Self::DiscogsCollection(state) = self {
+                    *self = Self::LinkInput(StateLinkInput {
+                        youtube_link: std::mem::take(&mut state.youtube_link),
+                        discogs_link: std::mem::take(&mut state.discogs_link),
+                        ..StateLinkInput::new()
+                    });
+                } else {
+                    log::warn!(
+                        "Received `Message::CloseDiscogsCollection` when not in DiscogsCollection state"
+                    );
+                }
+            }
+            Message::CloseResults => {
+                if let Self::Results(_) = self {
+                    *self = Self::LinkInput(StateLinkInput::new());
+                } else {
+                    log::warn!("Received `Message::CloseResults` when not in Results state");
+                }
+            }
+            Message::OpenFolder(dir) => Self::open_folder(&dir),
+            Message::OpenOutDir => {
+                // the one being edited if on the settings screen
+                let dir = match self {
+                    Self::Settings(state) => state.config.default_profile().resolved_out_dir(),
+                    _ => crate::config::current()
+                        .default_profile()
+                        .resolved_out_dir(),
+                };
+                if dir.is_dir() {
+                    Self::open_folder(&dir);
+                } else {
+                    log::error!(
+                        "{} doesn't exist yet; it's made when something's downloaded",
+                        dir.display()
+                    );
+                }
+            }
+            Message::RetryFailed => {
+                if let Self::Results(state) = self {
+                    if let Some(retry) = state.retry.take() {
+                        return self.start_download(retry);
+                    }
+                } else {
+                    log::warn!("Received `Message::RetryFailed` when not in Results state");
+                }
+            }
+            Message::OtherOutDirChanged(dir) => {
+                if let Self::Results(state) = self {
+                    state.other_out_dir = Some(dir);
+                } else {
+                    log::warn!("Received `Message::OtherOutDirChanged` when not in Results state");
+                }
+            }
+            Message::RetryInOtherOutDir => {
+                if let Self::Results(state) = self {
+                    let dir = state
+                        .other_out_dir
+                        .as_deref()
+                        .map(str::trim)
+                        .unwrap_or_default();
+                    if dir.is_empty() {
+                        return Command::none();
+                    }
+                    let dir = PathBuf::from(dir);
+                    if let Some(mut retry) = state.retry.take() {
+                        retry.profile.out_dir = Some(dir);
+                        return self.start_download(retry);
+                    }
+                } else {
+                    log::warn!("Received `Message::RetryInOtherOutDir` when not in Results state");
+                }
+            }
+            Message::FlagSource(index) => {
+                if let Self::Results(state) = self {
+                    state.flag_source(index);
+                } else {
+                    log::warn!("Received `Message::FlagSource` when not in Results state");
+                }
+            }
+            Message::HistoryExportPathChanged(path) => {
+                if let Self::LinkInput(state) = self {
+                    state.history_export_path = path;
+                } else {
+                    log::warn!(
+                        "Received `Message::HistoryExportPathChanged` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::ExportHistory => {
+                if let Self::LinkInput(state) = self {
+                    let path = PathBuf::from(state.history_export_path.trim());
+                    state.history_export_status = Some(match state.history.export(&path) {
+                        Ok(()) => format!("Exported to {}", path.display()),
+                        Err(err) => {
+                            log::error!("couldn't export the history: {err}");
+                            format!("Couldn't export: {err}")
+                        }
+                    });
+                } else {
+                    log::warn!("Received `Message::ExportHistory` when not in LinkInput state");
+                }
+            }
+            Message::UrlListPathChanged(path) => {
+                if let Self::LinkInput(state) = self {
+                    state.url_list_path = path;
+                } else {
+                    log::warn!(
+                        "Received `Message::UrlListPathChanged` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::ImportUrlList => {
+                return if let Self::LinkInput(state) = self {
+                    state.import_url_list()
+                } else {
+                    log::warn!("Received `Message::ImportUrlList` when not in LinkInput state");
+                    Command::none()
+                };
+            }
+            Message::UrlListImported(status) => {
+                if let Self::LinkInput(state) = self {
+                    state.url_list_status = Some(status);
+                    state.importing_url_list = false;
+                    state.reload_jobs();
+                } else {
+                    log::info!("{status}");
+                }
+            }
+            Message::UpgradeDirChanged(dir) => {
+                if let Self::LinkInput(state) = self {
+                    state.upgrade_dir = dir;
+                } else {
+                    log::warn!("Received `Message::UpgradeDirChanged` when not in LinkInput state");
+                }
+            }
+            Message::UpgradeProfileSelected(name) => {
+                if let Self::LinkInput(state) = self {
+                    state.upgrade_profile = Some(name);
+                } else {
+                    log::warn!(
+                        "Received `Message::UpgradeProfileSelected` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::UpgradeAlbum => {
+                return if let Self::LinkInput(state) = self {
+                    state.upgrade()
+                } else {
+                    log::warn!("Received `Message::UpgradeAlbum` when not in LinkInput state");
+                    Command::none()
+                };
+            }
+            Message::AlbumUpgraded(status) => {
+                if let Self::LinkInput(state) = self {
+                    state.upgrade_status = Some(status);
+                    state.upgrading = false;
+                } else {
+                    log::info!("{status}");
+                }
+            }
+            Message::DeviceAlbumDirChanged(dir) => {
+                if let Self::LinkInput(state) = self {
+                    state.device_album_dir = dir;
+                } else {
+                    log::warn!(
+                        "Received `Message::DeviceAlbumDirChanged` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::DeviceMountPointChanged(dir) => {
+                if let Self::LinkInput(state) = self {
+                    state.device_mount_point = dir;
+                } else {
+                    log::warn!(
+                        "Received `Message::DeviceMountPointChanged` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::DeviceProfileSelected(name) => {
+                if let Self::LinkInput(state) = self {
+                    state.device_profile = name;
+                } else {
+                    log::warn!(
+                        "Received `Message::DeviceProfileSelected` when not in LinkInput state"
+                    );
+                }
+            }
+            Message::ExportToDevice => {
+                return if let Self::LinkInput(state) = self {
+                    state.export_to_device()
+                } else {
+                    log::warn!("Received `Message::ExportToDevice` when not in LinkInput state");
+                    Command::none()
+                };
+            }
+            Message::ExportedToDevice(status) => {
+                if let Self::LinkInput(state) = self {
+                    state.device_export_status = Some(status);
+                    state.exporting = false;
+                } else {
+                    log::info!("{status}");
+                }
+            }
+            Message::ResumeJob(id) => {
+                if let App::LinkInput(state) = self {
+                    if let Some(i) = state.interrupted_jobs.iter().position(|job| job.id == id) {
+                        let job = state.interrupted_jobs.remove(i);
+                        return self.show_modifying_data(job.into_state());
+                    }
+                } else {
+                    log::warn!("Received `Message::ResumeJob` when not in LinkInput state");
+                }
+            }
+            Message::DiscardJob(id) => {
+                if let App::LinkInput(state) = self {
+                    let discard = |job: &Job| {
+                        if job.id == id {
+                            job.delete();
+                        }
+                        job.id != id
+                    };
+                    state.interrupted_jobs.retain(discard);
+                    state.queued_jobs.retain(discard);
+                } else {
+                    log::warn!("Received `Message::DiscardJob` when not in LinkInput state");
+                }
+            }
+            Message::QueueDelayChanged(delay) => {
+                if let App::LinkInput(state) = self {
+                    state.queue_delay = delay;
+                } else {
+                    log::warn!("Received `Message::QueueDelayChanged` when not in LinkInput state");
+                }
+            }
+            Message::StartQueue => queue::set_status(QueueStatus::Running),
+            Message::ScheduleQueue(delay) => queue::schedule(delay),
+            Message::PauseQueue(delay) => queue::pause(delay),
+            Message::StopQueue => queue::set_status(QueueStatus::Stopped),
+            Message::MoveQueuedJob(id, up) => {
+                queue::move_job(&id, up);
+                if let App::LinkInput(state) = self {
+                    state.reload_jobs();
+                }
+            }
+            Message::MakeJobNext(id) => {
+                queue::make_next(&id);
+                if let App::LinkInput(state) = self {
+                    state.reload_jobs();
+                }
+            }
+            Message::CheckWatched => return Self::check_watched(),
+            Message::WatchTick => {
+                let last_checked = History::load().map_or(0, |history| history.watch_checked);
+                if crate::config::current()
+                    .watch
+                    .is_due(last_checked, unix_time())
+                {
+                    return Self::check_watched();
+                }
+            }
+            Message::WatchChecked(queued) => {
+                // shown if it's on the first screen
+                if let Self::LinkInput(state) = self {
+                    state.reload_jobs();
+                    state.new_releases = queued.into_iter().map(|release| release.title).collect();
+                }
+            }
+            Message::QueueTick => {
+                if let Some((job, cancel)) = queue::take_next() {
+                    if let App::LinkInput(state) = self {
+                        state.reload_jobs();
+                    }
+                    return background::perform(
+                        "the queued download",
+                        move || queue::run(&job, &cancel),
+                        |()| Message::QueuedJobFinished,
+                    );
+                }
+            }
+            Message::QueuedJobFinished => {
+                if let App::LinkInput(state) = self {
+                    state.reload_jobs();
+                }
+            }
+            Message::RestoreQueue | Message::DismissSavedQueue => {
+                queue::restore_saved(matches!(message, Message::RestoreQueue));
+                if let App::LinkInput(state) = self {
+                    state.saved_queue = None;
+                } else {
+                    log::warn!("Received `Message::{message:?}` when not in LinkInput state");
+                }
+            }
+            Message::SubmitLinks { youtube, discogs } => {
+                return if let Self::LinkInput(state) = self {
+                    state.submit_links(youtube, discogs)
+                } else {
+                    log::warn!("Received `Message::SubmitLinks` when not in LinkInput state");
+                    Command::none()
+                };
+            }
+            Message::LinksScraped(scraped) => {
+                return if let Self::LinkInput(state) = self {
+                    state.scraping = false;
+                    match scraped {
+                        Ok(new_state) => self.show_modifying_data(*new_state),
+                        Err(err) => {
+                            log::error!("{err}");
+                            Command::none()
+                        }
+                    }
+                } else {
+                    log::warn!("Received `Message::LinksScraped` when not in LinkInput state");
+                    Command::none()
+                };
+            }
+            Message::ModifyDataInputChanged(change) => {
+                if let App::ModifyingData(data) = self {
+                    match change {
+                        ModifyDataInputChange::AlbumName(s) => {
+                            data.album_data.name = s;
+                            data.confidence.title = Confidence::Edited;
+                        }
+                        ModifyDataInputChange::Artist(s) => {
+                            data.album_data.artist = s;
+                            data.confidence.artist = Confidence::Edited;
+                        }
+                        ModifyDataInputChange::AlbumArtist(s) => data.album_data.album_artist = s,
+                        ModifyDataInputChange::ArtistSort(s) => data.album_data.artist_sort = s,
+                        ModifyDataInputChange::Genre(s) => {
+                            data.album_data.genre = s;
+                            data.confidence.genres = Confidence::Edited;
+                        }
+                        ModifyDataInputChange::Rating(rating) => data.album_data.rating = rating,
+                        ModifyDataInputChange::Favorite(b) => data.album_data.favorite = b,
+                        ModifyDataInputChange::Year(s) => {
+                            if let Ok(y) = s.parse() {
+                                data.album_data.year = y;
+                                data.confidence.year = Confidence::Edited;
+                            }
+                        }
+                        ModifyDataInputChange::Tracks { index, value } => {
+                            let track = &mut data.track_data[index];
+                            track.name = value;
+                            track.confidence = Confidence::Edited;
+                        }
+                        ModifyDataInputChange::TrackArtist { index, value } => {
+                            data.track_data[index].artist =
+                                Some(value).filter(|s| !s.trim().is_empty());
+                        }
+                        ModifyDataInputChange::TrackSource { index, value } => {
+                            let track = &mut data.track_data[index];
+                            track.source = Some(value).filter(|s| !s.trim().is_empty());
+                            // the section was for the old source
+                            track.section = None;
+                        }
+                        ModifyDataInputChange::TrackVideo { index, video } => {
+                            data.assign_video(index, video);
+                        }
+                        ModifyDataInputChange::TrackEnabled { index, enabled } => {
+                            if let Some(track) = data.track_data.get_mut(index) {
+                                track.skip = !enabled;
+                            }
+                        }
+                        ModifyDataInputChange::RenumberSkipped(b) => {
+                            data.album_data.renumber_skipped = b;
+                        }
+                        ModifyDataInputChange::AddTrack => {
+                            data.track_data.push(TrackData::default());
+                        }
+                        ModifyDataInputChange::Image(s) => {
+                            data.album_data.image = s;
+                            data.confidence.image = Confidence::Edited;
+                            data.image_status = ImageStatus::Unchecked;
+                            data.upscale_preview = None;
+                        }
+                        ModifyDataInputChange::UpscaleArt(b) => {
+                            data.album_data.keep_original_art = !b;
+                        }
+                        ModifyDataInputChange::Profile(name) => {
+                            if let Some(profile) = crate::config::current()
+                                .profiles()
+                                .into_iter()
+                                .find(|p| p.name == name)
+                            {
+                                data.profile = profile;
+                            }
+                        }
+                        ModifyDataInputChange::Format(format) => {
+                            data.profile.format = format;
+                            if !Quality::choices(format).contains(&data.profile.quality()) {
+                                data.profile.set_quality(Quality::Default);
+                            }
+                        }
+                        ModifyDataInputChange::Quality(quality) => {
+                            data.profile.set_quality(quality);
+                        }
+                        ModifyDataInputChange::FeatBrackets(style) => {
+                            data.title_rules.feat_brackets = style;
+                        }
+                        ModifyDataInputChange::StandardizeFeat(b) => {
+                            data.title_rules.standardize_feat = b;
+                        }
+                        ModifyDataInputChange::VersionBrackets(style) => {
+                            data.title_rules.version_brackets = style;
+                        }
+                        ModifyDataInputChange::CapitalizeVersions(b) => {
+                            data.title_rules.capitalize_versions = b;
+                        }
+                    }
+                } else {
+                    log::warn!(
+                        "Received `Message::ModifyDataInputChanged` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::CheckImage => {
+                return if let App::ModifyingData(state) = self {
+                    state.check_image();
+                    self.load_art_preview()
+                } else {
+                    log::warn!("Received `Message::CheckImage` when not in ModifyingData state");
+                    Command::none()
+                };
+            }
+            Message::PreviewArtUpscale => {
+                return if let App::ModifyingData(state) = self {
+                    let url = state.album_data.image.clone();
+                    background::perform(
+                        "previewing the upscaled cover",
+                        move || {
+                            UpscalePreview::new(&url, &crate::config::current().art_upscale)
+                                .map_err(|err| err.to_string())
+                        },
+                        Message::ArtUpscalePreviewed,
+                    )
+                } else {
+                    log::warn!(
+                        "Received `Message::PreviewArtUpscale` when not in ModifyingData state"
+                    );
+                    Command::none()
+                };
+            }
+            Message::ArtUpscalePreviewed(preview) => {
+                if let App::ModifyingData(state) = self {
+                    state.upscale_preview = Some(preview);
+                } else {
+                    log::warn!(
+                        "Received `Message::ArtUpscalePreviewed` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::FindCovers => {
+                return if let App::ModifyingData(state) = self {
+                    state.cover_search = CoverSearch::Searching;
+                    let query = state.cover_query();
+                    let artist = query.artist.to_string();
+                    let album = query.album.to_string();
+                    let release_image = query.release_image.map(str::to_string);
+                    let video = query.video.cloned();
+                    background::perform(
+                        "searching for covers",
+                        move || {
+                            find_covers(&CoverQuery {
+                                artist: &artist,
+                                album: &album,
+                                release_image: release_image.as_deref(),
+                                video: video.as_ref(),
+                            })
+                        },
+                        Message::CoversFound,
+                    )
+                } else {
+                    log::warn!("Received `Message::FindCovers` when not in ModifyingData state");
+                    Command::none()
+                };
+            }
+            Message::CoversFound(covers) => {
+                if let App::ModifyingData(state) = self {
+                    let covers = covers
+                        .into_iter()
+                        .map(|cover| {
+                            let thumbnail = cover.thumbnail.clone().map(image::Handle::from_memory);
+                            (cover, thumbnail)
+                        })
+                        .collect();
+                    state.cover_search = CoverSearch::Found(covers);
+                } else {
+                    log::warn!("Received `Message::CoversFound` when not in ModifyingData state");
+                }
+            }
+            Message::PickCover(url) => {
+                return if let App::ModifyingData(state) = self {
+                    state.pick_cover(url);
+                    self.load_art_preview()
+                } else {
+                    log::warn!("Received `Message::PickCover` when not in ModifyingData state");
+                    Command::none()
+                };
+            }
+            Message::ArtPreviewLoaded(url, image) => {
+                if let App::ModifyingData(state) = self {
+                    state.art_preview = Some(ArtPreview { url, image });
+                } else {
+                    log::warn!(
+                        "Received `Message::ArtPreviewLoaded` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::Rescrape => {
+                return if let App::ModifyingData(state) = self {
+                    if let Some(scrape) = state.scrape.clone() {
+                        state.rescrape = Rescrape::Scraping;
+                        let activity =
+                            activity::start(Activity::Scraping(metadata_site(&scrape.url)));
+                        return background::perform(
+                            "scraping the release again",
+                            move || {
+                                let _activity = activity;
+                                scrape.rescrape().map_err(|err| err.to_string())
+                            },
+                            Message::Rescraped,
+                        );
+                    }
+                    Command::none()
+                } else {
+                    log::warn!("Received `Message::Rescrape` when not in ModifyingData state");
+                    Command::none()
+                };
+            }
+            Message::Rescraped(newer) => {
+                if let App::ModifyingData(state) = self {
+                    state.rescraped(newer);
+                } else {
+                    log::warn!("Received `Message::Rescraped` when not in ModifyingData state");
+                }
+            }
+            Message::PickConflict(index, pick) => {
+                if let App::ModifyingData(state) = self {
+                    state.pick_conflict(index, pick);
+                } else {
+                    log::warn!("Received `Message::PickConflict` when not in ModifyingData state");
+                }
+            }
+            Message::SearchAlternative(index) => {
+                return if let App::ModifyingData(state) = self {
+                    if let Some((artist, title, length)) = state.alternative_query(index) {
+                        state.alternative = AlternativeSearch::Searching(index);
+                        let activity = activity::start(Activity::Searching);
+                        return background::perform(
+                            "searching for another upload",
+                            move || {
+                                let _activity = activity;
+                                versions::find_alternative(&artist, &title, length)
+                                    .map_err(|err| err.to_string())
+                            },
+                            move |result| Message::AlternativeFound(index, result),
+                        );
+                    }
+                    Command::none()
+                } else {
+                    log::warn!(
+                        "Received `Message::SearchAlternative` when not in ModifyingData state"
+                    );
+                    Command::none()
+                };
+            }
+            Message::AlternativeFound(index, result) => {
+                if let App::ModifyingData(state) = self {
+                    state.alternative_found(index, result);
+                } else {
+                    log::warn!(
+                        "Received `Message::AlternativeFound` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::ResolveChanges(index, accept) => {
+                if let App::ModifyingData(state) = self {
+                    state.resolve_changes(index, accept);
+                } else {
+                    log::warn!(
+                        "Received `Message::ResolveChanges` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::PasteTracklist => {
+                return if let App::ModifyingData(_) = self {
+                    clipboard::read(Message::TracklistPasted)
+                } else {
+                    log::warn!(
+                        "Received `Message::PasteTracklist` when not in ModifyingData state"
+                    );
+                    Command::none()
+                };
+            }
+            Message::TracklistPasted(pasted) => {
+                if let App::ModifyingData(state) = self {
+                    state.pasted_tracklist = Some(parse_tracklist(&pasted.unwrap_or_default()));
+                } else {
+                    log::warn!(
+                        "Received `Message::TracklistPasted` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::SplitFullAlbum => {
+                return if let App::ModifyingData(state) = self {
+                    if let Some(full_album) = state.full_album.as_mut().filter(|f| !f.splitting) {
+                        full_album.splitting = true;
+                        full_album.error = None;
+                        let url = full_album.url();
+                        let activity = activity::start(Activity::Scraping("Youtube"));
+                        return background::perform(
+                            "getting the video's chapters",
+                            move || {
+                                let _activity = activity;
+                                scrape_chapters(&url).unwrap_or_else(|err| {
+                                    log::warn!("couldn't get the chapters of {url}: {err}");
+                                    Vec::new()
+                                })
+                            },
+                            Message::FullAlbumChaptersFetched,
+                        );
+                    }
+                    Command::none()
+                } else {
+                    log::warn!(
+                        "Received `Message::SplitFullAlbum` when not in ModifyingData state"
+                    );
+                    Command::none()
+                };
+            }
+            Message::FullAlbumChaptersFetched(chapters) => {
+                if let App::ModifyingData(state) = self {
+                    state.split_full_album(&chapters);
+                } else {
+                    log::warn!(
+                        "Received `Message::FullAlbumChaptersFetched` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::IgnoreFullAlbum => {
+                if let App::ModifyingData(state) = self {
+                    state.full_album = None;
+                } else {
+                    log::warn!(
+                        "Received `Message::IgnoreFullAlbum` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::ApplyTracklist => {
+                if let App::ModifyingData(state) = self {
+                    state.apply_tracklist();
+                } else {
+                    log::warn!(
+                        "Received `Message::ApplyTracklist` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::DiscardTracklist => {
+                if let App::ModifyingData(state) = self {
+                    state.pasted_tracklist = None;
+                } else {
+                    log::warn!(
+                        "Received `Message::DiscardTracklist` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::ImportPathChanged(path) => {
+                if let App::ModifyingData(state) = self {
+                    state.import_path = path;
+                } else {
+                    log::warn!(
+                        "Received `Message::ImportPathChanged` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::LoadImport => {
+                if let App::ModifyingData(state) = self {
+                    state.load_import();
+                } else {
+                    log::warn!("Received `Message::LoadImport` when not in ModifyingData state");
+                }
+            }
+            Message::ImportColumnSelected(field, column) => {
+                if let App::ModifyingData(state) = self {
+                    if let Some(import) = &mut state.import {
+                        let i = import.table.columns.iter().position(|c| *c == column);
+                        import.mapping.set(field, i);
+                    }
+                } else {
+                    log::warn!(
+                        "Received `Message::ImportColumnSelected` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::ApplyImport => {
+                if let App::ModifyingData(state) = self {
+                    state.apply_import();
+                } else {
+                    log::warn!("Received `Message::ApplyImport` when not in ModifyingData state");
+                }
+            }
+            Message::ApplyTitleRules => {
+                if let App::ModifyingData(state) = self {
+                    state.apply_title_rules();
+                } else {
+                    log::warn!(
+                        "Received `Message::ApplyTitleRules` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::ResetTrackVideos => {
+                if let App::ModifyingData(state) = self {
+                    state.reset_videos();
+                } else {
+                    log::warn!(
+                        "Received `Message::ResetTrackVideos` when not in ModifyingData state"
+                    );
+                }
+            }
+            Message::DiscardImport => {
+                if let App::ModifyingData(state) = self {
+                    state.import = None;
+                } else {
+                    log::warn!("Received `Message::DiscardImport` when not in ModifyingData state");
+                }
+            }
+            Message::QueueAlbum => {
+                if let App::ModifyingData(state) = self {
+                    if matches!(state.image_status, ImageStatus::Invalid(_)) {
+                        log::error!("not queueing until the album cover URL is fixed or cleared");
+                        return Command::none();
+                    }
+                    queue::add(Job::new(state));
+                    *self = Self::LinkInput(StateLinkInput::new());
+                } else {
+                    log::warn!("Received `Message::QueueAlbum` when not in ModifyingData state");
+                }
+            }
+            Message::Download => {
+                let App::ModifyingData(state) = self else {
+                    log::warn!("Received `Message::Download` when not in ModifyingData state");
+                    return Command::none();
+                };
+                if matches!(state.image_status, ImageStatus::Unchecked) {
+                    state.check_image();
+                }
+                if matches!(state.image_status, ImageStatus::Invalid(_)) {
+                    log::error!("not downloading until the album cover URL is fixed or cleared");
+                    return Command::none();
+                }
+                let state = std::mem::take(state);
+                return self.start_download(state);
+            }
+            Message::DownloadTick => {
+                if let Self::Downloading(state) = self {
+                    state.tick();
+                }
+            }
+            // just redraws the status bar
+            Message::StatusTick => {}
+            Message::CancelDownload => {
+                if let Self::Downloading(state) = self {
+                    log::info!("Cancelling the download...");
+                    state.cancel.cancel();
+                } else {
+                    log::warn!("Received `Message::CancelDownload` when not in Downloading state");
+                }
+            }
+            Message::RetryDiskSpace => crate::check_disk_space_now(),
+            Message::DownloadFinished(report) => {
+                if self.ready_to_close() {
+                    return window::close();
+                }
+                if let Self::Downloading(state) = self {
+                    let album = std::mem::take(&mut state.album);
+                    let mut results = StateResults::new(album, Ok(&report));
+                    results.set_retry(std::mem::take(&mut state.retry));
+                    *self = Self::Results(results);
+                } else {
+                    log::warn!(
+                        "Received `Message::DownloadFinished` when not in Downloading state"
+                    );
+                }
+            }
+            Message::DownloadFailed(err) => {
+                log::error!("{err}");
+                if self.ready_to_close() {
+                    return window::close();
+                }
+                if let Self::Downloading(state) = self {
+                    let album = std::mem::take(&mut state.album);
+                    let mut results = StateResults::new(album, Err(&err));
+                    results.set_retry(std::mem::take(&mut state.retry));
+                    *self = Self::Results(results);
+                } else {
+                    log::warn!("Received `Message::DownloadFailed` when not in Downloading state");
+                }
+            }
+        }
+
+        Command::none()
+    }
+
+    /// The screen, with the status bar under it
     fn view(&self) -> Element<Self::Message> {
-        match self {
+        let screen = match self {
+            Self::Onboarding(state) => Self::view_onboarding(state),
             Self::LinkInput(state) => Self::view_link_input(state),
             Self::ModifyingData(state) => Self::view_modifying_data(state),
+            Self::Settings(state) => Self::view_settings(state),
+            Self::DiscogsCollection(state) => Self::view_discogs_collection(state),
+            Self::Downloading(state) => Self::view_downloading(state),
+            Self::Results(state) => Self::view_results(state),
+        };
+        column![
+            container(screen).height(Length::Fill),
+            style::status_bar(&activity::current())
+        ]
+        .into()
+    }
+
+    /// From the settings being edited if on the settings screen, so changes show up live
+    fn scale_factor(&self) -> f64 {
+        match self {
+            Self::Settings(state) => state.config.ui.scale_factor(),
+            _ => crate::config::current().ui.scale_factor(),
+        }
+    }
+
+    fn theme(&self) -> Theme {
+        match self {
+            Self::Settings(state) => style::theme(state.config.ui),
+            _ => style::theme(crate::config::current().ui),
+        }
+    }
+
+    /// Tab and Shift+Tab move between inputs, and closing the window is caught
+    fn subscription(&self) -> Subscription<Self::Message> {
+        let keyboard = subscription::events_with(|event, status| match (event, status) {
+            (
+                Event::Keyboard(keyboard::Event::KeyPressed {
+                    key_code: KeyCode::Tab,
+                    modifiers,
+                }),
+                event::Status::Ignored,
+            ) => Some(if modifiers.shift() {
+                Message::FocusPrevious
+            } else {
+                Message::FocusNext
+            }),
+            // closing is held up until downloads have stopped, see `App::close`
+            (Event::Window(window::Event::CloseRequested), _) => Some(Message::CloseRequested),
+            _ => None,
+        });
+
+        let mut subscriptions = vec![keyboard];
+        // ticks to update the countdown and start queued jobs on time
+        if queue::status().is_active() {
+            subscriptions.push(background::every(
+                "queue-tick",
+                Duration::from_secs(1),
+                || Message::QueueTick,
+            ));
+        }
+        // keeping the status bar up to date, which the download's ticks already do
+        if !activity::current().is_idle() && !matches!(self, Self::Downloading(_)) {
+            subscriptions.push(background::every(
+                "status-tick",
+                Duration::from_millis(500),
+                || Message::StatusTick,
+            ));
+        }
+        // moving the spinner along
+        if matches!(self, Self::Downloading(_)) {
+            subscriptions.push(background::every(
+                "download-tick",
+                Duration::from_millis(250),
+                || Message::DownloadTick,
+            ));
+        }
+        // checking the watched channels, which only happens if it's due
+        let watch = crate::config::current().watch;
+        if !watch.urls.is_empty() && watch.interval_hours > 0 {
+            subscriptions.push(background::every(
+                "watch-tick",
+                Duration::from_secs(60),
+                || Message::WatchTick,
+            ));
         }
+        Subscription::batch(subscriptions)
     }
 }
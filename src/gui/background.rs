@@ -0,0 +1,59 @@
+//! Running blocking work, like downloads, scraping and the waits between ticks, on threads of its
+//! own. iced polls commands and subscriptions on a small pool of threads, so anything that blocks
+//! there holds up the rest of them until it's done.
+
+use super::Message;
+use iced::{
+    futures::{
+        channel::{mpsc, oneshot},
+        StreamExt,
+    },
+    subscription, Command, Subscription,
+};
+use std::{thread, time::Duration};
+
+/// Runs `work` on a new thread, then sends the message `to_message` makes from what it returns. If
+/// it panics, [`Message::BackgroundPanicked`] is sent instead, with `what` it was doing.
+pub fn perform<T: Send + 'static>(
+    what: &'static str,
+    work: impl FnOnce() -> T + Send + 'static,
+    to_message: impl FnOnce(T) -> Message + Send + 'static,
+) -> Command<Message> {
+    Command::perform(
+        async move {
+            let (sender, receiver) = oneshot::channel();
+            thread::spawn(move || {
+                let _ = sender.send(work());
+            });
+            receiver.await
+        },
+        move |result| match result {
+            Ok(value) => to_message(value),
+            Err(_) => Message::BackgroundPanicked(what),
+        },
+    )
+}
+
+/// Sends the message `tick` makes every `interval`, for as long as the subscription is kept. The
+/// waiting is done by a thread that stops once the subscription's dropped, and ticks that come
+/// before the last one was handled are skipped.
+pub fn every(id: &'static str, interval: Duration, tick: fn() -> Message) -> Subscription<Message> {
+    subscription::unfold(
+        id,
+        None,
+        move |ticks: Option<mpsc::Receiver<()>>| async move {
+            let mut ticks = ticks.unwrap_or_else(|| {
+                let (mut sender, receiver) = mpsc::channel(0);
+                thread::spawn(move || loop {
+                    thread::sleep(interval);
+                    if sender.try_send(()).is_err_and(|err| err.is_disconnected()) {
+                        break;
+                    }
+                });
+                receiver
+            });
+            ticks.next().await;
+            (tick(), Some(ticks))
+        },
+    )
+}
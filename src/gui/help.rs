@@ -0,0 +1,129 @@
+//! What each form field is for, shown in a tooltip next to its label. They're all kept here
+//! rather than next to the fields, so they can be reworded or translated in one place.
+
+/// A field that has help
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Help {
+    YoutubeLink,
+    MetadataLink,
+    UrlList,
+    Rescrape,
+    Conflicts,
+    AlbumName,
+    Artist,
+    AlbumArtist,
+    ArtistSort,
+    Year,
+    Genre,
+    Rating,
+    AlbumCover,
+    FindCovers,
+    Profile,
+    Format,
+    Quality,
+    Track,
+    OutDir,
+    NamingTemplate,
+    YtdlpFormat,
+    DiscogsToken,
+    QueueDelay,
+    WatchUrl,
+    CollectionFilter,
+    CollectionSearch,
+}
+
+impl Help {
+    /// The help text, as it's shown in the tooltip
+    #[must_use]
+    pub const fn text(self) -> &'static str {
+        match self {
+            Self::YoutubeLink => {
+                "A Youtube or Youtube Music playlist link, or links to each video (one track each) \
+                 separated by spaces or new lines"
+            }
+            Self::MetadataLink => {
+                "A release on Discogs, MusicBrainz or Bandcamp, or the path to an album folder, \
+                 to take the tags from. Without one, they're taken from the playlist."
+            }
+            Self::UrlList => {
+                "A text or CSV file with an album on each line: its Youtube playlist, then \
+                 optionally its Discogs, MusicBrainz or Bandcamp release. Lines that can't be \
+                 queued are listed with why."
+            }
+            Self::Rescrape => {
+                "Scrapes the release again and lists what's changed on it since, to accept or \
+                 reject one at a time. Nothing you've edited changes unless you accept it."
+            }
+            Self::Conflicts => {
+                "Where the release's tags and the playlist's titles and lengths don't match. The \
+                 release's are used unless you pick the playlist's."
+            }
+            Self::AlbumName => "Saved as the album tag",
+            Self::Artist => {
+                "The artists as they're credited, like \"Artist feat. Guest\", saved as each \
+                 track's artist unless the track has its own"
+            }
+            Self::AlbumArtist => {
+                "The artist the album is filed under in music players. Empty uses the artist."
+            }
+            Self::ArtistSort => {
+                "How the artist is sorted, like \"Beatles, The\". Empty works it out from the \
+                 artist."
+            }
+            Self::Year => "The year the album was released, as a number like 2023",
+            Self::Genre => {
+                "Separate several genres with semicolons, like \"Rock; Pop\". They're saved as \
+                 they're typed."
+            }
+            Self::Rating => "Saved in the tags for players that read them",
+            Self::AlbumCover => {
+                "A link to a JPEG, PNG or other image, or to a Discogs release to use its cover. \
+                 \"Check\" makes sure it's an image."
+            }
+            Self::FindCovers => {
+                "Looks for the cover on the release, the first video's thumbnail, iTunes and the \
+                 Cover Art Archive, and lists the ones it finds with their sizes"
+            }
+            Self::Profile => "Where and how the files are saved, from the profiles in config.toml",
+            Self::Format => {
+                "The audio format the tracks are converted to. WAVs only get the title, artist, \
+                 album, date, track number and genre, and no cover."
+            }
+            Self::Quality => {
+                "The bitrate (or MP3 VBR level) to encode at. \"Default\" uses the profile's."
+            }
+            Self::Track => {
+                "The track's title, its artist if it's different to the album's, and a Youtube \
+                 link to download it from instead of its playlist video"
+            }
+            Self::OutDir => "The folder the albums are saved in",
+            Self::NamingTemplate => {
+                "{artist} is the album's artist, {album} its name, {track} the track number \
+                 (like 03) and {title} the track's title. The extension is added for you."
+            }
+            Self::YtdlpFormat => {
+                "Which of the video's formats yt-dlp downloads, in yt-dlp's format selection \
+                 syntax. Formats are tried from left to right, separated by /."
+            }
+            Self::DiscogsToken => {
+                "Lets ytmdl read your Discogs collection and wantlist, search Discogs, and get \
+                 releases from the API instead of scraping their pages. It's only stored in \
+                 config.toml."
+            }
+            Self::QueueDelay => {
+                "How long to wait before starting or carrying on with the queue, in hours, \
+                 minutes and seconds like 7h, 1h 30m or 2m30s"
+            }
+            Self::WatchUrl => {
+                "A Youtube channel, or a playlist of an artist's albums. Albums that weren't on \
+                 it last time are shown on the first screen."
+            }
+            Self::CollectionFilter => {
+                "Only shows the releases whose artist or title has this in it"
+            }
+            Self::CollectionSearch => {
+                "Searches all of Discogs for the barcode or catalog number printed on your copy"
+            }
+        }
+    }
+}
@@ -1,12 +1,204 @@
+use super::view_modifying_data::StateModifyingData;
+use crate::{
+    album_art::{CoverCandidate, UpscalePreview},
+    compare::Pick,
+    config::{OutputFormat, Quality},
+    deps::DependencyReport,
+    import::TrackField,
+    report::DownloadReport,
+    rescrape::StoredScrape,
+    scraping::{DiscogsIdentifier, DiscogsList, DiscogsSearchResult, PlaylistItem, YoutubeChapter},
+    titles::BracketStyle,
+    updates::Release,
+    watch, DownloadError,
+};
+use iced::widget::image;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
 #[derive(Debug, Clone)]
 pub enum Message {
+    // keyboard navigation
+    FocusNext,
+    FocusPrevious,
+
+    // closing the window
+    CloseRequested,
+    ReadyToClose,
+
+    /// Something [run in the background](super::background::perform) panicked, with what it was
+    /// doing
+    BackgroundPanicked(&'static str),
+
+    // status bar
+    /// Redraws it while something's going on in the background
+    StatusTick,
+
+    // onboarding view
+    OnboardingRecheckTools,
+    OnboardingOutDirChanged(String),
+    OnboardingFormatSelected(OutputFormat),
+    OnboardingFinish,
+    UpdateYtdlp,
+    YtdlpUpdated(String),
+
+    // settings view
+    OpenSettings,
+    SettingsOutDirChanged(String),
+    SettingsFormatSelected(OutputFormat),
+    SettingsNamingTemplateChanged(String),
+    SettingsKeepExistingToggled(bool),
+    SettingsLyricsToggled(bool),
+    SettingsSyncedLyricsToggled(bool),
+    SettingsLrcFileToggled(bool),
+    SettingsVerifyExistingToggled(bool),
+    SettingsThreadsChanged(u8),
+    SettingsDownloadThreadsChanged(u8),
+    SettingsAlbumsAtOnceChanged(u8),
+    SettingsScaleChanged(u16),
+    SettingsTextSizeChanged(u16),
+    SettingsAccessibleToggled(bool),
+    SettingsBpmToggled(bool),
+    SettingsKeyToggled(bool),
+    SettingsWatchUrlChanged(String),
+    SettingsWatchAdd,
+    SettingsWatchRemove(usize),
+    SettingsWatchIntervalChanged(u16),
+    SettingsDiscogsTokenChanged(String),
+    SettingsUpdateCheckToggled(bool),
+    SettingsAria2cToggled(bool),
+    SettingsConnectionsChanged(u8),
+    SettingsYtdlpFormatChanged(String),
+    SettingsRomanizeFileNamesToggled(bool),
+    SettingsRomanizeTagsToggled(bool),
+    SettingsRomanizeCommandChanged(String),
+    SettingsSave,
+    SettingsCancel,
+    SaveDiagnostics,
+    DiagnosticsSaved(String),
+
     // link submit view
+    UpdateChecked(Option<Release>),
+    CopyUpdateLink,
+    DismissUpdate,
+    DependenciesChecked(DependencyReport),
+    RecheckDependencies,
+    DismissDependencies,
     YoutubeLinkInputChanged(String),
     DiscogsLinkInputChanged(String),
-    SubmitLinks { youtube: String, discogs: String },
+    SubmitLinks {
+        youtube: String,
+        discogs: String,
+    },
+    /// The album to modify, or why the links couldn't be scraped
+    LinksScraped(Result<Box<StateModifyingData>, String>),
+    ResumeJob(String),
+    DiscardJob(String),
+    QueueDelayChanged(String),
+    StartQueue,
+    ScheduleQueue(Duration),
+    PauseQueue(Duration),
+    StopQueue,
+    /// Moves the queued job with this ID up if `true`, or down
+    MoveQueuedJob(String, bool),
+    MakeJobNext(String),
+    QueueTick,
+    QueuedJobFinished,
+    RestoreQueue,
+    DismissSavedQueue,
+    CheckWatched,
+    WatchTick,
+    /// With the albums that were queued
+    WatchChecked(Vec<watch::Release>),
+    OpenDiscogsCollection,
+    HistoryExportPathChanged(String),
+    ExportHistory,
+    UrlListPathChanged(String),
+    ImportUrlList,
+    /// With how it went
+    UrlListImported(String),
+    UpgradeDirChanged(String),
+    UpgradeProfileSelected(String),
+    UpgradeAlbum,
+    /// With how it went
+    AlbumUpgraded(String),
+    DeviceAlbumDirChanged(String),
+    DeviceMountPointChanged(String),
+    DeviceProfileSelected(Option<String>),
+    ExportToDevice,
+    /// With how it went
+    ExportedToDevice(String),
+
+    // discogs collection view
+    DiscogsListSelected(DiscogsList),
+    DiscogsFilterChanged(String),
+    DiscogsPageChanged(u32),
+    DiscogsSearchChanged(String),
+    DiscogsSearch,
+    /// What was searched for, with the releases found or why it failed
+    DiscogsSearched(DiscogsIdentifier, Result<Vec<DiscogsSearchResult>, String>),
+    ClearDiscogsSearch,
+    DiscogsReleasePicked(String),
+    CloseDiscogsCollection,
+
+    // downloading view
+    DownloadTick,
+    CancelDownload,
+    /// Check for free space again now, rather than waiting for the next check
+    RetryDiskSpace,
+    DownloadFinished(Arc<DownloadReport>),
+    DownloadFailed(Arc<DownloadError>),
+
+    // results view
+    CloseResults,
+    OpenFolder(PathBuf),
+    /// Opens where files are saved by default, from the results or settings screen
+    OpenOutDir,
+    /// Downloads the album again, which skips the tracks that were saved
+    RetryFailed,
+    /// The folder to download to instead, when the profile's couldn't be written to
+    OtherOutDirChanged(String),
+    /// Downloads the album again to the other folder
+    RetryInOtherOutDir,
+    /// Marks (or unmarks) the channel a result was downloaded from as a bad source
+    FlagSource(usize),
 
     // modify data view
     ModifyDataInputChanged(ModifyDataInputChange),
+    CheckImage,
+    QueueAlbum,
+    SplitFullAlbum,
+    /// The full album video's chapters, empty if it has none or they couldn't be fetched
+    FullAlbumChaptersFetched(Vec<YoutubeChapter>),
+    IgnoreFullAlbum,
+    PasteTracklist,
+    TracklistPasted(Option<String>),
+    ApplyTracklist,
+    DiscardTracklist,
+    ImportPathChanged(String),
+    LoadImport,
+    ImportColumnSelected(TrackField, String),
+    ApplyImport,
+    DiscardImport,
+    ApplyTitleRules,
+    ResetTrackVideos,
+    PreviewArtUpscale,
+    ArtUpscalePreviewed(Result<UpscalePreview, String>),
+    /// The cover's URL, and the cover to show or why it couldn't be downloaded
+    ArtPreviewLoaded(String, Result<image::Handle, String>),
+    FindCovers,
+    CoversFound(Vec<CoverCandidate>),
+    /// The URL of one of the covers that were found
+    PickCover(String),
+    Rescrape,
+    Rescraped(Result<StoredScrape, String>),
+    /// Accepts the change at this index if `true`, or rejects it. All of them if it's `None`.
+    ResolveChanges(Option<usize>, bool),
+    /// Uses the release's or the playlist's value for the conflicting field at this index
+    PickConflict(usize, Pick),
+    /// Searches Youtube for another upload of the track at this index
+    SearchAlternative(usize),
+    /// The track's index, and the video that's closest to its length if any were close enough
+    AlternativeFound(usize, Result<Option<PlaylistItem>, String>),
     Download,
 }
 
@@ -14,8 +206,26 @@ pub enum Message {
 pub enum ModifyDataInputChange {
     AlbumName(String),
     Artist(String),
+    AlbumArtist(String),
+    ArtistSort(String),
     Genre(String),
+    Rating(Option<u8>),
+    Favorite(bool),
     Year(String),
     Tracks { index: usize, value: String },
+    TrackArtist { index: usize, value: String },
+    TrackSource { index: usize, value: String },
+    TrackVideo { index: usize, video: usize },
+    TrackEnabled { index: usize, enabled: bool },
+    RenumberSkipped(bool),
+    AddTrack,
     Image(String),
+    UpscaleArt(bool),
+    Profile(String),
+    Format(OutputFormat),
+    Quality(Quality),
+    FeatBrackets(BracketStyle),
+    StandardizeFeat(bool),
+    VersionBrackets(BracketStyle),
+    CapitalizeVersions(bool),
 }
@@ -4,6 +4,15 @@ pub use app::*;
 mod message;
 pub use message::*;
 
+mod background;
+
+pub mod help;
+pub mod style;
 // pub mod view_scraping_data;
+pub mod view_discogs_collection;
+pub mod view_downloading;
 pub mod view_link_input;
 pub mod view_modifying_data;
+pub mod view_onboarding;
+pub mod view_results;
+pub mod view_settings;
@@ -0,0 +1,188 @@
+use super::{help::Help, Message};
+use crate::{activity::Activity, config::UiSettings, scraping::Confidence};
+use iced::{
+    theme::{self, Palette},
+    widget::{
+        column, container, row, text, text_input, tooltip, Column, Container, Row, Text, TextInput,
+        Tooltip,
+    },
+    Color, Element, Length, Padding, Theme,
+};
+
+/// Black, white and yellow for accessibility mode
+const HIGH_CONTRAST: Palette = Palette {
+    background: Color::BLACK,
+    text: Color::WHITE,
+    primary: Color::from_rgb(1.0, 0.85, 0.0),
+    success: Color::from_rgb(0.3, 1.0, 0.5),
+    danger: Color::from_rgb(1.0, 0.4, 0.4),
+};
+
+/// Orange, for the borders of fields that should be double-checked. It's the same in both themes
+/// since it stands out on black and white.
+const WARNING: Color = Color::from_rgb(0.95, 0.55, 0.1);
+
+#[must_use]
+pub fn theme(ui: UiSettings) -> Theme {
+    if ui.accessible {
+        Theme::custom(HIGH_CONTRAST)
+    } else {
+        Theme::default()
+    }
+}
+
+/// Bigger in accessibility mode so buttons are easier to hit
+#[must_use]
+pub fn button_padding(ui: UiSettings) -> Padding {
+    if ui.accessible {
+        Padding::from([14, 24])
+    } else {
+        Padding::from([5, 10])
+    }
+}
+
+/// Bigger in accessibility mode so text inputs are easier to hit
+#[must_use]
+pub fn input_padding(ui: UiSettings) -> Padding {
+    if ui.accessible {
+        Padding::new(14.0)
+    } else {
+        Padding::new(5.0)
+    }
+}
+
+/// Puts a label above a control, since placeholders disappear once something's been typed
+#[must_use]
+pub fn labelled<'a>(
+    label: impl ToString,
+    control: impl Into<Element<'a, Message>>,
+) -> Column<'a, Message> {
+    column![text(label), control.into()].spacing(5)
+}
+
+/// [`labelled`], with a [`help`] icon after the label
+#[must_use]
+pub fn labelled_with_help<'a>(
+    label: impl ToString,
+    help: Help,
+    control: impl Into<Element<'a, Message>>,
+) -> Column<'a, Message> {
+    column![with_help(text(label), help), control.into()].spacing(5)
+}
+
+/// Puts a [`help`] icon after a control that has its own label, like a checkbox
+#[must_use]
+pub fn with_help<'a>(control: impl Into<Element<'a, Message>>, help: Help) -> Row<'a, Message> {
+    row![control.into(), self::help(help)].spacing(5)
+}
+
+/// A "(?)" that shows what a field is for when it's hovered
+#[must_use]
+pub fn help<'a>(help: Help) -> Tooltip<'a, Message> {
+    tooltip(text("(?)"), help.text(), tooltip::Position::Top)
+        .gap(5)
+        .padding(10)
+        .style(theme::Container::Box)
+}
+
+/// The bar along the bottom of every screen saying what's going on, so it's clear something is
+/// happening during long scrapes and downloads
+#[must_use]
+pub fn status_bar<'a>(activity: &Activity) -> Container<'a, Message> {
+    container(text(activity.to_string()).size(14))
+        .width(Length::Fill)
+        .padding([4, 10])
+        .style(theme::Container::Box)
+}
+
+/// The label of a scraped field, with why it should be checked after it like "Year (not found)"
+#[must_use]
+pub fn confidence_label(label: &str, confidence: Confidence) -> String {
+    match confidence.warning() {
+        Some(warning) => format!("{label} ({warning})"),
+        None => label.to_string(),
+    }
+}
+
+/// [`labelled_with_help`] for a scraped field, pointing it out if `confidence` is low
+#[must_use]
+pub fn scraped_field<'a>(
+    label: &str,
+    help: Help,
+    confidence: Confidence,
+    input: TextInput<'a, Message>,
+) -> Column<'a, Message> {
+    labelled_with_help(
+        confidence_label(label, confidence),
+        help,
+        confidence_input(input, confidence),
+    )
+}
+
+/// Text in the [`WARNING`] colour
+#[must_use]
+pub fn warning_text<'a>(content: impl ToString) -> Text<'a> {
+    text(content).style(theme::Text::Color(WARNING))
+}
+
+/// Gives `input` a [`WARNING`] border if `confidence` is low
+#[must_use]
+pub fn confidence_input(
+    input: TextInput<'_, Message>,
+    confidence: Confidence,
+) -> TextInput<'_, Message> {
+    if confidence.is_low() {
+        input.style(theme::TextInput::Custom(Box::new(WarningInput)))
+    } else {
+        input
+    }
+}
+
+/// The theme's text input, with a [`WARNING`] border
+struct WarningInput;
+
+impl WarningInput {
+    fn tint(appearance: text_input::Appearance) -> text_input::Appearance {
+        text_input::Appearance {
+            border_color: WARNING,
+            border_width: appearance.border_width.max(2.0),
+            ..appearance
+        }
+    }
+}
+
+impl text_input::StyleSheet for WarningInput {
+    type Style = Theme;
+
+    fn active(&self, style: &Theme) -> text_input::Appearance {
+        Self::tint(style.active(&theme::TextInput::Default))
+    }
+
+    fn focused(&self, style: &Theme) -> text_input::Appearance {
+        Self::tint(style.focused(&theme::TextInput::Default))
+    }
+
+    fn hovered(&self, style: &Theme) -> text_input::Appearance {
+        Self::tint(style.hovered(&theme::TextInput::Default))
+    }
+
+    fn disabled(&self, style: &Theme) -> text_input::Appearance {
+        style.disabled(&theme::TextInput::Default)
+    }
+
+    fn placeholder_color(&self, style: &Theme) -> Color {
+        style.placeholder_color(&theme::TextInput::Default)
+    }
+
+    fn value_color(&self, style: &Theme) -> Color {
+        style.value_color(&theme::TextInput::Default)
+    }
+
+    fn disabled_color(&self, style: &Theme) -> Color {
+        style.disabled_color(&theme::TextInput::Default)
+    }
+
+    fn selection_color(&self, style: &Theme) -> Color {
+        style.selection_color(&theme::TextInput::Default)
+    }
+}
@@ -0,0 +1,243 @@
+use super::{background, help::Help, style, App, Message};
+use crate::{
+    activity::{self, Activity},
+    scraping::{
+        fetch_discogs_list, fetch_discogs_username, search_discogs, DiscogsApiError,
+        DiscogsIdentifier, DiscogsList, DiscogsListPage, DiscogsSearchResult,
+    },
+};
+use iced::{
+    widget::{
+        column, container, row, scrollable, text, text_input, Button, Column, PickList, Rule,
+        TextInput,
+    },
+    Command, Element, Length,
+};
+
+#[derive(Debug, Clone, Default)]
+pub struct StateDiscogsCollection {
+    /// Links from the link screen, so they're still there when going back
+    pub youtube_link: String,
+    pub discogs_link: String,
+    /// Who the token belongs to, `None` if it couldn't be found out
+    pub username: Option<String>,
+    pub list: DiscogsList,
+    pub page: Option<DiscogsListPage>,
+    /// Only shows releases on the page with this in the artist or title
+    pub filter: String,
+    /// A barcode or catalog number to search all of Discogs for
+    pub search: String,
+    /// What was searched for and what was found, shown instead of the page
+    pub results: Option<(DiscogsIdentifier, Vec<DiscogsSearchResult>)>,
+    /// Whether a search is still waiting on Discogs
+    pub searching: bool,
+    pub error: Option<String>,
+}
+
+impl StateDiscogsCollection {
+    /// Loads the first page of the user's collection
+    #[must_use]
+    pub fn new(youtube_link: String, discogs_link: String) -> Self {
+        let token = crate::config::current().discogs_token.unwrap_or_default();
+        let mut state = Self {
+            youtube_link,
+            discogs_link,
+            ..Self::default()
+        };
+        match fetch_discogs_username(&token) {
+            Ok(username) => {
+                state.username = Some(username);
+                state.load(DiscogsList::Collection, 1);
+            }
+            Err(err) => state.set_error(&err),
+        }
+        state
+    }
+
+    /// Loads a page (1-based) of the collection or wantlist, keeping the current one if it fails
+    pub fn load(&mut self, list: DiscogsList, page: u32) {
+        let Some(username) = &self.username else {
+            return;
+        };
+        let token = crate::config::current().discogs_token.unwrap_or_default();
+        match fetch_discogs_list(&token, username, list, page) {
+            Ok(page) => {
+                self.list = list;
+                self.page = Some(page);
+                self.error = None;
+            }
+            Err(err) => self.set_error(&err),
+        }
+    }
+
+    /// Searches by [`StateDiscogsCollection::search`] in the background, finishing with
+    /// [`Message::DiscogsSearched`]
+    pub fn search(&mut self) -> Command<Message> {
+        if self.searching {
+            return Command::none();
+        }
+        let Some(identifier) = DiscogsIdentifier::parse(&self.search) else {
+            return Command::none();
+        };
+        self.searching = true;
+        let token = crate::config::current().discogs_token.unwrap_or_default();
+        let activity = activity::start(Activity::Scraping("Discogs"));
+        background::perform(
+            "searching Discogs",
+            move || {
+                let _activity = activity;
+                let results = search_discogs(&token, &identifier).map_err(|err| {
+                    log::warn!("{err}");
+                    err.to_string()
+                });
+                (identifier, results)
+            },
+            |(identifier, results)| Message::DiscogsSearched(identifier, results),
+        )
+    }
+
+    /// Shows what a search found, keeping the current results if it failed
+    pub fn searched(
+        &mut self,
+        identifier: DiscogsIdentifier,
+        results: Result<Vec<DiscogsSearchResult>, String>,
+    ) {
+        self.searching = false;
+        match results {
+            Ok(results) => {
+                self.results = Some((identifier, results));
+                self.error = None;
+            }
+            Err(err) => self.error = Some(err),
+        }
+    }
+
+    fn set_error(&mut self, err: &DiscogsApiError) {
+        log::warn!("{err}");
+        self.error = Some(err.to_string());
+    }
+}
+
+impl App {
+    #[must_use]
+    pub fn view_discogs_collection<'a>(state: &'_ StateDiscogsCollection) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let title = match &state.username {
+            Some(username) => format!("{username}'s Discogs"),
+            None => String::from("Discogs"),
+        };
+        let list_picker = PickList::new(
+            DiscogsList::ALL,
+            Some(state.list),
+            Message::DiscogsListSelected,
+        )
+        .padding(style::input_padding(ui));
+        let filter_input = TextInput::new("Filter by artist or title", &state.filter)
+            .id(text_input::Id::new("discogs-filter"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::DiscogsFilterChanged);
+        let search_input = TextInput::new("Barcode or catalog number", &state.search)
+            .id(text_input::Id::new("discogs-search"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::DiscogsSearchChanged)
+            .on_submit(Message::DiscogsSearch);
+        let search_button = Button::new("Search")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                DiscogsIdentifier::parse(&state.search)
+                    .filter(|_| !state.searching)
+                    .map(|_| Message::DiscogsSearch),
+            );
+        let back_button = Button::new("Back")
+            .padding(style::button_padding(ui))
+            .on_press(Message::CloseDiscogsCollection);
+
+        let mut content = column![
+            text(title).size(32),
+            style::labelled_with_help(
+                "Find the exact pressing",
+                Help::CollectionSearch,
+                row![search_input, search_button].spacing(10)
+            ),
+            row![
+                list_picker,
+                style::with_help(filter_input, Help::CollectionFilter)
+            ]
+            .spacing(10),
+            Rule::horizontal(4)
+        ]
+        .spacing(20)
+        .max_width(800);
+        if let Some(error) = &state.error {
+            content = content.push(text(format!("Couldn't load from Discogs: {error}")));
+        }
+
+        if let Some((identifier, results)) = &state.results {
+            content = content.push(Self::view_discogs_results(identifier, results));
+        } else if let Some(page) = &state.page {
+            let mut releases = column![].spacing(10);
+            let matching = page.releases.iter().filter(|r| r.matches(&state.filter));
+            for release in matching {
+                let use_button = Button::new("Use")
+                    .padding(style::button_padding(ui))
+                    .on_press(Message::DiscogsReleasePicked(release.url()));
+                releases = releases.push(row![use_button, text(release.to_string())].spacing(10));
+            }
+            if page.releases.is_empty() {
+                releases = releases.push(text(format!("Your {} is empty", state.list)));
+            }
+
+            let previous_button = Button::new("Previous")
+                .padding(style::button_padding(ui))
+                .on_press_maybe(
+                    (page.page > 1).then(|| Message::DiscogsPageChanged(page.page - 1)),
+                );
+            let next_button = Button::new("Next")
+                .padding(style::button_padding(ui))
+                .on_press_maybe(
+                    (page.page < page.pages).then(|| Message::DiscogsPageChanged(page.page + 1)),
+                );
+            content = content.push(releases).push(
+                row![
+                    previous_button,
+                    text(format!("Page {} of {}", page.page, page.pages.max(1))),
+                    next_button
+                ]
+                .spacing(10),
+            );
+        }
+
+        scrollable(
+            container(content.push(back_button))
+                .width(Length::Fill)
+                .padding(40)
+                .center_x(),
+        )
+        .into()
+    }
+
+    /// Releases found by searching, and going back to the collection
+    fn view_discogs_results<'a>(
+        identifier: &DiscogsIdentifier,
+        results: &[DiscogsSearchResult],
+    ) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let mut content = column![text(format!(
+            "{} releases with the {identifier}",
+            results.len()
+        ))]
+        .spacing(10);
+        for result in results {
+            let use_button = Button::new("Use")
+                .padding(style::button_padding(ui))
+                .on_press(Message::DiscogsReleasePicked(result.url()));
+            content = content.push(row![use_button, text(result.to_string())].spacing(10));
+        }
+        let clear_button = Button::new("Back to my releases")
+            .padding(style::button_padding(ui))
+            .on_press(Message::ClearDiscogsSearch);
+        content.push(clear_button)
+    }
+}
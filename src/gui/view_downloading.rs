@@ -0,0 +1,95 @@
+use super::view_modifying_data::StateModifyingData;
+use super::{style, App, Message};
+use crate::{activity, parsing::format_duration, CancellationToken};
+use iced::{
+    widget::{column, container, row, text, Button},
+    Element, Length,
+};
+use std::time::Instant;
+
+/// Drawn one after another while the download runs
+const SPINNER: &[char] = &['|', '/', '-', '\\'];
+
+/// Shown while the album downloads in the background
+#[derive(Debug, Clone)]
+pub struct StateDownloading {
+    /// Like "Artist - Album"
+    pub album: String,
+    pub started: Instant,
+    /// Which [`SPINNER`] frame to draw, moved along on each tick
+    pub frame: usize,
+    /// Shared with the download
+    pub cancel: CancellationToken,
+    /// Whether the window was closed, so it should close once the download stops
+    pub close_when_done: bool,
+    /// What's being downloaded, to download again if any tracks fail
+    pub retry: StateModifyingData,
+}
+
+impl StateDownloading {
+    #[must_use]
+    pub fn new(retry: StateModifyingData) -> Self {
+        Self {
+            album: format!("{} - {}", retry.album_data.artist, retry.album_data.name),
+            started: Instant::now(),
+            frame: 0,
+            cancel: CancellationToken::default(),
+            close_when_done: false,
+            retry,
+        }
+    }
+
+    pub fn tick(&mut self) {
+        self.frame = (self.frame + 1) % SPINNER.len();
+    }
+}
+
+impl App {
+    #[must_use]
+    pub fn view_downloading<'a>(state: &'_ StateDownloading) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+        let cancelling = state.cancel.is_cancelled();
+        let activity = activity::current();
+
+        let status = if state.close_when_done {
+            String::from("Closing once the tracks in progress have stopped...")
+        } else if cancelling {
+            String::from("Cancelling, waiting for the tracks in progress to stop...")
+        } else if activity.is_disk_full() {
+            activity.to_string()
+        } else {
+            String::from("Progress is logged to the console")
+        };
+        let cancel_button = Button::new("Cancel")
+            .padding(style::button_padding(ui))
+            .on_press_maybe((!cancelling).then_some(Message::CancelDownload));
+        let mut buttons = row![cancel_button].spacing(10);
+        if activity.is_disk_full() && !cancelling {
+            // once some space has been freed up, rather than waiting for it to be noticed
+            buttons = buttons.push(
+                Button::new("Resume")
+                    .padding(style::button_padding(ui))
+                    .on_press(Message::RetryDiskSpace),
+            );
+        }
+
+        let content = column![
+            text(format!("Downloading {}", state.album)).size(32),
+            text(format!(
+                "{} {}",
+                SPINNER[state.frame % SPINNER.len()],
+                format_duration(state.started.elapsed())
+            )),
+            text(status),
+            buttons,
+        ]
+        .spacing(20)
+        .max_width(800);
+
+        container(content)
+            .width(Length::Fill)
+            .padding(40)
+            .center_x()
+            .into()
+    }
+}
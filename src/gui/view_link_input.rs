@@ -1,38 +1,387 @@
 use iced::{
-    widget::{column, container, scrollable, Button, TextInput},
-    Element, Length,
+    widget::{
+        column, container, row, scrollable, text, text_input, Button, Column, PickList, TextInput,
+    },
+    Command, Element, Length,
 };
 
-use super::{App, Message};
+use super::{background, help::Help, style, view_modifying_data::StateModifyingData, App, Message};
+use crate::{
+    activity::{self, Activity},
+    deps::{self, DependencyReport},
+    history::History,
+    jobs::Job,
+    parsing::{format_duration, parse_delay},
+    playlist::parse_video_list,
+    queue::{self, QueueStatus},
+    scraping::metadata_site,
+    updates::{self, Release},
+    urls::classify,
+    utils::unix_time,
+};
+use std::path::PathBuf;
 
 #[derive(Debug, Default)]
+#[allow(clippy::struct_excessive_bools)] // each is a separate job running in the background
 pub struct StateLinkInput {
     pub youtube_link: String,
     pub discogs_link: String,
+    /// The links are being scraped in the background, before going on to the modify screen
+    pub scraping: bool,
+    /// Downloads that failed or were cut off by a crash, which can be resumed
+    pub interrupted_jobs: Vec<Job>,
+    /// Oldest first
+    pub queued_jobs: Vec<Job>,
+    /// How long to wait before starting or carrying on with the queue, like "7h"
+    pub queue_delay: String,
+    /// How the queue was when ytmdl was last closed, if it was running or waiting to
+    pub saved_queue: Option<QueueStatus>,
+    /// Titles of albums just found on the watched channels (and queued)
+    pub new_releases: Vec<String>,
+    pub history: History,
+    /// `.csv` or `.json` file to export the history to
+    pub history_export_path: String,
+    /// Whether the last export worked
+    pub history_export_status: Option<String>,
+    /// Text or CSV file of album links to add to the queue
+    pub url_list_path: String,
+    /// How many albums were queued from the last list, and which lines weren't
+    pub url_list_status: Option<String>,
+    /// The list is being added to the queue in the background
+    pub importing_url_list: bool,
+    /// Folder of an album downloaded with `.info.json` sidecars, to download again with better
+    /// settings
+    pub upgrade_dir: String,
+    /// Name of the profile to upgrade to, the default one if `None`
+    pub upgrade_profile: Option<String>,
+    /// How the last upgrade went
+    pub upgrade_status: Option<String>,
+    /// The album's being upgraded in the background
+    pub upgrading: bool,
+    /// Folder of a downloaded album to copy to a device
+    pub device_album_dir: String,
+    /// Where the device is mounted, like its SD card
+    pub device_mount_point: String,
+    /// Name of the profile to convert to on the way, copied as-is if `None`
+    pub device_profile: Option<String>,
+    /// How the last export went
+    pub device_export_status: Option<String>,
+    /// The album's being exported in the background
+    pub exporting: bool,
+}
+
+/// Picked instead of a profile to export without converting
+const COPY_AS_IS: &str = "Copy as-is";
+
+impl StateLinkInput {
+    /// Empty links, with any queued and interrupted jobs and the history loaded from disk
+    #[must_use]
+    pub fn new() -> Self {
+        let mut state = Self {
+            history: History::load().unwrap_or_else(|err| {
+                log::warn!("couldn't load the download history: {err}");
+                History::default()
+            }),
+            saved_queue: queue::saved_status(),
+            ..Self::default()
+        };
+        state.reload_jobs();
+        state
+    }
+
+    /// Sets the Youtube link, unless a Discogs, Musicbrainz or Bandcamp link was pasted into it while
+    /// the Discogs field is empty, in which case it goes there instead
+    pub fn set_youtube_link(&mut self, link: String) {
+        let pasted = self.youtube_link.is_empty();
+        if pasted && self.discogs_link.is_empty() && classify(&link).is_metadata() {
+            self.discogs_link = link;
+        } else {
+            self.youtube_link = link;
+        }
+    }
+
+    /// Sets the Discogs link, unless a Youtube link was pasted into it while the Youtube field is
+    /// empty, in which case it goes there instead
+    pub fn set_discogs_link(&mut self, link: String) {
+        let pasted = self.discogs_link.is_empty();
+        if pasted && self.youtube_link.is_empty() && classify(&link).is_youtube() {
+            self.youtube_link = link;
+        } else {
+            self.discogs_link = link;
+        }
+    }
+
+    /// Downloads the tracks in [`StateLinkInput::upgrade_dir`] again with the picked profile's
+    /// settings in the background, and sends [`Message::AlbumUpgraded`] when it's done
+    pub fn upgrade(&mut self) -> Command<Message> {
+        if self.upgrading {
+            return Command::none();
+        }
+        let config = crate::config::current();
+        let profile = self
+            .upgrade_profile
+            .as_ref()
+            .and_then(|name| config.profiles().into_iter().find(|p| &p.name == name))
+            .unwrap_or_else(|| config.default_profile());
+
+        let dir = PathBuf::from(self.upgrade_dir.trim());
+        self.upgrading = true;
+        self.upgrade_status = Some(String::from("Upgrading..."));
+        background::perform(
+            "upgrading the album",
+            move || match crate::upgrade_album(&dir, &profile) {
+                Ok(tracks) if tracks.is_empty() => {
+                    String::from("No tracks with .info.json files in that folder")
+                }
+                Ok(tracks) => {
+                    let (mut upgraded, mut skipped) = (0, 0);
+                    let mut failures = Vec::new();
+                    for (path, result) in tracks {
+                        match result {
+                            Ok(Some(_)) => upgraded += 1,
+                            Ok(None) => skipped += 1,
+                            Err(err) => {
+                                log::error!(r#"couldn't upgrade "{}": {err}"#, path.display());
+                                failures.push(err.to_string());
+                            }
+                        }
+                    }
+                    let mut status =
+                        format!("Upgraded {upgraded} tracks to {profile} ({skipped} already were)");
+                    if !failures.is_empty() {
+                        status = format!(
+                            "{status}; {} failed: {}",
+                            failures.len(),
+                            failures.join("; ")
+                        );
+                    }
+                    status
+                }
+                Err(err) => format!("Couldn't upgrade: {err}"),
+            },
+            Message::AlbumUpgraded,
+        )
+    }
+
+    /// Copies the tracks in [`StateLinkInput::device_album_dir`] to the device in the background,
+    /// converting them with the picked profile if there is one, and sends
+    /// [`Message::ExportedToDevice`] when it's done
+    pub fn export_to_device(&mut self) -> Command<Message> {
+        if self.exporting {
+            return Command::none();
+        }
+        let profile = self.device_profile.as_ref().and_then(|name| {
+            crate::config::current()
+                .profiles()
+                .into_iter()
+                .find(|p| &p.name == name)
+        });
+
+        let dir = PathBuf::from(self.device_album_dir.trim());
+        let mount_point = PathBuf::from(self.device_mount_point.trim());
+        self.exporting = true;
+        self.device_export_status = Some(String::from("Exporting..."));
+        background::perform(
+            "exporting to the device",
+            move || match crate::export::export_album(&dir, &mount_point, profile.as_ref()) {
+                Ok(tracks) if tracks.is_empty() => String::from("No tracks in that folder"),
+                Ok(tracks) => {
+                    let mut exported = 0;
+                    let mut failures = Vec::new();
+                    for (path, result) in tracks {
+                        match result {
+                            Ok(_) => exported += 1,
+                            Err(err) => {
+                                log::error!(r#"couldn't export "{}": {err}"#, path.display());
+                                failures.push(err.to_string());
+                            }
+                        }
+                    }
+                    let mut status = format!("Exported {exported} tracks");
+                    if !failures.is_empty() {
+                        status = format!(
+                            "{status}; {} failed: {}",
+                            failures.len(),
+                            failures.join("; ")
+                        );
+                    }
+                    status
+                }
+                Err(err) => format!("Couldn't export: {err}"),
+            },
+            Message::ExportedToDevice,
+        )
+    }
+
+    /// Scrapes the album from the links in the background with
+    /// [`StateModifyingData::from_links`], sending [`Message::LinksScraped`] when it's done
+    pub fn submit_links(&mut self, youtube: String, metadata: String) -> Command<Message> {
+        if self.scraping {
+            return Command::none();
+        }
+        self.scraping = true;
+        let site = if metadata.trim().is_empty() {
+            "Youtube"
+        } else {
+            metadata_site(&metadata)
+        };
+        let activity = activity::start(Activity::Scraping(site));
+        background::perform(
+            "scraping the links",
+            move || {
+                let _activity = activity;
+                StateModifyingData::from_links(youtube, &metadata)
+                    .map(Box::new)
+                    .map_err(|err| err.to_string())
+            },
+            Message::LinksScraped,
+        )
+    }
+
+    /// Adds the albums in [`StateLinkInput::url_list_path`] to the queue in the background, since
+    /// each of them is scraped first, sending [`Message::UrlListImported`] when it's done
+    pub fn import_url_list(&mut self) -> Command<Message> {
+        if self.importing_url_list {
+            return Command::none();
+        }
+        let path = PathBuf::from(self.url_list_path.trim());
+        self.importing_url_list = true;
+        self.url_list_status = Some(String::from("Importing..."));
+        background::perform(
+            "importing the list of links",
+            move || match crate::batch::read_url_list(&path) {
+                Ok(list) if list.entries.is_empty() && list.invalid.is_empty() => {
+                    String::from("No links in that file")
+                }
+                Ok(list) => {
+                    let report = list.queue();
+                    for line in &report.invalid {
+                        log::warn!("skipped {line}");
+                    }
+                    report.to_string()
+                }
+                Err(err) => format!("Couldn't read {}: {err}", path.display()),
+            },
+            Message::UrlListImported,
+        )
+    }
+
+    pub fn reload_jobs(&mut self) {
+        let (mut queued_jobs, interrupted_jobs): (Vec<Job>, Vec<Job>) =
+            Job::load_all().into_iter().partition(Job::is_queued);
+        queue::sort_queued(&mut queued_jobs);
+        self.queued_jobs = queued_jobs;
+        self.interrupted_jobs = interrupted_jobs;
+    }
+}
+
+/// Focused when the screen opens
+#[must_use]
+pub fn youtube_link_id() -> text_input::Id {
+    text_input::Id::new("youtube-link")
 }
 
 impl App {
     pub fn view_link_input<'a>(state: &'_ StateLinkInput) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+
         let yt_link_input = TextInput::new(
-            "https://youtube.com/playlist?list=0123456789abcdef",
+            "https://youtube.com/playlist?list=0123456789abcdef (or leave it empty to find the release's)",
             state.youtube_link.as_str(),
         )
+        .id(youtube_link_id())
+        .padding(style::input_padding(ui))
         .on_input(Message::YoutubeLinkInputChanged);
 
         let discogs_link_input = TextInput::new(
             "https://discogs.com/release/12345678-Artist-Name-Album-Name",
             state.discogs_link.as_str(),
         )
+        .id(text_input::Id::new("discogs-link"))
+        .padding(style::input_padding(ui))
         .on_input(Message::DiscogsLinkInputChanged);
 
-        let submit_button = Button::new("Scrape").on_press(Message::SubmitLinks {
-            youtube: state.youtube_link.clone(),
-            discogs: state.discogs_link.clone(),
+        // building an album out of individual videos instead of a playlist
+        let video_list_hint = text(match parse_video_list(&state.youtube_link) {
+            Some(ids) if ids.len() == 1 => String::from("1 video, as a single track"),
+            Some(ids) => format!("{} videos, one track each", ids.len()),
+            None => String::new(),
         });
 
-        let content = column![yt_link_input, discogs_link_input, submit_button]
-            .spacing(20)
-            .max_width(800);
+        let discogs_collection_button = Button::new("Find on Discogs")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                crate::config::current()
+                    .discogs_token
+                    .filter(|token| !token.trim().is_empty())
+                    .map(|_| Message::OpenDiscogsCollection),
+            );
+
+        // the status bar says what's being scraped meanwhile
+        let submit_button = Button::new("Scrape")
+            .padding(style::button_padding(ui))
+            .on_press_maybe((!state.scraping).then(|| Message::SubmitLinks {
+                youtube: state.youtube_link.clone(),
+                discogs: state.discogs_link.clone(),
+            }));
+
+        let settings_button = Button::new("Settings")
+            .padding(style::button_padding(ui))
+            .on_press(Message::OpenSettings);
+        let check_watched_button = Button::new("Check for new releases")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!crate::config::current().watch.urls.is_empty()).then_some(Message::CheckWatched),
+            );
+
+        let mut content = column![
+            style::labelled_with_help(
+                "Youtube playlist, or video links separated by spaces or new lines",
+                Help::YoutubeLink,
+                yt_link_input
+            ),
+            video_list_hint,
+            style::labelled_with_help(
+                "Discogs, MusicBrainz or Bandcamp release, or an album folder (optional)",
+                Help::MetadataLink,
+                row![discogs_link_input, discogs_collection_button].spacing(10)
+            ),
+            submit_button,
+            row![settings_button, check_watched_button].spacing(10)
+        ]
+        .spacing(20)
+        .max_width(800);
+        if let Some(release) = updates::available() {
+            content = column![Self::view_update_banner(&release), content]
+                .spacing(20)
+                .max_width(800);
+        }
+        if let Some(report) = deps::last_report().filter(|report| !report.is_ok()) {
+            content = column![Self::view_dependency_problems(&report), content]
+                .spacing(20)
+                .max_width(800);
+        }
+        if !state.new_releases.is_empty() {
+            content = content.push(text(format!(
+                "New releases added to the queue: {}",
+                state.new_releases.join(", ")
+            )));
+        }
+        if let Some(saved) = state.saved_queue {
+            content = content.push(Self::view_saved_queue(saved, state.queued_jobs.len()));
+        }
+        if !state.queued_jobs.is_empty() || queue::status().is_active() {
+            content = content.push(Self::view_queue(state));
+        }
+        content = content.push(Self::view_import_url_list(state));
+        if !state.interrupted_jobs.is_empty() {
+            content = content.push(Self::view_interrupted_jobs(&state.interrupted_jobs));
+        }
+        if !state.history.albums.is_empty() {
+            content = content.push(Self::view_history(state));
+        }
+        content = content.push(Self::view_upgrade(state));
+        content = content.push(Self::view_export_to_device(state));
 
         scrollable(
             container(content)
@@ -42,4 +391,343 @@ impl App {
         )
         .into()
     }
+
+    /// A newer version being available, which stays out of the way at the top
+    fn view_update_banner<'a>(release: &Release) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+        let copy_button = Button::new("Copy link")
+            .padding(style::button_padding(ui))
+            .on_press(Message::CopyUpdateLink);
+        let dismiss_button = Button::new("Dismiss")
+            .padding(style::button_padding(ui))
+            .on_press(Message::DismissUpdate);
+
+        row![
+            text(format!(
+                "ytmdl {} is available (you have {}): {}",
+                release.tag_name,
+                updates::CURRENT,
+                release.html_url
+            ))
+            .width(Length::Fill),
+            copy_button,
+            dismiss_button
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    /// yt-dlp or ffmpeg being missing or out of date, and what to do about it
+    fn view_dependency_problems<'a>(report: &DependencyReport) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+        let recheck_button = Button::new("Check again")
+            .padding(style::button_padding(ui))
+            .on_press(Message::RecheckDependencies);
+        let dismiss_button = Button::new("Dismiss")
+            .padding(style::button_padding(ui))
+            .on_press(Message::DismissDependencies);
+
+        let problems = report
+            .problems()
+            .into_iter()
+            .fold(Column::new().spacing(5), |column, problem| {
+                column.push(text(problem))
+            });
+        row![problems.width(Length::Fill), recheck_button, dismiss_button]
+            .spacing(10)
+            .into()
+    }
+
+    /// Offering to carry on with the queue from when ytmdl was last closed
+    fn view_saved_queue<'a>(saved: QueueStatus, queued: usize) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+        let was = match saved {
+            QueueStatus::Scheduled(_) => "waiting to start",
+            QueueStatus::PausedUntil(_) => "paused",
+            QueueStatus::Running | QueueStatus::Stopped => "running",
+        };
+        let restore_button = Button::new("Carry on")
+            .padding(style::button_padding(ui))
+            .on_press(Message::RestoreQueue);
+        let dismiss_button = Button::new("Not now")
+            .padding(style::button_padding(ui))
+            .on_press(Message::DismissSavedQueue);
+
+        column![
+            text(format!(
+                "The queue was {was} when ytmdl was closed, with {queued} albums left"
+            )),
+            row![restore_button, dismiss_button].spacing(10)
+        ]
+        .spacing(10)
+    }
+
+    /// The queued jobs, and when they'll be downloaded
+    fn view_queue<'a>(state: &StateLinkInput) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+        let status = queue::status();
+        let delay = parse_delay(&state.queue_delay);
+
+        let status_text = match (status, status.countdown(unix_time())) {
+            (QueueStatus::Scheduled(_), Some(left)) => {
+                format!("Starts in {}", format_duration(left))
+            }
+            (QueueStatus::PausedUntil(_), Some(left)) => {
+                format!("Paused, carries on in {}", format_duration(left))
+            }
+            (QueueStatus::Running, _) => String::from("Running"),
+            _ => format!("{} queued", state.queued_jobs.len()),
+        };
+
+        let mut content = column![text(format!("Queue: {status_text}"))].spacing(10);
+        let last = state.queued_jobs.len().saturating_sub(1);
+        for (i, job) in state.queued_jobs.iter().enumerate() {
+            let job_button = |label: &str, message: Option<Message>| {
+                Button::new(text(label))
+                    .padding(style::button_padding(ui))
+                    .on_press_maybe(message)
+            };
+            let up_button = job_button(
+                "Up",
+                (i > 0).then(|| Message::MoveQueuedJob(job.id.clone(), true)),
+            );
+            let down_button = job_button(
+                "Down",
+                (i < last).then(|| Message::MoveQueuedJob(job.id.clone(), false)),
+            );
+            let next_button = job_button(
+                "Download next",
+                (i > 0 || queue::is_busy()).then(|| Message::MakeJobNext(job.id.clone())),
+            );
+            let remove_button = job_button("Remove", Some(Message::DiscardJob(job.id.clone())));
+            content = content.push(
+                row![
+                    text(job.to_string()),
+                    up_button,
+                    down_button,
+                    next_button,
+                    remove_button
+                ]
+                .spacing(10),
+            );
+        }
+
+        let delay_input = TextInput::new("Delay, like 7h or 1h 30m", &state.queue_delay)
+            .id(text_input::Id::new("queue-delay"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::QueueDelayChanged);
+        let button = |label: &str, message: Option<Message>| {
+            Button::new(text(label))
+                .padding(style::button_padding(ui))
+                .on_press_maybe(message)
+        };
+        let start_button = button(
+            "Start now",
+            (status != QueueStatus::Running).then_some(Message::StartQueue),
+        );
+        let schedule_button = button(
+            "Start after delay",
+            delay
+                .filter(|_| status == QueueStatus::Stopped)
+                .map(Message::ScheduleQueue),
+        );
+        let pause_button = button(
+            "Pause for delay",
+            delay
+                .filter(|_| status == QueueStatus::Running)
+                .map(Message::PauseQueue),
+        );
+        let stop_button = button("Stop", status.is_active().then_some(Message::StopQueue));
+
+        content.push(style::labelled_with_help(
+            "Run the queue",
+            Help::QueueDelay,
+            row![
+                delay_input,
+                start_button,
+                schedule_button,
+                pause_button,
+                stop_button
+            ]
+            .spacing(10),
+        ))
+    }
+
+    /// The last few downloaded albums, and exporting all of them
+    fn view_history<'a>(state: &StateLinkInput) -> Column<'a, Message> {
+        const RECENT: usize = 5;
+        let ui = crate::config::current().ui;
+        let albums = &state.history.albums;
+
+        let failed = albums.iter().filter(|a| !a.failures.is_empty()).count();
+        let mut content = column![text(format!(
+            "Download history: {} albums ({failed} with failures)",
+            albums.len()
+        ))]
+        .spacing(10);
+        for album in albums.iter().rev().take(RECENT) {
+            let line = format!(
+                "{} - {} ({} tracks)",
+                album.artist, album.name, album.tracks
+            );
+            content = content.push(text(match album.failures.len() {
+                0 => line,
+                failures => format!("{line}, {failures} failed"),
+            }));
+        }
+
+        let path_input = TextInput::new("history.csv or history.json", &state.history_export_path)
+            .id(text_input::Id::new("history-export-path"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::HistoryExportPathChanged)
+            .on_submit(Message::ExportHistory);
+        let export_button = Button::new("Export")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!state.history_export_path.trim().is_empty()).then_some(Message::ExportHistory),
+            );
+        content = content.push(style::labelled(
+            "Export to CSV or JSON",
+            row![path_input, export_button].spacing(10),
+        ));
+        if let Some(status) = &state.history_export_status {
+            content = content.push(text(status));
+        }
+        content
+    }
+
+    /// Queueing every album in a file of links
+    fn view_import_url_list<'a>(state: &StateLinkInput) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let path_input = TextInput::new("albums.txt or albums.csv", &state.url_list_path)
+            .id(text_input::Id::new("url-list-path"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::UrlListPathChanged)
+            .on_submit(Message::ImportUrlList);
+        let import_button = Button::new("Add to queue")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!state.url_list_path.trim().is_empty() && !state.importing_url_list)
+                    .then_some(Message::ImportUrlList),
+            );
+
+        let mut content = column![style::labelled_with_help(
+            "Queue a list of albums from a file",
+            Help::UrlList,
+            row![path_input, import_button].spacing(10),
+        )]
+        .spacing(10);
+        if let Some(status) = &state.url_list_status {
+            content = content.push(text(status));
+        }
+        content
+    }
+
+    /// Downloading an album that's already been downloaded again, with better settings
+    fn view_upgrade<'a>(state: &StateLinkInput) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+        let config = crate::config::current();
+
+        let dir_input = TextInput::new("Album folder", &state.upgrade_dir)
+            .id(text_input::Id::new("upgrade-dir"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::UpgradeDirChanged);
+        let profile_names: Vec<String> = config.profiles().into_iter().map(|p| p.name).collect();
+        let selected = state
+            .upgrade_profile
+            .clone()
+            .unwrap_or_else(|| config.default_profile().name);
+        let profile_picker = PickList::new(
+            profile_names,
+            Some(selected),
+            Message::UpgradeProfileSelected,
+        )
+        .padding(style::input_padding(ui));
+        let upgrade_button = Button::new("Upgrade")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!state.upgrade_dir.trim().is_empty() && !state.upgrading)
+                    .then_some(Message::UpgradeAlbum),
+            );
+
+        let mut content = column![style::labelled(
+            "Download an album again with a better profile (needs its .info.json files)",
+            row![dir_input, profile_picker, upgrade_button].spacing(10),
+        )]
+        .spacing(10);
+        if let Some(status) = &state.upgrade_status {
+            content = content.push(text(status));
+        }
+        content
+    }
+
+    /// Copying a downloaded album to a device, converting it on the way if needed
+    fn view_export_to_device<'a>(state: &StateLinkInput) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let dir_input = TextInput::new("Album folder", &state.device_album_dir)
+            .id(text_input::Id::new("device-album-dir"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::DeviceAlbumDirChanged);
+        let mount_point_input = TextInput::new("Device folder", &state.device_mount_point)
+            .id(text_input::Id::new("device-mount-point"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::DeviceMountPointChanged);
+        let mut options = vec![String::from(COPY_AS_IS)];
+        options.extend(
+            crate::config::current()
+                .profiles()
+                .into_iter()
+                .map(|p| p.name),
+        );
+        let selected = state
+            .device_profile
+            .clone()
+            .unwrap_or_else(|| String::from(COPY_AS_IS));
+        let profile_picker = PickList::new(options, Some(selected), |name| {
+            Message::DeviceProfileSelected((name != COPY_AS_IS).then_some(name))
+        })
+        .padding(style::input_padding(ui));
+        let export_button = Button::new("Export")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!state.device_album_dir.trim().is_empty()
+                    && !state.device_mount_point.trim().is_empty()
+                    && !state.exporting)
+                    .then_some(Message::ExportToDevice),
+            );
+
+        let mut content = column![style::labelled(
+            "Copy an album to a device, converting it with a profile on the way",
+            column![
+                row![dir_input, mount_point_input].spacing(10),
+                row![profile_picker, export_button].spacing(10)
+            ]
+            .spacing(10),
+        )]
+        .spacing(10);
+        if let Some(status) = &state.device_export_status {
+            content = content.push(text(status));
+        }
+        content
+    }
+
+    /// Each interrupted job with buttons to resume or forget about it
+    fn view_interrupted_jobs<'a>(jobs: &[Job]) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let mut content = column![text("Interrupted downloads")].spacing(10);
+        for job in jobs {
+            let resume_button = Button::new("Resume")
+                .padding(style::button_padding(ui))
+                .on_press(Message::ResumeJob(job.id.clone()));
+            let discard_button = Button::new("Discard")
+                .padding(style::button_padding(ui))
+                .on_press(Message::DiscardJob(job.id.clone()));
+            content = content
+                .push(row![text(job.to_string()), resume_button, discard_button].spacing(10));
+        }
+        content
+    }
 }
@@ -1,50 +1,227 @@
-use super::{App, Message, ModifyDataInputChange};
+use super::{help::Help, style, App, Message, ModifyDataInputChange};
 use crate::{
+    album_art::{check_url, CoverCandidate, CoverQuery, UpscalePreview},
+    clock::{Clock, SystemClock},
+    compare::{compare, Conflict, Pick},
+    config::{self, OutputFormat, Profile, Quality, UiSettings},
+    full_album::{find_full_album, sections_from_chapters, sections_from_lengths, Section},
+    ids::VideoId,
+    import::{ColumnMapping, ImportedTable, TrackField},
+    non_music::NonMusic,
+    parsing::{format_duration, TracklistLine},
+    playlist::{page_url, parse_video_id, parse_video_list},
+    rescrape::{Change, Field as RescrapedField, StoredScrape},
     scraping::{
-        scrape_playlist, DiscogsAlbum, DiscogsTrack, PlaylistItem, ScrapeYoutubePlaylistError,
+        find_album_playlist, scrape_metadata, scrape_playlist, scrape_titles, AlbumMetadata,
+        Confidence, FieldConfidence, Playlist, PlaylistItem, ScrapeYoutubePlaylistError,
+        TrackMetadata, YoutubeChapter,
     },
-    utils::music_to_www,
+    sources::{self, SourceRecord},
+    titles::{order_by_track_number, split_track_number, BracketStyle, TitleRules},
+    utils::sort_name,
+    versions::LengthMismatch,
 };
+use bytes::Bytes;
 use html_escape::decode_html_entities;
 use iced::{
-    widget::{column, container, scrollable, Button, Column, Rule, TextInput},
+    widget::{
+        checkbox, column, container, image, row, scrollable, text, text_input, Button, Column,
+        Image, PickList, Row, Rule, TextInput,
+    },
     Element, Length,
 };
 use id3::Timestamp;
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    path::Path,
+    time::Duration,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct StateModifyingData {
     pub youtube_url: String,
     pub album_data: AlbumData,
     pub track_data: Vec<TrackData>,
+    pub profile: Profile,
+    pub image_status: ImageStatus,
+    /// Parsed from the clipboard, waiting for the user to confirm replacing the tracks with it
+    pub pasted_tracklist: Option<Vec<TracklistLine>>,
+    /// Path typed into the import box
+    pub import_path: String,
+    pub import: Option<StateImport>,
+    pub import_error: Option<String>,
+    /// A playlist item that looks like the whole album in one video, waiting for the user to
+    /// decide whether to split it into the tracks
+    pub full_album: Option<FullAlbumItem>,
+    /// Set when resuming a [`Job`](crate::jobs::Job), so it carries on with the same one
+    pub job_id: Option<String>,
+    /// Starts as the config's, previewed on the track names until they're applied
+    pub title_rules: TitleRules,
+    /// The playlist's videos as they were scraped, for matching them up with the tracks. Empty if
+    /// it couldn't be scraped.
+    pub videos: Vec<PlaylistItem>,
+    /// The cover before and after upscaling, or why it couldn't be downloaded
+    pub upscale_preview: Option<Result<UpscalePreview, String>>,
+    /// The cover as it's shown, to make sure it's the right one
+    pub art_preview: Option<ArtPreview>,
+    /// The cover the release came with, offered again when looking for other covers
+    pub release_image: Option<String>,
+    pub cover_search: CoverSearch,
+    /// How much each album field can be trusted, so the ones to check can be pointed out.
+    /// Fields the user has changed are [`Confidence::Edited`].
+    pub confidence: FieldConfidence,
+    /// The release as it was last scraped, to see what's changed on it when it's scraped again
+    pub scrape: Option<StoredScrape>,
+    pub rescrape: Rescrape,
+    /// Channels that have been marked as a bad source before, to point out tracks from them. Empty
+    /// until [`StateModifyingData::load_flagged_sources`], since only the modify screen needs them.
+    pub flagged_sources: Vec<SourceRecord>,
+    pub alternative: AlternativeSearch,
+    /// Where the playlist disagrees with the release, if it was scraped from one
+    pub conflicts: Vec<Conflict>,
+    /// The tracks that were unticked because their videos don't look like songs, and why
+    pub non_music: Vec<(usize, NonMusic)>,
+}
+
+/// Searching Youtube for a different upload of a track whose video is a [`LengthMismatch`]. Each
+/// has the index of the track.
+#[derive(Debug, Clone, Default)]
+pub enum AlternativeSearch {
+    #[default]
+    NotSearched,
+    Searching(usize),
+    /// The video it's now downloaded from
+    Found(usize, PlaylistItem),
+    Failed(usize, String),
+}
+
+/// Scraping the release again to see what's changed on it
+#[derive(Debug, Clone, Default)]
+pub enum Rescrape {
+    #[default]
+    NotStarted,
+    Scraping,
+    Unchanged,
+    Failed(String),
+    /// The newer scrape, and the changes in it that haven't been accepted or rejected yet
+    Changed(StoredScrape, Vec<Change>),
+}
+
+/// Looking for other covers to pick from
+#[derive(Debug, Clone, Default)]
+pub enum CoverSearch {
+    #[default]
+    NotSearched,
+    Searching,
+    /// With their thumbnails, if they could be downloaded
+    Found(Vec<(CoverCandidate, Option<image::Handle>)>),
 }
 
+/// The album cover, downloaded to be shown
 #[derive(Debug, Clone)]
+pub struct ArtPreview {
+    /// Only shown while it's still [`AlbumData::image`]
+    pub url: String,
+    /// Or why it couldn't be downloaded
+    pub image: Result<image::Handle, String>,
+}
+
+/// Whether the album cover URL has been checked to point at an image
+#[derive(Debug, Clone, Default)]
+pub enum ImageStatus {
+    #[default]
+    Unchecked,
+    /// Description of the image, like "image/jpeg, 120 KB"
+    Valid(String),
+    Invalid(String),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AlbumData {
     pub name: String,
+    /// Shown as the artist of each track
     pub artist: String,
+    /// Who the album is by, if it's different to [`AlbumData::artist`] (e.g. for compilations)
+    pub album_artist: String,
+    /// What to sort [`AlbumData::artist`] by, if it shouldn't be worked out with
+    /// [`sort_name`](crate::utils::sort_name)
+    pub artist_sort: String,
     pub genre: String,
     pub year: i32,
     pub image: String,
+    #[serde(with = "crate::utils::option_display_from_str")]
     pub released: Option<Timestamp>,
+    /// Out of [`AlbumData::MAX_RATING`] stars, saved in each track's POPM frame
+    #[serde(default)]
+    pub rating: Option<u8>,
+    /// Saved as a `FAVORITE` TXXX frame
+    #[serde(default)]
+    pub favorite: bool,
+    /// Number the downloaded tracks 1, 2, 3… around the skipped ones, rather than by their
+    /// places in the tracklist
+    #[serde(default)]
+    pub renumber_skipped: bool,
+    /// Don't upscale the cover even if it's smaller than the config's `[art_upscale]` size
+    #[serde(default)]
+    pub keep_original_art: bool,
 }
 
 impl Default for AlbumData {
     fn default() -> Self {
+        Self::with_clock(&SystemClock)
+    }
+}
+
+impl AlbumData {
+    pub const MAX_RATING: u8 = 5;
+
+    /// Empty, with the year set to the current one
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::{clock::FixedClock, gui::view_modifying_data::AlbumData};
+    ///
+    /// let album = AlbumData::with_clock(&FixedClock::at_unix_time(1_704_067_200));
+    /// assert_eq!(album.year, 2024);
+    /// ```
+    #[must_use]
+    pub fn with_clock(clock: &impl Clock) -> Self {
         Self {
             name: String::new(),
             artist: String::new(),
+            album_artist: String::new(),
+            artist_sort: String::new(),
             genre: String::new(),
-            year: crate::utils::current_year(),
+            year: clock.year(),
             image: String::new(),
             released: None,
+            rating: None,
+            favorite: false,
+            renumber_skipped: false,
+            keep_original_art: false,
         }
     }
 }
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TrackData {
     pub name: String,
+    /// Overrides the album artist for just this track
+    pub artist: Option<String>,
+    /// Link to download this track from instead of the playlist, e.g. from Soundcloud
+    pub source: Option<String>,
+    /// Which part of [`TrackData::source`] this track is, if it's a full album upload
+    pub section: Option<Section>,
+    /// Left out of the download, e.g. for a bonus video or a duplicate in the playlist
+    #[serde(default)]
+    pub skip: bool,
+    /// How long the release says it is, to spot videos that are a different version of it
+    #[serde(default)]
+    pub length: Option<Duration>,
+    /// How much [`TrackData::name`] can be trusted, which isn't kept once it's been checked
+    #[serde(skip)]
+    pub confidence: Confidence,
 }
 
 impl TrackData {
@@ -52,138 +229,1537 @@ impl TrackData {
     pub fn new(name: &str) -> Self {
         Self {
             name: decode_html_entities(name).to_string(),
+            artist: None,
+            source: None,
+            section: None,
+            skip: false,
+            length: None,
+            confidence: Confidence::default(),
         }
     }
 }
 
-impl From<&DiscogsTrack> for TrackData {
-    fn from(value: &DiscogsTrack) -> Self {
-        Self::new(&value.title)
+/// One of [`StateModifyingData::videos`], as it's shown when picking a track's video
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoChoice {
+    /// 0-based position in the playlist
+    pub index: usize,
+    label: String,
+}
+
+impl Display for VideoChoice {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.label)
     }
 }
 
+/// A playlist item much longer than any of the tracks, found by [`find_full_album`]
+#[derive(Debug, Clone)]
+pub struct FullAlbumItem {
+    /// 0-based position in the playlist
+    pub index: usize,
+    pub id: VideoId,
+    pub title: String,
+    pub length: Duration,
+    /// From Discogs, for splitting the video up if it doesn't have chapters
+    pub track_lengths: Vec<Option<Duration>>,
+    /// Why it couldn't be split, if it couldn't
+    pub error: Option<String>,
+    /// Its chapters are being fetched in the background, to split it up with
+    pub splitting: bool,
+}
+
+impl FullAlbumItem {
+    /// Looks for a full album upload in the playlist
+    fn detect(playlist: &Playlist, track_lengths: Vec<Option<Duration>>) -> Option<Self> {
+        let item_lengths: Vec<Option<Duration>> =
+            playlist.tracks.iter().map(|item| item.duration).collect();
+        let index = find_full_album(&item_lengths, &track_lengths)?;
+        let item = &playlist.tracks[index];
+
+        Some(Self {
+            index,
+            id: item.id.clone()?,
+            title: item.title.clone().unwrap_or_default(),
+            length: item.duration?,
+            track_lengths,
+            error: None,
+            splitting: false,
+        })
+    }
+
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.id.url()
+    }
+}
+
+/// A file being imported, waiting for the user to map its columns and confirm
+#[derive(Debug, Clone)]
+pub struct StateImport {
+    pub table: ImportedTable,
+    pub mapping: ColumnMapping,
+}
+
+impl From<&TrackMetadata> for TrackData {
+    fn from(value: &TrackMetadata) -> Self {
+        Self {
+            name: value.title.clone(),
+            artist: value.artist.clone(),
+            length: value.length,
+            ..Self::default()
+        }
+    }
+}
+
+/// Without the track number if the title starts with one
 impl From<PlaylistItem> for TrackData {
     fn from(value: PlaylistItem) -> Self {
-        Self::new(&value.title.unwrap_or_default())
+        Self::new(split_track_number(&value.title.unwrap_or_default()).1)
     }
 }
 
-impl From<&DiscogsAlbum> for AlbumData {
-    fn from(discogs_album_data: &DiscogsAlbum) -> Self {
-        AlbumData {
-            name: decode_html_entities(&discogs_album_data.album_data.name).to_string(),
-            artist: discogs_album_data
-                .album_data
-                .release_of
-                .by_artist
-                .iter()
-                .fold(String::new(), |acc, artist| {
-                    if acc.is_empty() {
-                        decode_html_entities(&artist.name).to_string()
-                    } else {
-                        acc + "; " + &decode_html_entities(&artist.name)
-                    }
-                }),
-            genre: discogs_album_data
-                .album_data
-                .genre
-                .iter()
-                .fold(String::new(), |acc, genre| {
-                    if acc.is_empty() {
-                        genre.clone()
-                    } else {
-                        acc + "; " + &genre
-                    }
-                }),
-            year: discogs_album_data.album_data.date_published,
-            image: discogs_album_data.album_data.image.clone(),
-            released: discogs_album_data.released,
+/// With the year set to the current one if the source doesn't know it
+impl From<&AlbumMetadata> for AlbumData {
+    fn from(album: &AlbumMetadata) -> Self {
+        let mut album_data = AlbumData {
+            name: album.title.clone(),
+            artist: album.artist.clone(),
+            genre: album.genres.join("; "),
+            image: album.image.clone().unwrap_or_default(),
+            released: album.released,
+            ..AlbumData::default()
+        };
+        if let Some(year) = album.year() {
+            album_data.year = year;
         }
+        album_data
     }
 }
 
 impl StateModifyingData {
+    /// The album from wherever it was scraped (see [`MetadataSource`]), with the playlist checked
+    /// for a full album upload and numbered videos
+    ///
+    /// [`MetadataSource`]: crate::scraping::MetadataSource
     #[must_use]
-    pub fn new(youtube_url: String, scraped_discogs: &DiscogsAlbum) -> Self {
-        let album_data = AlbumData::from(scraped_discogs);
-        let mut track_data = Vec::with_capacity(scraped_discogs.tracks.len());
-        for track in &scraped_discogs.tracks {
-            if let Some(track) = track {
-                track_data.push(TrackData::from(track));
-            } else {
-                log::error!("failed to parse track");
+    pub fn new(youtube_url: String, album: &AlbumMetadata) -> Self {
+        let confidence = album.confidence();
+        let track_data = album
+            .tracks
+            .iter()
+            .map(|track| TrackData {
+                confidence: confidence.tracks,
+                ..TrackData::from(track)
+            })
+            .collect();
+        let mut state = Self::with_tracklist(youtube_url, album, track_data);
+        state.release_image.clone_from(&album.image);
+        state.confidence = confidence;
+        state
+    }
+
+    /// Checks the playlist for a full album upload and numbered videos against the tracklist, and
+    /// where the playlist disagrees with the release
+    fn with_tracklist(
+        youtube_url: String,
+        release: &AlbumMetadata,
+        track_data: Vec<TrackData>,
+    ) -> Self {
+        let mut state = Self::with_data(youtube_url, AlbumData::from(release), track_data);
+        if let Some(playlist) = scrape_playlist_quietly(&state.youtube_url) {
+            let track_lengths: Vec<Option<Duration>> =
+                release.tracks.iter().map(|track| track.length).collect();
+            state.full_album = FullAlbumItem::detect(&playlist, track_lengths.clone());
+            state.place_numbered_videos(&playlist.tracks);
+            let youtube = AlbumMetadata::from(&playlist);
+            state.videos = playlist.tracks;
+            if state.full_album.is_none() {
+                state.untick_non_music(&track_lengths);
             }
+            // the tracks are all in one video if it's a full album upload
+            let conflicts = compare(release, &youtube, |i| {
+                state.full_album.is_none().then(|| state.track_video(i))?
+            });
+            state.conflicts = conflicts;
         }
+        state
+    }
 
+    fn with_data(youtube_url: String, album_data: AlbumData, track_data: Vec<TrackData>) -> Self {
         Self {
             youtube_url,
             album_data,
             track_data,
+            profile: config::current().default_profile(),
+            image_status: ImageStatus::default(),
+            pasted_tracklist: None,
+            import_path: String::new(),
+            import: None,
+            import_error: None,
+            full_album: None,
+            job_id: None,
+            title_rules: config::current().titles,
+            videos: Vec::new(),
+            upscale_preview: None,
+            art_preview: None,
+            release_image: None,
+            cover_search: CoverSearch::NotSearched,
+            confidence: FieldConfidence::default(),
+            scrape: None,
+            rescrape: Rescrape::NotStarted,
+            flagged_sources: Vec::new(),
+            alternative: AlternativeSearch::NotSearched,
+            conflicts: Vec::new(),
+            non_music: Vec::new(),
         }
     }
 
+    /// Scrapes the album from `metadata_url` (a Discogs, Musicbrainz or Bandcamp release, or a
+    /// folder to read the tags from), or just the playlist with
+    /// [`StateModifyingData::new_without_discogs`] if it's empty or can't be scraped. Used for both
+    /// the link screen and the command line.
+    ///
+    /// Without a `youtube_url`, the album's playlist is searched for on Youtube.
+    ///
+    /// # Errors
+    /// If it falls back to the playlist and that can't be scraped
+    pub fn from_links(
+        youtube_url: String,
+        metadata_url: &str,
+    ) -> Result<Self, ScrapeYoutubePlaylistError> {
+        if !metadata_url.trim().is_empty() {
+            match scrape_metadata(metadata_url) {
+                Ok(album) => {
+                    let youtube_url = if youtube_url.trim().is_empty() {
+                        search_playlist_quietly(&album)
+                    } else {
+                        youtube_url
+                    };
+                    let mut state = Self::new(youtube_url, &album);
+                    state.scrape = Some(StoredScrape::new(metadata_url, &album));
+                    return Ok(state);
+                }
+                Err(err) => log::warn!("{err}"),
+            }
+        }
+        Self::new_without_discogs(youtube_url)
+    }
+
     /// Fails if [`scrape_playlist`] fails (used to see how many tracks in the album)
+    ///
+    /// If it's a list of videos rather than a playlist, the tracks are named after the videos.
     #[allow(clippy::missing_errors_doc)]
     pub fn new_without_discogs(youtube_url: String) -> Result<Self, ScrapeYoutubePlaylistError> {
-        scrape_playlist(&music_to_www(&youtube_url)).map(|playlist_data| Self {
-            youtube_url,
-            album_data: AlbumData {
-                name: playlist_data.title,
-                artist: playlist_data.artist,
-                ..AlbumData::default()
-            },
-            track_data: playlist_data.tracks.into_iter().map(Into::into).collect(),
+        if let Some(ids) = parse_video_list(&youtube_url) {
+            let titles = scrape_titles(&ids).unwrap_or_else(|err| {
+                log::warn!("couldn't get the video titles: {err}");
+                Vec::new()
+            });
+            let videos: Vec<PlaylistItem> = ids
+                .iter()
+                .map(|id| PlaylistItem {
+                    title: titles
+                        .iter()
+                        .find(|(video_id, _)| video_id == id)
+                        .map(|(_, title)| title.clone()),
+                    id: Some(id.clone()),
+                    duration: None,
+                    channel: None,
+                })
+                .collect();
+            let track_data = videos
+                .iter()
+                .cloned()
+                .map(|video| TrackData {
+                    confidence: Confidence::Guessed,
+                    ..video.into()
+                })
+                .collect();
+            let mut state = Self::with_data(youtube_url, AlbumData::default(), track_data);
+            state.place_numbered_videos(&videos);
+            state.videos = videos;
+            state.untick_non_music(&[]);
+            state.confidence = AlbumMetadata::default().confidence();
+            return Ok(state);
+        }
+
+        // not `YoutubePlaylist::scrape` since the videos are needed too
+        scrape_playlist(&page_url(&youtube_url)).map(|playlist| {
+            let album = AlbumMetadata::from(&playlist);
+            let confidence = album.confidence();
+            let track_data = album
+                .tracks
+                .iter()
+                .map(|track| TrackData {
+                    confidence: confidence.tracks,
+                    ..TrackData::from(track)
+                })
+                .collect();
+            let mut state = Self::with_data(youtube_url, AlbumData::from(&album), track_data);
+            state.place_numbered_videos(&playlist.tracks);
+            state.videos = playlist.tracks;
+            state.untick_non_music(&[]);
+            state.confidence = confidence;
+            state
         })
     }
+
+    /// Unticks the tracks whose videos don't look like songs (see
+    /// [`NonMusicRules`](crate::non_music::NonMusicRules)), remembering why. `track_lengths` are
+    /// the release's, or empty if the tracks came from the playlist.
+    fn untick_non_music(&mut self, track_lengths: &[Option<Duration>]) {
+        let rules = config::current().non_music;
+        for i in 0..self.track_data.len() {
+            let Some(video) = self.track_video(i).and_then(|video| self.videos.get(video)) else {
+                continue;
+            };
+            if let Some(reason) = rules.check(video, track_lengths) {
+                log::info!("unticking track {} since {reason}", i + 1);
+                self.track_data[i].skip = true;
+                self.non_music.push((i, reason));
+            }
+        }
+    }
+
+    /// If the video titles are numbered (like "03 - Song Name") but the videos are out of order,
+    /// gives each track the video with its number as its source, so the right video gets
+    /// downloaded for it. The track names are moved along with them if they came from the videos.
+    ///
+    /// Does nothing unless there's one video per track and they all have different numbers.
+    fn place_numbered_videos(&mut self, videos: &[PlaylistItem]) {
+        if videos.len() != self.track_data.len() {
+            return;
+        }
+        let numbers: Vec<Option<usize>> = videos
+            .iter()
+            .map(|video| split_track_number(video.title.as_deref().unwrap_or_default()).0)
+            .collect();
+        let Some(order) = order_by_track_number(&numbers) else {
+            return;
+        };
+        if order.iter().enumerate().all(|(i, index)| i == *index)
+            || videos.iter().any(|video| video.id.is_none())
+        {
+            return;
+        }
+
+        log::info!("the videos are numbered out of order, matching them to tracks by number");
+        let named_from_videos = self
+            .track_data
+            .iter()
+            .zip(videos)
+            .all(|(track, video)| track.name == TrackData::from(video.clone()).name);
+        let old_tracks = self.track_data.clone();
+        for ((video, index), old_track) in videos.iter().zip(order).zip(old_tracks) {
+            let track = &mut self.track_data[index];
+            if named_from_videos {
+                *track = old_track;
+            }
+            track.source = video.id.as_ref().map(VideoId::url);
+        }
+    }
+
+    /// Track `index`'s number and how many tracks there are, for its tags and file name. Skipped
+    /// tracks still count unless [`AlbumData::renumber_skipped`] is set.
+    #[must_use]
+    pub fn track_number(&self, index: usize) -> (usize, usize) {
+        if self.album_data.renumber_skipped {
+            let before = self.track_data.iter().take(index);
+            (
+                before.filter(|track| !track.skip).count() + 1,
+                self.tracks_to_download(),
+            )
+        } else {
+            (index + 1, self.track_data.len())
+        }
+    }
+
+    /// How many tracks aren't skipped
+    #[must_use]
+    pub fn tracks_to_download(&self) -> usize {
+        self.track_data.iter().filter(|track| !track.skip).count()
+    }
+
+    /// Which of [`StateModifyingData::videos`] track `index` is downloaded from: the one its
+    /// source links to, or the one at the same position if it doesn't have a source
+    #[must_use]
+    pub fn track_video(&self, index: usize) -> Option<usize> {
+        match &self.track_data.get(index)?.source {
+            Some(source) => {
+                let id = parse_video_id(source.trim())?;
+                self.videos
+                    .iter()
+                    .position(|video| video.id.as_ref() == Some(&id))
+            }
+            None => (index < self.videos.len()).then_some(index),
+        }
+    }
+
+    /// Why track `index` is unticked, if it still is because its video doesn't look like a song
+    #[must_use]
+    pub fn non_music_note(&self, index: usize) -> Option<String> {
+        if !self.track_data.get(index)?.skip {
+            return None;
+        }
+        self.non_music
+            .iter()
+            .find(|(i, _)| *i == index)
+            .map(|(_, reason)| format!("Unticked since {reason}"))
+    }
+
+    /// Reads [`StateModifyingData::flagged_sources`] from the saved history
+    pub fn load_flagged_sources(&mut self) {
+        self.flagged_sources = sources::flagged();
+    }
+
+    /// A warning if track `index` is from a channel that's been marked as a bad source before
+    #[must_use]
+    pub fn source_warning(&self, index: usize) -> Option<String> {
+        let channel = self
+            .videos
+            .get(self.track_video(index)?)?
+            .channel
+            .as_ref()?;
+        self.flagged_sources
+            .iter()
+            .find(|record| record.channel.id == channel.id)
+            .map(SourceRecord::warning)
+    }
+
+    /// If track `index`'s video from the playlist is much longer or shorter than the release says
+    /// the track is, like a live version. Parts of a full album upload aren't checked.
+    #[must_use]
+    pub fn length_mismatch(&self, index: usize) -> Option<LengthMismatch> {
+        let track = self.track_data.get(index)?;
+        if track.skip || track.section.is_some() {
+            return None;
+        }
+        let video = self.videos.get(self.track_video(index)?)?;
+        LengthMismatch::check(track.length?, video.duration?)
+    }
+
+    /// What to search Youtube for to find another upload of track `index`: its artist, its name and
+    /// how long it should be
+    #[must_use]
+    pub fn alternative_query(&self, index: usize) -> Option<(String, String, Duration)> {
+        let track = self.track_data.get(index)?;
+        let artist = track
+            .artist
+            .clone()
+            .unwrap_or_else(|| self.album_data.artist.clone());
+        Some((artist, track.name.clone(), track.length?))
+    }
+
+    /// Downloads track `index` from the video that was found, if one was
+    pub fn alternative_found(
+        &mut self,
+        index: usize,
+        result: Result<Option<PlaylistItem>, String>,
+    ) {
+        self.alternative = match result {
+            Ok(Some(video)) => {
+                if let Some(id) = &video.id {
+                    self.set_source(index, Some(id.url()));
+                }
+                AlternativeSearch::Found(index, video)
+            }
+            Ok(None) => AlternativeSearch::Failed(
+                index,
+                String::from("none of the videos that were found are close to the track's length"),
+            ),
+            Err(err) => AlternativeSearch::Failed(index, err),
+        };
+    }
+
+    /// The videos that can be picked for a track, like "3. Song Name (3:45)"
+    #[must_use]
+    pub fn video_choices(&self) -> Vec<VideoChoice> {
+        self.videos
+            .iter()
+            .enumerate()
+            .filter(|(_, video)| video.id.is_some())
+            .map(|(index, video)| {
+                let title = video.title.as_deref().unwrap_or("(no title)");
+                let label = match video.duration {
+                    Some(duration) => {
+                        format!("{}. {title} ({})", index + 1, format_duration(duration))
+                    }
+                    None => format!("{}. {title}", index + 1),
+                };
+                VideoChoice { index, label }
+            })
+            .collect()
+    }
+
+    /// Downloads `video` (from [`StateModifyingData::videos`]) for track `index`. The track that
+    /// had that video gets this track's old one, so the tracks can be put in order by swapping
+    /// without any video being downloaded twice. If this track didn't have one (it's past the end
+    /// of the playlist), that track is unticked instead, since there's nothing to swap it for.
+    pub fn assign_video(&mut self, index: usize, video: usize) {
+        let Some(id) = self.videos.get(video).and_then(|video| video.id.clone()) else {
+            return;
+        };
+        let Some(track) = self.track_data.get(index) else {
+            return;
+        };
+        let old_source = track.source.clone().or_else(|| {
+            self.videos
+                .get(index)
+                .and_then(|video| video.id.as_ref())
+                .map(VideoId::url)
+        });
+        let previous = (0..self.track_data.len())
+            .find(|&other| other != index && self.track_video(other) == Some(video));
+
+        self.set_source(index, Some(id.url()));
+        match (previous, old_source) {
+            (Some(other), Some(old_source)) => self.set_source(other, Some(old_source)),
+            (Some(other), None) => {
+                self.set_source(other, None);
+                self.track_data[other].skip = true;
+            }
+            (None, _) => {}
+        }
+    }
+
+    /// Goes back to downloading each track from the video at its position in the playlist,
+    /// keeping sources that aren't from the playlist
+    pub fn reset_videos(&mut self) {
+        for track in &mut self.track_data {
+            let from_playlist = track
+                .source
+                .as_deref()
+                .and_then(|source| parse_video_id(source.trim()))
+                .is_some_and(|id| {
+                    self.videos
+                        .iter()
+                        .any(|video| video.id.as_ref() == Some(&id))
+                });
+            // split tracks keep their part of the full album
+            if from_playlist && track.section.is_none() {
+                track.source = None;
+            }
+        }
+    }
+
+    /// Sets a track's source, leaving it empty if it's the video at the track's own position,
+    /// since that's what gets downloaded anyway
+    fn set_source(&mut self, index: usize, source: Option<String>) {
+        let own_video = self.videos.get(index).and_then(|video| video.id.clone());
+        let is_own_video = own_video.is_some()
+            && source.as_deref().and_then(|s| parse_video_id(s.trim())) == own_video;
+        let track = &mut self.track_data[index];
+        track.source = if is_own_video { None } else { source };
+        // the section was for the old source
+        track.section = None;
+    }
+
+    /// Replaces the track names with the ones from [`StateModifyingData::pasted_tracklist`].
+    pub fn apply_tracklist(&mut self) {
+        if let Some(lines) = self.pasted_tracklist.take() {
+            self.place_tracks(
+                lines
+                    .into_iter()
+                    .map(|line| (line.number, TrackData::new(&line.title))),
+            );
+        }
+    }
+
+    /// The tracks whose names [`StateModifyingData::title_rules`] would change, with their new
+    /// names
+    #[must_use]
+    pub fn title_changes(&self) -> Vec<(usize, String)> {
+        self.track_data
+            .iter()
+            .enumerate()
+            .map(|(i, track)| (i, self.title_rules.apply(&track.name)))
+            .filter(|(i, name)| *name != self.track_data[*i].name)
+            .collect()
+    }
+
+    /// Renames the tracks with [`StateModifyingData::title_rules`]
+    pub fn apply_title_rules(&mut self) {
+        for (i, name) in self.title_changes() {
+            self.track_data[i].name = name;
+        }
+    }
+
+    /// Reads the file at [`StateModifyingData::import_path`] and guesses its column mapping
+    pub fn load_import(&mut self) {
+        match ImportedTable::read(Path::new(self.import_path.trim())) {
+            Ok(table) => {
+                self.import = Some(StateImport {
+                    mapping: table.guess_mapping(),
+                    table,
+                });
+                self.import_error = None;
+            }
+            Err(err) => {
+                log::warn!("couldn't import {}: {err}", self.import_path);
+                self.import = None;
+                self.import_error = Some(err.to_string());
+            }
+        }
+    }
+
+    /// Replaces the tracks with the ones from [`StateModifyingData::import`].
+    ///
+    /// Also sets the album year if the file has one.
+    pub fn apply_import(&mut self) {
+        let Some(import) = self.import.take() else {
+            return;
+        };
+
+        let tracks = import.table.tracks(&import.mapping);
+        if let Some(year) = tracks.iter().find_map(|track| track.year) {
+            self.album_data.year = year;
+            self.confidence.year = Confidence::Edited;
+        }
+        self.place_tracks(tracks.into_iter().map(|track| {
+            let data = TrackData {
+                artist: track.artist,
+                ..TrackData::new(&track.title)
+            };
+            (track.number, data)
+        }));
+    }
+
+    /// Numbered tracks go to that position, and unnumbered ones go after the previous one.
+    /// Tracks are added if there are more than there were before.
+    ///
+    /// Numbers more than [`TRACK_NUMBER_MARGIN`] past the end (of whichever's longer of the tracks
+    /// there were and the ones being placed) are ignored, so a stray number like a year doesn't
+    /// add thousands of empty tracks.
+    fn place_tracks(&mut self, tracks: impl IntoIterator<Item = (Option<usize>, TrackData)>) {
+        let tracks: Vec<_> = tracks.into_iter().collect();
+        let max_number = self.track_data.len().max(tracks.len()) + TRACK_NUMBER_MARGIN;
+        let mut index = 0;
+        for (number, track) in tracks {
+            match number.filter(|n| *n > 0) {
+                Some(number) if number <= max_number => index = number - 1,
+                Some(number) => log::warn!("ignoring track number {number}, it's past the end"),
+                None => {}
+            }
+            if index >= self.track_data.len() {
+                self.track_data.resize_with(index + 1, TrackData::default);
+            }
+            self.track_data[index] = track;
+            index += 1;
+        }
+    }
+
+    /// Makes every track a [`Section`] of [`StateModifyingData::full_album`], using the video's
+    /// `chapters` (fetched in the background) if there's one per track, otherwise the track
+    /// lengths from Discogs.
+    ///
+    /// If neither works the error is shown and the tracks are left alone.
+    pub fn split_full_album(&mut self, chapters: &[YoutubeChapter]) {
+        let Some(full_album) = &mut self.full_album else {
+            return;
+        };
+        full_album.splitting = false;
+
+        let url = full_album.url();
+        let sections = sections_from_chapters(chapters, self.track_data.len()).or_else(|| {
+            sections_from_lengths(&full_album.track_lengths)
+                .filter(|sections| sections.len() == self.track_data.len())
+        });
+
+        let Some(sections) = sections else {
+            full_album.error = Some(format!(
+                "the video doesn't have a chapter for each of the {} tracks, and some track lengths are missing",
+                self.track_data.len()
+            ));
+            return;
+        };
+        for (track, section) in self.track_data.iter_mut().zip(sections) {
+            track.source = Some(url.clone());
+            track.section = Some(section);
+        }
+        self.full_album = None;
+    }
+
+    /// Checks the album cover URL with [`check_url`], replacing it with the (https) image URL if it's
+    /// valid. An empty URL just means no cover.
+    pub fn check_image(&mut self) {
+        if self.album_data.image.trim().is_empty() {
+            self.image_status = ImageStatus::Unchecked;
+            return;
+        }
+
+        self.image_status = match check_url(&self.album_data.image) {
+            Ok(image) => {
+                self.album_data.image = image.url.to_string();
+                ImageStatus::Valid(image.to_string())
+            }
+            Err(err) => {
+                log::warn!("bad album cover URL: {err}");
+                ImageStatus::Invalid(err.to_string())
+            }
+        };
+    }
+
+    /// What to look for other covers with
+    #[must_use]
+    pub fn cover_query(&self) -> CoverQuery<'_> {
+        CoverQuery {
+            artist: &self.album_data.artist,
+            album: &self.album_data.name,
+            release_image: self.release_image.as_deref(),
+            video: self.videos.iter().find_map(|video| video.id.as_ref()),
+        }
+    }
+
+    /// Uses one of the covers that were found, checking it like one that's typed in
+    pub fn pick_cover(&mut self, url: String) {
+        self.album_data.image = url;
+        self.confidence.image = Confidence::Edited;
+        self.check_image();
+    }
+
+    /// Compares the release scraped again with [`StateModifyingData::scrape`]. If nothing's
+    /// changed it's kept as the latest scrape, otherwise the changes are offered until they've
+    /// all been accepted or rejected.
+    pub fn rescraped(&mut self, newer: Result<StoredScrape, String>) {
+        self.rescrape = match (newer, &self.scrape) {
+            (Err(err), _) => Rescrape::Failed(err),
+            (Ok(newer), Some(scrape)) => {
+                let changes = scrape.changes(&newer);
+                if changes.is_empty() {
+                    self.scrape = Some(newer);
+                    Rescrape::Unchanged
+                } else {
+                    Rescrape::Changed(newer, changes)
+                }
+            }
+            (Ok(newer), None) => {
+                self.scrape = Some(newer);
+                Rescrape::Unchanged
+            }
+        };
+    }
+
+    /// Uses `pick`'s value for the conflicting field at `index`
+    pub fn pick_conflict(&mut self, index: usize, pick: Pick) {
+        if index < self.conflicts.len() {
+            let mut conflict = self.conflicts[index].clone();
+            conflict.pick(pick, self);
+            self.conflicts[index] = conflict;
+        }
+    }
+
+    /// Accepts or rejects the change at `index`, or all of them if it's `None`. Once none are
+    /// left, the newer scrape replaces the stored one so rejected changes aren't offered again.
+    pub fn resolve_changes(&mut self, index: Option<usize>, accept: bool) {
+        let Rescrape::Changed(_, changes) = &mut self.rescrape else {
+            return;
+        };
+        let resolved: Vec<Change> = match index {
+            Some(index) if index < changes.len() => vec![changes.remove(index)],
+            Some(_) => return,
+            None => std::mem::take(changes),
+        };
+        let done = changes.is_empty();
+        if accept {
+            for change in &resolved {
+                change.apply(self);
+            }
+        }
+        if done {
+            if let Rescrape::Changed(newer, _) = std::mem::take(&mut self.rescrape) {
+                self.scrape = Some(newer);
+            }
+        }
+    }
+
+    /// The upscaled cover from [`StateModifyingData::upscale_preview`], if it's of the current
+    /// cover
+    #[must_use]
+    pub fn previewed_upscale(&self) -> Option<(Bytes, &'static str)> {
+        match &self.upscale_preview {
+            Some(Ok(preview)) if preview.url == self.album_data.image => preview.after.clone().ok(),
+            _ => None,
+        }
+    }
+}
+
+/// The URL of the album's playlist on Youtube, or an empty one (just logging why) if it can't be
+/// found
+fn search_playlist_quietly(album: &AlbumMetadata) -> String {
+    match find_album_playlist(&album.artist, &album.title) {
+        Ok(Some(playlist)) => {
+            log::info!(r#"found the album's playlist, "{}""#, playlist.title);
+            playlist.url()
+        }
+        Ok(None) => {
+            log::warn!("couldn't find the album's playlist on Youtube");
+            String::new()
+        }
+        Err(err) => {
+            log::warn!("couldn't search Youtube for the album's playlist: {err}");
+            String::new()
+        }
+    }
+}
+
+/// The playlist (if it isn't a list of videos), or `None` if it can't be scraped, since the tracks
+/// can still be downloaded as normal without it
+fn scrape_playlist_quietly(youtube_url: &str) -> Option<Playlist> {
+    if parse_video_list(youtube_url).is_some() {
+        return None;
+    }
+
+    scrape_playlist(&page_url(youtube_url))
+        .map_err(|err| log::debug!("couldn't scrape the playlist: {err}"))
+        .ok()
+}
+
+/// How big the cover is shown, in logical pixels. It keeps its aspect ratio within this.
+const ART_PREVIEW_SIZE: f32 = 200.0;
+/// How far past the end of the tracks a pasted or imported track number can go
+const TRACK_NUMBER_MARGIN: usize = 20;
+/// How big the thumbnails of other covers are shown
+const COVER_THUMBNAIL_SIZE: f32 = 80.0;
+
+fn input<'a>(
+    placeholder: &str,
+    id: &'static str,
+    value: &str,
+    ui: UiSettings,
+) -> TextInput<'a, Message> {
+    TextInput::new(placeholder, value)
+        .id(text_input::Id::new(id))
+        .padding(style::input_padding(ui))
+}
+
+/// Like "★★★☆☆", or "Unrated" for 0
+fn rating_label(stars: u8) -> String {
+    if stars == 0 {
+        return String::from("Unrated");
+    }
+    (0..AlbumData::MAX_RATING)
+        .map(|i| if i < stars { '★' } else { '☆' })
+        .collect()
 }
 
 impl App {
     #[must_use]
     pub fn view_modifying_data<'a>(state: &'_ StateModifyingData) -> Element<'a, Message> {
+        let ui = config::current().ui;
+
         // submit buttons
-        let download_button: Button<'_, Message> =
-            Button::new("Download").on_press(Message::Download);
+        let download_button: Button<'_, Message> = Button::new("Download")
+            .padding(style::button_padding(ui))
+            .on_press(Message::Download);
+        let queue_button = Button::new("Add to queue")
+            .padding(style::button_padding(ui))
+            .on_press(Message::QueueAlbum);
 
         // album data
+        let input =
+            |placeholder: &str, id: &'static str, value: &str| input(placeholder, id, value, ui);
+        let confidence = state.confidence;
         let album_name_input: TextInput<'_, Message> =
-            TextInput::new("Album name", state.album_data.name.as_str())
+            input("Album name", "album-name", state.album_data.name.as_str())
                 .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::AlbumName(s)));
-        let album_artist_input = TextInput::new("Artists", &state.album_data.artist)
+        let album_artist_input = input("Artists", "artist", &state.album_data.artist)
             .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::Artist(s)));
-        let album_date_input = TextInput::new("Date", &format!("{}", state.album_data.year))
+        let album_album_artist_input = input(
+            &state.album_data.artist,
+            "album-artist",
+            &state.album_data.album_artist,
+        )
+        .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::AlbumArtist(s)));
+        let artist_sort_placeholder =
+            sort_name(&state.album_data.artist).unwrap_or_else(|| state.album_data.artist.clone());
+        let album_artist_sort_input = input(
+            &artist_sort_placeholder,
+            "artist-sort",
+            &state.album_data.artist_sort,
+        )
+        .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::ArtistSort(s)));
+        let album_date_input = input("Date", "album-year", &format!("{}", state.album_data.year))
             .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::Year(s)));
-        let album_genre_input = TextInput::new("Genre", &state.album_data.genre)
+        let album_genre_input = input("Genre", "album-genre", &state.album_data.genre)
             .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::Genre(s)));
-        let album_cover_url_input = TextInput::new("Album Cover URL", &state.album_data.image)
-            .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::Image(s)));
+        let ratings: Vec<String> = (0..=AlbumData::MAX_RATING).map(rating_label).collect();
+        let album_rating_picker = PickList::new(
+            ratings.clone(),
+            Some(rating_label(state.album_data.rating.unwrap_or_default())),
+            move |s| {
+                let stars = ratings
+                    .iter()
+                    .position(|label| *label == s)
+                    .unwrap_or_default();
+                #[allow(clippy::cast_possible_truncation)]
+                let rating = Some(stars as u8).filter(|&stars| stars > 0);
+                Message::ModifyDataInputChanged(ModifyDataInputChange::Rating(rating))
+            },
+        )
+        .padding(style::input_padding(ui));
+        let album_favorite_checkbox = checkbox("Favorite", state.album_data.favorite, |b| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::Favorite(b))
+        });
 
         let mut content: Column<'_, Message> = column![
-            download_button,
+            row![download_button, queue_button].spacing(10),
+            Self::view_output(state, ui),
             Rule::horizontal(4),
-            album_name_input,
-            album_artist_input,
-            album_date_input,
-            album_genre_input,
-            album_cover_url_input,
+            Self::view_rescrape(state, ui),
+            Self::view_conflicts(state, ui),
+            style::scraped_field(
+                "Album name",
+                Help::AlbumName,
+                confidence.title,
+                album_name_input
+            ),
+            style::scraped_field(
+                "Artist (display)",
+                Help::Artist,
+                confidence.artist,
+                album_artist_input
+            ),
+            style::labelled_with_help("Album artist", Help::AlbumArtist, album_album_artist_input),
+            style::labelled_with_help("Artist (sort)", Help::ArtistSort, album_artist_sort_input),
+            style::scraped_field("Year", Help::Year, confidence.year, album_date_input),
+            style::scraped_field("Genre", Help::Genre, confidence.genres, album_genre_input),
+            style::labelled_with_help(
+                "Rating",
+                Help::Rating,
+                row![album_rating_picker, album_favorite_checkbox].spacing(20)
+            ),
+            Self::view_cover(state, ui),
+            Self::view_covers(state, ui),
+            Self::view_art_upscale(state, ui),
             Rule::horizontal(4)
         ]
         .spacing(20)
         .max_width(800);
 
         // tracks
+        if let Some(full_album) = &state.full_album {
+            content = content.push(Self::view_full_album(full_album, ui));
+        }
+        content = content.push(Self::view_tracklist_tools(state, ui));
+        content = content.push(Self::view_tracks(state, ui));
+
+        scrollable(container(content).width(Length::Fill).padding(40)).into()
+    }
+
+    /// Scraping the release again, and what's changed on it to accept or reject
+    fn view_rescrape<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        if state.scrape.is_none() {
+            return column![];
+        }
+        let rescrape_button = Button::new("Re-scrape")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!matches!(state.rescrape, Rescrape::Scraping)).then_some(Message::Rescrape),
+            );
+        let mut content = column![style::with_help(rescrape_button, Help::Rescrape)].spacing(10);
+        let button = |label: &str, message: Message| {
+            Button::new(text(label))
+                .padding(style::button_padding(ui))
+                .on_press(message)
+        };
+
+        match &state.rescrape {
+            Rescrape::NotStarted => {}
+            Rescrape::Scraping => content = content.push(text("Scraping the release again...")),
+            Rescrape::Unchanged => {
+                content = content.push(text("Nothing's changed since it was last scraped"));
+            }
+            Rescrape::Failed(err) => content = content.push(text(format!("✘ {err}"))),
+            Rescrape::Changed(_, changes) => {
+                content = content.push(
+                    row![
+                        text(format!(
+                            "{} changed since it was last scraped:",
+                            changes.len()
+                        )),
+                        button("Accept all", Message::ResolveChanges(None, true)),
+                        button("Reject all", Message::ResolveChanges(None, false))
+                    ]
+                    .spacing(10),
+                );
+                for (i, change) in changes.iter().enumerate() {
+                    let current = change.current(state);
+                    let description = match change.field {
+                        RescrapedField::RemovedTrack(_) => {
+                            format!(r#"{}: skip "{current}""#, change.field)
+                        }
+                        _ => format!(r#"{}: "{current}" → "{}""#, change.field, change.new),
+                    };
+                    content = content.push(
+                        row![
+                            button("Accept", Message::ResolveChanges(Some(i), true)),
+                            button("Reject", Message::ResolveChanges(Some(i), false)),
+                            text(description)
+                        ]
+                        .spacing(10),
+                    );
+                }
+            }
+        }
+        content
+    }
+
+    /// The fields the release and the playlist disagree on side by side, with a button on each
+    /// side to use that one
+    fn view_conflicts<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        if state.conflicts.is_empty() {
+            return column![];
+        }
+        let side = |value: &str, pick: Pick, picked: Pick, i: usize| {
+            let label = if pick == picked { "✔ Using" } else { "Use" };
+            row![
+                Button::new(text(label))
+                    .padding(style::button_padding(ui))
+                    .on_press_maybe((pick != picked).then_some(Message::PickConflict(i, pick))),
+                text(value.to_string())
+            ]
+            .spacing(10)
+            .width(Length::FillPortion(2))
+        };
+
+        let mut content = column![
+            style::with_help(
+                text(format!(
+                    "The release and the Youtube playlist disagree on {} fields:",
+                    state.conflicts.len()
+                )),
+                Help::Conflicts
+            ),
+            row![
+                text("").width(Length::FillPortion(1)),
+                text("Release").width(Length::FillPortion(2)),
+                text("Youtube").width(Length::FillPortion(2))
+            ]
+            .spacing(10)
+        ]
+        .spacing(10);
+        for (i, conflict) in state.conflicts.iter().enumerate() {
+            content = content.push(
+                row![
+                    text(conflict.field.to_string()).width(Length::FillPortion(1)),
+                    side(&conflict.release, Pick::Release, conflict.picked, i),
+                    side(&conflict.youtube, Pick::Youtube, conflict.picked, i)
+                ]
+                .spacing(10),
+            );
+        }
+        content
+    }
+
+    /// The cover's URL, and whether it's been checked to be an image
+    fn view_cover<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        let confidence = state.confidence.image;
+        let album_cover_url_input = input(
+            "Album Cover URL",
+            "album-cover",
+            &state.album_data.image,
+            ui,
+        )
+        .on_input(|s| Message::ModifyDataInputChanged(ModifyDataInputChange::Image(s)))
+        .on_submit(Message::CheckImage);
+        let check_image_button = Button::new("Check")
+            .padding(style::button_padding(ui))
+            .on_press(Message::CheckImage);
+        let album_cover_status = text(match &state.image_status {
+            ImageStatus::Unchecked => String::new(),
+            ImageStatus::Valid(image) => format!("✔ {image}"),
+            ImageStatus::Invalid(err) => format!("✘ {err}"),
+        });
+
+        let mut content = column![
+            style::labelled_with_help(
+                style::confidence_label("Album cover URL", confidence),
+                Help::AlbumCover,
+                row![
+                    style::confidence_input(album_cover_url_input, confidence),
+                    check_image_button
+                ]
+                .spacing(10)
+            ),
+            album_cover_status
+        ]
+        .spacing(20);
+
+        match &state.art_preview {
+            Some(preview) if preview.url == state.album_data.image => match &preview.image {
+                Ok(handle) => {
+                    let cover: Image<image::Handle> = image(handle.clone())
+                        .width(Length::Fixed(ART_PREVIEW_SIZE))
+                        .height(Length::Fixed(ART_PREVIEW_SIZE));
+                    content = content.push(cover);
+                }
+                Err(err) => content = content.push(text(format!("✘ couldn't show it: {err}"))),
+            },
+            _ => {}
+        }
+        content
+    }
+
+    /// Other covers to pick from, from the release, the playlist and searches
+    fn view_covers<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        let find_button = Button::new("Find other covers")
+            .padding(style::button_padding(ui))
+            .on_press_maybe(
+                (!matches!(state.cover_search, CoverSearch::Searching))
+                    .then_some(Message::FindCovers),
+            );
+        let mut content = column![style::with_help(find_button, Help::FindCovers)].spacing(10);
+
+        match &state.cover_search {
+            CoverSearch::NotSearched => {}
+            CoverSearch::Searching => content = content.push(text("Looking for covers...")),
+            CoverSearch::Found(covers) if covers.is_empty() => {
+                content = content.push(text("Couldn't find any covers"));
+            }
+            CoverSearch::Found(covers) => {
+                for (cover, thumbnail) in covers {
+                    let is_current = cover.url == state.album_data.image;
+                    let use_button = Button::new(if is_current { "Using" } else { "Use" })
+                        .padding(style::button_padding(ui))
+                        .on_press_maybe(
+                            (!is_current).then(|| Message::PickCover(cover.url.clone())),
+                        );
+                    let mut candidate = row![use_button].spacing(10);
+                    if let Some(handle) = thumbnail {
+                        let thumbnail: Image<image::Handle> = image(handle.clone())
+                            .width(Length::Fixed(COVER_THUMBNAIL_SIZE))
+                            .height(Length::Fixed(COVER_THUMBNAIL_SIZE));
+                        candidate = candidate.push(thumbnail);
+                    }
+                    let description = text(format!(
+                        "{}: {} ({})",
+                        cover.source, cover.title, cover.info
+                    ));
+                    content = content.push(candidate.push(description));
+                }
+            }
+        }
+        content
+    }
+
+    /// Upscaling the cover if there's a command or API for it, with the cover before and after
+    fn view_art_upscale<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        let settings = config::current().art_upscale;
+        if !settings.is_configured() {
+            return column![];
+        }
+
+        let upscale_checkbox = checkbox(
+            format!("Upscale the cover if it's under {0}×{0}", settings.min_size),
+            !state.album_data.keep_original_art,
+            |b| Message::ModifyDataInputChanged(ModifyDataInputChange::UpscaleArt(b)),
+        );
+        let mut preview_button = Button::new("Preview").padding(style::button_padding(ui));
+        if matches!(state.image_status, ImageStatus::Valid(_)) {
+            preview_button = preview_button.on_press(Message::PreviewArtUpscale);
+        }
+        let mut content = column![row![upscale_checkbox, preview_button].spacing(20)].spacing(10);
+
+        match &state.upscale_preview {
+            Some(Ok(preview)) if preview.url == state.album_data.image => {
+                content = content.push(text(format!("Before: {}", preview.before)));
+                content = content.push(text(match (&preview.after, preview.after_info()) {
+                    (Ok(_), Some(after)) => format!("After: {after}"),
+                    (Ok(_), None) => String::from("After: ✘ not an image"),
+                    (Err(err), _) => format!("After: ✘ {err}"),
+                }));
+            }
+            Some(Err(err)) => content = content.push(text(format!("✘ {err}"))),
+            _ => {}
+        }
+        content
+    }
+
+    /// The profile, and the format and quality which can be changed just for this download
+    fn view_output<'a>(state: &StateModifyingData, ui: UiSettings) -> Row<'a, Message> {
+        let profile_names: Vec<String> = config::current()
+            .profiles()
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        let profile_picker = PickList::new(profile_names, Some(state.profile.name.clone()), |s| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::Profile(s))
+        })
+        .padding(style::input_padding(ui));
+        let format_picker = PickList::new(OutputFormat::ALL, Some(state.profile.format), |f| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::Format(f))
+        })
+        .padding(style::input_padding(ui));
+        let quality_picker = PickList::new(
+            Quality::choices(state.profile.format),
+            Some(state.profile.quality()),
+            |q| Message::ModifyDataInputChanged(ModifyDataInputChange::Quality(q)),
+        )
+        .padding(style::input_padding(ui));
+
+        row![
+            style::labelled_with_help("Profile", Help::Profile, profile_picker),
+            style::labelled_with_help("Format", Help::Format, format_picker),
+            style::labelled_with_help("Quality", Help::Quality, quality_picker)
+        ]
+        .spacing(20)
+    }
+
+    /// Each track's name and where it's downloaded from
+    fn view_tracks<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        let video_choices = state.video_choices();
+        let mut content = Column::new().spacing(20);
         for (i, track) in state.track_data.iter().enumerate() {
-            let track_change_input =
-                TextInput::new(format!("Track {}", i + 1).as_str(), track.name.as_str()).on_input(
-                    move |s| {
+            let track_change_input = style::confidence_input(
+                TextInput::new(format!("Track {}", i + 1).as_str(), track.name.as_str())
+                    .id(text_input::Id::new(format!("track-{}", i + 1)))
+                    .padding(style::input_padding(ui))
+                    .on_input(move |s| {
                         Message::ModifyDataInputChanged(ModifyDataInputChange::Tracks {
                             index: i,
                             value: s,
                         })
-                    },
-                );
-            content = content.push(track_change_input);
+                    }),
+                track.confidence,
+            );
+            let track_source_input = TextInput::new(
+                "From the playlist",
+                track.source.as_deref().unwrap_or_default(),
+            )
+            .id(text_input::Id::new(format!("track-{}-source", i + 1)))
+            .padding(style::input_padding(ui))
+            .on_input(move |s| {
+                Message::ModifyDataInputChanged(ModifyDataInputChange::TrackSource {
+                    index: i,
+                    value: s,
+                })
+            });
+            // the album's artist shows through when it's empty
+            let track_artist_input = TextInput::new(
+                &state.album_data.artist,
+                track.artist.as_deref().unwrap_or_default(),
+            )
+            .id(text_input::Id::new(format!("track-{}-artist", i + 1)))
+            .padding(style::input_padding(ui))
+            .on_input(move |s| {
+                Message::ModifyDataInputChanged(ModifyDataInputChange::TrackArtist {
+                    index: i,
+                    value: s,
+                })
+            });
+            let track_enabled_checkbox = checkbox("Download", !track.skip, move |enabled| {
+                Message::ModifyDataInputChanged(ModifyDataInputChange::TrackEnabled {
+                    index: i,
+                    enabled,
+                })
+            });
+            let label = format!("Track {}, its artist and where it's from", i + 1);
+            let mut track_row = row![
+                track_enabled_checkbox,
+                track_change_input,
+                track_artist_input,
+                track_source_input
+            ]
+            .spacing(10);
+            if let Some(section) = track.section {
+                track_row = track_row.push(text(section.to_string()));
+            }
+            let mut track_column = column![track_row].spacing(10);
+            if !video_choices.is_empty() {
+                track_column =
+                    track_column.push(Self::view_video_picker(state, i, &video_choices, ui));
+            }
+            if let Some(warning) = state.source_warning(i) {
+                track_column = track_column.push(style::warning_text(warning));
+            }
+            if let Some(note) = state.non_music_note(i) {
+                track_column = track_column.push(text(note));
+            }
+            if let Some(alternative) = Self::view_alternative(state, i, ui) {
+                track_column = track_column.push(alternative);
+            }
+            content = content.push(style::labelled_with_help(label, Help::Track, track_column));
         }
 
-        scrollable(container(content).width(Length::Fill).padding(40)).into()
+        if state.track_data.iter().any(|track| track.skip) {
+            content = content.push(checkbox(
+                "Number the downloaded tracks 1, 2, 3… instead of by their places in the tracklist",
+                state.album_data.renumber_skipped,
+                |b| Message::ModifyDataInputChanged(ModifyDataInputChange::RenumberSkipped(b)),
+            ));
+        }
+
+        let add_track_button = Button::new("Add track")
+            .padding(style::button_padding(ui))
+            .on_press(Message::ModifyDataInputChanged(
+                ModifyDataInputChange::AddTrack,
+            ));
+        if video_choices.is_empty() {
+            return content.push(add_track_button);
+        }
+        let reset_videos_button = Button::new("Back to playlist order")
+            .padding(style::button_padding(ui))
+            .on_press(Message::ResetTrackVideos);
+        content.push(row![add_track_button, reset_videos_button].spacing(10))
+    }
+
+    /// A warning if track `index`'s video looks like a different version of it, with a button to
+    /// search for another upload, or how that search went
+    fn view_alternative<'a>(
+        state: &StateModifyingData,
+        index: usize,
+        ui: UiSettings,
+    ) -> Option<Element<'a, Message>> {
+        match &state.alternative {
+            AlternativeSearch::Searching(i) if *i == index => {
+                return Some(text("Searching Youtube for another upload...").into());
+            }
+            AlternativeSearch::Found(i, video) if *i == index => {
+                let mut found = video.title.clone().unwrap_or_default();
+                if let Some(duration) = video.duration {
+                    found = format!("{found} ({})", format_duration(duration));
+                }
+                if let Some(channel) = &video.channel {
+                    found = format!("{found} from {}", channel.name);
+                }
+                return Some(text(format!("Downloading {found} instead")).into());
+            }
+            _ => {}
+        }
+
+        let mismatch = state.length_mismatch(index)?;
+        let mut warning = column![row![
+            style::warning_text(format!("⚠ Might be a different version: {mismatch}")),
+            Button::new("Search for another upload")
+                .padding(style::button_padding(ui))
+                .on_press(Message::SearchAlternative(index)),
+        ]
+        .spacing(10)]
+        .spacing(10);
+        if let AlternativeSearch::Failed(i, err) = &state.alternative {
+            if *i == index {
+                warning = warning.push(text(format!("Couldn't find another upload: {err}")));
+            }
+        }
+        Some(warning.into())
+    }
+
+    /// Which playlist video a track is downloaded from, so they can be matched up when the
+    /// playlist is in a different order to the tracklist
+    fn view_video_picker<'a>(
+        state: &StateModifyingData,
+        index: usize,
+        choices: &[VideoChoice],
+        ui: UiSettings,
+    ) -> Row<'a, Message> {
+        let selected = state
+            .track_video(index)
+            .and_then(|video| choices.iter().find(|choice| choice.index == video))
+            .cloned();
+        let picker = PickList::new(choices.to_vec(), selected, move |choice: VideoChoice| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::TrackVideo {
+                index,
+                video: choice.index,
+            })
+        })
+        .placeholder("Its own link")
+        .padding(style::input_padding(ui));
+        row![text("Video"), picker].spacing(10)
+    }
+
+    /// Offer to split a full album upload into the tracks
+    fn view_full_album<'a>(full_album: &FullAlbumItem, ui: UiSettings) -> Column<'a, Message> {
+        let notice = text(format!(
+            r#"Playlist item {} ("{}", {}) is much longer than any of the tracks, so it's probably the whole album in one video."#,
+            full_album.index + 1,
+            full_album.title,
+            format_duration(full_album.length),
+        ));
+        let split_button = Button::new("Split it into the tracks")
+            .padding(style::button_padding(ui))
+            .on_press_maybe((!full_album.splitting).then_some(Message::SplitFullAlbum));
+        let ignore_button = Button::new("Ignore")
+            .padding(style::button_padding(ui))
+            .on_press(Message::IgnoreFullAlbum);
+
+        let mut content =
+            column![notice, row![split_button, ignore_button].spacing(10)].spacing(10);
+        if let Some(err) = &full_album.error {
+            content = content.push(text(format!("✘ couldn't split it: {err}")));
+        }
+        content
+    }
+
+    /// Pasting a tracklist and importing one from a file
+    fn view_tracklist_tools<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        let paste_tracklist_button = Button::new("Paste tracklist from clipboard")
+            .padding(style::button_padding(ui))
+            .on_press(Message::PasteTracklist);
+
+        let import_path_input = input(
+            "/path/to/tracklist.csv or .json",
+            "import-path",
+            &state.import_path,
+            ui,
+        )
+        .on_input(Message::ImportPathChanged)
+        .on_submit(Message::LoadImport);
+        let load_import_button = Button::new("Load")
+            .padding(style::button_padding(ui))
+            .on_press(Message::LoadImport);
+
+        let mut content = column![paste_tracklist_button].spacing(20);
+        if let Some(lines) = &state.pasted_tracklist {
+            content = content.push(Self::view_pasted_tracklist(lines, ui));
+        }
+        content = content.push(style::labelled(
+            "Import tracks from a file",
+            row![import_path_input, load_import_button].spacing(10),
+        ));
+        if let Some(err) = &state.import_error {
+            content = content.push(text(format!("✘ {err}")));
+        }
+        if let Some(import) = &state.import {
+            content = content.push(Self::view_import(import, ui));
+        }
+        content.push(Self::view_title_rules(state, ui))
+    }
+
+    /// The title rules, with a preview of the track names they'd change
+    fn view_title_rules<'a>(state: &StateModifyingData, ui: UiSettings) -> Column<'a, Message> {
+        const PREVIEW_ROWS: usize = 10;
+
+        let rules = state.title_rules;
+        let feat_picker = PickList::new(BracketStyle::ALL, Some(rules.feat_brackets), |style| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::FeatBrackets(style))
+        })
+        .padding(style::input_padding(ui));
+        let standardize_feat_checkbox =
+            checkbox(r#"Write as "feat.""#, rules.standardize_feat, |b| {
+                Message::ModifyDataInputChanged(ModifyDataInputChange::StandardizeFeat(b))
+            });
+        let version_picker =
+            PickList::new(BracketStyle::ALL, Some(rules.version_brackets), |style| {
+                Message::ModifyDataInputChanged(ModifyDataInputChange::VersionBrackets(style))
+            })
+            .padding(style::input_padding(ui));
+        let capitalize_versions_checkbox = checkbox("Capitalize", rules.capitalize_versions, |b| {
+            Message::ModifyDataInputChanged(ModifyDataInputChange::CapitalizeVersions(b))
+        });
+
+        let mut content = column![
+            style::labelled(
+                "Featured artists",
+                row![feat_picker, standardize_feat_checkbox].spacing(20)
+            ),
+            style::labelled(
+                "Versions, like (Remix) or (Live)",
+                row![version_picker, capitalize_versions_checkbox].spacing(20)
+            ),
+        ]
+        .spacing(10);
+        if !rules.any() {
+            return content;
+        }
+
+        let changes = state.title_changes();
+        if changes.is_empty() {
+            content = content.push(text("✔ the track names already follow these rules"));
+        }
+        for (i, name) in changes.iter().take(PREVIEW_ROWS) {
+            content = content.push(text(format!("{} → {name}", state.track_data[*i].name)));
+        }
+        if changes.len() > PREVIEW_ROWS {
+            content = content.push(text(format!("…and {} more", changes.len() - PREVIEW_ROWS)));
+        }
+        let apply_button = Button::new(text(match changes.len() {
+            1 => String::from("Rename 1 track"),
+            n => format!("Rename {n} tracks"),
+        }))
+        .padding(style::button_padding(ui))
+        .on_press_maybe((!changes.is_empty()).then_some(Message::ApplyTitleRules));
+        content.push(apply_button)
+    }
+
+    /// Lets the user pick which column each field comes from, with a preview of the first few tracks
+    fn view_import<'a>(import: &StateImport, ui: UiSettings) -> Column<'a, Message> {
+        const NONE: &str = "(none)";
+        const PREVIEW_ROWS: usize = 5;
+
+        let mut options = vec![String::from(NONE)];
+        options.extend(import.table.columns.iter().cloned());
+
+        let mut content = Column::new().spacing(10);
+        for &field in TrackField::ALL {
+            let selected = import
+                .mapping
+                .get(field)
+                .and_then(|i| import.table.columns.get(i))
+                .cloned()
+                .unwrap_or_else(|| String::from(NONE));
+            let picker = PickList::new(options.clone(), Some(selected), move |column| {
+                Message::ImportColumnSelected(field, column)
+            })
+            .padding(style::input_padding(ui));
+            content = content.push(row![text(field.to_string()).width(120), picker].spacing(10));
+        }
+
+        let tracks = import.table.tracks(&import.mapping);
+        if tracks.is_empty() {
+            content = content.push(text("✘ no tracks with a title"));
+        }
+        for track in tracks.iter().take(PREVIEW_ROWS) {
+            let mut s = track.number.map_or_else(String::new, |n| format!("{n}. "));
+            s.push_str(&track.title);
+            if let Some(artist) = &track.artist {
+                s.push_str(" — ");
+                s.push_str(artist);
+            }
+            if let Some(year) = track.year {
+                s.push_str(" (");
+                s.push_str(&year.to_string());
+                s.push(')');
+            }
+            content = content.push(text(s));
+        }
+        if tracks.len() > PREVIEW_ROWS {
+            content = content.push(text(format!("…and {} more", tracks.len() - PREVIEW_ROWS)));
+        }
+
+        let apply_button = Button::new("Replace tracks")
+            .padding(style::button_padding(ui))
+            .on_press_maybe((!tracks.is_empty()).then_some(Message::ApplyImport));
+        let discard_button = Button::new("Cancel")
+            .padding(style::button_padding(ui))
+            .on_press(Message::DiscardImport);
+        content.push(row![apply_button, discard_button].spacing(10))
+    }
+
+    /// Preview of a pasted tracklist, with buttons to use it or throw it away
+    fn view_pasted_tracklist<'a>(lines: &[TracklistLine], ui: UiSettings) -> Column<'a, Message> {
+        let mut preview = Column::new().spacing(5);
+        if lines.is_empty() {
+            preview = preview.push(text("✘ couldn't find any tracks in the clipboard"));
+        }
+        let mut number = 0;
+        for line in lines {
+            number = line.number.unwrap_or(number + 1);
+            preview = preview.push(text(match line.duration {
+                Some(duration) => {
+                    format!("{number}. {} ({})", line.title, format_duration(duration))
+                }
+                None => format!("{number}. {}", line.title),
+            }));
+        }
+
+        let apply_button = Button::new("Replace tracks")
+            .padding(style::button_padding(ui))
+            .on_press_maybe((!lines.is_empty()).then_some(Message::ApplyTracklist));
+        let discard_button = Button::new("Cancel")
+            .padding(style::button_padding(ui))
+            .on_press(Message::DiscardTracklist);
+
+        column![preview, row![apply_button, discard_button].spacing(10)].spacing(10)
     }
 }
@@ -0,0 +1,104 @@
+use super::{help::Help, style, App, Message};
+use crate::{
+    config::OutputFormat,
+    deps::{check_dependencies, DependencyReport},
+};
+use iced::{
+    widget::{
+        column, container, row, scrollable, text, text_input, Button, PickList, Rule, TextInput,
+    },
+    Element, Length,
+};
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct StateOnboarding {
+    /// Whether yt-dlp and ffmpeg were found
+    pub dependencies: DependencyReport,
+    /// How downloading ytmdl's own copy of yt-dlp went
+    pub ytdlp_status: Option<String>,
+    pub out_dir: String,
+    pub format: OutputFormat,
+}
+
+impl Default for StateOnboarding {
+    fn default() -> Self {
+        Self {
+            dependencies: check_dependencies(),
+            ytdlp_status: None,
+            out_dir: env::var("YTMDL_OUT_DIR").unwrap_or_default(),
+            format: OutputFormat::default(),
+        }
+    }
+}
+
+impl StateOnboarding {
+    pub fn check_tools(&mut self) {
+        self.dependencies = check_dependencies();
+    }
+}
+
+impl App {
+    #[must_use]
+    pub fn view_onboarding<'a>(state: &'_ StateOnboarding) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let [ytdlp_status, ffmpeg_status] = state.dependencies.lines().map(text);
+        let mut ytdlp_status = column![ytdlp_status].spacing(10);
+        if !state.dependencies.ytdlp.is_ok() {
+            let download_button = Button::new("Download yt-dlp for me")
+                .padding(style::button_padding(ui))
+                .on_press(Message::UpdateYtdlp);
+            ytdlp_status = ytdlp_status.push(
+                row![
+                    download_button,
+                    text(state.ytdlp_status.as_deref().unwrap_or_default())
+                ]
+                .spacing(10),
+            );
+        }
+        let recheck_button = Button::new("Check again")
+            .padding(style::button_padding(ui))
+            .on_press(Message::OnboardingRecheckTools);
+
+        let out_dir_input = TextInput::new("Output directory", &state.out_dir)
+            .id(text_input::Id::new("out-dir"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::OnboardingOutDirChanged);
+        let format_picker = PickList::new(
+            OutputFormat::ALL,
+            Some(state.format),
+            Message::OnboardingFormatSelected,
+        )
+        .padding(style::input_padding(ui));
+
+        let finish_button = Button::new("Finish")
+            .padding(style::button_padding(ui))
+            .on_press(Message::OnboardingFinish);
+
+        let content = column![
+            text("Welcome to ytmdl").size(32),
+            Rule::horizontal(4),
+            ytdlp_status,
+            ffmpeg_status,
+            recheck_button,
+            Rule::horizontal(4),
+            text("Where should downloaded albums go?"),
+            style::with_help(out_dir_input, Help::OutDir),
+            text("What format should they be saved as?"),
+            style::with_help(format_picker, Help::Format),
+            Rule::horizontal(4),
+            finish_button
+        ]
+        .spacing(20)
+        .max_width(800);
+
+        scrollable(
+            container(content)
+                .width(Length::Fill)
+                .padding(40)
+                .center_x(),
+        )
+        .into()
+    }
+}
@@ -0,0 +1,324 @@
+use super::{style, view_modifying_data::StateModifyingData, App, Message};
+use crate::{
+    parsing::format_duration,
+    probe::{probe, AudioProbe},
+    report::{DownloadReport, SourceFormat, TrackOutcome},
+    sources::{self, Channel},
+    versions::LengthMismatch,
+    DownloadError, TrackError,
+};
+use iced::{
+    widget::{column, container, row, scrollable, text, Button, Column, Row, Rule, TextInput},
+    Element, Length,
+};
+use std::{path::PathBuf, time::Duration};
+
+/// A file that was saved, and what it came out as
+#[derive(Debug, Clone)]
+pub struct ResultFile {
+    pub title: String,
+    pub path: PathBuf,
+    /// The error as a string if it couldn't be probed
+    pub probe: Result<AudioProbe, String>,
+    /// What yt-dlp downloaded it from
+    pub source_format: Option<SourceFormat>,
+    /// Which track it is, since a track can be saved in more than one format
+    pub track: usize,
+    /// Who uploaded what it was downloaded from
+    pub channel: Option<Channel>,
+    /// Whether it's been marked as downloaded from a bad source
+    pub flagged: bool,
+    /// If what it was downloaded from might be a different version of the song
+    pub length_mismatch: Option<LengthMismatch>,
+}
+
+/// Something that went wrong, with what the user could do about it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    pub message: String,
+    pub hint: Option<&'static str>,
+}
+
+impl Failure {
+    /// For the whole download, when it failed before any tracks were attempted
+    #[must_use]
+    pub fn from_error(err: &DownloadError) -> Self {
+        Self {
+            message: err.to_string(),
+            hint: err.hint(),
+        }
+    }
+
+    #[must_use]
+    pub fn from_track(err: &TrackError) -> Self {
+        Self {
+            message: err.to_string(),
+            hint: err.source.hint(),
+        }
+    }
+}
+
+/// What happened with a download, shown once it's finished
+#[derive(Debug, Clone, Default)]
+pub struct StateResults {
+    /// Like "Artist - Album"
+    pub album: String,
+    pub files: Vec<ResultFile>,
+    /// One per failed track, or just the one if the whole download failed
+    pub failures: Vec<Failure>,
+    /// `None` if the whole download failed
+    pub elapsed: Option<Duration>,
+    /// How many tracks were already downloaded by an earlier run, so weren't saved again
+    pub already_saved: usize,
+    /// Where the files were saved, once each
+    pub out_dirs: Vec<PathBuf>,
+    /// What was downloaded, if anything failed, to download it again
+    pub retry: Option<StateModifyingData>,
+    /// The output dir, if the download failed because it couldn't be written to
+    pub unwritable: Option<PathBuf>,
+    /// Another folder to download to instead, as it's typed, when the profile's
+    /// [`unwritable`](Self::unwritable) one is the problem
+    pub other_out_dir: Option<String>,
+}
+
+impl StateResults {
+    /// Probes each file that was saved. If only some tracks failed, the ones that were saved are
+    /// still shown.
+    #[must_use]
+    pub fn new(album: String, result: Result<&DownloadReport, &DownloadError>) -> Self {
+        let mut state = Self {
+            album,
+            ..Self::default()
+        };
+        match result {
+            Ok(report) => state.add_report(report),
+            Err(DownloadError::TracksFailed(report)) => state.add_report(report),
+            Err(err @ DownloadError::OutDirNotWritable { path, .. }) => {
+                state.failures.push(Failure::from_error(err));
+                state.unwritable = Some(path.clone());
+            }
+            Err(err) => state.failures.push(Failure::from_error(err)),
+        }
+        for dir in state.files.iter().filter_map(|file| file.path.parent()) {
+            if !state.out_dirs.iter().any(|out_dir| out_dir == dir) {
+                state.out_dirs.push(dir.to_path_buf());
+            }
+        }
+        state
+    }
+
+    /// Keeps `state` to download again, if anything failed. If its profile's output dir couldn't
+    /// be written to, another one can be picked for it.
+    pub fn set_retry(&mut self, state: StateModifyingData) {
+        if self.failures.is_empty() {
+            return;
+        }
+        let out_dir = state.profile.resolved_out_dir();
+        if self.unwritable.as_ref() == Some(&out_dir) {
+            self.other_out_dir = Some(out_dir.to_string_lossy().to_string());
+        }
+        self.retry = Some(state);
+    }
+
+    fn add_report(&mut self, report: &DownloadReport) {
+        self.elapsed = Some(report.elapsed);
+        for outcome in report.outcomes() {
+            let track = match outcome {
+                TrackOutcome::Saved(track) => track,
+                TrackOutcome::Failed(err) => {
+                    self.failures.push(Failure::from_track(err));
+                    continue;
+                }
+            };
+            if track.already_saved {
+                self.already_saved += 1;
+            }
+            for path in &track.outputs {
+                self.files.push(ResultFile {
+                    title: track.title.clone(),
+                    path: path.clone(),
+                    probe: probe(path).map_err(|err| {
+                        log::warn!("{err}");
+                        err.to_string()
+                    }),
+                    source_format: track.source_format.clone(),
+                    track: track.index,
+                    channel: track.channel.clone(),
+                    flagged: false,
+                    length_mismatch: track.length_mismatch,
+                });
+            }
+        }
+    }
+
+    /// Marks the channel `files[index]`'s track was downloaded from as a bad source in the
+    /// history, or takes that back if it already was
+    pub fn flag_source(&mut self, index: usize) {
+        let Some(file) = self.files.get(index) else {
+            return;
+        };
+        let Some(channel) = file.channel.clone() else {
+            return;
+        };
+        let (track, flagged) = (file.track, !file.flagged);
+        sources::flag(&channel, flagged);
+        for file in self.files.iter_mut().filter(|file| file.track == track) {
+            file.flagged = flagged;
+        }
+    }
+}
+
+impl App {
+    #[must_use]
+    pub fn view_results<'a>(state: &'_ StateResults) -> Element<'a, Message> {
+        let ui = crate::config::current().ui;
+
+        let summary = match state.elapsed {
+            Some(elapsed) if state.already_saved > 0 => format!(
+                "{} files saved in {} ({} tracks were already downloaded)",
+                state.files.len(),
+                format_duration(elapsed),
+                state.already_saved
+            ),
+            Some(elapsed) => format!(
+                "{} files saved in {}",
+                state.files.len(),
+                format_duration(elapsed)
+            ),
+            None => String::from("The download failed"),
+        };
+        let summary = if state.elapsed.is_some() && !state.failures.is_empty() {
+            format!("{summary}, but {} tracks failed", state.failures.len())
+        } else {
+            summary
+        };
+        let mut content = column![
+            text(&state.album).size(32),
+            text(summary),
+            Rule::horizontal(4)
+        ]
+        .spacing(20)
+        .max_width(800);
+
+        let mut files = column![].spacing(10);
+        for (i, file) in state.files.iter().enumerate() {
+            let name = file
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default();
+            let details = match &file.probe {
+                Ok(probe) if probe.is_low_bitrate() => format!("{probe} (low bitrate)"),
+                Ok(probe) => probe.to_string(),
+                Err(err) => format!("couldn't read it: {err}"),
+            };
+            let details = match &file.source_format {
+                Some(format) => format!("{details}, downloaded as {format}"),
+                None => details,
+            };
+            let mut title = row![text(&file.title)].spacing(10);
+            if let Some(channel) = &file.channel {
+                let (label, info) = if file.flagged {
+                    ("Undo", format!("marked {} as a bad source", channel.name))
+                } else {
+                    ("Bad source", format!("from {}", channel.name))
+                };
+                title = title.push(text(info)).push(
+                    Button::new(label)
+                        .padding(style::button_padding(ui))
+                        .on_press(Message::FlagSource(i)),
+                );
+            }
+            let mut file_column = column![title, text(format!("{name}: {details}"))];
+            if let Some(mismatch) = file.length_mismatch {
+                file_column = file_column.push(style::warning_text(format!(
+                    "⚠ Might be a different version: {mismatch}"
+                )));
+            }
+            files = files.push(file_column);
+        }
+        content = content.push(files);
+
+        if !state.failures.is_empty() {
+            let mut failures = column![text("Failed")].spacing(10);
+            for failure in &state.failures {
+                let mut line = column![text(&failure.message)];
+                if let Some(hint) = failure.hint {
+                    line = line.push(text(format!("Try: {hint}")));
+                }
+                failures = failures.push(line);
+            }
+            content = content.push(failures);
+        }
+        if let Some(other_out_dir) = &state.other_out_dir {
+            content = content.push(Self::view_other_out_dir(other_out_dir));
+        }
+
+        scrollable(
+            container(content.push(Self::view_results_buttons(state)))
+                .width(Length::Fill)
+                .padding(40)
+                .center_x(),
+        )
+        .into()
+    }
+
+    /// Picking another folder when the profile's can't be written to
+    fn view_other_out_dir<'a>(other_out_dir: &str) -> Column<'a, Message> {
+        let ui = crate::config::current().ui;
+        let mut download = Button::new("Download there").padding(style::button_padding(ui));
+        if !other_out_dir.trim().is_empty() {
+            download = download.on_press(Message::RetryInOtherOutDir);
+        }
+        column![
+            text("Save to another folder instead (just for this download):"),
+            row![
+                TextInput::new("Folder", other_out_dir)
+                    .padding(style::input_padding(ui))
+                    .on_input(Message::OtherOutDirChanged)
+                    .on_submit(Message::RetryInOtherOutDir),
+                download
+            ]
+            .spacing(10)
+        ]
+        .spacing(10)
+    }
+
+    /// Opening the folders, retrying and starting on the next album
+    fn view_results_buttons<'a>(state: &'_ StateResults) -> Row<'a, Message> {
+        let ui = crate::config::current().ui;
+        let mut buttons = row![].spacing(10);
+        for (i, dir) in state.out_dirs.iter().enumerate() {
+            let label = if state.out_dirs.len() == 1 {
+                String::from("Open folder")
+            } else {
+                format!("Open folder {}", i + 1)
+            };
+            buttons = buttons.push(
+                Button::new(text(label))
+                    .padding(style::button_padding(ui))
+                    .on_press(Message::OpenFolder(dir.clone())),
+            );
+        }
+        // so there's somewhere to look even if nothing was saved
+        if state.out_dirs.is_empty() {
+            buttons = buttons.push(
+                Button::new("Open folder")
+                    .padding(style::button_padding(ui))
+                    .on_press(Message::OpenOutDir),
+            );
+        }
+        if state.retry.is_some() {
+            buttons = buttons.push(
+                Button::new("Retry failed tracks")
+                    .padding(style::button_padding(ui))
+                    .on_press(Message::RetryFailed),
+            );
+        }
+        buttons.push(
+            Button::new("New album")
+                .padding(style::button_padding(ui))
+                .on_press(Message::CloseResults),
+        )
+    }
+}
@@ -0,0 +1,404 @@
+use super::{help::Help, style, App, Message};
+use crate::{
+    config::{Config, DownloaderSettings, OutputFormat, Profile, UiSettings},
+    watch::WatchSettings,
+};
+use iced::{
+    widget::{
+        checkbox, column, container, row, scrollable, slider, text, text_input, Button, Column,
+        PickList, Rule, TextInput,
+    },
+    Element, Length,
+};
+
+#[derive(Debug, Clone)]
+pub struct StateSettings {
+    /// Edited copy of the config, only saved when the user hits "Save"
+    pub config: Config,
+    /// Channel or playlist typed in to be added to the watch list
+    pub watch_url: String,
+    /// Where the diagnostics bundle was saved, or why it couldn't be
+    pub diagnostics_status: Option<String>,
+    /// How downloading or updating ytmdl's copy of yt-dlp went
+    pub ytdlp_status: Option<String>,
+}
+
+impl Default for StateSettings {
+    fn default() -> Self {
+        Self {
+            config: crate::config::current(),
+            watch_url: String::new(),
+            diagnostics_status: None,
+            ytdlp_status: None,
+        }
+    }
+}
+
+impl App {
+    #[must_use]
+    pub fn view_settings<'a>(state: &'_ StateSettings) -> Element<'a, Message> {
+        let ui = state.config.ui;
+
+        let scale_slider = slider(
+            UiSettings::SCALE_RANGE,
+            ui.scale,
+            Message::SettingsScaleChanged,
+        )
+        .step(5u16);
+        let text_size_slider = slider(
+            UiSettings::TEXT_SIZE_RANGE,
+            ui.text_size,
+            Message::SettingsTextSizeChanged,
+        );
+
+        let accessible_checkbox = checkbox(
+            "Accessibility mode (high contrast, bigger controls)",
+            ui.accessible,
+            Message::SettingsAccessibleToggled,
+        );
+
+        let bpm_checkbox = checkbox(
+            "Detect BPM of each track (slower)",
+            state.config.analysis.bpm,
+            Message::SettingsBpmToggled,
+        );
+
+        let key_checkbox = checkbox(
+            "Detect key of each track (slower)",
+            state.config.analysis.key,
+            Message::SettingsKeyToggled,
+        );
+
+        let discogs_token_input = TextInput::new(
+            "Token",
+            state.config.discogs_token.as_deref().unwrap_or_default(),
+        )
+        .id(text_input::Id::new("discogs-token"))
+        .password()
+        .padding(style::input_padding(ui))
+        .on_input(Message::SettingsDiscogsTokenChanged);
+
+        let diagnostics_button = Button::new("Save diagnostics for a bug report")
+            .padding(style::button_padding(ui))
+            .on_press(Message::SaveDiagnostics);
+        let diagnostics_status = text(state.diagnostics_status.as_deref().unwrap_or_default());
+
+        let save_button = Button::new("Save")
+            .padding(style::button_padding(ui))
+            .on_press(Message::SettingsSave);
+        let cancel_button = Button::new("Cancel")
+            .padding(style::button_padding(ui))
+            .on_press(Message::SettingsCancel);
+
+        let content = column![
+            text("Settings").size(32),
+            Rule::horizontal(4),
+            Self::view_output_settings(state),
+            Rule::horizontal(4),
+            text(format!("Interface scale: {}%", ui.scale)),
+            scale_slider,
+            text(format!(
+                "Text size: {}px (applies after restarting)",
+                ui.text_size
+            )),
+            text_size_slider,
+            accessible_checkbox,
+            Rule::horizontal(4),
+            bpm_checkbox,
+            key_checkbox,
+            Self::view_lyrics_settings(state),
+            Rule::horizontal(4),
+            Self::view_romanize_settings(state),
+            Rule::horizontal(4),
+            Self::view_downloader_settings(state),
+            Rule::horizontal(4),
+            Self::view_watch_settings(state),
+            Rule::horizontal(4),
+            style::labelled_with_help(
+                "Discogs personal access token, to pick releases from your collection or wantlist \
+                 (make one at https://www.discogs.com/settings/developers)",
+                Help::DiscogsToken,
+                discogs_token_input
+            ),
+            Self::view_update_settings(state),
+            Rule::horizontal(4),
+            style::labelled(
+                "Zips the recent log, the settings (without the Discogs token), the last \
+                 download's report and the versions of yt-dlp and ffmpeg into your downloads \
+                 folder, to attach to a Github issue. Nothing is uploaded.",
+                row![diagnostics_button, diagnostics_status].spacing(10)
+            ),
+            Rule::horizontal(4),
+            row![save_button, cancel_button].spacing(10)
+        ]
+        .spacing(20)
+        .max_width(800);
+
+        scrollable(
+            container(content)
+                .width(Length::Fill)
+                .padding(40)
+                .center_x(),
+        )
+        .into()
+    }
+
+    /// Where and how files are saved when there aren't any profiles, and how many tracks are done
+    /// at once
+    fn view_output_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let config = &state.config;
+        let ui = config.ui;
+
+        let out_dir = config
+            .out_dir
+            .as_ref()
+            .map(|p| p.display().to_string())
+            .unwrap_or_default();
+        let out_dir_input = TextInput::new("Output directory", &out_dir)
+            .id(text_input::Id::new("settings-out-dir"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::SettingsOutDirChanged);
+        let format_picker = PickList::new(
+            OutputFormat::ALL,
+            Some(config.format),
+            Message::SettingsFormatSelected,
+        )
+        .padding(style::input_padding(ui));
+        let naming_template_input = TextInput::new(
+            Profile::DEFAULT_NAMING_TEMPLATE,
+            config.naming_template.as_deref().unwrap_or_default(),
+        )
+        .id(text_input::Id::new("settings-naming-template"))
+        .padding(style::input_padding(ui))
+        .on_input(Message::SettingsNamingTemplateChanged);
+        let keep_existing_checkbox = checkbox(
+            "Keep files that are already there instead of replacing them (and don't download them again)",
+            config.keep_existing_files,
+            Message::SettingsKeepExistingToggled,
+        );
+        let verify_existing_checkbox = checkbox(
+            "Only keep them if their title and track number match",
+            config.verify_existing_files,
+            Message::SettingsVerifyExistingToggled,
+        );
+        let threads_text = |what, threads| match threads {
+            0 => format!("{what} at once: one per CPU core (applies after restarting)"),
+            threads => format!("{what} at once: {threads} (applies after restarting)"),
+        };
+        let download_threads_slider = slider(
+            0..=16u8,
+            config.download_threads,
+            Message::SettingsDownloadThreadsChanged,
+        );
+        let threads_slider = slider(0..=16u8, config.threads, Message::SettingsThreadsChanged);
+        let albums_slider = slider(
+            1..=4u8,
+            config.albums_at_once.max(1),
+            Message::SettingsAlbumsAtOnceChanged,
+        );
+
+        let mut content = column![text("Output")].spacing(10);
+        if !config.profiles.is_empty() {
+            content = content.push(text(
+                "Your profiles in config.toml are used instead of the directory, format and names",
+            ));
+        }
+        content
+            .push(style::labelled_with_help(
+                "Directory",
+                Help::OutDir,
+                row![
+                    out_dir_input,
+                    Button::new("Open folder")
+                        .padding(style::button_padding(ui))
+                        .on_press(Message::OpenOutDir)
+                ]
+                .spacing(10),
+            ))
+            .push(style::labelled_with_help(
+                "Format",
+                Help::Format,
+                format_picker,
+            ))
+            .push(style::labelled_with_help(
+                "File names ({artist}, {album}, {track} and {title})",
+                Help::NamingTemplate,
+                naming_template_input,
+            ))
+            .push(keep_existing_checkbox)
+            .push(verify_existing_checkbox)
+            .push(text(threads_text("Downloads", config.download_threads)))
+            .push(download_threads_slider)
+            .push(text(threads_text("Conversions", config.threads)))
+            .push(threads_slider)
+            .push(text(format!(
+                "Queued albums downloaded at once: {} (sharing the downloads and conversions, applies \
+                 after restarting)",
+                config.albums_at_once.max(1)
+            )))
+            .push(albums_slider)
+    }
+
+    /// Checking for new versions of ytmdl, and ytmdl's own copy of yt-dlp
+    fn view_update_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let update_check_checkbox = checkbox(
+            "Check for new versions at startup",
+            !state.config.skip_update_check,
+            Message::SettingsUpdateCheckToggled,
+        );
+
+        let ytdlp_button = Button::new(if crate::ytdlp::is_installed() {
+            "Check for yt-dlp updates"
+        } else {
+            "Download yt-dlp"
+        })
+        .padding(style::button_padding(state.config.ui))
+        .on_press(Message::UpdateYtdlp);
+        let ytdlp_status = text(state.ytdlp_status.as_deref().unwrap_or_default());
+
+        column![
+            update_check_checkbox,
+            style::labelled(
+                "Keep ytmdl's own copy of yt-dlp in its data folder, so it doesn't have to be \
+                 installed. It's used instead of the one on your PATH.",
+                row![ytdlp_button, ytdlp_status].spacing(10)
+            )
+        ]
+        .spacing(20)
+    }
+
+    /// Whether lyrics are looked up and embedded, and which kind
+    fn view_lyrics_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let lyrics = &state.config.lyrics;
+
+        let embed_checkbox = checkbox(
+            "Embed lyrics from LRCLIB (tracks without any are saved without them)",
+            lyrics.embed,
+            Message::SettingsLyricsToggled,
+        );
+        let mut content = column![embed_checkbox].spacing(10);
+        if lyrics.embed {
+            content = content.push(checkbox(
+                "Embed timestamped lyrics when there are some",
+                lyrics.synced,
+                Message::SettingsSyncedLyricsToggled,
+            ));
+            content = content.push(checkbox(
+                "Save timestamped lyrics in an .lrc file next to each track too",
+                lyrics.lrc_file,
+                Message::SettingsLrcFileToggled,
+            ));
+        }
+        content
+    }
+
+    /// Whether Korean and Japanese names are romanized in file names and tags, and the command
+    /// that romanizes them
+    fn view_romanize_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let romanize = &state.config.romanize;
+
+        let file_names_checkbox = checkbox(
+            "Romanize Korean and Japanese names in file names",
+            romanize.file_names,
+            Message::SettingsRomanizeFileNamesToggled,
+        );
+        let tags_checkbox = checkbox(
+            "Romanize Korean and Japanese names in the tags",
+            romanize.tags,
+            Message::SettingsRomanizeTagsToggled,
+        );
+        let command_input = TextInput::new(
+            "kakasi -i utf8 -o utf8 -Ja -Ha -Ka -s",
+            romanize.command.as_deref().unwrap_or_default(),
+        )
+        .id(text_input::Id::new("romanize-command"))
+        .padding(style::input_padding(state.config.ui))
+        .on_input(Message::SettingsRomanizeCommandChanged);
+
+        column![
+            file_names_checkbox,
+            tags_checkbox,
+            style::labelled(
+                "Command to romanize with instead (needed for kanji), given the name on stdin",
+                command_input
+            )
+        ]
+        .spacing(10)
+    }
+
+    /// Which format yt-dlp downloads, whether to download with aria2c, and how many connections it
+    /// opens
+    fn view_downloader_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let downloader = &state.config.downloader;
+
+        let format_input = TextInput::new(DownloaderSettings::DEFAULT_FORMAT, &downloader.format)
+            .id(text_input::Id::new("ytdlp-format"))
+            .padding(style::input_padding(state.config.ui))
+            .on_input(Message::SettingsYtdlpFormatChanged);
+
+        let aria2c_checkbox = checkbox(
+            "Download with aria2c (can be much faster if your connection to YouTube is throttled)",
+            downloader.aria2c,
+            Message::SettingsAria2cToggled,
+        );
+        let connections_slider = slider(
+            DownloaderSettings::CONNECTIONS_RANGE,
+            downloader.connections,
+            Message::SettingsConnectionsChanged,
+        );
+
+        column![
+            style::labelled_with_help(
+                "yt-dlp format (empty for yt-dlp's default)",
+                Help::YtdlpFormat,
+                format_input
+            ),
+            aria2c_checkbox,
+            text(format!("Connections per track: {}", downloader.connections)),
+            connections_slider
+        ]
+        .spacing(10)
+    }
+
+    /// The watched channels and playlists, and how often they're checked
+    fn view_watch_settings<'a>(state: &StateSettings) -> Column<'a, Message> {
+        let ui = state.config.ui;
+        let watch = &state.config.watch;
+
+        let mut content = column![text("Watch for new releases")].spacing(10);
+        for (i, url) in watch.urls.iter().enumerate() {
+            let remove_button = Button::new("Remove")
+                .padding(style::button_padding(ui))
+                .on_press(Message::SettingsWatchRemove(i));
+            content = content.push(row![text(url), remove_button].spacing(10));
+        }
+
+        let url_input = TextInput::new("https://www.youtube.com/@artist", &state.watch_url)
+            .id(text_input::Id::new("watch-url"))
+            .padding(style::input_padding(ui))
+            .on_input(Message::SettingsWatchUrlChanged)
+            .on_submit(Message::SettingsWatchAdd);
+        let add_button = Button::new("Add")
+            .padding(style::button_padding(ui))
+            .on_press(Message::SettingsWatchAdd);
+        let interval_slider = slider(
+            WatchSettings::INTERVAL_RANGE,
+            watch.interval_hours,
+            Message::SettingsWatchIntervalChanged,
+        );
+        let interval_text = match watch.interval_hours {
+            0 => String::from("Check only when asked"),
+            1 => String::from("Check every hour while open"),
+            hours => format!("Check every {hours} hours while open"),
+        };
+
+        content
+            .push(style::labelled_with_help(
+                "Channel or artist playlist",
+                Help::WatchUrl,
+                row![url_input, add_button].spacing(10),
+            ))
+            .push(text(interval_text))
+            .push(interval_slider)
+    }
+}
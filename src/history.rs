@@ -0,0 +1,301 @@
+//! Albums that have already been downloaded, so they aren't downloaded again
+
+use crate::{
+    clock::{format_unix_time, Clock, SystemClock},
+    gui::view_modifying_data::StateModifyingData,
+    paths, playlist,
+    sources::{Channel, SourceRecord},
+    utils::csv_field,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, PoisonError},
+};
+use thiserror::Error;
+
+/// Held while the history's being updated, since the queue can finish more than one album at
+/// once and each would save over the other's
+static RECORDING: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Error)]
+pub enum HistoryError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("couldn't determine the data directory")]
+    NoDataDir,
+    #[error("unsupported file type (expected .csv or .json)")]
+    UnsupportedFileType,
+}
+
+/// A downloaded album
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// From [`playlist::album_key`]
+    pub key: String,
+    pub youtube_url: String,
+    pub name: String,
+    pub artist: String,
+    /// Unix timestamp
+    pub downloaded: u64,
+    /// Links that tracks came from instead of the playlist
+    #[serde(default)]
+    pub track_sources: Vec<String>,
+    /// Where the files were saved, one for each output profile
+    #[serde(default)]
+    pub out_dirs: Vec<PathBuf>,
+    #[serde(default)]
+    pub tracks: usize,
+    /// Why the album or some of its tracks failed, empty if it all downloaded
+    #[serde(default)]
+    pub failures: Vec<String>,
+}
+
+impl HistoryEntry {
+    const CSV_HEADER: &'static str =
+        "album,artist,downloaded,youtube_url,track_sources,out_dirs,tracks,failures";
+
+    /// Lists (like the track sources) are joined with " | "
+    fn csv_row(&self) -> String {
+        let join = |items: Vec<&str>| items.join(" | ");
+        [
+            self.name.clone(),
+            self.artist.clone(),
+            format_unix_time(self.downloaded),
+            self.youtube_url.clone(),
+            join(self.track_sources.iter().map(String::as_str).collect()),
+            join(
+                self.out_dirs
+                    .iter()
+                    .map(|dir| dir.to_str().unwrap_or_default())
+                    .collect(),
+            ),
+            self.tracks.to_string(),
+            join(self.failures.iter().map(String::as_str).collect()),
+        ]
+        .iter()
+        .map(|field| csv_field(field))
+        .collect::<Vec<_>>()
+        .join(",")
+    }
+}
+
+/// Saved to `history.json` in [`paths::data_dir`]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct History {
+    pub albums: Vec<HistoryEntry>,
+    /// Keys of albums found by [`watch`](crate::watch) that have already been queued, so they're
+    /// only announced once even if they haven't been downloaded yet
+    pub seen: Vec<String>,
+    /// Unix timestamp of the last time the watched playlists were checked
+    pub watch_checked: u64,
+    /// Which channels tracks were downloaded from, and how many were marked as a bad source
+    pub sources: Vec<SourceRecord>,
+}
+
+impl History {
+    #[must_use]
+    pub fn path() -> Option<PathBuf> {
+        let mut path = paths::data_dir()?;
+        path.push("history.json");
+        Some(path)
+    }
+
+    /// Loads the history, or an empty one if nothing's been downloaded yet.
+    ///
+    /// # Errors
+    /// - If the file exists but can't be read
+    /// - If the file isn't valid JSON
+    pub fn load() -> Result<Self, HistoryError> {
+        let path = Self::path().ok_or(HistoryError::NoDataDir)?;
+        match fs::read_to_string(path) {
+            Ok(s) => Ok(serde_json::from_str(&s)?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Writes the history, creating its directory if needed.
+    ///
+    /// # Errors
+    /// - If the data dir can't be determined or created
+    /// - If the file can't be written
+    pub fn save(&self) -> Result<(), HistoryError> {
+        let path = Self::path().ok_or(HistoryError::NoDataDir)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Whether the album has been downloaded without failing, or already queued from being watched
+    #[must_use]
+    pub fn contains(&self, key: &str) -> bool {
+        self.albums
+            .iter()
+            .any(|album| album.key == key && album.failures.is_empty())
+            || self.seen.iter().any(|seen| seen == key)
+    }
+
+    /// Adds the album, if it has a key, with why it failed if it did
+    pub fn add(&mut self, state: &StateModifyingData, failures: Vec<String>, clock: &impl Clock) {
+        let Some(key) = playlist::album_key(&state.youtube_url) else {
+            return;
+        };
+        let config = crate::config::current();
+        self.albums.push(HistoryEntry {
+            key,
+            youtube_url: state.youtube_url.clone(),
+            name: state.album_data.name.clone(),
+            artist: state.album_data.artist.clone(),
+            downloaded: clock.unix_time(),
+            track_sources: state
+                .track_data
+                .iter()
+                .filter_map(|track| track.source.clone())
+                .collect(),
+            out_dirs: config
+                .outputs_for(&state.profile)
+                .iter()
+                .map(crate::config::Profile::resolved_out_dir)
+                .collect(),
+            tracks: state.tracks_to_download(),
+            failures,
+        });
+    }
+
+    /// The downloaded albums as CSV, with a header row
+    #[must_use]
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(HistoryEntry::CSV_HEADER);
+        csv.push('\n');
+        for album in &self.albums {
+            csv.push_str(&album.csv_row());
+            csv.push('\n');
+        }
+        csv
+    }
+
+    /// Writes the downloaded albums to a `.csv` or `.json` file, for keeping track of them
+    /// somewhere else
+    ///
+    /// # Errors
+    /// - If it isn't a `.csv` or `.json` file
+    /// - If the file can't be written
+    pub fn export(&self, path: &Path) -> Result<(), HistoryError> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let contents = match extension.as_deref() {
+            Some("csv") => self.to_csv(),
+            Some("json") => serde_json::to_string_pretty(&self.albums)?,
+            _ => return Err(HistoryError::UnsupportedFileType),
+        };
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Adds a downloaded (or failed) album to the saved history, with the channels its tracks were
+/// downloaded from, just logging if that fails since the album has still been downloaded
+pub fn record(state: &StateModifyingData, failures: Vec<String>, channels: &[Channel]) {
+    let _recording = RECORDING.lock().unwrap_or_else(PoisonError::into_inner);
+    let result = History::load().and_then(|mut history| {
+        history.add(state, failures, &SystemClock);
+        history.keep_sources(channels);
+        history.save()
+    });
+    if let Err(err) = result {
+        log::warn!("couldn't add the album to the download history: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::FixedClock;
+
+    #[test]
+    fn contains_downloaded_and_seen() {
+        let clock = FixedClock::at_unix_time(1_700_000_000);
+        let mut history = History {
+            seen: vec![String::from("OLAK5uy_seen")],
+            ..History::default()
+        };
+        history.add(
+            &StateModifyingData {
+                youtube_url: String::from("https://youtube.com/playlist?list=OLAK5uy_done"),
+                ..StateModifyingData::default()
+            },
+            Vec::new(),
+            &clock,
+        );
+        history.add(
+            &StateModifyingData {
+                youtube_url: String::from("https://youtube.com/playlist?list=OLAK5uy_failed"),
+                ..StateModifyingData::default()
+            },
+            vec![String::from("ytdlp error when downloading abc")],
+            &clock,
+        );
+
+        assert_eq!(history.albums[0].downloaded, 1_700_000_000);
+        assert!(history.contains("OLAK5uy_done"));
+        assert!(history.contains("OLAK5uy_seen"));
+        assert!(!history.contains("OLAK5uy_failed"));
+        assert!(!history.contains("OLAK5uy_new"));
+    }
+
+    #[test]
+    fn csv_export() {
+        let history = History {
+            albums: vec![HistoryEntry {
+                key: String::from("OLAK5uy_abc"),
+                youtube_url: String::from("https://youtube.com/playlist?list=OLAK5uy_abc"),
+                name: String::from("Hello, World"),
+                artist: String::from("Artist"),
+                downloaded: 1_700_000_000,
+                track_sources: vec![
+                    String::from("https://youtu.be/a"),
+                    String::from("https://youtu.be/b"),
+                ],
+                out_dirs: vec![PathBuf::from("/music")],
+                tracks: 12,
+                failures: vec![String::from(r#"couldn't find "track 3""#)],
+            }],
+            ..History::default()
+        };
+
+        let rows = crate::parsing::parse_csv(&history.to_csv());
+        assert_eq!(rows[0].len(), 8);
+        assert_eq!(
+            rows[1],
+            [
+                "Hello, World",
+                "Artist",
+                "2023-11-14T22:13:20Z",
+                "https://youtube.com/playlist?list=OLAK5uy_abc",
+                "https://youtu.be/a | https://youtu.be/b",
+                "/music",
+                "12",
+                r#"couldn't find "track 3""#,
+            ]
+        );
+    }
+
+    #[test]
+    fn loads_old_files() {
+        let history: History = serde_json::from_str(r#"{"albums": []}"#).unwrap();
+        assert!(history.seen.is_empty());
+        assert_eq!(history.watch_checked, 0);
+    }
+}
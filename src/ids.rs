@@ -0,0 +1,197 @@
+//! Youtube video and playlist IDs, checked when they're parsed so malformed ones are caught there
+//! rather than as yt-dlp failures
+
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+};
+use thiserror::Error;
+
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum IdError {
+    #[error(r#""{0}" isn't a Youtube video ID (expected 11 letters, numbers, '-' or '_')"#)]
+    InvalidVideoId(String),
+    #[error(
+        r#""{0}" isn't a Youtube playlist ID (expected 2 to 64 letters, numbers, '-' or '_')"#
+    )]
+    InvalidPlaylistId(String),
+}
+
+fn is_id_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '-' || c == '_'
+}
+
+/// Implements everything both ID types need on top of their own `is_valid`
+macro_rules! id_type {
+    ($name:ident, $error:ident) => {
+        impl $name {
+            #[must_use]
+            pub fn as_str(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl FromStr for $name {
+            type Err = IdError;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                if Self::is_valid(s) {
+                    Ok(Self(s.to_string()))
+                } else {
+                    Err(IdError::$error(s.to_string()))
+                }
+            }
+        }
+
+        impl TryFrom<String> for $name {
+            type Error = IdError;
+
+            fn try_from(s: String) -> Result<Self, Self::Error> {
+                if Self::is_valid(&s) {
+                    Ok(Self(s))
+                } else {
+                    Err(IdError::$error(s))
+                }
+            }
+        }
+
+        impl From<$name> for String {
+            fn from(id: $name) -> Self {
+                id.0
+            }
+        }
+
+        impl Display for $name {
+            fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+                f.write_str(&self.0)
+            }
+        }
+
+        impl AsRef<str> for $name {
+            fn as_ref(&self) -> &str {
+                &self.0
+            }
+        }
+
+        impl PartialEq<str> for $name {
+            fn eq(&self, other: &str) -> bool {
+                self.0 == other
+            }
+        }
+
+        impl PartialEq<&str> for $name {
+            fn eq(&self, other: &&str) -> bool {
+                self.0 == *other
+            }
+        }
+    };
+}
+
+/// A Youtube video ID, like `dQw4w9WgXcQ`
+///
+/// # Examples
+/// ```
+/// use ytmdl::ids::VideoId;
+///
+/// let id: VideoId = "dQw4w9WgXcQ".parse().unwrap();
+/// assert_eq!(id.url(), "https://youtu.be/dQw4w9WgXcQ");
+/// assert!("dQw4w9WgXc".parse::<VideoId>().is_err());
+/// assert!("dQw4w9WgXc!".parse::<VideoId>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct VideoId(String);
+
+impl VideoId {
+    pub const LEN: usize = 11;
+
+    #[must_use]
+    pub fn is_valid(s: &str) -> bool {
+        s.len() == Self::LEN && s.chars().all(is_id_char)
+    }
+
+    /// What's passed to yt-dlp
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("https://youtu.be/{}", self.0)
+    }
+}
+
+id_type!(VideoId, InvalidVideoId);
+
+/// A Youtube playlist ID, like `OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ`
+///
+/// # Examples
+/// ```
+/// use ytmdl::ids::PlaylistId;
+///
+/// let id: PlaylistId = "OLAK5uy_abc".parse().unwrap();
+/// assert!(id.is_album());
+/// assert_eq!(id.url(), "https://www.youtube.com/playlist?list=OLAK5uy_abc");
+/// assert!("PL abc".parse::<PlaylistId>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PlaylistId(String);
+
+impl PlaylistId {
+    /// Shortest are the special playlists like `WL` and `LL`, longest are mixes
+    pub const LEN_RANGE: std::ops::RangeInclusive<usize> = 2..=64;
+    /// Album playlists Youtube makes for releases all start with this
+    pub const ALBUM_PREFIX: &'static str = "OLAK5uy_";
+    /// Mixes Youtube makes from a video (e.g. when sharing something that's autoplaying) start
+    /// with this
+    pub const MIX_PREFIX: &'static str = "RD";
+
+    #[must_use]
+    pub fn is_valid(s: &str) -> bool {
+        Self::LEN_RANGE.contains(&s.len()) && s.chars().all(is_id_char)
+    }
+
+    /// Whether it's one of the playlists Youtube makes for albums
+    #[must_use]
+    pub fn is_album(&self) -> bool {
+        self.0.starts_with(Self::ALBUM_PREFIX)
+    }
+
+    /// Whether it's one of Youtube's mixes, which aren't really a playlist of their own
+    #[must_use]
+    pub fn is_mix(&self) -> bool {
+        self.0.starts_with(Self::MIX_PREFIX)
+    }
+
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("https://www.youtube.com/playlist?list={}", self.0)
+    }
+}
+
+id_type!(PlaylistId, InvalidPlaylistId);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn serde_round_trip() {
+        let id: VideoId = serde_json::from_str(r#""dQw4w9WgXcQ""#).unwrap();
+        assert_eq!(id, "dQw4w9WgXcQ");
+        assert_eq!(serde_json::to_string(&id).unwrap(), r#""dQw4w9WgXcQ""#);
+        assert!(serde_json::from_str::<VideoId>(r#""not an id""#).is_err());
+        assert!(serde_json::from_str::<PlaylistId>(r#""""#).is_err());
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(
+            "abc".parse::<VideoId>(),
+            Err(IdError::InvalidVideoId(String::from("abc")))
+        );
+        assert_eq!(
+            "a".parse::<PlaylistId>(),
+            Err(IdError::InvalidPlaylistId(String::from("a")))
+        );
+        assert!("ÄÖÜäöüßabcd".parse::<VideoId>().is_err());
+    }
+}
@@ -0,0 +1,301 @@
+use crate::parsing::parse_csv;
+use serde_json::Value;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::Path,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("expected a JSON array of objects")]
+    NotAnArrayOfObjects,
+    #[error("unsupported file type (expected .csv or .json)")]
+    UnsupportedFileType,
+    #[error("file doesn't have any tracks")]
+    Empty,
+}
+
+/// A piece of track metadata that a column can be mapped to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackField {
+    Title,
+    Artist,
+    Number,
+    Year,
+}
+
+impl TrackField {
+    pub const ALL: &'static [Self] = &[Self::Title, Self::Artist, Self::Number, Self::Year];
+
+    /// Lowercase column names that get mapped to this field automatically
+    fn aliases(self) -> &'static [&'static str] {
+        match self {
+            Self::Title => &["title", "name", "track title", "song", "track name"],
+            Self::Artist => &["artist", "artists", "track artist", "performer"],
+            Self::Number => &[
+                "track",
+                "track no",
+                "track number",
+                "tracknumber",
+                "no",
+                "#",
+                "number",
+                "position",
+            ],
+            Self::Year => &["year", "date", "release year"],
+        }
+    }
+}
+
+impl Display for TrackField {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Title => "Title",
+            Self::Artist => "Artist",
+            Self::Number => "Track number",
+            Self::Year => "Year",
+        })
+    }
+}
+
+/// Which column (by index) each field comes from
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub title: Option<usize>,
+    pub artist: Option<usize>,
+    pub number: Option<usize>,
+    pub year: Option<usize>,
+}
+
+impl ColumnMapping {
+    #[must_use]
+    pub fn get(&self, field: TrackField) -> Option<usize> {
+        match field {
+            TrackField::Title => self.title,
+            TrackField::Artist => self.artist,
+            TrackField::Number => self.number,
+            TrackField::Year => self.year,
+        }
+    }
+
+    pub fn set(&mut self, field: TrackField, column: Option<usize>) {
+        match field {
+            TrackField::Title => self.title = column,
+            TrackField::Artist => self.artist = column,
+            TrackField::Number => self.number = column,
+            TrackField::Year => self.year = column,
+        }
+    }
+}
+
+/// Track metadata after the columns have been mapped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedTrack {
+    pub number: Option<usize>,
+    pub title: String,
+    pub artist: Option<String>,
+    pub year: Option<i32>,
+}
+
+/// A table of track metadata read from a file, before its columns have been mapped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportedTable {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<String>>,
+}
+
+impl ImportedTable {
+    /// Reads a `.csv` (with a header row) or `.json` (array of objects) file.
+    ///
+    /// # Errors
+    /// - If the file can't be read
+    /// - If it isn't a `.csv` or `.json` file
+    /// - If the JSON isn't an array of objects
+    /// - If there aren't any rows
+    pub fn read(path: &Path) -> Result<Self, ImportError> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        let s = fs::read_to_string(path)?;
+        match extension.as_deref() {
+            Some("csv" | "tsv" | "txt") => Self::from_csv(&s),
+            Some("json") => Self::from_json(&s),
+            _ => Err(ImportError::UnsupportedFileType),
+        }
+    }
+
+    /// # Errors
+    /// If there aren't any rows after the header
+    pub fn from_csv(s: &str) -> Result<Self, ImportError> {
+        let mut rows = parse_csv(s.trim_start_matches('\u{feff}')).into_iter();
+        let columns = rows.next().ok_or(ImportError::Empty)?;
+        let rows: Vec<_> = rows.collect();
+        if rows.is_empty() {
+            return Err(ImportError::Empty);
+        }
+        Ok(Self { columns, rows })
+    }
+
+    /// The columns are all the keys used by any of the objects.
+    ///
+    /// # Errors
+    /// - If it isn't valid JSON
+    /// - If it isn't an array of objects
+    /// - If the array is empty
+    pub fn from_json(s: &str) -> Result<Self, ImportError> {
+        let Value::Array(items) = serde_json::from_str(s)? else {
+            return Err(ImportError::NotAnArrayOfObjects);
+        };
+        if items.is_empty() {
+            return Err(ImportError::Empty);
+        }
+
+        let mut columns: Vec<String> = Vec::new();
+        for item in &items {
+            let Value::Object(object) = item else {
+                return Err(ImportError::NotAnArrayOfObjects);
+            };
+            for key in object.keys() {
+                if !columns.contains(key) {
+                    columns.push(key.clone());
+                }
+            }
+        }
+
+        let rows = items
+            .iter()
+            .map(|item| {
+                columns
+                    .iter()
+                    .map(|column| match item.get(column) {
+                        Some(Value::String(s)) => s.clone(),
+                        Some(Value::Null) | None => String::new(),
+                        Some(value) => value.to_string(),
+                    })
+                    .collect()
+            })
+            .collect();
+        Ok(Self { columns, rows })
+    }
+
+    /// Maps columns to fields by their names, e.g. a column called "Track No" is the track number
+    #[must_use]
+    pub fn guess_mapping(&self) -> ColumnMapping {
+        let mut mapping = ColumnMapping::default();
+        for &field in TrackField::ALL {
+            let column = self.columns.iter().position(|column| {
+                field
+                    .aliases()
+                    .contains(&column.trim().to_lowercase().as_str())
+            });
+            mapping.set(field, column);
+        }
+        mapping
+    }
+
+    /// Pulls the mapped fields out of each row, skipping rows without a title
+    #[must_use]
+    pub fn tracks(&self, mapping: &ColumnMapping) -> Vec<ImportedTrack> {
+        let Some(title_column) = mapping.title else {
+            return Vec::new();
+        };
+
+        self.rows
+            .iter()
+            .filter_map(|row| {
+                let field = |column: Option<usize>| {
+                    column
+                        .and_then(|column| row.get(column))
+                        .map(|value| value.trim())
+                        .filter(|value| !value.is_empty())
+                };
+
+                Some(ImportedTrack {
+                    title: field(Some(title_column))?.to_string(),
+                    artist: field(mapping.artist).map(String::from),
+                    // handles things like "3/12"
+                    number: field(mapping.number)
+                        .and_then(|n| n.split('/').next()?.trim().parse().ok()),
+                    // handles full dates like "2001-05-04"
+                    year: field(mapping.year).and_then(|y| y.get(..4)?.parse().ok()),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_import() {
+        let table = ImportedTable::from_csv(
+            "Track No,Title,Artist,Year\n1,Intro,,2001\n2,\"Song, Part 2\",Guest,2001-05-04\n,,,\n",
+        )
+        .unwrap();
+        let mapping = table.guess_mapping();
+        assert_eq!(
+            mapping,
+            ColumnMapping {
+                title: Some(1),
+                artist: Some(2),
+                number: Some(0),
+                year: Some(3),
+            }
+        );
+        assert_eq!(
+            table.tracks(&mapping),
+            [
+                ImportedTrack {
+                    number: Some(1),
+                    title: "Intro".into(),
+                    artist: None,
+                    year: Some(2001),
+                },
+                ImportedTrack {
+                    number: Some(2),
+                    title: "Song, Part 2".into(),
+                    artist: Some("Guest".into()),
+                    year: Some(2001),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn json_import() {
+        let table = ImportedTable::from_json(
+            r#"[{"name": "Intro", "tracknumber": "1/2"}, {"name": "Outro", "tracknumber": 2, "bpm": 120}]"#,
+        )
+        .unwrap();
+        assert_eq!(table.columns, ["name", "tracknumber", "bpm"]);
+        assert_eq!(table.rows[0], ["Intro", "1/2", ""]);
+
+        let mut mapping = table.guess_mapping();
+        assert_eq!(mapping.title, Some(0));
+        assert_eq!(mapping.number, Some(1));
+        let numbers: Vec<_> = table.tracks(&mapping).iter().map(|t| t.number).collect();
+        assert_eq!(numbers, [Some(1), Some(2)]);
+
+        // nothing without a title
+        mapping.set(TrackField::Title, None);
+        assert!(table.tracks(&mapping).is_empty());
+
+        assert!(matches!(
+            ImportedTable::from_json(r#"{"name": "Intro"}"#),
+            Err(ImportError::NotAnArrayOfObjects)
+        ));
+        assert!(matches!(
+            ImportedTable::from_json("[]"),
+            Err(ImportError::Empty)
+        ));
+    }
+}
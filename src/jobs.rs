@@ -0,0 +1,300 @@
+//! Album downloads as jobs that are saved to disk as they go, so they can be resumed after a crash
+
+use crate::{
+    config::Profile,
+    gui::view_modifying_data::{AlbumData, StateModifyingData, TrackData},
+    paths, playlist,
+    rescrape::StoredScrape,
+    utils::{sanitize_file_name, unix_time},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::Reverse,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum JobError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    JsonError(#[from] serde_json::Error),
+    #[error("couldn't determine the data directory")]
+    NoDataDir,
+}
+
+/// How far along a job is, in order
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobState {
+    /// Download was pressed but nothing has happened yet
+    #[default]
+    Pending,
+    /// Getting the video IDs from the playlist
+    Scraping,
+    Downloading,
+    /// At least one track has been downloaded and is being converted
+    Converting,
+    Complete,
+    Failed,
+}
+
+impl Display for JobState {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Pending => "pending",
+            Self::Scraping => "scraping",
+            Self::Downloading => "downloading",
+            Self::Converting => "converting",
+            Self::Complete => "complete",
+            Self::Failed => "failed",
+        })
+    }
+}
+
+/// An album download, with everything needed to start it again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    /// Also the file name it's saved as
+    pub id: String,
+    pub state: JobState,
+    /// Why it failed, if it did
+    pub error: Option<String>,
+    /// Unix timestamp of the last change of state
+    pub updated: u64,
+    /// Where it is in the [`queue`](crate::queue), lowest first
+    #[serde(default)]
+    pub position: u32,
+    pub youtube_url: String,
+    pub profile: Profile,
+    pub album_data: AlbumData,
+    pub track_data: Vec<TrackData>,
+    /// The release as it was last scraped, so it can be scraped again once it's resumed
+    #[serde(default)]
+    pub scrape: Option<StoredScrape>,
+}
+
+impl Job {
+    /// A pending job for the album, carrying on with the same job if it's being resumed
+    #[must_use]
+    pub fn new(state: &StateModifyingData) -> Self {
+        let updated = unix_time();
+        let id = state.job_id.clone().unwrap_or_else(|| {
+            let key = playlist::album_key(&state.youtube_url).unwrap_or_default();
+            format!("{}-{updated}", sanitize_file_name(&key))
+        });
+
+        Self {
+            id,
+            state: JobState::Pending,
+            error: None,
+            updated,
+            position: 0,
+            youtube_url: state.youtube_url.clone(),
+            profile: state.profile.clone(),
+            album_data: state.album_data.clone(),
+            track_data: state.track_data.clone(),
+            scrape: state.scrape.clone(),
+        }
+    }
+
+    /// Where jobs are saved
+    #[must_use]
+    pub fn dir() -> Option<PathBuf> {
+        let mut path = paths::data_dir()?;
+        path.push("jobs");
+        Some(path)
+    }
+
+    fn path(&self) -> Option<PathBuf> {
+        let mut path = Self::dir()?;
+        path.push(format!("{}.json", self.id));
+        Some(path)
+    }
+
+    /// Whether the job is waiting in the [`queue`](crate::queue) rather than interrupted
+    #[must_use]
+    pub fn is_queued(&self) -> bool {
+        self.state == JobState::Pending && self.error.is_none()
+    }
+
+    /// Moves the job on to `state` and saves it, unless it's already there or past it.
+    ///
+    /// Tracks are handled in parallel, so this ends up being the furthest along any track is.
+    pub fn advance(&mut self, state: JobState) {
+        if state > self.state {
+            self.state = state;
+            self.updated = unix_time();
+            self.save_or_warn();
+        }
+    }
+
+    /// Marks the job as failed and saves it, so it can be resumed later
+    pub fn fail(&mut self, error: String) {
+        self.state = JobState::Failed;
+        self.error = Some(error);
+        self.updated = unix_time();
+        self.save_or_warn();
+    }
+
+    /// Deletes the job, since there's nothing left to resume
+    pub fn complete(self) {
+        self.delete();
+    }
+
+    /// Writes the job to [`Job::dir`]
+    ///
+    /// # Errors
+    /// - If the data dir can't be determined or created
+    /// - If the file can't be written
+    pub fn save(&self) -> Result<(), JobError> {
+        let path = self.path().ok_or(JobError::NoDataDir)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Downloading can go ahead without the job being saved, it just can't be resumed
+    pub fn save_or_warn(&self) {
+        if let Err(err) = self.save() {
+            log::warn!("couldn't save job {}: {err}", self.id);
+        }
+    }
+
+    pub fn delete(&self) {
+        if let Some(path) = self.path() {
+            if let Err(err) = fs::remove_file(&path) {
+                if err.kind() != io::ErrorKind::NotFound {
+                    log::warn!(r#"couldn't delete "{}": {err}"#, path.display());
+                }
+            }
+        }
+    }
+
+    /// Loads every saved job, which are the ones that are queued, interrupted or failed since
+    /// finished ones get deleted. Newest first.
+    ///
+    /// Files that can't be read are skipped.
+    #[must_use]
+    pub fn load_all() -> Vec<Self> {
+        Self::dir().map_or_else(Vec::new, |dir| Self::load_all_in(&dir))
+    }
+
+    fn load_all_in(dir: &Path) -> Vec<Self> {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return Vec::new();
+        };
+
+        let mut jobs: Vec<Self> = entries
+            .filter_map(Result::ok)
+            .map(|entry| entry.path())
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+            .filter_map(|path| {
+                fs::read_to_string(&path)
+                    .map_err(JobError::from)
+                    .and_then(|s| Ok(serde_json::from_str(&s)?))
+                    .map_err(|err| log::warn!(r#"couldn't load job "{}": {err}"#, path.display()))
+                    .ok()
+            })
+            .collect();
+        jobs.sort_by_key(|job| Reverse(job.updated));
+        jobs
+    }
+
+    /// The data to resume the job with, which carries on with the same job when it's downloaded
+    #[must_use]
+    pub fn into_state(self) -> StateModifyingData {
+        StateModifyingData {
+            youtube_url: self.youtube_url,
+            album_data: self.album_data,
+            track_data: self.track_data,
+            profile: self.profile,
+            job_id: Some(self.id),
+            scrape: self.scrape,
+            ..StateModifyingData::default()
+        }
+    }
+}
+
+/// Like "Album Name (downloading)", or "Album Name (failed: reason)"
+impl Display for Job {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let name = if self.album_data.name.is_empty() {
+            &self.youtube_url
+        } else {
+            &self.album_data.name
+        };
+        match &self.error {
+            Some(error) => write!(f, "{name} ({}: {error})", self.state),
+            None => write!(f, "{name} ({})", self.state),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempdir::TempDir;
+
+    fn job() -> Job {
+        let mut state = StateModifyingData {
+            youtube_url: String::from("https://youtube.com/playlist?list=OLAK5uy_abc"),
+            track_data: vec![TrackData::new("One"), TrackData::new("Two")],
+            ..StateModifyingData::default()
+        };
+        state.album_data.name = String::from("Album");
+        state.album_data.released = "2023-03-13".parse().ok();
+        Job::new(&state)
+    }
+
+    #[test]
+    fn only_advances() {
+        let mut job = job();
+        job.state = JobState::Converting;
+        job.advance(JobState::Downloading);
+        assert_eq!(job.state, JobState::Converting);
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("ytmdl-jobs").unwrap();
+        let mut older = job();
+        older.id = String::from("older");
+        older.updated = 1;
+        let newer = Job {
+            id: String::from("newer"),
+            updated: 2,
+            ..job()
+        };
+        for job in [&older, &newer] {
+            fs::write(
+                dir.path().join(format!("{}.json", job.id)),
+                serde_json::to_string(job).unwrap(),
+            )
+            .unwrap();
+        }
+        fs::write(dir.path().join("broken.json"), "{").unwrap();
+
+        let jobs = Job::load_all_in(dir.path());
+        assert_eq!(
+            jobs.iter().map(|job| job.id.as_str()).collect::<Vec<_>>(),
+            ["newer", "older"]
+        );
+        assert_eq!(jobs[1].track_data[1].name, "Two");
+        assert_eq!(jobs[1].album_data.released, older.album_data.released);
+        assert_eq!(jobs[1].to_string(), "Album (pending)");
+    }
+
+    #[test]
+    fn resumes_same_job() {
+        let job = job();
+        let id = job.id.clone();
+        assert!(id.starts_with("OLAK5uy_abc-"));
+        assert_eq!(Job::new(&job.into_state()).id, id);
+    }
+}
@@ -1,14 +1,51 @@
 #![warn(clippy::pedantic)]
 #![allow(clippy::module_name_repetitions)]
 
+pub mod activity;
+pub mod album_art;
+pub mod analysis;
+pub mod batch;
+pub mod cli;
+pub mod clock;
+pub mod compare;
+pub mod config;
+pub mod deps;
+pub mod diagnostics;
+pub mod export;
+pub mod full_album;
 pub mod gui;
+pub mod history;
+pub mod ids;
+pub mod import;
+pub mod jobs;
+pub mod network;
+pub mod non_music;
 pub mod parsing;
+pub mod paths;
 pub mod playlist;
+pub mod postprocess;
+pub mod probe;
+pub mod queue;
+pub mod report;
+pub mod rescrape;
+pub mod romanize;
 pub mod scraping;
+pub mod sources;
+pub mod tagging;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+pub mod titles;
+#[cfg(feature = "libav")]
+pub mod transcode;
+pub mod updates;
+pub mod urls;
 pub mod utils;
+pub mod versions;
+pub mod watch;
+pub mod ytdlp;
 
 mod download;
 pub use download::*;
 
 mod threading;
-use threading::POOL;
+use threading::{CPU, NETWORK};
@@ -1,11 +1,26 @@
 use iced::{Application, Settings};
-use std::env;
-use ytmdl::*;
+use std::{env, process};
+use ytmdl::{
+    cli::{self, CliCommand},
+    config, paths, *,
+};
 
 fn main() -> iced::Result {
     if env::var("RUST_LOG").is_err() {
         env::set_var("RUST_LOG", "ytmdl");
     }
+
+    // the log's also kept for the diagnostics bundle
+    env_logger::Builder::from_default_env()
+        .target(env_logger::Target::Pipe(Box::new(diagnostics::LogTee)))
+        .init();
+
+    paths::init(env::args().skip(1).any(|arg| arg == "--portable"));
+    if paths::is_portable() {
+        log::info!("Running in portable mode");
+    }
+
+    config::current().apply_to_env();
     if env::var("YTMDL_OUT_DIR").is_err() {
         if let Some(mut p) = dirs::download_dir() {
             p.push("ytmdl");
@@ -13,13 +28,31 @@ fn main() -> iced::Result {
         }
     }
 
-    env_logger::init();
+    match CliCommand::parse(env::args().skip(1)) {
+        Ok(CliCommand::Gui) => {}
+        Ok(CliCommand::Help) => {
+            print!("{}", cli::USAGE);
+            return Ok(());
+        }
+        Ok(CliCommand::Download(args)) => process::exit(cli::run_download(&args)),
+        Err(err) => {
+            eprintln!("{err}\n\n{}", cli::USAGE);
+            process::exit(2);
+        }
+    }
+
+    // grow the window with the UI so the same amount fits in it
+    let ui = config::current().ui;
+    let scale = |size: f64| (size * ui.scale_factor()).round() as u32;
 
     gui::App::run(Settings {
         window: iced::window::Settings {
-            size: (800, 640),
+            size: (scale(800.0), scale(640.0)),
             ..Default::default()
         },
+        default_text_size: ui.text_size(),
+        // the app closes itself once any downloads have stopped
+        exit_on_close_request: false,
         ..Default::default()
     })
 }
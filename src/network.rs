@@ -0,0 +1,363 @@
+use crate::config;
+use reqwest::{
+    blocking::{Client, ClientBuilder, RequestBuilder, Response},
+    header::{ACCEPT_LANGUAGE, AUTHORIZATION, CONTENT_TYPE, COOKIE},
+    Certificate, NoProxy, Proxy,
+};
+use serde::{Deserialize, Serialize};
+use std::{env, fs, path::PathBuf, process::Command};
+use url::Url;
+
+/// Cookies that say we've already gone through Youtube's consent page
+const CONSENT_COOKIES: &str = "CONSENT=YES+cb; SOCS=CAI";
+
+/// Region and language to request Youtube pages in, so titles don't depend on the machine's locale.
+///
+/// Read from `YTMDL_REGION` (e.g. `US`, sent as `gl`) and `YTMDL_LANGUAGE` (e.g. `en`, sent as `hl`
+/// and `Accept-Language`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Locale {
+    pub region: Option<String>,
+    pub language: Option<String>,
+}
+
+impl Locale {
+    #[must_use]
+    pub fn from_env() -> Self {
+        let var = |key| env::var(key).ok().filter(|v: &String| !v.is_empty());
+        Self {
+            region: var("YTMDL_REGION"),
+            language: var("YTMDL_LANGUAGE"),
+        }
+    }
+
+    /// Adds the `gl`/`hl` query parameters to the url if it's a Youtube url.
+    #[must_use]
+    pub fn apply_to_url(&self, mut url: Url) -> Url {
+        if !is_youtube(&url) {
+            return url;
+        }
+
+        let existing: Vec<String> = url.query_pairs().map(|(k, _)| k.into_owned()).collect();
+        for (key, value) in [("gl", &self.region), ("hl", &self.language)] {
+            if let Some(value) = value {
+                if !existing.iter().any(|k| k == key) {
+                    url.query_pairs_mut().append_pair(key, value);
+                }
+            }
+        }
+        url
+    }
+
+    fn apply_to_request(&self, request: RequestBuilder, url: &Url) -> RequestBuilder {
+        match &self.language {
+            Some(language) if is_youtube(url) => request.header(ACCEPT_LANGUAGE, language),
+            _ => request,
+        }
+    }
+}
+
+/// Set under `[network]` in the config, for networks that only reach the internet through a proxy
+/// or that intercept TLS. Without a `proxy`, the usual `HTTP_PROXY`, `HTTPS_PROXY`, `ALL_PROXY` and
+/// `NO_PROXY` environment variables are used, by both ytmdl and yt-dlp.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NetworkSettings {
+    /// Like `http://proxy.example.com:8080` or `socks5://localhost:1080`, used for everything
+    pub proxy: Option<String>,
+    /// Comma-separated hosts that don't go through `proxy`, like `localhost, .internal.example.com`
+    pub no_proxy: Option<String>,
+    /// A PEM file of CA certificates to trust as well as the system's, like a company's TLS
+    /// inspection certificate
+    pub ca_certificates: Option<PathBuf>,
+}
+
+impl NetworkSettings {
+    fn proxy(&self) -> Option<&str> {
+        self.proxy
+            .as_deref()
+            .map(str::trim)
+            .filter(|proxy| !proxy.is_empty())
+    }
+
+    fn ca_certificates(&self) -> Option<&PathBuf> {
+        self.ca_certificates
+            .as_ref()
+            .filter(|path| !path.as_os_str().is_empty())
+    }
+
+    /// Adds the proxy and certificates to a reqwest client. A certificate file that can't be
+    /// read is logged and left out, so the requests fail with the usual TLS error.
+    ///
+    /// # Errors
+    /// If the proxy isn't a valid URL
+    fn apply_to_client(&self, mut builder: ClientBuilder) -> Result<ClientBuilder, reqwest::Error> {
+        if let Some(proxy) = self.proxy() {
+            let no_proxy = self.no_proxy.as_deref().and_then(NoProxy::from_string);
+            builder = builder.proxy(Proxy::all(proxy)?.no_proxy(no_proxy));
+        }
+        if let Some(path) = self.ca_certificates() {
+            match fs::read(path) {
+                Ok(pem) => {
+                    for cert in split_pem(&pem) {
+                        match Certificate::from_pem(cert) {
+                            Ok(cert) => builder = builder.add_root_certificate(cert),
+                            Err(err) => log::error!("bad certificate in {}: {err}", path.display()),
+                        }
+                    }
+                }
+                Err(err) => log::error!("couldn't read {}: {err}", path.display()),
+            }
+        }
+        Ok(builder)
+    }
+
+    /// Gives yt-dlp the proxy and certificates. yt-dlp only trusts its own bundled certificates
+    /// unless it's told not to, so then it's pointed at `ca_certificates` through
+    /// `SSL_CERT_FILE`, which takes the place of the system's certificates for it.
+    pub fn apply_to_command(&self, command: &mut Command) {
+        if let Some(proxy) = self.proxy() {
+            command.args(["--proxy", proxy]);
+            if let Some(no_proxy) = &self.no_proxy {
+                command.env("NO_PROXY", no_proxy);
+            }
+        }
+        if let Some(path) = self.ca_certificates() {
+            command
+                .args(["--compat-options", "no-certifi"])
+                .env("SSL_CERT_FILE", path);
+        }
+    }
+}
+
+/// Each certificate in a PEM file, which can have several
+///
+/// # Examples
+/// ```
+/// use ytmdl::network::split_pem;
+///
+/// let pem = b"-----BEGIN CERTIFICATE-----\nAAA\n-----END CERTIFICATE-----\n\
+///     -----BEGIN CERTIFICATE-----\nBBB\n-----END CERTIFICATE-----\n";
+/// let certs = split_pem(pem);
+/// assert_eq!(certs.len(), 2);
+/// assert!(certs[1].starts_with(b"-----BEGIN CERTIFICATE-----\nBBB"));
+/// ```
+#[must_use]
+pub fn split_pem(pem: &[u8]) -> Vec<&[u8]> {
+    const BEGIN: &[u8] = b"-----BEGIN CERTIFICATE-----";
+    let starts: Vec<usize> = (0..pem.len())
+        .filter(|&i| pem[i..].starts_with(BEGIN))
+        .collect();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(n, &start)| &pem[start..starts.get(n + 1).copied().unwrap_or(pem.len())])
+        .collect()
+}
+
+/// A reqwest client builder with the config's [`NetworkSettings`]
+fn builder() -> Result<ClientBuilder, reqwest::Error> {
+    config::current().network.apply_to_client(Client::builder())
+}
+
+fn client() -> Result<Client, reqwest::Error> {
+    builder()?.user_agent("Chrome/116.0.0.0").build() // lol
+}
+
+/// yt-dlp with the config's [`NetworkSettings`]
+#[must_use]
+pub fn ytdlp() -> Command {
+    let mut command = Command::new(crate::ytdlp::program());
+    config::current().network.apply_to_command(&mut command);
+    command
+}
+
+/// Makes a get request via [reqwest] using a fake user agent.
+///
+/// If it gets redirected to Youtube's consent interstitial (mostly happens in the EU),
+/// it retries once with the consent cookies set.
+///
+/// Youtube requests also get the region/language from [`Locale::from_env`].
+#[allow(clippy::missing_errors_doc)]
+pub fn download(url: &str) -> Result<Response, reqwest::Error> {
+    let client = client()?;
+    let locale = Locale::from_env();
+    let request = |url: &str| match Url::parse(url) {
+        Ok(parsed) => {
+            let parsed = locale.apply_to_url(parsed);
+            locale.apply_to_request(client.get(parsed.as_str()), &parsed)
+        }
+        Err(_) => client.get(url),
+    };
+
+    let resp = request(url).send()?;
+
+    if is_consent_page(resp.url()) {
+        log::debug!("got redirected to the consent page, retrying with consent cookies");
+        request(url).header(COOKIE, CONSENT_COOKIES).send()
+    } else {
+        Ok(resp)
+    }
+}
+
+/// Makes a head request via [reqwest] using the same fake user agent as [`download`].
+#[allow(clippy::missing_errors_doc)]
+pub fn head(url: &str) -> Result<Response, reqwest::Error> {
+    client()?.head(url).send()
+}
+
+/// Makes a get request via [reqwest] with an `Authorization` header, for APIs that need a token.
+///
+/// Unlike [`download`], error statuses (like a wrong token) are returned as errors.
+#[allow(clippy::missing_errors_doc)]
+pub fn download_authorized(url: &str, authorization: &str) -> Result<Response, reqwest::Error> {
+    client()?
+        .get(url)
+        .header(AUTHORIZATION, authorization)
+        .send()?
+        .error_for_status()
+}
+
+/// Makes a get request via [reqwest] with a user agent that says it's ytmdl, which APIs like
+/// Musicbrainz's ask for instead of a browser's.
+///
+/// Like [`download_authorized`], error statuses are returned as errors.
+#[allow(clippy::missing_errors_doc)]
+pub fn download_identified(url: &str) -> Result<Response, reqwest::Error> {
+    identified()?.build()?.get(url).send()?.error_for_status()
+}
+
+/// [`download_identified`] without the 30 second limit on the whole request, for big files like
+/// yt-dlp itself
+#[allow(clippy::missing_errors_doc)]
+pub fn download_large(url: &str) -> Result<Response, reqwest::Error> {
+    identified()?
+        .timeout(None)
+        .build()?
+        .get(url)
+        .send()?
+        .error_for_status()
+}
+
+fn identified() -> Result<ClientBuilder, reqwest::Error> {
+    Ok(builder()?.user_agent(concat!(
+        "ytmdl/",
+        env!("CARGO_PKG_VERSION"),
+        " ( ",
+        env!("CARGO_PKG_REPOSITORY"),
+        " )"
+    )))
+}
+
+/// Makes a post request via [reqwest] with `body` sent as `content_type`, for APIs that take a
+/// file.
+///
+/// Like [`download_authorized`], error statuses are returned as errors.
+#[allow(clippy::missing_errors_doc)]
+pub fn post(url: &str, body: Vec<u8>, content_type: &str) -> Result<Response, reqwest::Error> {
+    client()?
+        .post(url)
+        .header(CONTENT_TYPE, content_type)
+        .body(body)
+        .send()?
+        .error_for_status()
+}
+
+fn is_consent_page(url: &Url) -> bool {
+    url.host_str()
+        .map_or(false, |host| host.starts_with("consent."))
+}
+
+fn is_youtube(url: &Url) -> bool {
+    url.host_str().map_or(false, |host| {
+        host == "youtube.com" || host.ends_with(".youtube.com")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn proxy_for_ytdlp() {
+        let settings = NetworkSettings {
+            proxy: Some(String::from(" http://proxy:8080 ")),
+            no_proxy: Some(String::from("localhost")),
+            ca_certificates: Some(PathBuf::from("/etc/ssl/company.pem")),
+        };
+        let mut command = Command::new("yt-dlp");
+        settings.apply_to_command(&mut command);
+        let args: Vec<_> = command.get_args().collect();
+        assert_eq!(
+            args,
+            [
+                "--proxy",
+                "http://proxy:8080",
+                "--compat-options",
+                "no-certifi"
+            ]
+        );
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&("NO_PROXY".as_ref(), Some("localhost".as_ref()))));
+        assert!(envs.contains(&(
+            "SSL_CERT_FILE".as_ref(),
+            Some("/etc/ssl/company.pem".as_ref())
+        )));
+
+        // the environment's proxy is left to yt-dlp
+        let mut command = Command::new("yt-dlp");
+        NetworkSettings {
+            proxy: Some(String::new()),
+            ..NetworkSettings::default()
+        }
+        .apply_to_command(&mut command);
+        assert_eq!(command.get_args().count(), 0);
+        assert_eq!(command.get_envs().count(), 0);
+    }
+
+    #[test]
+    fn consent_page_detection() {
+        let consent = Url::parse("https://consent.youtube.com/m?continue=https%3A%2F%2Fwww.youtube.com%2Fplaylist%3Flist%3DOLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ&gl=DE").unwrap();
+        assert!(is_consent_page(&consent));
+
+        let playlist = Url::parse(
+            "https://www.youtube.com/playlist?list=OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ",
+        )
+        .unwrap();
+        assert!(!is_consent_page(&playlist));
+    }
+
+    #[test]
+    fn locale_query_params() {
+        let locale = Locale {
+            region: Some("US".into()),
+            language: Some("en".into()),
+        };
+
+        let url = Url::parse("https://www.youtube.com/playlist?list=abc").unwrap();
+        assert_eq!(
+            locale.apply_to_url(url).as_str(),
+            "https://www.youtube.com/playlist?list=abc&gl=US&hl=en"
+        );
+
+        // doesn't override params that are already there
+        let url = Url::parse("https://music.youtube.com/playlist?list=abc&hl=ko").unwrap();
+        assert_eq!(
+            locale.apply_to_url(url).as_str(),
+            "https://music.youtube.com/playlist?list=abc&hl=ko&gl=US"
+        );
+
+        // leaves other sites alone
+        let url = Url::parse("https://www.discogs.com/release/1").unwrap();
+        assert_eq!(
+            locale.apply_to_url(url).as_str(),
+            "https://www.discogs.com/release/1"
+        );
+
+        let url = Url::parse("https://www.youtube.com/playlist?list=abc").unwrap();
+        assert_eq!(
+            Locale::default().apply_to_url(url).as_str(),
+            "https://www.youtube.com/playlist?list=abc"
+        );
+    }
+}
@@ -0,0 +1,184 @@
+//! Spotting the videos in a playlist that obviously aren't songs, like teasers and interviews, so
+//! their tracks can start out unticked
+
+use crate::{parsing::format_duration, scraping::PlaylistItem};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// A video shorter than [`NonMusicRules::min_secs`] is still a song if the release has a track
+/// this close to its length
+const LENGTH_TOLERANCE: Duration = Duration::from_secs(3);
+
+/// Set under `[non_music]` in the config
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NonMusicRules {
+    /// Untick the tracks whose videos look like they aren't songs
+    pub enabled: bool,
+    /// Words (or phrases) that make a video title not a song, ignoring case. They have to be whole
+    /// words, so "MV" doesn't match "MVP".
+    pub keywords: Vec<String>,
+    /// Videos shorter than this many seconds aren't songs, unless the release has a track that's
+    /// about as long. 0 to turn it off.
+    pub min_secs: u64,
+}
+
+impl Default for NonMusicRules {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            keywords: ["Teaser", "Trailer", "MV", "Interview", "Behind the Scenes"]
+                .into_iter()
+                .map(String::from)
+                .collect(),
+            min_secs: 45,
+        }
+    }
+}
+
+/// Why a video doesn't look like a song
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NonMusic {
+    /// With the keyword, as it's written in the rules
+    Keyword(String),
+    /// With the video's length
+    Short(Duration),
+}
+
+impl Display for NonMusic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Keyword(keyword) => write!(f, r#"its video's title has "{keyword}" in it"#),
+            Self::Short(length) => write!(
+                f,
+                "its video is only {} long, and no track on the release is",
+                format_duration(*length)
+            ),
+        }
+    }
+}
+
+impl NonMusicRules {
+    /// Why `video` doesn't look like a song, if it doesn't. `track_lengths` are the release's, and
+    /// are empty if the tracks came from the playlist.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use ytmdl::{
+    ///     non_music::{NonMusic, NonMusicRules},
+    ///     scraping::PlaylistItem,
+    /// };
+    ///
+    /// let video = |title: &str, secs| PlaylistItem {
+    ///     title: Some(title.to_string()),
+    ///     id: None,
+    ///     duration: Some(Duration::from_secs(secs)),
+    ///     channel: None,
+    /// };
+    /// let rules = NonMusicRules::default();
+    /// let lengths = [Some(Duration::from_secs(31))];
+    ///
+    /// assert_eq!(
+    ///     rules.check(&video("Album 'Version Up' Teaser", 200), &lengths),
+    ///     Some(NonMusic::Keyword(String::from("Teaser")))
+    /// );
+    /// assert_eq!(rules.check(&video("MVP", 200), &lengths), None);
+    /// assert_eq!(
+    ///     rules.check(&video("Intro", 20), &lengths),
+    ///     Some(NonMusic::Short(Duration::from_secs(20)))
+    /// );
+    /// // as long as the release's first track
+    /// assert_eq!(rules.check(&video("Intro", 30), &lengths), None);
+    /// ```
+    #[must_use]
+    pub fn check(
+        &self,
+        video: &PlaylistItem,
+        track_lengths: &[Option<Duration>],
+    ) -> Option<NonMusic> {
+        if !self.enabled {
+            return None;
+        }
+        let title = format!(" {} ", words(video.title.as_deref().unwrap_or_default()));
+        if let Some(keyword) = self.keywords.iter().find(|keyword| {
+            let keyword = words(keyword);
+            !keyword.is_empty() && title.contains(&format!(" {keyword} "))
+        }) {
+            return Some(NonMusic::Keyword(keyword.clone()));
+        }
+
+        let length = video.duration?;
+        let is_track = track_lengths
+            .iter()
+            .flatten()
+            .any(|track| track.abs_diff(length) <= LENGTH_TOLERANCE);
+        (length < Duration::from_secs(self.min_secs) && !is_track)
+            .then_some(NonMusic::Short(length))
+    }
+}
+
+/// The words in `s`, lowercase with one space between each
+fn words(s: &str) -> String {
+    s.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(title: &str, secs: Option<u64>) -> PlaylistItem {
+        PlaylistItem {
+            title: Some(title.to_string()),
+            id: None,
+            duration: secs.map(Duration::from_secs),
+            channel: None,
+        }
+    }
+
+    #[test]
+    fn keywords_and_lengths() {
+        let rules = NonMusicRules::default();
+        let check = |title, secs| rules.check(&video(title, secs), &[]);
+
+        assert_eq!(check("Song (Official Audio)", Some(200)), None);
+        assert_eq!(
+            check("ODD EYE CIRCLE 'Air Force One' M/V", None),
+            None,
+            "M/V isn't one word"
+        );
+        assert_eq!(
+            check("[MV] Song", None),
+            Some(NonMusic::Keyword(String::from("MV")))
+        );
+        assert_eq!(
+            check("Song - behind-the-scenes", Some(600)),
+            Some(NonMusic::Keyword(String::from("Behind the Scenes")))
+        );
+        // nothing to match it with without a release
+        assert_eq!(
+            check("Intro", Some(44)),
+            Some(NonMusic::Short(Duration::from_secs(44)))
+        );
+        assert_eq!(check("Intro", Some(45)), None);
+        assert_eq!(check("Untitled", None), None);
+
+        let off = NonMusicRules {
+            enabled: false,
+            ..NonMusicRules::default()
+        };
+        assert_eq!(off.check(&video("Teaser", Some(10)), &[]), None);
+        let no_length = NonMusicRules {
+            min_secs: 0,
+            ..NonMusicRules::default()
+        };
+        assert_eq!(no_length.check(&video("Intro", Some(10)), &[]), None);
+    }
+}
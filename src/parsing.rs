@@ -1,11 +1,114 @@
-use std::str::Chars;
+use std::{str::Chars, time::Duration};
 
+use chrono::NaiveDate;
+use id3::Timestamp;
 use itertools::PeekNth;
+use thiserror::Error;
 
-/// Optionally consumes the given sequence from the chars. Returns true if consumed.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+pub enum ParseError {
+    #[error(r#"expected "{expected}" at byte {position}"#)]
+    Expected { expected: String, position: usize },
+}
+
+/// Walks forward through a string, handing back the parts it moves past as slices of it.
+///
+/// # Examples
+/// ```
+/// use ytmdl::parsing::Cursor;
+///
+/// let mut url = Cursor::new("https://www.youtube.com/playlist?list=PLabc&si=xyz");
+/// url.strip_any(&["http://", "https://"]);
+/// url.strip("www.");
+/// url.expect("youtube.com/playlist?list=").unwrap();
+/// assert_eq!(url.take_until("&"), "PLabc");
+/// assert_eq!(url.rest(), "&si=xyz");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor<'a> {
+    source: &'a str,
+    /// Byte offset into `source`, always on a char boundary
+    position: usize,
+}
+
+impl<'a> Cursor<'a> {
+    #[must_use]
+    pub fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            position: 0,
+        }
+    }
+
+    /// What hasn't been moved past yet
+    #[must_use]
+    pub fn rest(&self) -> &'a str {
+        &self.source[self.position..]
+    }
+
+    /// How many bytes have been moved past
+    #[must_use]
+    pub fn position(&self) -> usize {
+        self.position
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.rest().is_empty()
+    }
+
+    fn advance(&mut self, bytes: usize) -> &'a str {
+        let taken = &self.rest()[..bytes];
+        self.position += bytes;
+        taken
+    }
+
+    /// Moves past `prefix` if the rest starts with it, returning it
+    pub fn strip(&mut self, prefix: &str) -> Option<&'a str> {
+        self.rest()
+            .starts_with(prefix)
+            .then(|| self.advance(prefix.len()))
+    }
+
+    /// [`strip`](Self::strip)s the first of the prefixes the rest starts with, so longer ones
+    /// that start with shorter ones should go first
+    pub fn strip_any(&mut self, prefixes: &[&str]) -> Option<&'a str> {
+        prefixes.iter().find_map(|prefix| self.strip(prefix))
+    }
+
+    /// Like [`strip`](Self::strip), but it's an error if the rest doesn't start with `prefix`
+    ///
+    /// # Errors
+    /// If the rest doesn't start with `prefix`, in which case nothing is moved past
+    pub fn expect(&mut self, prefix: &str) -> Result<&'a str, ParseError> {
+        self.strip(prefix).ok_or_else(|| ParseError::Expected {
+            expected: prefix.to_string(),
+            position: self.position,
+        })
+    }
+
+    /// Moves up to (but not past) the next `delimiter`, or to the end if there isn't one,
+    /// returning what was moved past
+    pub fn take_until(&mut self, delimiter: &str) -> &'a str {
+        let end = self.rest().find(delimiter).unwrap_or(self.rest().len());
+        self.advance(end)
+    }
+
+    /// Moves past chars while `predicate` holds, returning them
+    pub fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'a str {
+        let end = self
+            .rest()
+            .find(|c| !predicate(c))
+            .unwrap_or(self.rest().len());
+        self.advance(end)
+    }
+}
+
+/// Optionally consumes the given sequence from the chars. Returns how many chars were consumed.
 ///
 /// # Example
 /// ```
+/// # #![allow(deprecated)]
 /// let s = "abcdef";
 /// let mut chars = itertools::peek_nth(s.chars());
 ///
@@ -15,6 +118,7 @@ use itertools::PeekNth;
 /// ```
 ///
 ///```
+/// # #![allow(deprecated)]
 /// let s = "abcdef";
 /// let mut chars = itertools::peek_nth(s.chars());
 ///
@@ -22,31 +126,28 @@ use itertools::PeekNth;
 /// assert_eq!(found, 0);
 /// assert_eq!(chars.collect::<String>(), String::from("abcdef"));
 /// ```
+#[deprecated(note = "use `Cursor::strip`")]
 pub fn consume(chars: &mut PeekNth<Chars<'_>>, sequence: &str) -> usize {
-    let mut matches = true;
+    let mut count = 0;
     for (i, ch) in sequence.chars().enumerate() {
         if chars.peek_nth(i) != Some(&ch) {
-            matches = false;
-            break;
+            return 0;
         }
+        count += 1;
     }
 
-    if matches {
-        // maybe a better way to do this without this loop
-        for _ in 0..sequence.len() {
-            chars.next();
-        }
-        sequence.len()
-    } else {
-        0
+    for _ in 0..count {
+        chars.next();
     }
+    count
 }
 
-/// [consume]s the first of the sequences if possible and returns true,
-/// else returns false and consumes nothing.
+/// [consume]s the first of the sequences if possible and returns how many chars it was,
+/// else returns 0 and consumes nothing.
 ///
 /// # Examples
 /// ```
+/// # #![allow(deprecated)]
 /// let url = "https://foo.bar";
 /// let mut chars = itertools::peek_nth(url.chars());
 ///
@@ -57,12 +158,15 @@ pub fn consume(chars: &mut PeekNth<Chars<'_>>, sequence: &str) -> usize {
 ///
 /// Here is an example of what **NOT** to do.
 /// ```
+/// # #![allow(deprecated)]
 /// let url = "https://foo.bar";
 /// let mut chars = itertools::peek_nth(url.chars());
 /// let found = ytmdl::parsing::consume_mutually_exclusive(&mut chars, &["http", "https"]);
 /// assert_eq!(found, 4);
 /// assert_eq!(chars.collect::<String>(), String::from("s://foo.bar")); // bad
 /// ```
+#[deprecated(note = "use `Cursor::strip_any`")]
+#[allow(deprecated)]
 pub fn consume_mutually_exclusive(chars: &mut PeekNth<Chars<'_>>, sequences: &[&str]) -> usize {
     for sequence in sequences {
         let chars = consume(chars, sequence);
@@ -72,3 +176,483 @@ pub fn consume_mutually_exclusive(chars: &mut PeekNth<Chars<'_>>, sequences: &[&
     }
     0
 }
+
+/// Finds the first balanced `{...}` object in the given string and returns it as a slice,
+/// ignoring any braces that are inside of string literals.
+///
+/// Returns `None` if there is no `{` or it never gets closed.
+///
+/// # Examples
+/// ```
+/// let s = r#"var foo = {"a": {"b": "}"}}; var bar = {};"#;
+/// let found = ytmdl::parsing::find_json_object(s);
+/// assert_eq!(found, Some(r#"{"a": {"b": "}"}}"#));
+/// ```
+///
+/// ```
+/// let s = r#"var foo = {"a": "\"{"#;
+/// assert_eq!(ytmdl::parsing::find_json_object(s), None);
+/// ```
+#[must_use]
+pub fn find_json_object(s: &str) -> Option<&str> {
+    let start = s.find('{')?;
+    let mut depth = 0_usize;
+    let mut in_string = false;
+    let mut escaped = false;
+
+    // working on bytes is fine since all the delimiters are ascii
+    for (i, b) in s.bytes().enumerate().skip(start) {
+        if in_string {
+            if escaped {
+                escaped = false;
+            } else if b == b'\\' {
+                escaped = true;
+            } else if b == b'"' {
+                in_string = false;
+            }
+            continue;
+        }
+
+        match b {
+            b'"' => in_string = true,
+            b'{' => depth += 1,
+            b'}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&s[start..=i]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    None
+}
+
+/// Characters that can go between the title and duration in a pasted tracklist
+const TRACKLIST_SEPARATORS: &[char] = &['-', '–', '—', '|'];
+/// Characters that can go after the track number in a pasted tracklist, as well as the separators
+const TRACK_NUMBER_SEPARATORS: &[char] = &['-', '–', '—', '|', '.', ')', ':'];
+
+/// A track parsed from a line of a pasted tracklist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TracklistLine {
+    pub number: Option<usize>,
+    pub title: String,
+    pub duration: Option<Duration>,
+}
+
+/// Parses a tracklist pasted as text, one track per line, skipping blank lines.
+///
+/// Each line can start with a track number (followed by `.`, `)`, `:`, a dash or a tab),
+/// and end with a duration like `3:45` or `(1:02:03)`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::parsing::{parse_tracklist, TracklistLine};
+///
+/// let tracks = parse_tracklist("1. Intro — 1:02\n\n2) Song Name (Remix) [3:45]\nOutro");
+/// assert_eq!(tracks, [
+///     TracklistLine { number: Some(1), title: "Intro".into(), duration: Some(Duration::from_secs(62)) },
+///     TracklistLine { number: Some(2), title: "Song Name (Remix)".into(), duration: Some(Duration::from_secs(225)) },
+///     TracklistLine { number: None, title: "Outro".into(), duration: None },
+/// ]);
+///
+/// // copied from a Discogs tracklist
+/// let tracks = parse_tracklist("3\tSong\t4:01");
+/// assert_eq!(tracks[0].number, Some(3));
+/// assert_eq!(tracks[0].title, "Song");
+/// ```
+#[must_use]
+pub fn parse_tracklist(text: &str) -> Vec<TracklistLine> {
+    text.lines().filter_map(parse_tracklist_line).collect()
+}
+
+fn parse_tracklist_line(line: &str) -> Option<TracklistLine> {
+    let mut rest = line.trim();
+
+    // track number
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    let mut number = None;
+    if digits > 0 {
+        let after = &rest[digits..];
+        let trimmed = after.trim_start_matches(' ');
+        if after.starts_with('\t') || trimmed.starts_with(TRACK_NUMBER_SEPARATORS) {
+            number = rest[..digits].parse().ok();
+            rest = trimmed
+                .trim_start_matches(TRACK_NUMBER_SEPARATORS)
+                .trim_start();
+        }
+    }
+
+    // duration
+    let mut duration = None;
+    if let Some((title, last)) = rest.rsplit_once(char::is_whitespace) {
+        let last = last.trim_matches(|c| matches!(c, '(' | ')' | '[' | ']'));
+        if let Some(parsed) = parse_duration(last) {
+            duration = Some(parsed);
+            rest = title
+                .trim_end()
+                .trim_end_matches(TRACKLIST_SEPARATORS)
+                .trim_end();
+        }
+    }
+
+    (!rest.is_empty()).then(|| TracklistLine {
+        number,
+        title: rest.to_string(),
+        duration,
+    })
+}
+
+/// Parses a duration in the format `m:ss` or `h:mm:ss`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::parsing::parse_duration;
+///
+/// assert_eq!(parse_duration("3:45"), Some(Duration::from_secs(225)));
+/// assert_eq!(parse_duration("1:02:03"), Some(Duration::from_secs(3723)));
+/// assert_eq!(parse_duration("3:75"), None);
+/// assert_eq!(parse_duration("345"), None);
+/// ```
+#[must_use]
+pub fn parse_duration(s: &str) -> Option<Duration> {
+    let parts: Vec<&str> = s.split(':').collect();
+    if !(2..=3).contains(&parts.len()) {
+        return None;
+    }
+
+    let mut secs = 0;
+    for (i, part) in parts.iter().enumerate() {
+        if part.is_empty() || !part.bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        let value: u64 = part.parse().ok()?;
+        if i > 0 && (value >= 60 || part.len() != 2) {
+            return None;
+        }
+        secs = secs * 60 + value;
+    }
+    Some(Duration::from_secs(secs))
+}
+
+/// Month names and abbreviations in English, German, French, Spanish, Italian, Portuguese and
+/// Dutch, lowercase and without any trailing `.`
+const MONTHS: [&[&str]; 12] = [
+    &[
+        "jan", "january", "januar", "jänner", "janvier", "janv", "enero", "ene", "gennaio", "gen",
+        "janeiro", "januari",
+    ],
+    &[
+        "feb",
+        "february",
+        "februar",
+        "février",
+        "févr",
+        "fevrier",
+        "febrero",
+        "febbraio",
+        "fevereiro",
+        "fev",
+        "februari",
+    ],
+    &[
+        "mar", "march", "märz", "maerz", "mars", "marzo", "março", "maart", "mrt",
+    ],
+    &["apr", "april", "avril", "avr", "abril", "abr", "aprile"],
+    &["may", "mai", "mayo", "maggio", "mag", "maio", "mei"],
+    &[
+        "jun", "june", "juni", "juin", "junio", "giugno", "giu", "junho",
+    ],
+    &[
+        "jul", "july", "juli", "juillet", "juil", "julio", "luglio", "lug", "julho",
+    ],
+    &["aug", "august", "août", "aout", "agosto", "ago", "augustus"],
+    &[
+        "sep",
+        "sept",
+        "september",
+        "septembre",
+        "septiembre",
+        "settembre",
+        "set",
+        "setembro",
+    ],
+    &[
+        "oct", "october", "oktober", "okt", "octobre", "octubre", "ottobre", "ott", "outubro",
+        "out",
+    ],
+    &["nov", "november", "novembre", "noviembre", "novembro"],
+    &[
+        "dec",
+        "december",
+        "dezember",
+        "dez",
+        "décembre",
+        "déc",
+        "diciembre",
+        "dic",
+        "dicembre",
+        "dezembro",
+    ],
+];
+
+/// Parses a release date in whatever format it's in, as precisely as it can: ISO 8601
+/// (`2023-06-02`, `2023-06` or `2023`), or a day, month name and year in any order and in a few
+/// languages (`02 Jun 2023`, `June 2, 2023`, `2. Juni 2023`, `juin 2023`).
+///
+/// All-number dates other than ISO ones could be day or month first, so only the year is taken
+/// from them.
+///
+/// # Examples
+/// ```
+/// use ytmdl::parsing::parse_release_date;
+///
+/// let date = parse_release_date("02 Jun 2023").unwrap();
+/// assert_eq!((date.year, date.month, date.day), (2023, Some(6), Some(2)));
+///
+/// let date = parse_release_date("2023-06-02").unwrap();
+/// assert_eq!((date.year, date.month, date.day), (2023, Some(6), Some(2)));
+///
+/// let date = parse_release_date("1. März 1999").unwrap();
+/// assert_eq!((date.year, date.month, date.day), (1999, Some(3), Some(1)));
+///
+/// let date = parse_release_date("2023").unwrap();
+/// assert_eq!((date.year, date.month, date.day), (2023, None, None));
+///
+/// assert!(parse_release_date("31 Feb 2023").is_none());
+/// assert!(parse_release_date("soon").is_none());
+/// ```
+#[must_use]
+pub fn parse_release_date(s: &str) -> Option<Timestamp> {
+    let s = s.trim();
+    // id3's parser takes any number at the start as the year, so only give it ISO ones
+    let iso = s.len() >= 4
+        && s.as_bytes()[..4].iter().all(u8::is_ascii_digit)
+        && matches!(s.as_bytes().get(4), None | Some(b'-' | b'T'));
+    if let (true, Ok(timestamp)) = (iso, s.parse::<Timestamp>()) {
+        return Some(timestamp);
+    }
+
+    let mut year = None;
+    let mut month = None;
+    let mut numbers = Vec::new();
+    let tokens = s
+        .split(|c: char| c.is_whitespace() || matches!(c, ',' | '.' | '/' | '-'))
+        .filter(|token| !token.is_empty());
+    for token in tokens {
+        if token.bytes().all(|b| b.is_ascii_digit()) {
+            match token.parse::<u16>() {
+                Ok(n) if token.len() == 4 => year = year.or(Some(i32::from(n))),
+                Ok(n) => numbers.push(n),
+                Err(_) => {}
+            }
+        } else if month.is_none() {
+            let word = token.to_lowercase();
+            month = MONTHS
+                .iter()
+                .position(|names| names.contains(&word.as_str()))
+                .and_then(|i| u8::try_from(i + 1).ok());
+        }
+    }
+
+    let year = year?;
+    let (month, day) = match (month, numbers.as_slice()) {
+        (Some(month), []) => (Some(month), None),
+        (Some(month), [day]) => {
+            let day = u8::try_from(*day).ok()?;
+            NaiveDate::from_ymd_opt(year, u32::from(month), u32::from(day))?;
+            (Some(month), Some(day))
+        }
+        _ => (None, None),
+    };
+    Some(Timestamp {
+        year,
+        month,
+        day,
+        hour: None,
+        minute: None,
+        second: None,
+    })
+}
+
+/// Formats a duration as `m:ss`, or `h:mm:ss` if it's at least an hour. The opposite of
+/// [`parse_duration`].
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::parsing::format_duration;
+///
+/// assert_eq!(format_duration(Duration::from_secs(225)), "3:45");
+/// assert_eq!(format_duration(Duration::from_secs(3723)), "1:02:03");
+/// ```
+#[must_use]
+pub fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    if secs >= 3600 {
+        format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+    } else {
+        format!("{}:{:02}", secs / 60, secs % 60)
+    }
+}
+
+/// Parses a delay made of hours, minutes and seconds, like `7h`, `1h 30m` or `90m`.
+///
+/// # Examples
+/// ```
+/// use std::time::Duration;
+/// use ytmdl::parsing::parse_delay;
+///
+/// assert_eq!(parse_delay("7h"), Some(Duration::from_secs(7 * 3600)));
+/// assert_eq!(parse_delay("1h 30m"), Some(Duration::from_secs(5400)));
+/// assert_eq!(parse_delay("2m30s"), Some(Duration::from_secs(150)));
+/// assert_eq!(parse_delay("90"), None);
+/// assert_eq!(parse_delay("1d"), None);
+/// assert_eq!(parse_delay(""), None);
+/// ```
+#[must_use]
+pub fn parse_delay(s: &str) -> Option<Duration> {
+    let mut secs = 0;
+    let mut number: Option<u64> = None;
+    let mut any = false;
+    for c in s.chars().filter(|c| !c.is_whitespace()) {
+        if let Some(digit) = c.to_digit(10) {
+            number = Some(
+                number
+                    .unwrap_or(0)
+                    .checked_mul(10)?
+                    .checked_add(u64::from(digit))?,
+            );
+            continue;
+        }
+        let unit = match c.to_ascii_lowercase() {
+            'h' => 3600,
+            'm' => 60,
+            's' => 1,
+            _ => return None,
+        };
+        secs += number.take()?.checked_mul(unit)?;
+        any = true;
+    }
+
+    (any && number.is_none()).then(|| Duration::from_secs(secs))
+}
+
+/// Parses CSV into rows of fields, handling quoted fields (with `""` for a literal quote).
+///
+/// The delimiter is whichever of `,`, `;` or tab appears most in the first line, since
+/// spreadsheet programs in some locales export with `;`. Blank lines are skipped.
+///
+/// # Examples
+/// ```
+/// let rows = ytmdl::parsing::parse_csv("title,artist\n\"Hello, World\",\"The \"\"Band\"\"\"\n");
+/// assert_eq!(rows, [
+///     vec!["title", "artist"],
+///     vec!["Hello, World", "The \"Band\""],
+/// ]);
+///
+/// let rows = ytmdl::parsing::parse_csv("no;title\r\n1;Song\r\n");
+/// assert_eq!(rows, [vec!["no", "title"], vec!["1", "Song"]]);
+/// ```
+#[must_use]
+pub fn parse_csv(s: &str) -> Vec<Vec<String>> {
+    let first_line = s.lines().next().unwrap_or_default();
+    let delimiter = [',', ';', '\t']
+        .into_iter()
+        .max_by_key(|d| first_line.matches(*d).count())
+        .unwrap_or(',');
+
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut in_quotes = false;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if in_quotes {
+            match c {
+                '"' if chars.peek() == Some(&'"') => {
+                    chars.next();
+                    field.push('"');
+                }
+                '"' => in_quotes = false,
+                c => field.push(c),
+            }
+        } else {
+            match c {
+                '"' if field.is_empty() => in_quotes = true,
+                '\r' => {}
+                '\n' => {
+                    row.push(std::mem::take(&mut field));
+                    if row.iter().any(|f| !f.is_empty()) {
+                        rows.push(std::mem::take(&mut row));
+                    }
+                    row.clear();
+                }
+                c if c == delimiter => row.push(std::mem::take(&mut field)),
+                c => field.push(c),
+            }
+        }
+    }
+
+    row.push(field);
+    if row.iter().any(|f| !f.is_empty()) {
+        rows.push(row);
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    proptest! {
+        #[test]
+        fn strip_leaves_the_rest(prefix in "\\PC*", rest in "\\PC*") {
+            let s = format!("{prefix}{rest}");
+            let mut cursor = Cursor::new(&s);
+            prop_assert_eq!(cursor.strip(&prefix), Some(prefix.as_str()));
+            prop_assert_eq!(cursor.rest(), rest.as_str());
+            prop_assert_eq!(cursor.position(), prefix.len());
+        }
+
+        #[test]
+        fn take_until_splits(s in "\\PC*", delimiter in "\\PC+") {
+            let mut cursor = Cursor::new(&s);
+            let taken = cursor.take_until(&delimiter);
+            prop_assert!(!taken.contains(delimiter.as_str()));
+            prop_assert!(cursor.is_empty() || cursor.rest().starts_with(delimiter.as_str()));
+            prop_assert_eq!(format!("{taken}{}", cursor.rest()), s);
+        }
+
+        #[test]
+        fn expect_failing_moves_nothing(s in "\\PC*", prefix in "\\PC+") {
+            prop_assume!(!s.starts_with(prefix.as_str()));
+            let mut cursor = Cursor::new(&s);
+            prop_assert!(cursor.expect(&prefix).is_err());
+            prop_assert_eq!(cursor.rest(), s.as_str());
+        }
+
+        #[test]
+        #[allow(deprecated)]
+        fn consume_counts_chars(prefix in "\\PC*", rest in "\\PC*") {
+            let s = format!("{prefix}{rest}");
+            let mut chars = itertools::peek_nth(s.chars());
+            prop_assert_eq!(consume(&mut chars, &prefix), prefix.chars().count());
+            prop_assert_eq!(chars.collect::<String>(), rest);
+        }
+
+        #[test]
+        fn duration_round_trip(secs in 0_u64..1_000_000) {
+            let duration = Duration::from_secs(secs);
+            prop_assert_eq!(parse_duration(&format_duration(duration)), Some(duration));
+        }
+    }
+}
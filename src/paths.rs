@@ -0,0 +1,152 @@
+use once_cell::sync::OnceCell;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+    process::{self, Command},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+const PORTABLE_DIR: &str = "ytmdl-data";
+const PORTABLE_MARKER: &str = "ytmdl.portable";
+
+static PORTABLE: OnceCell<bool> = OnceCell::new();
+/// Numbers [`ensure_writable`]'s files, so albums downloading to the same dir at once don't delete
+/// each other's
+static NEXT_PROBE: AtomicU64 = AtomicU64::new(0);
+
+/// Sets whether portable mode was asked for on the command line.
+///
+/// Has no effect if called more than once or after any of the dirs have already been used.
+pub fn init(portable_flag: bool) {
+    let _ = PORTABLE.set(portable_flag || marker_exists());
+}
+
+/// Whether everything's being kept next to the executable.
+///
+/// Normally ytmdl's own files (config, cache, history) go in the platform's usual dirs, but in
+/// portable mode they all live in a `ytmdl-data` folder next to the executable instead, e.g. for
+/// running it off a USB stick. It's turned on by passing `--portable` (see [`init`]), or by putting
+/// an empty `ytmdl.portable` file next to the executable.
+#[must_use]
+pub fn is_portable() -> bool {
+    *PORTABLE.get_or_init(marker_exists)
+}
+
+fn exe_dir() -> Option<PathBuf> {
+    env::current_exe().ok()?.parent().map(Path::to_path_buf)
+}
+
+fn marker_exists() -> bool {
+    exe_dir().map_or(false, |dir| dir.join(PORTABLE_MARKER).exists())
+}
+
+fn portable_dir_in(exe_dir: &Path) -> PathBuf {
+    exe_dir.join(PORTABLE_DIR)
+}
+
+fn dir(platform_dir: fn() -> Option<PathBuf>, portable_sub_dir: Option<&str>) -> Option<PathBuf> {
+    if is_portable() {
+        let mut path = portable_dir_in(&exe_dir()?);
+        if let Some(sub_dir) = portable_sub_dir {
+            path.push(sub_dir);
+        }
+        Some(path)
+    } else {
+        let mut path = platform_dir()?;
+        path.push("ytmdl");
+        Some(path)
+    }
+}
+
+/// Opens `dir` in the platform's file manager, without waiting for it to close
+///
+/// # Errors
+/// If the file manager can't be started
+pub fn open_in_file_manager(dir: &Path) -> io::Result<()> {
+    let program = if cfg!(windows) {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    let mut child = Command::new(program).arg(dir).spawn()?;
+    // waited on so it doesn't linger once it exits
+    thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// Makes `dir` if it doesn't exist, and checks files can be saved in it by making one and deleting
+/// it again
+///
+/// # Errors
+/// If `dir` can't be made, or the file can't be made or deleted
+pub fn ensure_writable(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(
+        ".ytmdl-write-test-{}-{}",
+        process::id(),
+        NEXT_PROBE.fetch_add(1, Ordering::Relaxed)
+    ));
+    fs::File::create(&probe)?;
+    fs::remove_file(probe)
+}
+
+/// Where `config.toml` goes
+#[must_use]
+pub fn config_dir() -> Option<PathBuf> {
+    dir(dirs::config_dir, None)
+}
+
+/// For things that can be safely deleted
+#[must_use]
+pub fn cache_dir() -> Option<PathBuf> {
+    dir(dirs::cache_dir, Some("cache"))
+}
+
+/// For things that should stick around, like the download history
+#[must_use]
+pub fn data_dir() -> Option<PathBuf> {
+    dir(dirs::data_dir, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portable_dir_next_to_exe() {
+        let exe_dir = Path::new("/media/usb/ytmdl");
+        assert_eq!(
+            portable_dir_in(exe_dir),
+            PathBuf::from("/media/usb/ytmdl/ytmdl-data")
+        );
+    }
+
+    #[test]
+    fn writable() {
+        let dir = tempdir::TempDir::new("ytmdl-writable").unwrap();
+        let out_dir = dir.path().join("Music").join("ytmdl");
+        ensure_writable(&out_dir).unwrap();
+        assert!(out_dir.is_dir());
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 0);
+
+        // albums going to the same folder at once
+        let checks: Vec<_> = (0..8)
+            .map(|_| {
+                let out_dir = out_dir.clone();
+                thread::spawn(move || ensure_writable(&out_dir))
+            })
+            .collect();
+        for check in checks {
+            check.join().unwrap().unwrap();
+        }
+        assert_eq!(fs::read_dir(&out_dir).unwrap().count(), 0);
+
+        // somewhere a folder can't be made
+        let file = dir.path().join("file");
+        fs::write(&file, "").unwrap();
+        assert!(ensure_writable(&file.join("ytmdl")).is_err());
+    }
+}
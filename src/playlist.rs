@@ -1,14 +1,96 @@
-use crate::parsing::{consume, consume_mutually_exclusive};
+use crate::{
+    ids::{PlaylistId, VideoId},
+    utils::music_to_www,
+};
+use sha2::{Digest, Sha256};
+use std::borrow::Cow;
+use url::Url;
 
+/// Whether the URL has a valid playlist ID in it
 #[must_use]
 pub fn validate(url: impl AsRef<str>) -> bool {
-    match parse_id_from_url(url.as_ref()) {
-        Some(_id) => todo!(),
-        None => false,
+    parse_id_from_url(url.as_ref()).is_some()
+}
+
+/// The IDs in a Youtube link, from [`parse_youtube_url`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct YoutubeIds {
+    pub video: Option<VideoId>,
+    pub playlist: Option<PlaylistId>,
+}
+
+/// Parses the video and playlist IDs out of any shape of Youtube or Youtube Music link, e.g.
+/// `watch?v=...&list=...`, `youtu.be/...?list=...`, `playlist?list=...`, `shorts/...` or
+/// `embed/...`. Either is `None` if it isn't there or isn't valid.
+///
+/// # Examples
+/// ```
+/// use ytmdl::playlist::parse_youtube_url;
+///
+/// let ids = parse_youtube_url("https://youtu.be/dQw4w9WgXcQ?list=OLAK5uy_abc&si=xyz");
+/// assert_eq!(ids.video.unwrap(), "dQw4w9WgXcQ");
+/// assert_eq!(ids.playlist.unwrap(), "OLAK5uy_abc");
+///
+/// let ids = parse_youtube_url("music.youtube.com/playlist?list=OLAK5uy_abc");
+/// assert_eq!(ids.video, None);
+/// assert_eq!(ids.playlist.unwrap(), "OLAK5uy_abc");
+///
+/// assert_eq!(parse_youtube_url("https://example.com/watch?v=dQw4w9WgXcQ"), Default::default());
+/// ```
+#[must_use]
+pub fn parse_youtube_url(url: &str) -> YoutubeIds {
+    let url = url.trim();
+    let with_scheme;
+    let url = if url.contains("://") {
+        url
+    } else {
+        with_scheme = format!("https://{url}");
+        &with_scheme
+    };
+    let Ok(parsed) = Url::parse(url) else {
+        return YoutubeIds::default();
+    };
+    let Some(host) = parsed.host_str() else {
+        return YoutubeIds::default();
+    };
+    let query = |key: &str| {
+        parsed
+            .query_pairs()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.into_owned())
+    };
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map_or_else(Vec::new, Iterator::collect);
+
+    let video = if host == "youtu.be" {
+        segments.first().map(|id| (*id).to_string())
+    } else if is_youtube_host(host) {
+        match segments.as_slice() {
+            ["watch"] => query("v"),
+            // a playlist embed, which happens to be a valid video ID
+            ["embed", "videoseries"] => None,
+            ["shorts" | "embed" | "live" | "v", id, ..] => Some((*id).to_string()),
+            _ => None,
+        }
+    } else {
+        return YoutubeIds::default();
+    };
+
+    YoutubeIds {
+        video: video.and_then(|id| id.try_into().ok()),
+        playlist: query("list").and_then(|id| id.try_into().ok()),
     }
 }
 
-/// Parses out the playlist ID from a playlist
+fn is_youtube_host(host: &str) -> bool {
+    ["youtube.com", "youtube-nocookie.com"]
+        .iter()
+        .any(|domain| host == *domain || host.ends_with(&format!(".{domain}")))
+}
+
+/// Parses out the playlist ID from any Youtube link with one in (see [`parse_youtube_url`]), or a
+/// bare playlist ID, if it's a valid one
 ///
 /// # Examples
 /// ```
@@ -16,29 +98,218 @@ pub fn validate(url: impl AsRef<str>) -> bool {
 /// let id = ytmdl::playlist::parse_id_from_url(url);
 /// assert!(id.is_some());
 /// assert_eq!(id.unwrap(), r#"OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ"#);
+///
+/// let url = "https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=OLAK5uy_abc&index=2";
+/// assert_eq!(ytmdl::playlist::parse_id_from_url(url).unwrap(), "OLAK5uy_abc");
+///
+/// assert_eq!(ytmdl::playlist::parse_id_from_url("https://youtube.com/playlist?list=<oops>"), None);
 /// ```
 #[must_use]
-pub fn parse_id_from_url(url: &str) -> Option<String> {
-    // it would probably be better to just do this with indexes and whatnot, then return a slice
+pub fn parse_id_from_url(url: &str) -> Option<PlaylistId> {
+    parse_youtube_url(url)
+        .playlist
+        .or_else(|| url.trim().parse().ok())
+}
 
-    let mut url = itertools::peek_nth(url.chars());
+/// The playlist's page on www.youtube.com, which is what can be scraped, for any shape of link to
+/// it. Links without a playlist in are just moved to www.youtube.com.
+///
+/// # Examples
+/// ```
+/// use ytmdl::playlist::page_url;
+///
+/// assert_eq!(
+///     page_url("https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=OLAK5uy_abc"),
+///     "https://www.youtube.com/playlist?list=OLAK5uy_abc",
+/// );
+/// ```
+#[must_use]
+pub fn page_url(url: &str) -> Cow<str> {
+    parse_id_from_url(url).map_or_else(|| music_to_www(url), |id| Cow::Owned(id.url()))
+}
 
-    // eat up the stuff that doesn't matter
-    consume_mutually_exclusive(&mut url, &["https://", "http://"]);
-    consume_mutually_exclusive(&mut url, &["www.", "music."]);
-    consume(&mut url, "youtube.com/playlist?list=");
+/// Parses the video ID out of a video URL (`youtu.be/...`, `watch?v=...` or `shorts/...`), or a
+/// bare 11 character ID.
+///
+/// # Examples
+/// ```
+/// use ytmdl::playlist::parse_video_id;
+///
+/// assert_eq!(parse_video_id("https://youtu.be/dQw4w9WgXcQ?si=abc").unwrap(), "dQw4w9WgXcQ");
+/// assert_eq!(
+///     parse_video_id("https://music.youtube.com/watch?v=dQw4w9WgXcQ&list=OLAK5uy_abc").unwrap(),
+///     "dQw4w9WgXcQ",
+/// );
+/// assert_eq!(parse_video_id("dQw4w9WgXcQ").unwrap(), "dQw4w9WgXcQ");
+/// assert_eq!(parse_video_id("https://youtube.com/playlist?list=OLAK5uy_abc"), None);
+/// ```
+#[must_use]
+pub fn parse_video_id(url: &str) -> Option<VideoId> {
+    url.trim()
+        .parse()
+        .ok()
+        .or_else(|| parse_youtube_url(url).video)
+}
 
-    let mut s = String::with_capacity(64);
-    for ch in url {
-        if ch == '&' {
-            break;
+/// Parses a list of video URLs separated by whitespace or commas (e.g. one per line), for building
+/// an album out of individual videos rather than a playlist.
+///
+/// Returns `None` if it's empty or anything in it isn't a video URL. A single video link that's
+/// in a playlist (`watch?v=...&list=...`) counts as the playlist rather than a list of one video,
+/// unless it's one of Youtube's mixes.
+///
+/// # Examples
+/// ```
+/// use ytmdl::playlist::parse_video_list;
+///
+/// let ids = parse_video_list("https://youtu.be/dQw4w9WgXcQ\nhttps://www.youtube.com/watch?v=9bZkp7q19f0");
+/// assert_eq!(ids.unwrap(), ["dQw4w9WgXcQ", "9bZkp7q19f0"]);
+///
+/// assert_eq!(parse_video_list("https://youtube.com/playlist?list=OLAK5uy_abc"), None);
+/// ```
+#[must_use]
+pub fn parse_video_list(text: &str) -> Option<Vec<VideoId>> {
+    let links: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|s| !s.is_empty())
+        .collect();
+    if let [link] = links.as_slice() {
+        if parse_youtube_url(link)
+            .playlist
+            .is_some_and(|id| !id.is_mix())
+        {
+            return None;
         }
-        s.push(ch);
     }
 
-    if s.is_empty() {
-        None
+    let ids: Option<Vec<VideoId>> = links.into_iter().map(parse_video_id).collect();
+    ids.filter(|ids| !ids.is_empty())
+}
+
+/// A name for the album that's the same each time it's downloaded, to keep partial downloads under.
+///
+/// It's the playlist ID, or the start of the SHA-256 of the video IDs for a list of videos (which
+/// unlike `std`'s hasher stays the same between Rust versions, since the key's saved).
+#[must_use]
+pub fn album_key(youtube_url: &str) -> Option<String> {
+    if let Some(ids) = parse_video_list(youtube_url) {
+        let ids: Vec<&str> = ids.iter().map(VideoId::as_str).collect();
+        let hash = format!("{:x}", Sha256::digest(ids.join("\n")));
+        Some(format!("videos-{}", &hash[..16]))
     } else {
-        Some(s)
+        parse_id_from_url(youtube_url).map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VIDEO: &str = "dQw4w9WgXcQ";
+    const PLAYLIST: &str = "OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ";
+
+    fn ids(url: &str) -> (Option<String>, Option<String>) {
+        let ids = parse_youtube_url(url);
+        (ids.video.map(String::from), ids.playlist.map(String::from))
+    }
+
+    #[test]
+    fn every_shape() {
+        let both = (Some(VIDEO.to_string()), Some(PLAYLIST.to_string()));
+        let video = (Some(VIDEO.to_string()), None);
+        let playlist = (None, Some(PLAYLIST.to_string()));
+
+        for (url, expected) in [
+            (format!("https://www.youtube.com/watch?v={VIDEO}"), &video),
+            (
+                format!("https://www.youtube.com/watch?v={VIDEO}&list={PLAYLIST}"),
+                &both,
+            ),
+            (
+                format!("https://www.youtube.com/watch?list={PLAYLIST}&v={VIDEO}&index=3"),
+                &both,
+            ),
+            (
+                format!("https://m.youtube.com/watch?v={VIDEO}&list={PLAYLIST}"),
+                &both,
+            ),
+            (
+                format!("https://music.youtube.com/watch?v={VIDEO}&list={PLAYLIST}"),
+                &both,
+            ),
+            (format!("https://youtu.be/{VIDEO}"), &video),
+            (format!("https://youtu.be/{VIDEO}?list={PLAYLIST}"), &both),
+            (
+                format!("https://youtu.be/{VIDEO}?si=abc&list={PLAYLIST}"),
+                &both,
+            ),
+            (format!("youtu.be/{VIDEO}?list={PLAYLIST}"), &both),
+            (
+                format!("https://www.youtube.com/playlist?list={PLAYLIST}"),
+                &playlist,
+            ),
+            (
+                format!("http://youtube.com/playlist?list={PLAYLIST}&si=abc"),
+                &playlist,
+            ),
+            (
+                format!("https://music.youtube.com/playlist?list={PLAYLIST}&si=abc"),
+                &playlist,
+            ),
+            (
+                format!("music.youtube.com/playlist?list={PLAYLIST}"),
+                &playlist,
+            ),
+            (format!("https://www.youtube.com/shorts/{VIDEO}"), &video),
+            (
+                format!("https://www.youtube.com/embed/{VIDEO}?list={PLAYLIST}"),
+                &both,
+            ),
+            (
+                format!("https://www.youtube-nocookie.com/embed/{VIDEO}"),
+                &video,
+            ),
+            (
+                format!("https://www.youtube.com/embed/videoseries?list={PLAYLIST}"),
+                &playlist,
+            ),
+            (format!("  https://youtu.be/{VIDEO}  "), &video),
+        ] {
+            assert_eq!(&ids(&url), expected, "{url}");
+        }
+    }
+
+    #[test]
+    fn invalid() {
+        for url in [
+            String::from("https://www.youtube.com/watch?v=short&list=a"),
+            format!("https://example.com/watch?v={VIDEO}&list={PLAYLIST}"),
+            format!("https://notyoutube.com/watch?v={VIDEO}"),
+            String::from("https://www.youtube.com/@artist"),
+            String::from("not a link"),
+        ] {
+            assert_eq!(ids(&url), (None, None), "{url}");
+        }
+        assert_eq!(parse_id_from_url(PLAYLIST).unwrap(), PLAYLIST);
+        assert_eq!(parse_id_from_url("https://youtu.be/dQw4w9WgXcQ"), None);
+    }
+
+    #[test]
+    fn video_list_or_playlist() {
+        let watch = format!("https://www.youtube.com/watch?v={VIDEO}&list={PLAYLIST}");
+        assert_eq!(parse_video_list(&watch), None);
+        assert_eq!(album_key(&watch).unwrap(), PLAYLIST);
+        assert_eq!(
+            page_url(&watch),
+            format!("https://www.youtube.com/playlist?list={PLAYLIST}")
+        );
+
+        let mix = format!("https://www.youtube.com/watch?v={VIDEO}&list=RD{VIDEO}&start_radio=1");
+        assert_eq!(parse_video_list(&mix).unwrap(), [VIDEO]);
+
+        let several = format!("{watch}\nhttps://youtu.be/9bZkp7q19f0");
+        assert_eq!(parse_video_list(&several).unwrap(), [VIDEO, "9bZkp7q19f0"]);
+        // saved, so it mustn't change
+        assert_eq!(album_key(&several).unwrap(), "videos-d8538d19a0875202");
     }
 }
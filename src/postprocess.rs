@@ -0,0 +1,391 @@
+//! Optional steps run on each track as it's converted for a profile, in the order its
+//! [`post_process`](crate::config::Profile::post_process) lists them. Steps that change the audio
+//! (trimming silence and normalizing the loudness) are ffmpeg filters added to the conversion, so
+//! lossy formats aren't encoded twice. The rest run on the converted file before it's tagged, and
+//! add to its tags.
+
+use crate::{
+    analysis::{self, AnalysisSettings, TrackAnalysis},
+    config::{Config, Profile},
+    download::{output_with_deadline, stderr, DownloadError},
+    report::StepTiming,
+};
+use id3::{frame::ExtendedText, Tag, TagLike};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt::{self, Debug, Formatter},
+    path::Path,
+    process::Command,
+    time::Instant,
+};
+
+/// A step that can be listed in a profile's `post_process`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PostStep {
+    /// Cut the silence from the start and end
+    TrimSilence,
+    /// Make it as loud as [`PostProcessSettings::loudness`]
+    Normalize,
+    /// Whichever of BPM and key detection are turned on under `[analysis]`
+    Analyze,
+    /// Measure its replay gain and write it to `REPLAYGAIN_TRACK_GAIN` and `REPLAYGAIN_TRACK_PEAK`
+    /// custom tags
+    ReplayGain,
+}
+
+/// Set under `[post_process]` in the config
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PostProcessSettings {
+    /// What [`PostStep::Normalize`] aims for, in LUFS
+    pub loudness: i8,
+    /// Anything quieter than this many dB is silence for [`PostStep::TrimSilence`]
+    pub silence_threshold: i8,
+}
+
+impl Default for PostProcessSettings {
+    fn default() -> Self {
+        Self {
+            loudness: -14,
+            silence_threshold: -50,
+        }
+    }
+}
+
+/// One step of a [`Pipeline`]
+pub trait PostProcessor: Send + Sync {
+    /// Like "normalize", for the report's timings and the log
+    fn name(&self) -> &'static str;
+
+    /// An ffmpeg audio filter to convert the track with, for steps that change the audio
+    fn filter(&self) -> Option<String> {
+        None
+    }
+
+    /// Runs on the converted file at `path` (for steps without a [`filter`](Self::filter)),
+    /// adding anything it finds out to `tag`
+    ///
+    /// # Errors
+    /// If ffmpeg can't be run or fails, or it's still going at `deadline`
+    fn process(
+        &self,
+        _path: &Path,
+        _id: &str,
+        _tag: &mut Tag,
+        _deadline: Option<Instant>,
+    ) -> Result<(), DownloadError> {
+        Ok(())
+    }
+}
+
+struct TrimSilence {
+    threshold: i8,
+}
+
+impl PostProcessor for TrimSilence {
+    fn name(&self) -> &'static str {
+        "trim silence"
+    }
+
+    fn filter(&self) -> Option<String> {
+        // `silenceremove` only trims reliably from the start, so the end is trimmed backwards
+        let trim = format!(
+            "silenceremove=start_periods=1:start_threshold={}dB",
+            self.threshold
+        );
+        Some(format!("{trim},areverse,{trim},areverse"))
+    }
+}
+
+struct Normalize {
+    loudness: i8,
+}
+
+impl PostProcessor for Normalize {
+    fn name(&self) -> &'static str {
+        "normalize"
+    }
+
+    fn filter(&self) -> Option<String> {
+        // `loudnorm` outputs 192 kHz, which most encoders can't take
+        Some(format!(
+            "loudnorm=I={}:TP=-1.5:LRA=11,aresample=48000",
+            self.loudness
+        ))
+    }
+}
+
+struct Analyze(AnalysisSettings);
+
+impl PostProcessor for Analyze {
+    fn name(&self) -> &'static str {
+        "analyze"
+    }
+
+    fn process(
+        &self,
+        path: &Path,
+        id: &str,
+        tag: &mut Tag,
+        deadline: Option<Instant>,
+    ) -> Result<(), DownloadError> {
+        let samples = decode_for_analysis(path, id, deadline)?;
+        TrackAnalysis::new(&samples, self.0).apply_to_tag(tag);
+        Ok(())
+    }
+}
+
+struct ReplayGain;
+
+impl PostProcessor for ReplayGain {
+    fn name(&self) -> &'static str {
+        "replay gain"
+    }
+
+    fn process(
+        &self,
+        path: &Path,
+        id: &str,
+        tag: &mut Tag,
+        deadline: Option<Instant>,
+    ) -> Result<(), DownloadError> {
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-hide_banner", "-nostats", "-i"])
+            .arg(path.as_os_str())
+            .args(["-af", "replaygain", "-f", "null", "-"]);
+        let what = format!("measuring the replay gain of {id}");
+        let output = output_with_deadline(&mut command, deadline, &what)?;
+        let printed = String::from_utf8_lossy(&output.stderr);
+        let Some((gain, peak)) = output
+            .status
+            .success()
+            .then(|| parse_replay_gain(&printed))
+            .flatten()
+        else {
+            log::error!("{printed}");
+            return Err(DownloadError::FfmpegError(id.to_string(), stderr(&output)));
+        };
+        for (description, value) in [
+            ("REPLAYGAIN_TRACK_GAIN", format!("{gain:.2} dB")),
+            ("REPLAYGAIN_TRACK_PEAK", format!("{peak:.6}")),
+        ] {
+            tag.add_frame(ExtendedText {
+                description: description.to_string(),
+                value,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// The track gain (in dB) and peak from what ffmpeg's `replaygain` filter prints
+///
+/// # Examples
+/// ```
+/// use ytmdl::postprocess::parse_replay_gain;
+///
+/// let printed = "\
+///     [Parsed_replaygain_0 @ 0x5581] track_gain = -7.23 dB\n\
+///     [Parsed_replaygain_0 @ 0x5581] track_peak = 0.988525\n";
+/// assert_eq!(parse_replay_gain(printed), Some((-7.23, 0.988525)));
+/// assert_eq!(parse_replay_gain("Output #0, null"), None);
+/// ```
+#[must_use]
+pub fn parse_replay_gain(printed: &str) -> Option<(f64, f64)> {
+    let value = |name: &str| {
+        printed.lines().find_map(|line| {
+            let (_, value) = line.split_once(name)?;
+            value
+                .trim_start_matches([' ', '='])
+                .trim_end_matches("dB")
+                .trim()
+                .parse()
+                .ok()
+        })
+    };
+    Some((value("track_gain")?, value("track_peak")?))
+}
+
+/// Decodes the track with ffmpeg to mono samples at [`analysis::SAMPLE_RATE`]
+fn decode_for_analysis(
+    path: &Path,
+    id: &str,
+    deadline: Option<Instant>,
+) -> Result<Vec<f32>, DownloadError> {
+    let mut command = Command::new("ffmpeg");
+    command
+        .args(["-v", "error", "-i"])
+        .arg(path.as_os_str())
+        .args(["-ac", "1", "-ar"])
+        .arg(analysis::SAMPLE_RATE.to_string())
+        .args(["-f", "f32le", "-"]);
+    let output = output_with_deadline(&mut command, deadline, &format!("decoding {id}"))?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(DownloadError::FfmpegError(id.to_string(), stderr(&output)));
+    }
+
+    Ok(output
+        .stdout
+        .chunks_exact(4)
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect())
+}
+
+/// A profile's post-processing steps, in order
+#[derive(Default)]
+pub struct Pipeline(Vec<Box<dyn PostProcessor>>);
+
+impl Debug for Pipeline {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.iter().map(|step| step.name()))
+            .finish()
+    }
+}
+
+impl Pipeline {
+    /// [`PostStep::Analyze`] is left out if `analysis` has nothing turned on, since it wouldn't do
+    /// anything
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::{
+    ///     analysis::AnalysisSettings,
+    ///     postprocess::{Pipeline, PostProcessSettings, PostStep},
+    /// };
+    ///
+    /// let steps = [PostStep::TrimSilence, PostStep::Analyze, PostStep::Normalize];
+    /// let pipeline = Pipeline::new(
+    ///     &steps,
+    ///     PostProcessSettings::default(),
+    ///     AnalysisSettings::default(),
+    /// );
+    /// assert_eq!(pipeline.names(), ["trim silence", "normalize"]);
+    /// assert!(pipeline.filter().unwrap().ends_with("loudnorm=I=-14:TP=-1.5:LRA=11,aresample=48000"));
+    /// ```
+    #[must_use]
+    pub fn new(
+        steps: &[PostStep],
+        settings: PostProcessSettings,
+        analysis: AnalysisSettings,
+    ) -> Self {
+        Self(
+            steps
+                .iter()
+                .filter_map(|step| -> Option<Box<dyn PostProcessor>> {
+                    match step {
+                        PostStep::TrimSilence => Some(Box::new(TrimSilence {
+                            threshold: settings.silence_threshold,
+                        })),
+                        PostStep::Normalize => Some(Box::new(Normalize {
+                            loudness: settings.loudness,
+                        })),
+                        PostStep::Analyze => {
+                            analysis.any().then(|| Box::new(Analyze(analysis)) as _)
+                        }
+                        PostStep::ReplayGain => Some(Box::new(ReplayGain)),
+                    }
+                })
+                .collect(),
+        )
+    }
+
+    /// The steps `profile` lists, with the config's settings for them
+    #[must_use]
+    pub fn for_profile(profile: &Profile, config: &Config) -> Self {
+        Self::new(&profile.post_process, config.post_process, config.analysis)
+    }
+
+    #[must_use]
+    pub fn names(&self) -> Vec<&'static str> {
+        self.0.iter().map(|step| step.name()).collect()
+    }
+
+    /// The steps' filters joined into one chain, in order, or `None` if none of them change the
+    /// audio
+    #[must_use]
+    pub fn filter(&self) -> Option<String> {
+        let filters: Vec<String> = self.0.iter().filter_map(|step| step.filter()).collect();
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+
+    /// Runs the steps without filters on the converted file at `path` in order, with how long
+    /// each took. A step that fails is skipped with a warning, since all they do is add tags.
+    pub fn run(
+        &self,
+        path: &Path,
+        id: &str,
+        tag: &mut Tag,
+        deadline: Option<Instant>,
+    ) -> Vec<StepTiming> {
+        self.0
+            .iter()
+            .filter(|step| step.filter().is_none())
+            .map(|step| {
+                let started = Instant::now();
+                if let Err(err) = step.process(path, id, tag, deadline) {
+                    log::warn!("couldn't {} {id}: {err}", step.name());
+                }
+                StepTiming {
+                    step: step.name(),
+                    took: started.elapsed(),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn steps_from_config() {
+        #[derive(Deserialize)]
+        struct Profile {
+            post_process: Vec<PostStep>,
+        }
+        let profile: Profile =
+            toml::from_str(r#"post_process = ["replay_gain", "analyze", "trim_silence"]"#).unwrap();
+        let analysis = AnalysisSettings {
+            bpm: true,
+            key: false,
+        };
+        let settings = PostProcessSettings {
+            silence_threshold: -60,
+            ..PostProcessSettings::default()
+        };
+        let pipeline = Pipeline::new(&profile.post_process, settings, analysis);
+        assert_eq!(pipeline.names(), ["replay gain", "analyze", "trim silence"]);
+        assert_eq!(
+            pipeline.filter().as_deref(),
+            Some(
+                "silenceremove=start_periods=1:start_threshold=-60dB,areverse,\
+                 silenceremove=start_periods=1:start_threshold=-60dB,areverse"
+            )
+        );
+        assert_eq!(
+            format!("{pipeline:?}"),
+            r#"["replay gain", "analyze", "trim silence"]"#
+        );
+
+        assert!(Pipeline::new(&[], settings, analysis).filter().is_none());
+    }
+
+    #[test]
+    fn replay_gain_output() {
+        let printed = "\
+            Input #0, flac, from 'track.flac':\n\
+            [Parsed_replaygain_0 @ 0x55d1c2e0] track_gain = +2.41 dB\n\
+            [Parsed_replaygain_0 @ 0x55d1c2e0] track_peak = 0.501160\n";
+        assert_eq!(parse_replay_gain(printed), Some((2.41, 0.501_16)));
+        assert_eq!(
+            parse_replay_gain("[Parsed_replaygain_0 @ 0x1] track_gain = -1.00 dB"),
+            None,
+            "no peak"
+        );
+    }
+}
@@ -0,0 +1,174 @@
+//! What an output file actually came out as, from ffprobe
+
+use serde::Deserialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    io,
+    path::Path,
+    process::Command,
+    time::Duration,
+};
+use thiserror::Error;
+
+use crate::parsing::format_duration;
+
+#[derive(Debug, Error)]
+pub enum ProbeError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("ffprobe error probing {0}")]
+    FfprobeError(String),
+    #[error("no audio stream in {0}")]
+    NoAudio(String),
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeStream>,
+    format: FfprobeFormat,
+}
+
+/// ffprobe gives numbers as strings
+#[derive(Debug, Deserialize)]
+struct FfprobeStream {
+    codec_name: String,
+    bit_rate: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct FfprobeFormat {
+    duration: Option<String>,
+    size: Option<String>,
+    bit_rate: Option<String>,
+}
+
+/// The audio stream of a file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AudioProbe {
+    pub codec: String,
+    /// In kbps, `None` if ffprobe couldn't tell
+    pub bitrate: Option<u32>,
+    pub duration: Option<Duration>,
+    /// In bytes
+    pub size: u64,
+}
+
+impl AudioProbe {
+    /// Anything under this is probably a bad source rather than a choice
+    pub const LOW_BITRATE: u32 = 96;
+
+    #[must_use]
+    pub fn is_low_bitrate(&self) -> bool {
+        self.bitrate
+            .map_or(false, |bitrate| bitrate < Self::LOW_BITRATE)
+    }
+}
+
+/// Like "mp3, 320 kbps, 3:45, 8.6 MB"
+impl Display for AudioProbe {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.codec)?;
+        if let Some(bitrate) = self.bitrate {
+            write!(f, ", {bitrate} kbps")?;
+        }
+        if let Some(duration) = self.duration {
+            write!(f, ", {}", format_duration(duration))?;
+        }
+        #[allow(clippy::cast_precision_loss)]
+        let mb = self.size as f64 / 1_000_000.0;
+        write!(f, ", {mb:.1} MB")
+    }
+}
+
+/// Runs ffprobe on the file
+///
+/// # Errors
+/// - If ffprobe can't be run or fails (e.g. it isn't an audio file)
+/// - If its output can't be parsed, or there's no audio in the file
+pub fn probe(path: &Path) -> Result<AudioProbe, ProbeError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "stream=codec_name,bit_rate:format=duration,size,bit_rate",
+            "-of",
+            "json",
+        ])
+        .arg(path)
+        .output()?;
+    let name = path.to_string_lossy().to_string();
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(ProbeError::FfprobeError(name));
+    }
+    parse_ffprobe_json(&String::from_utf8_lossy(&output.stdout))?.ok_or(ProbeError::NoAudio(name))
+}
+
+/// Parses ffprobe's JSON output, or `None` if there wasn't an audio stream
+///
+/// # Errors
+/// If it isn't ffprobe's JSON
+pub fn parse_ffprobe_json(json: &str) -> Result<Option<AudioProbe>, serde_json::Error> {
+    let output: FfprobeOutput = serde_json::from_str(json)?;
+    let Some(stream) = output.streams.into_iter().next() else {
+        return Ok(None);
+    };
+
+    // VBR mp3s only have the overall bitrate
+    let bitrate = stream
+        .bit_rate
+        .or(output.format.bit_rate)
+        .and_then(|bps| bps.parse::<u32>().ok())
+        .map(|bps| (bps + 500) / 1000);
+    let duration = output
+        .format
+        .duration
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs >= 0.0)
+        .map(Duration::from_secs_f64);
+    let size = output
+        .format
+        .size
+        .and_then(|size| size.parse().ok())
+        .unwrap_or_default();
+
+    Ok(Some(AudioProbe {
+        codec: stream.codec_name,
+        bitrate,
+        duration,
+        size,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let json = r#"{
+            "programs": [],
+            "streams": [{"codec_name": "mp3", "bit_rate": "48000"}],
+            "format": {"duration": "225.018776", "size": "1350834", "bit_rate": "48026"}
+        }"#;
+        let probe = parse_ffprobe_json(json).unwrap().unwrap();
+        assert_eq!(probe.bitrate, Some(48));
+        assert!(probe.is_low_bitrate());
+        assert_eq!(probe.to_string(), "mp3, 48 kbps, 3:45, 1.4 MB");
+
+        let vbr = r#"{"streams": [{"codec_name": "mp3"}], "format": {"bit_rate": "245123"}}"#;
+        let probe = parse_ffprobe_json(vbr).unwrap().unwrap();
+        assert_eq!(probe.bitrate, Some(245));
+        assert_eq!(probe.duration, None);
+        assert!(!probe.is_low_bitrate());
+
+        let no_audio = r#"{"streams": [], "format": {"duration": "1.0"}}"#;
+        assert_eq!(parse_ffprobe_json(no_audio).unwrap(), None);
+    }
+}
@@ -0,0 +1,394 @@
+//! Downloading queued [`Job`]s in order, optionally starting later. Up to the config's
+//! `albums_at_once` are downloaded at the same time, sharing the limits on how many tracks are
+//! downloaded and converted at once.
+
+use crate::{jobs::Job, paths, threading, utils::unix_time, CancellationToken, DownloadError};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard, PoisonError, RwLock},
+    thread,
+    time::Duration,
+};
+
+/// Whether the queue is running, and if not when it will
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueueStatus {
+    #[default]
+    Stopped,
+    /// Starts at this Unix timestamp
+    Scheduled(u64),
+    Running,
+    /// Was running, and carries on at this Unix timestamp
+    PausedUntil(u64),
+}
+
+impl QueueStatus {
+    /// Whether the next job should be started now, starting the queue if a scheduled or paused
+    /// time has come
+    pub fn poll(&mut self, now: u64) -> bool {
+        match *self {
+            Self::Stopped => false,
+            Self::Running => true,
+            Self::Scheduled(at) | Self::PausedUntil(at) => {
+                if now >= at {
+                    *self = Self::Running;
+                }
+                now >= at
+            }
+        }
+    }
+
+    /// How long until the queue starts or carries on, if it's waiting to
+    #[must_use]
+    pub fn countdown(&self, now: u64) -> Option<Duration> {
+        match *self {
+            Self::Scheduled(at) | Self::PausedUntil(at) => {
+                Some(Duration::from_secs(at.saturating_sub(now)))
+            }
+            Self::Stopped | Self::Running => None,
+        }
+    }
+
+    /// Whether anything needs to happen over time, so the app knows whether to keep checking
+    #[must_use]
+    pub fn is_active(&self) -> bool {
+        !matches!(self, Self::Stopped)
+    }
+}
+
+/// Kept outside of the GUI state so it carries on when switching screens
+static STATUS: Lazy<RwLock<QueueStatus>> = Lazy::new(RwLock::default);
+/// The IDs of the jobs the queue is downloading, oldest first, for stopping them
+static IN_FLIGHT: Lazy<Mutex<Vec<(String, CancellationToken)>>> = Lazy::new(Mutex::default);
+
+#[must_use]
+pub fn status() -> QueueStatus {
+    STATUS.read().map(|status| *status).unwrap_or_default()
+}
+
+pub fn set_status(status: QueueStatus) {
+    if let Ok(mut current) = STATUS.write() {
+        *current = status;
+    }
+}
+
+/// Starts the queue once `delay` has passed
+pub fn schedule(delay: Duration) {
+    set_status(QueueStatus::Scheduled(unix_time() + delay.as_secs()));
+}
+
+/// Stops starting new jobs until `delay` has passed
+pub fn pause(delay: Duration) {
+    set_status(QueueStatus::PausedUntil(unix_time() + delay.as_secs()));
+}
+
+/// Jobs waiting in the queue, in the order they'll be downloaded
+#[must_use]
+pub fn queued_jobs() -> Vec<Job> {
+    let mut jobs: Vec<Job> = Job::load_all().into_iter().filter(Job::is_queued).collect();
+    sort_queued(&mut jobs);
+    jobs
+}
+
+/// Into the order they'll be downloaded: by [`Job::position`], then oldest first
+pub fn sort_queued(jobs: &mut [Job]) {
+    jobs.sort_by_key(|job| (job.position, job.updated));
+}
+
+/// Adds the job to the end of the queue
+pub fn add(mut job: Job) {
+    job.position = queued_jobs()
+        .last()
+        .map_or(0, |last| last.position.saturating_add(1));
+    job.save_or_warn();
+}
+
+/// Moves the queued job with `id` one place up or down
+pub fn move_job(id: &str, up: bool) {
+    let mut jobs = queued_jobs();
+    let Some(index) = jobs.iter().position(|job| job.id == id) else {
+        return;
+    };
+    let index = if up {
+        index.saturating_sub(1)
+    } else {
+        index + 1
+    };
+    reorder(&mut jobs, id, index);
+    save_all(&jobs);
+}
+
+/// Moves the queued job with `id` to the front of the queue. If the queue is already downloading
+/// as many jobs as it can at once, the one it started last stops once its tracks in progress are
+/// done and goes back in the queue after this one.
+pub fn make_next(id: &str) {
+    let mut jobs = queued_jobs();
+    if !reorder(&mut jobs, id, 0) {
+        return;
+    }
+    save_all(&jobs);
+    let in_flight = in_flight();
+    if in_flight.len() >= threading::albums_at_once() {
+        if let Some((_, cancel)) = in_flight.last() {
+            log::info!("finishing the tracks in progress before switching to job {id}");
+            cancel.stop_starting();
+        }
+    }
+}
+
+/// Moves the job with `id` to `index` (or the end), and numbers every job's position to match.
+/// Returns whether the job was there to move.
+fn reorder(jobs: &mut Vec<Job>, id: &str, index: usize) -> bool {
+    let Some(from) = jobs.iter().position(|job| job.id == id) else {
+        return false;
+    };
+    let job = jobs.remove(from);
+    jobs.insert(index.min(jobs.len()), job);
+    for (position, job) in (0..).zip(jobs.iter_mut()) {
+        job.position = position;
+    }
+    true
+}
+
+fn save_all(jobs: &[Job]) {
+    for job in jobs {
+        job.save_or_warn();
+    }
+}
+
+fn in_flight() -> MutexGuard<'static, Vec<(String, CancellationToken)>> {
+    IN_FLIGHT.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// Whether a job from the queue is downloading
+#[must_use]
+pub fn is_busy() -> bool {
+    !in_flight().is_empty()
+}
+
+/// The next queued job if it's time to start it and the queue isn't already downloading as many
+/// as it can at once, stopping the queue once it's empty.
+///
+/// The job counts as downloading from here, so it has to be passed to [`run`].
+#[must_use]
+pub fn take_next() -> Option<(Job, CancellationToken)> {
+    let mut in_flight = in_flight();
+    if in_flight.len() >= threading::albums_at_once() {
+        return None;
+    }
+    let mut status = status();
+    if !status.poll(unix_time()) {
+        return None;
+    }
+
+    // the ones that have just started are still queued until they've scraped the playlist
+    let next = queued_jobs()
+        .into_iter()
+        .find(|job| in_flight.iter().all(|(id, _)| *id != job.id));
+    let Some(job) = next else {
+        if in_flight.is_empty() {
+            log::info!("the queue is empty");
+            set_status(QueueStatus::Stopped);
+        }
+        return None;
+    };
+    set_status(status);
+
+    let cancel = CancellationToken::default();
+    in_flight.push((job.id.clone(), cancel.clone()));
+    Some((job, cancel))
+}
+
+/// Downloads a job from [`take_next`]. If it's stopped with [`cancel_in_flight`], it goes back to
+/// the front of the queue to carry on with later, or second if it made way for [`make_next`].
+pub fn run(job: &Job, cancel: &CancellationToken) {
+    let _in_flight = InFlight(&job.id);
+    log::info!("starting queued job {job}");
+    match crate::download_album_cancellable(&job.clone().into_state(), cancel) {
+        Err(DownloadError::Cancelled) => {
+            log::info!("putting {job} back in the queue");
+            let mut jobs = queued_jobs();
+            jobs.retain(|queued| queued.id != job.id);
+            jobs.push(job.clone());
+            reorder(&mut jobs, &job.id, usize::from(!cancel.is_cancelled()));
+            save_all(&jobs);
+        }
+        Err(err) => log::error!("{err}"),
+        Ok(_) => {}
+    }
+}
+
+/// The ID of a job from [`take_next`], which stops counting as downloading once this is dropped,
+/// even if its download panics
+struct InFlight<'a>(&'a str);
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        in_flight().retain(|(id, _)| id != self.0);
+    }
+}
+
+/// Stops the queue's downloads between steps, letting the tracks in progress get to the end of
+/// the one they're on.
+///
+/// Returns whether there were any to stop.
+#[must_use]
+pub fn cancel_in_flight() -> bool {
+    let in_flight = in_flight();
+    for (_, cancel) in in_flight.iter() {
+        cancel.cancel();
+    }
+    !in_flight.is_empty()
+}
+
+/// Blocks until the queue's downloads have stopped, if there are any
+pub fn wait_until_idle() {
+    while is_busy() {
+        thread::sleep(Duration::from_millis(100));
+    }
+}
+
+/// Where the status is saved when ytmdl is closed with the queue running
+fn saved_path() -> Option<PathBuf> {
+    let mut path = paths::data_dir()?;
+    path.push("queue.json");
+    Some(path)
+}
+
+/// Saves the status if the queue's running or waiting to, so the next launch can offer to carry
+/// on with it (see [`saved_status`]), and stops it starting anything else
+///
+/// # Errors
+/// - If the data dir can't be determined or created
+/// - If the file can't be written
+pub fn save_for_next_launch() -> io::Result<()> {
+    let status = status();
+    set_status(QueueStatus::Stopped);
+    if !status.is_active() {
+        return Ok(());
+    }
+    let path =
+        saved_path().ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+    save_in(&path, status)
+}
+
+fn save_in(path: &Path, status: QueueStatus) -> io::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(path, serde_json::to_string(&status)?)
+}
+
+/// The status the queue was in when ytmdl was last closed, if it was active
+#[must_use]
+pub fn saved_status() -> Option<QueueStatus> {
+    saved_path().and_then(|path| load_in(&path))
+}
+
+fn load_in(path: &Path) -> Option<QueueStatus> {
+    let saved = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&saved)
+        .map_err(|err| log::warn!("couldn't load the saved queue: {err}"))
+        .ok()
+}
+
+/// Carries on with the queue as it was when ytmdl was last closed (a scheduled or paused queue
+/// whose time has passed starts straight away), or just forgets it
+pub fn restore_saved(restore: bool) {
+    if let Some(status) = saved_status().filter(|_| restore) {
+        set_status(status);
+    }
+    if let Some(path) = saved_path() {
+        if let Err(err) = fs::remove_file(&path) {
+            if err.kind() != io::ErrorKind::NotFound {
+                log::warn!(r#"couldn't delete "{}": {err}"#, path.display());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::view_modifying_data::StateModifyingData;
+
+    #[test]
+    fn scheduled_starts_on_time() {
+        let mut status = QueueStatus::Scheduled(100);
+        assert!(!status.poll(99));
+        assert_eq!(status.countdown(99), Some(Duration::from_secs(1)));
+        assert!(status.poll(100));
+        assert_eq!(status, QueueStatus::Running);
+        assert_eq!(status.countdown(100), None);
+    }
+
+    #[test]
+    fn paused_carries_on() {
+        let mut status = QueueStatus::PausedUntil(100);
+        assert!(!status.poll(50));
+        assert_eq!(status, QueueStatus::PausedUntil(100));
+        assert!(status.poll(150));
+        assert_eq!(status, QueueStatus::Running);
+    }
+
+    #[test]
+    fn saved_round_trip() {
+        let dir = tempdir::TempDir::new("ytmdl-queue").unwrap();
+        let path = dir.path().join("queue.json");
+        assert_eq!(load_in(&path), None);
+        save_in(&path, QueueStatus::PausedUntil(100)).unwrap();
+        assert_eq!(load_in(&path), Some(QueueStatus::PausedUntil(100)));
+        fs::write(&path, "{").unwrap();
+        assert_eq!(load_in(&path), None);
+    }
+
+    #[test]
+    fn in_flight_cleared_on_panic() {
+        let id = "in_flight_cleared_on_panic";
+        in_flight().push((id.to_string(), CancellationToken::default()));
+        let result = std::panic::catch_unwind(|| {
+            let _in_flight = InFlight(id);
+            panic!("the download panicked");
+        });
+        assert!(result.is_err());
+        assert!(in_flight().iter().all(|(in_flight, _)| in_flight != id));
+    }
+
+    #[test]
+    fn reorders() {
+        let job = |id: &str, position, updated| {
+            let mut job = Job::new(&StateModifyingData::default());
+            job.id = id.to_string();
+            job.position = position;
+            job.updated = updated;
+            job
+        };
+        let ids = |jobs: &[Job]| jobs.iter().map(|job| job.id.clone()).collect::<Vec<_>>();
+
+        // older jobs without a position come first
+        let mut jobs = vec![job("c", 1, 0), job("b", 0, 20), job("a", 0, 10)];
+        sort_queued(&mut jobs);
+        assert_eq!(ids(&jobs), ["a", "b", "c"]);
+
+        assert!(reorder(&mut jobs, "c", 0));
+        assert_eq!(ids(&jobs), ["c", "a", "b"]);
+        assert_eq!(
+            jobs.iter().map(|job| job.position).collect::<Vec<_>>(),
+            [0, 1, 2]
+        );
+        assert!(reorder(&mut jobs, "c", 10));
+        assert_eq!(ids(&jobs), ["a", "b", "c"]);
+        assert!(!reorder(&mut jobs, "d", 0));
+    }
+
+    #[test]
+    fn stopped_stays_stopped() {
+        let mut status = QueueStatus::Stopped;
+        assert!(!status.poll(u64::MAX));
+        assert!(!status.is_active());
+    }
+}
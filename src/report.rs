@@ -0,0 +1,561 @@
+use crate::{
+    config::OutputFormat,
+    download::TrackError,
+    gui::view_modifying_data::{AlbumData, TrackData},
+    sources::Channel,
+    versions::LengthMismatch,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::{self, File},
+    io,
+    ops::AddAssign,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TrackInfoError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+}
+
+/// How long each stage of processing a single track took
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StageTimings {
+    /// Includes generating the file name via yt-dlp
+    pub download: Duration,
+    /// Includes the post-processing steps that change the audio, which are done while converting
+    pub convert: Duration,
+    /// The rest of the [post-processing](crate::postprocess) steps, in the order they first ran
+    pub post_process: Vec<StepTiming>,
+    pub tag: Duration,
+    pub move_to_out_dir: Duration,
+}
+
+/// How long a post-processing step took
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct StepTiming {
+    /// Its [`name`](crate::postprocess::PostProcessor::name)
+    pub step: &'static str,
+    pub took: Duration,
+}
+
+impl StageTimings {
+    #[must_use]
+    pub fn total(&self) -> Duration {
+        self.download
+            + self.convert
+            + self
+                .post_process
+                .iter()
+                .map(|step| step.took)
+                .sum::<Duration>()
+            + self.tag
+            + self.move_to_out_dir
+    }
+
+    /// Adds the time to the step's timing if it's already run (for another output), otherwise
+    /// adds it to the end
+    pub fn add_step(&mut self, timing: StepTiming) {
+        match self.post_process.iter_mut().find(|t| t.step == timing.step) {
+            Some(existing) => existing.took += timing.took,
+            None => self.post_process.push(timing),
+        }
+    }
+}
+
+impl AddAssign<&StageTimings> for StageTimings {
+    fn add_assign(&mut self, rhs: &Self) {
+        self.download += rhs.download;
+        self.convert += rhs.convert;
+        for step in &rhs.post_process {
+            self.add_step(step.clone());
+        }
+        self.tag += rhs.tag;
+        self.move_to_out_dir += rhs.move_to_out_dir;
+    }
+}
+
+impl Display for StageTimings {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "download {:.1}s, convert {:.1}s, ",
+            self.download.as_secs_f64(),
+            self.convert.as_secs_f64()
+        )?;
+        for step in &self.post_process {
+            write!(f, "{} {:.1}s, ", step.step, step.took.as_secs_f64())?;
+        }
+        write!(
+            f,
+            "tag {:.1}s, move {:.1}s",
+            self.tag.as_secs_f64(),
+            self.move_to_out_dir.as_secs_f64(),
+        )
+    }
+}
+
+/// Info about a track that was successfully downloaded
+#[derive(Debug, Clone, Serialize)]
+pub struct TrackReport {
+    /// 0-based index into the track list
+    pub index: usize,
+    pub video_id: String,
+    pub title: String,
+    pub timings: StageTimings,
+    /// Where it was saved, once per profile (missing for any that kept an existing file)
+    pub outputs: Vec<PathBuf>,
+    /// What yt-dlp downloaded, if it said
+    pub source_format: Option<SourceFormat>,
+    /// Where yt-dlp downloaded it from, if it said
+    pub channel: Option<Channel>,
+    /// The video (or its section) is much longer or shorter than the release says the track is,
+    /// so it might be a different version
+    pub length_mismatch: Option<LengthMismatch>,
+    /// It was already in every output dir (from a download that didn't finish), so it wasn't
+    /// downloaded again
+    pub already_saved: bool,
+}
+
+impl TrackReport {
+    /// For a track that was already in every output dir, so nothing was done
+    #[must_use]
+    pub fn already_saved(index: usize, video_id: String, title: String) -> Self {
+        Self {
+            index,
+            video_id,
+            title,
+            timings: StageTimings::default(),
+            outputs: Vec::new(),
+            source_format: None,
+            channel: None,
+            length_mismatch: None,
+            already_saved: true,
+        }
+    }
+}
+
+/// The format yt-dlp picked to download, from the config's
+/// [`DownloaderSettings::format`](crate::config::DownloaderSettings::format)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SourceFormat {
+    /// Like "251", or "137+251" if video and audio were downloaded separately
+    pub id: String,
+    /// Like "opus"
+    pub codec: Option<String>,
+    /// Average audio bitrate
+    pub kbps: Option<u32>,
+}
+
+impl SourceFormat {
+    /// yt-dlp `--print` template for [`SourceFormat::parse`]
+    pub const TEMPLATE: &'static str = "%(format_id)s\t%(acodec)s\t%(abr)s";
+
+    /// What's printed for yt-dlp's `--print` [`SourceFormat::TEMPLATE`], which has "NA" for
+    /// anything it doesn't know
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::report::SourceFormat;
+    ///
+    /// let format = SourceFormat::parse("251\topus\t129.478").unwrap();
+    /// assert_eq!(format.to_string(), "251 (opus, 129 kbps)");
+    /// assert_eq!(SourceFormat::parse("18\tNA\tNA").unwrap().to_string(), "18");
+    /// assert_eq!(SourceFormat::parse("NA\tNA\tNA"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(printed: &str) -> Option<Self> {
+        let mut fields = printed
+            .trim()
+            .split('\t')
+            .map(|field| Some(field.trim()).filter(|f| !f.is_empty() && *f != "NA"));
+        let id = fields.next()??.to_string();
+        let codec = fields.next().flatten().map(str::to_string);
+        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+        let kbps = fields
+            .next()
+            .flatten()
+            .and_then(|abr| abr.parse::<f64>().ok())
+            .filter(|abr| abr.is_finite() && *abr > 0.0)
+            .map(|abr| abr.round() as u32);
+        Some(Self { id, codec, kbps })
+    }
+}
+
+impl Display for SourceFormat {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.id)?;
+        match (&self.codec, self.kbps) {
+            (Some(codec), Some(kbps)) => write!(f, " ({codec}, {kbps} kbps)"),
+            (Some(codec), None) => write!(f, " ({codec})"),
+            (None, Some(kbps)) => write!(f, " ({kbps} kbps)"),
+            (None, None) => Ok(()),
+        }
+    }
+}
+
+/// Summary of a finished [`crate::download_album`] call, or of one where some tracks failed (in
+/// [`DownloadError::TracksFailed`](crate::DownloadError::TracksFailed))
+#[derive(Debug, Default, Serialize)]
+pub struct DownloadReport {
+    /// When the download started, from the [`SystemClock`](crate::clock::SystemClock)
+    pub started: DateTime<Utc>,
+    /// Wall-clock time for the whole album
+    pub elapsed: Duration,
+    /// The tracks that were saved
+    pub tracks: Vec<TrackReport>,
+    /// The tracks that weren't, with which step they got to
+    pub failed: Vec<TrackError>,
+}
+
+/// How a single track in a [`DownloadReport`] went
+#[derive(Debug, Clone, Copy)]
+pub enum TrackOutcome<'a> {
+    Saved(&'a TrackReport),
+    Failed(&'a TrackError),
+}
+
+impl TrackOutcome<'_> {
+    /// 0-based index into the track list
+    #[must_use]
+    pub fn index(&self) -> usize {
+        match self {
+            Self::Saved(track) => track.index,
+            Self::Failed(err) => err.index,
+        }
+    }
+}
+
+impl DownloadReport {
+    /// Timings of every track added together.
+    ///
+    /// Since tracks are processed in parallel this will be more than [`DownloadReport::elapsed`],
+    /// but it's useful for seeing which stage dominates.
+    #[must_use]
+    pub fn total_timings(&self) -> StageTimings {
+        let mut total = StageTimings::default();
+        for track in &self.tracks {
+            total += &track.timings;
+        }
+        total
+    }
+
+    /// Every track that was attempted, saved or not, in track list order
+    #[must_use]
+    pub fn outcomes(&self) -> Vec<TrackOutcome<'_>> {
+        let mut outcomes: Vec<TrackOutcome> = self
+            .tracks
+            .iter()
+            .map(TrackOutcome::Saved)
+            .chain(self.failed.iter().map(TrackOutcome::Failed))
+            .collect();
+        outcomes.sort_by_key(TrackOutcome::index);
+        outcomes
+    }
+
+    /// The saved tracks whose videos might be a different version, in track list order
+    #[must_use]
+    pub fn length_mismatches(&self) -> Vec<(&TrackReport, LengthMismatch)> {
+        let mut mismatches: Vec<_> = self
+            .tracks
+            .iter()
+            .filter_map(|track| Some((track, track.length_mismatch?)))
+            .collect();
+        mismatches.sort_by_key(|(track, _)| track.index);
+        mismatches
+    }
+}
+
+/// How a track was encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EncodeSettings {
+    pub format: OutputFormat,
+    /// In kbps, `None` if it was left up to ffmpeg
+    pub bitrate: Option<u32>,
+    /// LAME's VBR level (see [`Profile::vbr_quality`](crate::config::Profile::vbr_quality))
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vbr_quality: Option<u8>,
+}
+
+impl EncodeSettings {
+    /// The ffmpeg arguments for the bitrate or VBR level, which are empty if it's left up to
+    /// ffmpeg
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::{config::OutputFormat, report::EncodeSettings};
+    ///
+    /// let mut settings = EncodeSettings {
+    ///     format: OutputFormat::Mp3,
+    ///     bitrate: Some(192),
+    ///     vbr_quality: None,
+    /// };
+    /// assert_eq!(settings.ffmpeg_args(), ["-b:a", "192k"]);
+    /// settings.vbr_quality = Some(2);
+    /// assert_eq!(settings.ffmpeg_args(), ["-q:a", "2"]);
+    /// ```
+    #[must_use]
+    pub fn ffmpeg_args(&self) -> Vec<String> {
+        match (self.vbr_quality, self.bitrate) {
+            (Some(quality), _) => vec![String::from("-q:a"), quality.to_string()],
+            (None, Some(bitrate)) => vec![String::from("-b:a"), format!("{bitrate}k")],
+            (None, None) => Vec::new(),
+        }
+    }
+}
+
+/// Everything about where a track came from and how it was made, saved next to it as
+/// `<name>.info.json` (like yt-dlp's) when the profile has
+/// [`info_json`](crate::config::Profile::info_json) on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackInfo {
+    /// What yt-dlp downloaded the track from
+    pub source_url: String,
+    /// The playlist or videos the album was downloaded from
+    pub youtube_url: String,
+    pub album: AlbumData,
+    pub track: TrackData,
+    /// 1-based
+    pub track_number: usize,
+    pub tracks: usize,
+    pub encode: EncodeSettings,
+    pub downloaded: DateTime<Utc>,
+    /// Of the audio file, in lowercase hex
+    pub sha256: String,
+    /// Of the audio file, in bytes
+    pub size: u64,
+    pub ytmdl_version: String,
+}
+
+impl TrackInfo {
+    /// The sidecar's path for an audio file, e.g. `Song.info.json` for `Song.mp3`
+    ///
+    /// # Examples
+    /// ```
+    /// use std::path::Path;
+    /// use ytmdl::report::TrackInfo;
+    ///
+    /// assert_eq!(
+    ///     TrackInfo::path_for(Path::new("/music/Artist - Song.mp3")),
+    ///     Path::new("/music/Artist - Song.info.json"),
+    /// );
+    /// ```
+    #[must_use]
+    pub fn path_for(audio: &Path) -> PathBuf {
+        audio.with_extension("info.json")
+    }
+
+    /// Writes it next to `audio`
+    ///
+    /// # Errors
+    /// If it can't be serialized or written
+    pub fn write(&self, audio: &Path) -> Result<(), TrackInfoError> {
+        fs::write(Self::path_for(audio), serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// Reads the sidecar next to `audio`
+    ///
+    /// # Errors
+    /// If there isn't one, or it can't be parsed
+    pub fn read(audio: &Path) -> Result<Self, TrackInfoError> {
+        Ok(serde_json::from_str(&fs::read_to_string(Self::path_for(
+            audio,
+        ))?)?)
+    }
+
+    /// Whether `audio` is still the same file it was when the sidecar was written, going by its
+    /// size and checksum
+    ///
+    /// # Errors
+    /// If `audio` can't be read
+    pub fn verify(&self, audio: &Path) -> Result<bool, TrackInfoError> {
+        if fs::metadata(audio)?.len() != self.size {
+            return Ok(false);
+        }
+        Ok(sha256_file(audio)? == self.sha256)
+    }
+}
+
+/// The tracks in `dir` that have a [`TrackInfo`] sidecar next to them, sorted by name
+///
+/// # Errors
+/// If `dir` can't be read
+pub fn find_tracks_with_info(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut tracks = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_sidecar = path.to_string_lossy().ends_with(".info.json");
+        if path.is_file() && !is_sidecar && TrackInfo::path_for(&path).is_file() {
+            tracks.push(path);
+        }
+    }
+    tracks.sort();
+    Ok(tracks)
+}
+
+/// The SHA-256 of a file in lowercase hex
+///
+/// # Errors
+/// If the file can't be read
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut hasher = Sha256::new();
+    io::copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn total_timings() {
+        let timings = |secs| StageTimings {
+            download: Duration::from_secs(secs),
+            convert: Duration::from_secs(secs * 2),
+            post_process: (secs > 1)
+                .then(|| StepTiming {
+                    step: "replay gain",
+                    took: Duration::from_millis(500),
+                })
+                .into_iter()
+                .collect(),
+            tag: Duration::from_millis(10),
+            move_to_out_dir: Duration::from_millis(5),
+        };
+        let report = DownloadReport {
+            started: DateTime::default(),
+            elapsed: Duration::from_secs(10),
+            tracks: (1..=3)
+                .map(|i| TrackReport {
+                    index: usize::try_from(i).unwrap() - 1,
+                    video_id: String::new(),
+                    title: String::new(),
+                    timings: timings(i),
+                    outputs: Vec::new(),
+                    source_format: None,
+                    channel: None,
+                    length_mismatch: (i == 2).then_some(LengthMismatch {
+                        track: Duration::from_secs(200),
+                        video: Duration::from_secs(600),
+                    }),
+                    already_saved: false,
+                })
+                .collect(),
+            failed: Vec::new(),
+        };
+
+        let total = report.total_timings();
+        assert_eq!(total.download, Duration::from_secs(6));
+        assert_eq!(total.convert, Duration::from_secs(12));
+        assert_eq!(total.tag, Duration::from_millis(30));
+        assert_eq!(total.move_to_out_dir, Duration::from_millis(15));
+        assert_eq!(total.post_process[0].took, Duration::from_secs(1));
+        assert_eq!(total.total(), Duration::from_millis(19_045));
+        assert_eq!(
+            total.to_string(),
+            "download 6.0s, convert 12.0s, replay gain 1.0s, tag 0.0s, move 0.0s"
+        );
+
+        let mismatches = report.length_mismatches();
+        assert_eq!(mismatches.len(), 1);
+        assert_eq!(mismatches[0].0.index, 1);
+        assert_eq!(
+            mismatches[0].1.to_string(),
+            "the video is 10:00, but the track is 3:20"
+        );
+    }
+
+    #[test]
+    fn outcomes_in_order() {
+        let report = DownloadReport {
+            tracks: vec![
+                TrackReport::already_saved(2, String::new(), String::from("Three")),
+                TrackReport::already_saved(0, String::new(), String::from("One")),
+            ],
+            failed: vec![TrackError {
+                index: 1,
+                video_id: String::from("abc"),
+                title: String::from("Two"),
+                stage: crate::TrackStage::Convert,
+                source: Box::new(crate::DownloadError::Timeout(String::from("ffmpeg"))),
+            }],
+            ..DownloadReport::default()
+        };
+        let outcomes = report.outcomes();
+        let indices: Vec<usize> = outcomes.iter().map(TrackOutcome::index).collect();
+        assert_eq!(indices, [0, 1, 2]);
+        assert!(matches!(outcomes[1], TrackOutcome::Failed(err) if err.title == "Two"));
+        assert_eq!(
+            crate::DownloadError::TracksFailed(Box::new(report)).to_string(),
+            r#"track 2 ("Two", id "abc") failed while converting: ffmpeg timed out"#
+        );
+    }
+
+    #[test]
+    fn track_info_round_trip() {
+        let dir = tempdir::TempDir::new("ytmdl-track-info").unwrap();
+        let audio = dir.path().join("Song.mp3");
+        fs::write(&audio, b"abc").unwrap();
+
+        let info = TrackInfo {
+            source_url: String::from("https://youtu.be/dQw4w9WgXcQ"),
+            youtube_url: String::from("https://www.youtube.com/playlist?list=OLAK5uy_abc"),
+            album: AlbumData::default(),
+            track: TrackData::new("Song"),
+            track_number: 1,
+            tracks: 1,
+            encode: EncodeSettings {
+                format: OutputFormat::Mp3,
+                bitrate: Some(320),
+                vbr_quality: None,
+            },
+            downloaded: DateTime::default(),
+            sha256: sha256_file(&audio).unwrap(),
+            size: 3,
+            ytmdl_version: String::new(),
+        };
+        assert_eq!(
+            info.sha256,
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        info.write(&audio).unwrap();
+
+        let read = TrackInfo::read(&audio).unwrap();
+        assert_eq!(read.track.name, "Song");
+        assert_eq!(read.encode, info.encode);
+        assert!(read.verify(&audio).unwrap());
+
+        fs::write(&audio, b"abd").unwrap();
+        assert!(!read.verify(&audio).unwrap());
+
+        fs::write(dir.path().join("No sidecar.mp3"), b"").unwrap();
+        fs::write(dir.path().join("cover.jpg"), b"").unwrap();
+        assert_eq!(find_tracks_with_info(dir.path()).unwrap(), [audio]);
+    }
+
+    #[test]
+    fn vbr_encode_settings() {
+        let vbr = EncodeSettings {
+            format: OutputFormat::Mp3,
+            bitrate: None,
+            vbr_quality: Some(0),
+        };
+        let json = serde_json::to_string(&vbr).unwrap();
+        assert_eq!(serde_json::from_str::<EncodeSettings>(&json).unwrap(), vbr);
+
+        // sidecars from before VBR don't have it, and constant bitrates still leave it out
+        let cbr: EncodeSettings =
+            serde_json::from_str(r#"{"format":"mp3","bitrate":320}"#).unwrap();
+        assert_eq!(cbr.vbr_quality, None);
+        assert!(!serde_json::to_string(&cbr).unwrap().contains("vbr_quality"));
+    }
+}
@@ -0,0 +1,366 @@
+//! Scraping an album's release again after it's changed (like added credits or fixed titles),
+//! offering just the fields that changed instead of overwriting what's been edited since
+
+use crate::{
+    gui::view_modifying_data::{ImageStatus, StateModifyingData, TrackData},
+    scraping::{scrape_metadata, AlbumMetadata, Confidence, MetadataError},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fmt::{self, Display, Formatter};
+
+/// The fields of a scrape that are compared with the next one
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapedAlbum {
+    pub name: String,
+    pub artist: String,
+    /// Joined like the genre field, e.g. "Rock; Pop"
+    pub genre: String,
+    pub year: Option<i32>,
+    pub image: Option<String>,
+    pub tracks: Vec<ScrapedTrack>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScrapedTrack {
+    pub title: String,
+    pub artist: Option<String>,
+}
+
+impl From<&AlbumMetadata> for ScrapedAlbum {
+    fn from(album: &AlbumMetadata) -> Self {
+        Self {
+            name: album.title.clone(),
+            artist: album.artist.clone(),
+            genre: album.genres.join("; "),
+            year: album.year(),
+            image: album.image.clone(),
+            tracks: album
+                .tracks
+                .iter()
+                .map(|track| ScrapedTrack {
+                    title: track.title.clone(),
+                    artist: track.artist.clone(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl ScrapedAlbum {
+    /// SHA-256 of the fields as hex, so an unchanged release can be spotted without comparing them
+    #[must_use]
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha256::new();
+        // there's nothing in it that can't be serialized
+        hasher.update(serde_json::to_vec(self).unwrap_or_default());
+        format!("{:x}", hasher.finalize())
+    }
+}
+
+/// The last scrape of an album's release, kept with the album (and its job) until it's scraped
+/// again
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredScrape {
+    /// What was scraped, as given to [`scrape_metadata`]
+    pub url: String,
+    /// [`ScrapedAlbum::hash`] of [`StoredScrape::album`]
+    pub hash: String,
+    pub album: ScrapedAlbum,
+}
+
+impl StoredScrape {
+    #[must_use]
+    pub fn new(url: &str, album: &AlbumMetadata) -> Self {
+        let album = ScrapedAlbum::from(album);
+        Self {
+            url: url.trim().to_string(),
+            hash: album.hash(),
+            album,
+        }
+    }
+
+    /// Scrapes [`StoredScrape::url`] again
+    ///
+    /// # Errors
+    /// If it can't be scraped any more
+    pub fn rescrape(&self) -> Result<Self, MetadataError> {
+        scrape_metadata(&self.url).map(|album| Self::new(&self.url, &album))
+    }
+
+    /// What's different in `newer`, which is nothing if they have the same hash
+    #[must_use]
+    pub fn changes(&self, newer: &Self) -> Vec<Change> {
+        if self.hash == newer.hash {
+            return Vec::new();
+        }
+        diff(&self.album, &newer.album)
+    }
+}
+
+/// Which field a [`Change`] is to. Tracks are 0-based.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    AlbumName,
+    Artist,
+    Genre,
+    Year,
+    Image,
+    TrackName(usize),
+    TrackArtist(usize),
+    /// A track the release didn't have before, which is added if the change is accepted
+    NewTrack(usize),
+    /// A track the release doesn't have any more, which is skipped if the change is accepted
+    RemovedTrack(usize),
+}
+
+impl Display for Field {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::AlbumName => f.write_str("Album name"),
+            Self::Artist => f.write_str("Artist"),
+            Self::Genre => f.write_str("Genre"),
+            Self::Year => f.write_str("Year"),
+            Self::Image => f.write_str("Album cover"),
+            Self::TrackName(i) => write!(f, "Track {}", i + 1),
+            Self::TrackArtist(i) => write!(f, "Track {}'s artist", i + 1),
+            Self::NewTrack(i) => write!(f, "New track {}", i + 1),
+            Self::RemovedTrack(i) => write!(f, "Track {} (removed)", i + 1),
+        }
+    }
+}
+
+/// A field that's different on the release since it was last scraped
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub field: Field,
+    /// As it was last scraped
+    pub old: String,
+    pub new: String,
+}
+
+impl Change {
+    /// The field as it is now, which may have been edited since it was scraped
+    #[must_use]
+    pub fn current(&self, state: &StateModifyingData) -> String {
+        let album = &state.album_data;
+        let track = |i: usize| state.track_data.get(i);
+        match self.field {
+            Field::AlbumName => album.name.clone(),
+            Field::Artist => album.artist.clone(),
+            Field::Genre => album.genre.clone(),
+            Field::Year => album.year.to_string(),
+            Field::Image => album.image.clone(),
+            Field::TrackName(i) | Field::NewTrack(i) | Field::RemovedTrack(i) => {
+                track(i).map(|track| track.name.clone()).unwrap_or_default()
+            }
+            Field::TrackArtist(i) => track(i)
+                .and_then(|track| track.artist.clone())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Changes the field to the new value
+    pub fn apply(&self, state: &mut StateModifyingData) {
+        let new = self.new.clone();
+        let confidence = &mut state.confidence;
+        match self.field {
+            Field::AlbumName => {
+                state.album_data.name = new;
+                confidence.title = Confidence::Parsed;
+            }
+            Field::Artist => {
+                state.album_data.artist = new;
+                confidence.artist = Confidence::Parsed;
+            }
+            Field::Genre => {
+                state.album_data.genre = new;
+                confidence.genres = Confidence::Parsed;
+            }
+            Field::Year => {
+                if let Ok(year) = new.parse() {
+                    state.album_data.year = year;
+                    confidence.year = Confidence::Parsed;
+                }
+            }
+            Field::Image => {
+                state.album_data.image = new;
+                state.image_status = ImageStatus::Unchecked;
+                state.upscale_preview = None;
+                confidence.image = Confidence::Parsed;
+            }
+            Field::TrackName(i) | Field::NewTrack(i) => {
+                if i >= state.track_data.len() {
+                    state.track_data.resize_with(i + 1, TrackData::default);
+                }
+                let track = &mut state.track_data[i];
+                track.name = new;
+                track.confidence = Confidence::Parsed;
+            }
+            Field::TrackArtist(i) => {
+                if let Some(track) = state.track_data.get_mut(i) {
+                    track.artist = Some(new).filter(|artist| !artist.is_empty());
+                }
+            }
+            Field::RemovedTrack(i) => {
+                if let Some(track) = state.track_data.get_mut(i) {
+                    track.skip = true;
+                }
+            }
+        }
+    }
+}
+
+/// Every field that's different in `new`. A year that's gone isn't a change, since there's
+/// always a year.
+#[must_use]
+pub fn diff(old: &ScrapedAlbum, new: &ScrapedAlbum) -> Vec<Change> {
+    let change = |field: Field, old: &str, new: &str| {
+        (old != new).then(|| Change {
+            field,
+            old: old.to_string(),
+            new: new.to_string(),
+        })
+    };
+    let year = |year: Option<i32>| year.map(|year| year.to_string()).unwrap_or_default();
+
+    let mut changes: Vec<Change> = [
+        change(Field::AlbumName, &old.name, &new.name),
+        change(Field::Artist, &old.artist, &new.artist),
+        change(Field::Genre, &old.genre, &new.genre),
+        new.year
+            .and_then(|_| change(Field::Year, &year(old.year), &year(new.year))),
+        change(
+            Field::Image,
+            old.image.as_deref().unwrap_or_default(),
+            new.image.as_deref().unwrap_or_default(),
+        ),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    for i in 0..old.tracks.len().max(new.tracks.len()) {
+        match (old.tracks.get(i), new.tracks.get(i)) {
+            (Some(old), Some(new)) => changes.extend(
+                [
+                    change(Field::TrackName(i), &old.title, &new.title),
+                    change(
+                        Field::TrackArtist(i),
+                        old.artist.as_deref().unwrap_or_default(),
+                        new.artist.as_deref().unwrap_or_default(),
+                    ),
+                ]
+                .into_iter()
+                .flatten(),
+            ),
+            (None, Some(new)) => changes.extend(change(Field::NewTrack(i), "", &new.title)),
+            (Some(old), None) => changes.push(Change {
+                field: Field::RemovedTrack(i),
+                old: old.title.clone(),
+                new: String::new(),
+            }),
+            (None, None) => {}
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gui::view_modifying_data::AlbumData;
+
+    fn track(title: &str) -> ScrapedTrack {
+        ScrapedTrack {
+            title: title.to_string(),
+            artist: None,
+        }
+    }
+
+    fn album() -> ScrapedAlbum {
+        ScrapedAlbum {
+            name: String::from("Album"),
+            artist: String::from("Band"),
+            genre: String::from("Rock"),
+            year: Some(2021),
+            image: None,
+            tracks: vec![track("One"), track("Two"), track("Three")],
+        }
+    }
+
+    #[test]
+    fn changed_fields() {
+        let old = album();
+        let mut new = album();
+        new.name = String::from("Album (Deluxe)");
+        new.year = None;
+        new.tracks[0].artist = Some(String::from("Band feat. Guest"));
+        new.tracks[1].title = String::from("Two (Remastered)");
+        new.tracks.push(track("Four"));
+
+        let changes = diff(&old, &new);
+        let fields: Vec<Field> = changes.iter().map(|change| change.field).collect();
+        assert_eq!(
+            fields,
+            [
+                Field::AlbumName,
+                Field::TrackArtist(0),
+                Field::TrackName(1),
+                Field::NewTrack(3)
+            ]
+        );
+        assert_eq!(changes[2].old, "Two");
+        assert_eq!(changes[2].new, "Two (Remastered)");
+
+        new.tracks.truncate(2);
+        assert_eq!(
+            diff(&old, &new).last().map(|change| change.field),
+            Some(Field::RemovedTrack(2))
+        );
+    }
+
+    #[test]
+    fn same_hash_no_changes() {
+        let stored = StoredScrape {
+            url: String::from("https://www.discogs.com/release/1"),
+            hash: album().hash(),
+            album: album(),
+        };
+        assert_eq!(stored.hash.len(), 64);
+        assert!(stored.changes(&stored.clone()).is_empty());
+
+        let mut newer = stored.clone();
+        newer.album.genre = String::from("Rock; Pop");
+        newer.hash = newer.album.hash();
+        assert_ne!(newer.hash, stored.hash);
+        assert_eq!(stored.changes(&newer).len(), 1);
+    }
+
+    #[test]
+    fn keeps_other_edits() {
+        let mut state = StateModifyingData {
+            album_data: AlbumData {
+                name: String::from("My Album Name"),
+                genre: String::from("Edited"),
+                ..AlbumData::default()
+            },
+            track_data: vec![TrackData::new("One"), TrackData::new("Two")],
+            ..StateModifyingData::default()
+        };
+        let mut new = album();
+        new.name = String::from("Album (Deluxe)");
+        new.tracks[1].title = String::from("Two (Remastered)");
+        new.tracks[2].title = String::from("Three (Live)");
+        let changes = diff(&album(), &new);
+        assert_eq!(changes[0].current(&state), "My Album Name");
+
+        for change in &changes[1..] {
+            change.apply(&mut state);
+        }
+        assert_eq!(state.album_data.name, "My Album Name");
+        assert_eq!(state.album_data.genre, "Edited");
+        let names: Vec<&str> = state.track_data.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, ["One", "Two (Remastered)", "Three (Live)"]);
+    }
+}
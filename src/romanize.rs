@@ -0,0 +1,313 @@
+//! Romanizing Korean and Japanese titles, so file names can be typed and sorted on anything while
+//! the tags keep the original script (or both are romanized, if that's what the config says).
+//!
+//! Korean is romanized syllable by syllable with the Revised Romanization, and Japanese kana with
+//! Hepburn. Kanji can't be worked out without a dictionary, so for those a command like kakasi
+//! can be set instead.
+
+use serde::{Deserialize, Serialize};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+/// Set under `[romanize]` in the config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RomanizeSettings {
+    /// Romanize the artist, album and title in file names
+    pub file_names: bool,
+    /// Romanize the artist, album and title tags
+    pub tags: bool,
+    /// Run to romanize instead of the built-in romanization, with the text on its stdin, like
+    /// `kakasi -i utf8 -o utf8 -Ja -Ha -Ka -s`
+    pub command: Option<String>,
+}
+
+impl RomanizeSettings {
+    /// `text` as it goes in a file name, romanized if [`RomanizeSettings::file_names`] is set
+    #[must_use]
+    pub fn for_file_name(&self, text: &str) -> String {
+        if self.file_names {
+            romanize(text, self)
+        } else {
+            text.to_string()
+        }
+    }
+
+    /// `text` as it goes in a tag, romanized if [`RomanizeSettings::tags`] is set
+    #[must_use]
+    pub fn for_tag(&self, text: &str) -> String {
+        if self.tags {
+            romanize(text, self)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+/// Hepburn for each hiragana from ぁ (U+3041) to ゖ (U+3096). Katakana are the same, 0x60 higher.
+const KANA: [&str; 86] = [
+    "a", "a", "i", "i", "u", "u", "e", "e", "o", "o", "ka", "ga", "ki", "gi", "ku", "gu", "ke",
+    "ge", "ko", "go", "sa", "za", "shi", "ji", "su", "zu", "se", "ze", "so", "zo", "ta", "da",
+    "chi", "ji", "", "tsu", "zu", "te", "de", "to", "do", "na", "ni", "nu", "ne", "no", "ha", "ba",
+    "pa", "hi", "bi", "pi", "fu", "bu", "pu", "he", "be", "pe", "ho", "bo", "po", "ma", "mi", "mu",
+    "me", "mo", "ya", "ya", "yu", "yu", "yo", "yo", "ra", "ri", "ru", "re", "ro", "wa", "wa", "wi",
+    "we", "o", "n", "vu", "ka", "ke",
+];
+const SMALL_KANA: &[char] = &['ぁ', 'ぃ', 'ぅ', 'ぇ', 'ぉ', 'ゃ', 'ゅ', 'ょ', 'ゎ'];
+const SMALL_TSU: char = 'っ';
+const LONG_VOWEL: char = 'ー';
+
+const HANGUL_INITIALS: [&str; 19] = [
+    "g", "kk", "n", "d", "tt", "r", "m", "b", "pp", "s", "ss", "", "j", "jj", "ch", "k", "t", "p",
+    "h",
+];
+const HANGUL_MEDIALS: [&str; 21] = [
+    "a", "ae", "ya", "yae", "eo", "e", "yeo", "ye", "o", "wa", "wae", "oe", "yo", "u", "wo", "we",
+    "wi", "yu", "eu", "ui", "i",
+];
+const HANGUL_FINALS: [&str; 28] = [
+    "", "k", "k", "k", "n", "n", "n", "t", "l", "k", "m", "l", "l", "l", "p", "l", "m", "p", "p",
+    "t", "t", "ng", "t", "t", "k", "t", "p", "t",
+];
+/// The finals as they're said when the next syllable starts with a vowel, like 한국어 "hangugeo"
+const HANGUL_CARRIED_FINALS: [&str; 28] = [
+    "", "g", "kk", "gs", "n", "nj", "n", "d", "r", "lg", "lm", "lb", "ls", "lt", "lp", "r", "m",
+    "b", "bs", "s", "ss", "ng", "j", "ch", "k", "t", "p", "",
+];
+/// ㅇ, which is silent at the start of a syllable
+const SILENT_INITIAL: usize = 11;
+/// ㄹ as an initial and as a final
+const RIEUL: (usize, usize) = (5, 8);
+
+/// Whether `text` has any letters that aren't Latin
+#[must_use]
+pub fn needs_romanizing(text: &str) -> bool {
+    text.chars().any(|c| c.is_alphabetic() && c > '\u{24F}')
+}
+
+/// `text` romanized with the settings' command, or [`transliterate`] if there isn't one or it
+/// fails. A title that already has a romanized version, like "사랑해 = Saranghae", uses that.
+#[must_use]
+pub fn romanize(text: &str, settings: &RomanizeSettings) -> String {
+    if !needs_romanizing(text) {
+        return text.to_string();
+    }
+    if let Some(alternate) = latin_alternate(text) {
+        return alternate.to_string();
+    }
+    if let Some(command) = settings.command.as_deref().filter(|c| !c.trim().is_empty()) {
+        if let Some(romanized) = run_command(command, text) {
+            return romanized;
+        }
+        log::warn!(r#"couldn't romanize "{text}" with `{command}`"#);
+    }
+    transliterate(text)
+}
+
+/// Pipes `text` through `command` (split on spaces), returning what it prints
+fn run_command(command: &str, text: &str) -> Option<String> {
+    let mut args = command.split_whitespace();
+    let mut child = Command::new(args.next()?)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .ok()?;
+    child.stdin.take()?.write_all(text.as_bytes()).ok()?;
+    let output = child.wait_with_output().ok()?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return None;
+    }
+    let romanized = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    (!romanized.is_empty()).then_some(romanized)
+}
+
+/// The Latin part of a title written in two scripts separated by " = ", which is how Discogs
+/// gives translations and romanizations
+///
+/// # Examples
+/// ```
+/// use ytmdl::romanize::latin_alternate;
+///
+/// assert_eq!(latin_alternate("사랑해 = Saranghae"), Some("Saranghae"));
+/// assert_eq!(latin_alternate("Koi = 恋"), Some("Koi"));
+/// assert_eq!(latin_alternate("사랑해"), None);
+/// assert_eq!(latin_alternate("1 + 1 = 2"), None);
+/// ```
+#[must_use]
+pub fn latin_alternate(title: &str) -> Option<&str> {
+    let parts: Vec<&str> = title.split(" = ").map(str::trim).collect();
+    if !parts.iter().any(|part| needs_romanizing(part)) {
+        return None;
+    }
+    parts
+        .into_iter()
+        .find(|part| part.chars().any(char::is_alphabetic) && !needs_romanizing(part))
+}
+
+/// Romanizes the Korean and Japanese kana in `text`, leaving everything else as it is. Each word
+/// that's romanized starts with a capital.
+///
+/// # Examples
+/// ```
+/// use ytmdl::romanize::transliterate;
+///
+/// assert_eq!(transliterate("사랑해"), "Saranghae");
+/// assert_eq!(transliterate("한국어 (Remix)"), "Hangugeo (Remix)");
+/// assert_eq!(transliterate("さくらんぼ"), "Sakuranbo");
+/// assert_eq!(transliterate("マッチ"), "Matchi");
+/// ```
+#[must_use]
+pub fn transliterate(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let mut romanized = String::with_capacity(text.len());
+    let mut previous_final = None;
+    let mut double_next = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let word_start = i == 0 || !chars[i - 1].is_alphanumeric();
+        let (syllable, consumed) = if let Some((initial, medial, last)) = hangul(chars[i]) {
+            let next_initial = chars.get(i + 1).and_then(|c| hangul(*c)).map(|s| s.0);
+            let initial = if initial == RIEUL.0 && previous_final == Some(RIEUL.1) {
+                "l"
+            } else {
+                HANGUL_INITIALS[initial]
+            };
+            previous_final = Some(last);
+            let last = if next_initial == Some(SILENT_INITIAL) {
+                HANGUL_CARRIED_FINALS[last]
+            } else {
+                HANGUL_FINALS[last]
+            };
+            (format!("{initial}{}{last}", HANGUL_MEDIALS[medial]), 1)
+        } else {
+            previous_final = None;
+            let c = to_hiragana(chars[i]);
+            if c == SMALL_TSU {
+                double_next = true;
+                i += 1;
+                continue;
+            }
+            if c == LONG_VOWEL {
+                if let Some(vowel) = romanized.chars().last().filter(|c| "aeiou".contains(*c)) {
+                    romanized.push(vowel);
+                }
+                i += 1;
+                continue;
+            }
+            let Some(syllable) = kana_syllable(&chars[i..]) else {
+                romanized.push(chars[i]);
+                i += 1;
+                continue;
+            };
+            syllable
+        };
+
+        let mut syllable = syllable;
+        if std::mem::take(&mut double_next) {
+            if syllable.starts_with("ch") {
+                syllable.insert(0, 't');
+            } else if let Some(consonant) =
+                syllable.chars().next().filter(|c| !"aeiou".contains(*c))
+            {
+                syllable.insert(0, consonant);
+            }
+        }
+        if word_start {
+            let mut letters = syllable.chars();
+            if let Some(first) = letters.next() {
+                syllable = first.to_uppercase().chain(letters).collect();
+            }
+        }
+        romanized.push_str(&syllable);
+        i += consumed;
+    }
+    romanized
+}
+
+/// The initial, medial and final of a Hangul syllable
+fn hangul(c: char) -> Option<(usize, usize, usize)> {
+    let index = (c as usize).checked_sub(0xAC00).filter(|i| *i < 11_172)?;
+    Some((index / 588, index % 588 / 28, index % 28))
+}
+
+/// Katakana as the matching hiragana, and anything else as it is
+fn to_hiragana(c: char) -> char {
+    match c {
+        'ァ'..='ヶ' => char::from_u32(c as u32 - 0x60).unwrap_or(c),
+        _ => c,
+    }
+}
+
+/// The kana at the start of `chars` and the small kana after it (like きゃ "kya"), and how many
+/// chars that was
+fn kana_syllable(chars: &[char]) -> Option<(String, usize)> {
+    let kana = |c: char| {
+        let index = (to_hiragana(c) as usize).checked_sub(0x3041)?;
+        KANA.get(index).copied()
+    };
+    let base = kana(*chars.first()?)?;
+    let small = chars
+        .get(1)
+        .filter(|c| SMALL_KANA.contains(&to_hiragana(**c)))
+        .and_then(|c| kana(*c));
+    match small {
+        // きゃ kya, but しゃ sha rather than shya
+        Some(small @ ("ya" | "yu" | "yo")) if base.len() > 1 && base.ends_with('i') => {
+            let stem = &base[..base.len() - 1];
+            let combined = if matches!(stem, "sh" | "ch" | "j") {
+                format!("{stem}{}", &small[1..])
+            } else {
+                format!("{stem}{small}")
+            };
+            Some((combined, 2))
+        }
+        // ファ fa, ティ ti
+        Some(small @ ("a" | "i" | "u" | "e" | "o")) if base.len() > 1 => {
+            Some((format!("{}{small}", &base[..base.len() - 1]), 2))
+        }
+        _ => Some((base.to_string(), 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn korean() {
+        assert_eq!(transliterate("방탄소년단"), "Bangtansonyeondan");
+        assert_eq!(transliterate("빨간 맛"), "Ppalgan Mat");
+        // ㄹㄹ is "ll", and ㅎ before a vowel is silent
+        assert_eq!(transliterate("별로 좋아"), "Byeollo Joa");
+    }
+
+    #[test]
+    fn japanese() {
+        assert_eq!(transliterate("きょうしゃ"), "Kyousha");
+        assert_eq!(transliterate("ラーメン"), "Raamen");
+        assert_eq!(transliterate("ファン"), "Fan");
+        assert_eq!(transliterate("がっこう"), "Gakkou");
+        // kanji are left for a command
+        assert_eq!(transliterate("恋する"), "恋suru");
+    }
+
+    #[test]
+    fn only_when_needed() {
+        let settings = RomanizeSettings::default();
+        assert_eq!(romanize("Café Señor", &settings), "Café Señor");
+        assert_eq!(romanize("봄날 = Spring Day", &settings), "Spring Day");
+        assert_eq!(romanize("봄날", &settings), "Bomnal");
+
+        // a command that doesn't exist falls back to the built-in one
+        let settings = RomanizeSettings {
+            command: Some(String::from("ytmdl-no-such-romanizer")),
+            ..RomanizeSettings::default()
+        };
+        assert_eq!(romanize("봄날", &settings), "Bomnal");
+    }
+}
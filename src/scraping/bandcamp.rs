@@ -0,0 +1,164 @@
+//! Album data from a Bandcamp album page, from the `data-tralbum` JSON it's built from
+
+use super::{AlbumMetadata, FieldConfidence, MetadataSource, TrackMetadata};
+use crate::{network::download, parsing::parse_release_date, utils::selectors::TRALBUM};
+use scraper::Html;
+use serde::Deserialize;
+use std::time::Duration;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum BandcampError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("couldn't find the album data (`data-tralbum`) on the page")]
+    MissingTralbum,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Current {
+    title: String,
+    release_date: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct TrackInfo {
+    title: String,
+    artist: Option<String>,
+    /// In seconds, 0 if the track can't be played
+    duration: Option<f64>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Tralbum {
+    artist: String,
+    current: Current,
+    album_release_date: Option<String>,
+    art_id: Option<u64>,
+    #[serde(default)]
+    trackinfo: Vec<TrackInfo>,
+}
+
+/// Bandcamp album pages
+#[derive(Debug, Clone, Copy)]
+pub struct Bandcamp;
+
+impl MetadataSource for Bandcamp {
+    type Error = BandcampError;
+
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error> {
+        scrape_bandcamp(url)
+    }
+}
+
+/// Scrapes a Bandcamp album page
+///
+/// # Errors
+/// - If the page can't be downloaded
+/// - If [`parse_album_html`] fails
+pub fn scrape_bandcamp(url: &str) -> Result<AlbumMetadata, BandcampError> {
+    parse_album_html(&download(url)?.text()?)
+}
+
+/// Parses the album out of the HTML of a Bandcamp album page. The cover is the original upload,
+/// which is usually much bigger than the one shown on the page.
+///
+/// # Errors
+/// If there isn't an element with valid `data-tralbum` JSON
+pub fn parse_album_html(html: &str) -> Result<AlbumMetadata, BandcampError> {
+    let document = Html::parse_document(html);
+    let json = document
+        .select(&TRALBUM)
+        .find_map(|element| element.value().attr("data-tralbum"))
+        .ok_or(BandcampError::MissingTralbum)?;
+    let tralbum: Tralbum = serde_json::from_str(json)?;
+
+    let tracks = tralbum
+        .trackinfo
+        .into_iter()
+        .map(|track| TrackMetadata {
+            title: track.title,
+            artist: track
+                .artist
+                .filter(|artist| !artist.is_empty() && *artist != tralbum.artist),
+            length: track
+                .duration
+                .filter(|secs| secs.is_finite() && *secs > 0.0)
+                .map(Duration::from_secs_f64),
+        })
+        .collect();
+
+    // like "12 Jul 2023 00:00:00 GMT"
+    let released = tralbum
+        .current
+        .release_date
+        .or(tralbum.album_release_date)
+        .and_then(|date| {
+            parse_release_date(
+                &date
+                    .split_whitespace()
+                    .take(3)
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            )
+        });
+
+    Ok(AlbumMetadata {
+        title: tralbum.current.title,
+        artist: tralbum.artist,
+        genres: Vec::new(),
+        year: None,
+        released,
+        image: tralbum
+            .art_id
+            .map(|art_id| format!("https://f4.bcbits.com/img/a{art_id:010}_0.jpg")),
+        tracks,
+        confidence: FieldConfidence::default(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn album_page() {
+        let html = r#"<html><head>
+            <script type="text/javascript" src="https://s4.bcbits.com/bundle.js"
+                data-tralbum="{&quot;artist&quot;:&quot;Some Band&quot;,&quot;art_id&quot;:123456789,&quot;album_release_date&quot;:&quot;01 Jan 2020 00:00:00 GMT&quot;,&quot;current&quot;:{&quot;title&quot;:&quot;Rock &amp; Roll&quot;,&quot;release_date&quot;:&quot;12 Jul 2023 00:00:00 GMT&quot;},&quot;trackinfo&quot;:[{&quot;title&quot;:&quot;Opener&quot;,&quot;artist&quot;:null,&quot;duration&quot;:185.5},{&quot;title&quot;:&quot;Collab&quot;,&quot;artist&quot;:&quot;Some Band feat. Guest&quot;,&quot;duration&quot;:0}]}"></script>
+        </head></html>"#;
+        let album = parse_album_html(html).unwrap();
+        assert_eq!(album.title, "Rock & Roll");
+        assert_eq!(album.artist, "Some Band");
+        assert_eq!(
+            album.released.map(|date| (date.year, date.month, date.day)),
+            Some((2023, Some(7), Some(12)))
+        );
+        assert_eq!(
+            album.image.as_deref(),
+            Some("https://f4.bcbits.com/img/a0123456789_0.jpg")
+        );
+        assert_eq!(
+            album.tracks,
+            [
+                TrackMetadata {
+                    title: String::from("Opener"),
+                    artist: None,
+                    length: Some(Duration::from_secs_f64(185.5)),
+                },
+                TrackMetadata {
+                    title: String::from("Collab"),
+                    artist: Some(String::from("Some Band feat. Guest")),
+                    length: None,
+                },
+            ]
+        );
+
+        assert!(matches!(
+            parse_album_html("<html></html>"),
+            Err(BandcampError::MissingTralbum)
+        ));
+    }
+}
@@ -1,9 +1,16 @@
-use std::{borrow::Cow, str::FromStr};
+use std::borrow::Cow;
 
-use crate::utils::{
-    download,
-    selectors::{RELEASE_SCHEMA, SPAN, TD, TIME, TRACKLIST, VERSIONS_TABLE_LINK},
+use super::{
+    fetch_discogs_album, AlbumMetadata, DiscogsApiError, FieldConfidence, MetadataSource,
+    TrackMetadata,
 };
+use crate::{
+    config,
+    network::download,
+    parsing::{parse_duration, parse_release_date},
+    utils::selectors::{RELEASE_SCHEMA, SPAN, TD, TIME, TRACKLIST, VERSIONS_TABLE_LINK},
+};
+use html_escape::decode_html_entities;
 use id3::Timestamp;
 use scraper::{html::Select, Html};
 use serde::Deserialize;
@@ -98,6 +105,8 @@ pub enum DiscogsScrapeError {
     SerdeError(#[from] serde_json::Error),
     #[error("couldn't find release page from master page")]
     CouldntFindReleasePage,
+    #[error("{0}")]
+    Api(#[from] DiscogsApiError),
 }
 
 /// Scrapes a Discogs master page to find a release
@@ -126,13 +135,22 @@ fn release_from_master(url: &str) -> Result<Cow<str>, DiscogsScrapeError> {
     }
 }
 
-/// Scrapes Discogs for various album data
+/// Gets various album data from Discogs, from the API if there's a Discogs token in the config
+/// (which doesn't break when the pages change) and otherwise by scraping the page
 ///
 /// # Errors
+/// - If the API request fails, when there's a token
 /// - If it can't download the page at the given URL
 /// - If there was no JSON script tag with the id `release_schema`
 /// - If the JSON couldn't be parsed
 pub fn scrape_discogs(url: &str) -> Result<DiscogsAlbum, DiscogsScrapeError> {
+    if let Some(token) = config::current()
+        .discogs_token
+        .filter(|token| !token.trim().is_empty())
+    {
+        return Ok(fetch_discogs_album(&token, url)?);
+    }
+
     let url = release_from_master(url)?;
     let resp = download(&url)?;
     let document = Html::parse_document(resp.text()?.as_str());
@@ -148,6 +166,58 @@ pub fn scrape_discogs(url: &str) -> Result<DiscogsAlbum, DiscogsScrapeError> {
     })
 }
 
+/// Discogs release pages, or master pages (which use their first release)
+#[derive(Debug, Clone, Copy)]
+pub struct Discogs;
+
+impl MetadataSource for Discogs {
+    type Error = DiscogsScrapeError;
+
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error> {
+        scrape_discogs(url).map(|album| AlbumMetadata::from(&album))
+    }
+}
+
+/// With the HTML entities decoded, and without any tracks that couldn't be parsed
+impl From<&DiscogsAlbum> for AlbumMetadata {
+    fn from(album: &DiscogsAlbum) -> Self {
+        let data = &album.album_data;
+        let tracks = album
+            .tracks
+            .iter()
+            .filter_map(|track| {
+                if track.is_none() {
+                    log::error!("failed to parse track");
+                }
+                track.as_ref()
+            })
+            .map(|track| TrackMetadata {
+                title: decode_html_entities(&track.title).to_string(),
+                artist: None,
+                length: parse_duration(&track.duration),
+            })
+            .collect();
+
+        Self {
+            title: decode_html_entities(&data.name).to_string(),
+            artist: data
+                .release_of
+                .by_artist
+                .iter()
+                .map(|artist| decode_html_entities(&artist.name))
+                .collect::<Vec<_>>()
+                .join("; "),
+            genres: data.genre.clone(),
+            year: Some(data.date_published),
+            released: album.released,
+            image: Some(data.image.clone()).filter(|image| !image.is_empty()),
+            tracks,
+            // all from the release's schema
+            confidence: FieldConfidence::default(),
+        }
+    }
+}
+
 fn parse_release_schema(document: &Html) -> Result<DiscogsAlbumData, DiscogsScrapeError> {
     serde_json::de::from_str(
         document
@@ -178,20 +248,45 @@ fn parse_tracks(document: &Html) -> Vec<Option<DiscogsTrack>> {
         .collect()
 }
 
+/// From the `datetime` attribute, or the text if that's missing or in a format that can't be read
 fn parse_released(document: &Html) -> Option<Timestamp> {
-    document
-        .select(&TIME)
-        .next()?
-        .value()
+    let time = document.select(&TIME).next()?;
+    time.value()
         .attr("datetime")
-        .map(FromStr::from_str)
-        .and_then(Result::ok)
+        .and_then(parse_release_date)
+        .or_else(|| parse_release_date(&time.text().collect::<String>()))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn released_formats() {
+        let released = |html: &str| {
+            parse_released(&Html::parse_document(html))
+                .map(|date| (date.year, date.month, date.day))
+        };
+        assert_eq!(
+            released(r#"<time datetime="2023-06-02">02 Jun 2023</time>"#),
+            Some((2023, Some(6), Some(2)))
+        );
+        // localized text with an unreadable datetime
+        assert_eq!(
+            released(r#"<time datetime="02.06.23">2. Juni 2023</time>"#),
+            Some((2023, Some(6), Some(2)))
+        );
+        assert_eq!(
+            released("<time>juin 2023</time>"),
+            Some((2023, Some(6), None))
+        );
+        assert_eq!(
+            released("<time>02/06/2023</time>"),
+            Some((2023, None, None))
+        );
+        assert_eq!(released("<p>no date</p>"), None);
+    }
+
     #[test]
     fn release_basic() {
         let album =
@@ -0,0 +1,672 @@
+//! The user's Discogs collection and wantlist, and releases themselves, from the Discogs API using
+//! a personal access token (made at <https://www.discogs.com/settings/developers>)
+
+use super::{
+    DiscogsAlbum, DiscogsAlbumData, DiscogsLocation, DiscogsNamedObject, DiscogsReleaseOf,
+    DiscogsReleasedEvent, DiscogsTrack,
+};
+use crate::{
+    network::download_authorized,
+    parsing::parse_release_date,
+    urls::{classify, UrlKind},
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use std::fmt::{self, Display, Formatter};
+use thiserror::Error;
+use url::Url;
+
+const API_URL: &str = "https://api.discogs.com";
+const PER_PAGE: u32 = 100;
+
+#[derive(Debug, Error)]
+pub enum DiscogsApiError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("no Discogs token has been set in the settings")]
+    NoToken,
+    #[error("{0}")]
+    UrlError(#[from] url::ParseError),
+    #[error("{0} isn't a Discogs release or master")]
+    NotARelease(String),
+}
+
+/// Which of the user's lists to browse
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DiscogsList {
+    #[default]
+    Collection,
+    Wantlist,
+}
+
+impl DiscogsList {
+    pub const ALL: &'static [Self] = &[Self::Collection, Self::Wantlist];
+
+    fn url(self, username: &str, page: u32) -> String {
+        match self {
+            Self::Collection => format!(
+                "{API_URL}/users/{username}/collection/folders/0/releases?page={page}&per_page={PER_PAGE}"
+            ),
+            Self::Wantlist => {
+                format!("{API_URL}/users/{username}/wants?page={page}&per_page={PER_PAGE}")
+            }
+        }
+    }
+}
+
+impl Display for DiscogsList {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Collection => "Collection",
+            Self::Wantlist => "Wantlist",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Identity {
+    username: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Pagination {
+    page: u32,
+    pages: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListPage {
+    pagination: Pagination,
+    /// Collection pages call them `releases` and wantlist pages `wants`
+    #[serde(alias = "wants")]
+    releases: Vec<ListItem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ListItem {
+    basic_information: DiscogsRelease,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DiscogsArtist {
+    pub name: String,
+}
+
+/// A release in the user's collection or wantlist
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DiscogsRelease {
+    pub id: u64,
+    pub title: String,
+    /// 0 if unknown
+    pub year: u16,
+    pub artists: Vec<DiscogsArtist>,
+}
+
+impl DiscogsRelease {
+    /// The release page, which can be given to [`scrape_discogs`](super::scrape_discogs)
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("https://www.discogs.com/release/{}", self.id)
+    }
+
+    /// The artists joined together, without the numbers Discogs adds to tell apart artists with
+    /// the same name (e.g. "Artist (2)")
+    #[must_use]
+    pub fn artist(&self) -> String {
+        self.artists
+            .iter()
+            .map(|artist| strip_artist_number(&artist.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Whether the artist or title contains `filter`, ignoring case
+    #[must_use]
+    pub fn matches(&self, filter: &str) -> bool {
+        let filter = filter.trim().to_lowercase();
+        filter.is_empty()
+            || self.title.to_lowercase().contains(&filter)
+            || self.artist().to_lowercase().contains(&filter)
+    }
+}
+
+/// Like "Artist - Album (2023)"
+impl Display for DiscogsRelease {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} - {}", self.artist(), self.title)?;
+        if self.year > 0 {
+            write!(f, " ({})", self.year)?;
+        }
+        Ok(())
+    }
+}
+
+/// One page of the user's collection or wantlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiscogsListPage {
+    pub releases: Vec<DiscogsRelease>,
+    /// 1-based
+    pub page: u32,
+    pub pages: u32,
+}
+
+/// Removes the " (2)" that Discogs adds to the end of artists that share a name
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::strip_artist_number;
+///
+/// assert_eq!(strip_artist_number("Nirvana (2)"), "Nirvana");
+/// assert_eq!(strip_artist_number("Nirvana"), "Nirvana");
+/// assert_eq!(strip_artist_number("Live (Band)"), "Live (Band)");
+/// ```
+#[must_use]
+pub fn strip_artist_number(name: &str) -> &str {
+    name.strip_suffix(')')
+        .and_then(|rest| rest.rsplit_once(" ("))
+        .filter(|(_, number)| !number.is_empty() && number.chars().all(|c| c.is_ascii_digit()))
+        .map_or(name, |(name, _)| name)
+}
+
+/// What to search for a release by, to find the exact pressing
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiscogsIdentifier {
+    /// Only the digits
+    Barcode(String),
+    CatalogNumber(String),
+}
+
+impl DiscogsIdentifier {
+    /// Reads a barcode or catalog number as it's typed, or copied from the "Barcode and Other
+    /// Identifiers" section of a release (like "Barcode (Text): 8 809704 426329"). Anything
+    /// that's just 8 to 14 digits (ignoring spaces and dashes) is taken to be a barcode.
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::scraping::DiscogsIdentifier;
+    ///
+    /// let barcode = DiscogsIdentifier::Barcode(String::from("8809704426329"));
+    /// assert_eq!(DiscogsIdentifier::parse("8 809704 426329"), Some(barcode.clone()));
+    /// assert_eq!(DiscogsIdentifier::parse("Barcode (Text): 8 809704-426329"), Some(barcode));
+    /// assert_eq!(
+    ///     DiscogsIdentifier::parse(" CMCC-11234 "),
+    ///     Some(DiscogsIdentifier::CatalogNumber(String::from("CMCC-11234")))
+    /// );
+    /// assert_eq!(
+    ///     DiscogsIdentifier::parse("Cat#: 20220118"),
+    ///     Some(DiscogsIdentifier::CatalogNumber(String::from("20220118")))
+    /// );
+    /// assert_eq!(DiscogsIdentifier::parse("  "), None);
+    /// ```
+    #[must_use]
+    pub fn parse(s: &str) -> Option<Self> {
+        let digits = |s: &str| -> String {
+            s.chars()
+                .filter(|c| !c.is_whitespace() && *c != '-')
+                .collect()
+        };
+        let (label, value) = match s.split_once(':') {
+            Some((label, value)) => (Some(label.trim().to_lowercase()), value.trim()),
+            None => (None, s.trim()),
+        };
+        if value.is_empty() {
+            return None;
+        }
+        Some(match label.as_deref() {
+            Some(label) if label.starts_with("barcode") => Self::Barcode(digits(value)),
+            Some(label) if label.starts_with("cat") => Self::CatalogNumber(value.to_string()),
+            // a colon that isn't after a label is part of it
+            Some(_) => Self::CatalogNumber(s.trim().to_string()),
+            None => {
+                let digits = digits(value);
+                if (8..=14).contains(&digits.len()) && digits.chars().all(|c| c.is_ascii_digit()) {
+                    Self::Barcode(digits)
+                } else {
+                    Self::CatalogNumber(value.to_string())
+                }
+            }
+        })
+    }
+
+    fn search_url(&self) -> Result<Url, url::ParseError> {
+        let (param, value) = match self {
+            Self::Barcode(barcode) => ("barcode", barcode),
+            Self::CatalogNumber(catno) => ("catno", catno),
+        };
+        Url::parse_with_params(
+            &format!("{API_URL}/database/search"),
+            [
+                ("type", "release"),
+                (param, value),
+                ("per_page", &PER_PAGE.to_string()),
+            ],
+        )
+    }
+}
+
+/// Like "barcode 8809704426329"
+impl Display for DiscogsIdentifier {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Barcode(barcode) => write!(f, "barcode {barcode}"),
+            Self::CatalogNumber(catno) => write!(f, "catalog number {catno}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SearchPage {
+    results: Vec<DiscogsSearchResult>,
+}
+
+/// A release found by [`search_discogs`], with what tells pressings apart
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DiscogsSearchResult {
+    pub id: u64,
+    /// Like "Artist - Album"
+    pub title: String,
+    pub year: Option<String>,
+    pub country: Option<String>,
+    pub catno: Option<String>,
+    #[serde(default)]
+    pub label: Vec<String>,
+    /// Like `["CD", "Album"]`
+    #[serde(default)]
+    pub format: Vec<String>,
+}
+
+impl DiscogsSearchResult {
+    /// The release page, which can be given to [`scrape_discogs`](super::scrape_discogs)
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("https://www.discogs.com/release/{}", self.id)
+    }
+}
+
+/// Like "Artist - Album (2023, Label CAT-001, South Korea, CD, Album)"
+impl Display for DiscogsSearchResult {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let label = match (self.label.first(), self.catno.as_deref()) {
+            (_, Some("none") | None) => self.label.first().cloned(),
+            (Some(label), Some(catno)) => Some(format!("{label} {catno}")),
+            (None, Some(catno)) => Some(catno.to_string()),
+        };
+        let details: Vec<String> = [self.year.clone(), label, self.country.clone()]
+            .into_iter()
+            .flatten()
+            .chain(self.format.iter().cloned())
+            .filter(|detail| !detail.is_empty())
+            .collect();
+        f.write_str(&self.title)?;
+        if !details.is_empty() {
+            write!(f, " ({})", details.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+fn get<T: DeserializeOwned>(url: &str, token: &str) -> Result<T, DiscogsApiError> {
+    let resp = download_authorized(url, &format!("Discogs token={token}"))?;
+    Ok(serde_json::from_str(&resp.text()?)?)
+}
+
+/// Gets the username the token belongs to
+///
+/// # Errors
+/// - If the token is empty
+/// - If the request fails (e.g. the token is wrong)
+/// - If the response couldn't be parsed
+pub fn fetch_discogs_username(token: &str) -> Result<String, DiscogsApiError> {
+    if token.trim().is_empty() {
+        return Err(DiscogsApiError::NoToken);
+    }
+    let identity: Identity = get(&format!("{API_URL}/oauth/identity"), token.trim())?;
+    Ok(identity.username)
+}
+
+/// Gets a page (1-based) of the user's collection or wantlist
+///
+/// # Errors
+/// - If the token is empty
+/// - If the request fails (e.g. the token is wrong)
+/// - If the response couldn't be parsed
+pub fn fetch_discogs_list(
+    token: &str,
+    username: &str,
+    list: DiscogsList,
+    page: u32,
+) -> Result<DiscogsListPage, DiscogsApiError> {
+    if token.trim().is_empty() {
+        return Err(DiscogsApiError::NoToken);
+    }
+    let page: ListPage = get(&list.url(username, page.max(1)), token.trim())?;
+    Ok(page.into())
+}
+
+/// Searches Discogs for releases with the barcode or catalog number, which needs a token
+///
+/// # Errors
+/// - If the token is empty
+/// - If the request fails (e.g. the token is wrong)
+/// - If the response couldn't be parsed
+pub fn search_discogs(
+    token: &str,
+    identifier: &DiscogsIdentifier,
+) -> Result<Vec<DiscogsSearchResult>, DiscogsApiError> {
+    if token.trim().is_empty() {
+        return Err(DiscogsApiError::NoToken);
+    }
+    let page: SearchPage = get(identifier.search_url()?.as_str(), token.trim())?;
+    Ok(page.results)
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Master {
+    main_release: u64,
+}
+
+/// A release from `/releases/{id}`, with only what [`DiscogsAlbum`] needs
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseDetails {
+    uri: String,
+    title: String,
+    /// 0 if unknown
+    #[serde(default)]
+    year: i32,
+    /// Like "2023-03-08", or with zeros for what isn't known
+    released: Option<String>,
+    country: Option<String>,
+    notes: Option<String>,
+    artists: Vec<DiscogsArtist>,
+    #[serde(default)]
+    genres: Vec<String>,
+    #[serde(default)]
+    labels: Vec<ReleaseLabel>,
+    #[serde(default)]
+    formats: Vec<ReleaseFormat>,
+    #[serde(default)]
+    images: Vec<ReleaseImage>,
+    tracklist: Vec<ReleaseTrack>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseLabel {
+    name: String,
+    catno: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseFormat {
+    name: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseImage {
+    /// "primary" or "secondary"
+    #[serde(rename = "type")]
+    r#type: String,
+    uri: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseTrack {
+    /// "track", or "heading" and "index" for the lines that group tracks
+    type_: String,
+    title: String,
+    /// Like "2:44", or empty if unknown
+    duration: String,
+}
+
+/// Gets a release (or a master's main release) from the API, as the same [`DiscogsAlbum`] that
+/// [`scrape_discogs`](super::scrape_discogs) gets from the release page
+///
+/// # Errors
+/// - If the token is empty
+/// - If `url` isn't a Discogs release or master
+/// - If the request fails (e.g. the token is wrong)
+/// - If the response couldn't be parsed
+pub fn fetch_discogs_album(token: &str, url: &str) -> Result<DiscogsAlbum, DiscogsApiError> {
+    if token.trim().is_empty() {
+        return Err(DiscogsApiError::NoToken);
+    }
+    let token = token.trim();
+    let id = match classify(url) {
+        UrlKind::DiscogsRelease(id) => id,
+        UrlKind::DiscogsMaster(id) => {
+            get::<Master>(&format!("{API_URL}/masters/{id}"), token)?.main_release
+        }
+        _ => return Err(DiscogsApiError::NotARelease(url.to_string())),
+    };
+    let release: ReleaseDetails = get(&format!("{API_URL}/releases/{id}"), token)?;
+    Ok(release.into())
+}
+
+/// Filled in like the release page's schema, so it doesn't matter where the album came from
+impl From<ReleaseDetails> for DiscogsAlbum {
+    fn from(release: ReleaseDetails) -> Self {
+        let named = |r#type: &str, name: &str| DiscogsNamedObject {
+            r#type: r#type.to_string(),
+            id: String::new(),
+            name: strip_artist_number(name).to_string(),
+        };
+        let image = release
+            .images
+            .iter()
+            .find(|image| image.r#type == "primary")
+            .or_else(|| release.images.first())
+            .map(|image| image.uri.clone())
+            .unwrap_or_default();
+        let tracks = release
+            .tracklist
+            .into_iter()
+            .filter(|track| track.type_ == "track")
+            .zip(1..)
+            .map(|(track, number)| {
+                Some(DiscogsTrack {
+                    number,
+                    title: track.title,
+                    duration: track.duration,
+                })
+            })
+            .collect();
+
+        let album_data = DiscogsAlbumData {
+            context: String::from("http://schema.org"),
+            r#type: String::from("MusicRelease"),
+            id: release.uri,
+            name: release.title.clone(),
+            music_release_format: release
+                .formats
+                .first()
+                .map(|format| format.name.clone())
+                .unwrap_or_default(),
+            genre: release.genres,
+            description: release.notes,
+            date_published: release.year,
+            catalog_number: release
+                .labels
+                .first()
+                .map(|label| label.catno.clone())
+                .unwrap_or_default(),
+            record_label: release
+                .labels
+                .iter()
+                .map(|label| named("MusicLabel", &label.name))
+                .collect(),
+            release_of: DiscogsReleaseOf {
+                r#type: String::from("MusicAlbum"),
+                id: None,
+                name: release.title,
+                date_published: release.year,
+                by_artist: release
+                    .artists
+                    .iter()
+                    .map(|artist| named("MusicGroup", &artist.name))
+                    .collect(),
+            },
+            released_event: DiscogsReleasedEvent {
+                r#type: String::from("PublicationEvent"),
+                start_date: release.year,
+                location: DiscogsLocation {
+                    r#type: String::from("Place"),
+                    name: release.country.unwrap_or_default(),
+                },
+            },
+            image,
+        };
+
+        Self {
+            album_data,
+            tracks,
+            // without the month or day when they're zeros
+            released: release
+                .released
+                .as_deref()
+                .and_then(|released| parse_release_date(released.trim_end_matches("-00"))),
+        }
+    }
+}
+
+impl From<ListPage> for DiscogsListPage {
+    fn from(page: ListPage) -> Self {
+        Self {
+            releases: page
+                .releases
+                .into_iter()
+                .map(|item| item.basic_information)
+                .collect(),
+            page: page.pagination.page,
+            pages: page.pagination.pages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_wantlist_page() {
+        let json = r#"{
+            "pagination": {"page": 1, "pages": 3, "per_page": 100, "items": 250},
+            "wants": [{
+                "id": 27651927,
+                "rating": 0,
+                "basic_information": {
+                    "id": 27651927,
+                    "title": "Version Up",
+                    "year": 2023,
+                    "artists": [{"name": "ODD EYE CIRCLE (2)", "id": 1}],
+                    "formats": [{"name": "CD"}]
+                }
+            }]
+        }"#;
+        let page: DiscogsListPage = serde_json::from_str::<ListPage>(json).unwrap().into();
+
+        assert_eq!((page.page, page.pages), (1, 3));
+        let release = &page.releases[0];
+        assert_eq!(release.to_string(), "ODD EYE CIRCLE - Version Up (2023)");
+        assert_eq!(release.url(), "https://www.discogs.com/release/27651927");
+        assert!(release.matches("odd eye"));
+        assert!(release.matches("VERSION"));
+        assert!(!release.matches("loona"));
+    }
+
+    #[test]
+    fn parse_search_results() {
+        let json = r#"{
+            "pagination": {"page": 1, "pages": 1, "per_page": 100, "items": 2},
+            "results": [{
+                "id": 27651927,
+                "type": "release",
+                "title": "ODD EYE CIRCLE (2) - Version Up",
+                "year": "2023",
+                "country": "South Korea",
+                "catno": "CMCC-11234",
+                "label": ["Modhaus", "Kakao Entertainment"],
+                "format": ["CD", "Album"],
+                "barcode": ["8809704426329"]
+            }, {
+                "id": 1,
+                "type": "release",
+                "title": "Someone - Something",
+                "catno": "none"
+            }]
+        }"#;
+        let results = serde_json::from_str::<SearchPage>(json).unwrap().results;
+
+        assert_eq!(
+            results[0].to_string(),
+            "ODD EYE CIRCLE (2) - Version Up (2023, Modhaus CMCC-11234, South Korea, CD, Album)"
+        );
+        assert_eq!(results[0].url(), "https://www.discogs.com/release/27651927");
+        assert_eq!(results[1].to_string(), "Someone - Something");
+
+        let url = DiscogsIdentifier::CatalogNumber(String::from("CAT 1/2"))
+            .search_url()
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.discogs.com/database/search?type=release&catno=CAT+1%2F2&per_page=100"
+        );
+    }
+
+    #[test]
+    fn parse_release() {
+        let json = r#"{
+            "id": 27651927,
+            "uri": "https://www.discogs.com/release/27651927-Odd-Eye-Circle-Version-Up",
+            "title": "Version Up",
+            "year": 2023,
+            "released": "2023-07-12",
+            "country": "South Korea",
+            "artists": [{"name": "ODD EYE CIRCLE (2)", "anv": "", "id": 1}],
+            "genres": ["Electronic", "Pop"],
+            "labels": [{"name": "Modhaus", "catno": "CMCC-11234"}],
+            "formats": [{"name": "CD", "qty": "1"}],
+            "images": [
+                {"type": "secondary", "uri": "https://i.discogs.com/back.jpeg"},
+                {"type": "primary", "uri": "https://i.discogs.com/front.jpeg"}
+            ],
+            "tracklist": [
+                {"position": "", "type_": "heading", "title": "Side A", "duration": ""},
+                {"position": "1", "type_": "track", "title": "Did You Wait?", "duration": "1:10"},
+                {"position": "2", "type_": "track", "title": "Air Force One", "duration": "2:44"}
+            ]
+        }"#;
+        let album: DiscogsAlbum = serde_json::from_str::<ReleaseDetails>(json).unwrap().into();
+
+        assert_eq!(album.album_data.catalog_number, "CMCC-11234");
+        assert_eq!(album.album_data.image, "https://i.discogs.com/front.jpeg");
+        let released = album.released.unwrap();
+        assert_eq!((released.year, released.month), (2023, Some(7)));
+        let tracks: Vec<(i32, &str)> = album
+            .tracks
+            .iter()
+            .flatten()
+            .map(|track| (track.number, track.title.as_str()))
+            .collect();
+        assert_eq!(tracks, [(1, "Did You Wait?"), (2, "Air Force One")]);
+
+        let metadata = crate::scraping::AlbumMetadata::from(&album);
+        assert_eq!(metadata.artist, "ODD EYE CIRCLE");
+        assert_eq!(metadata.year, Some(2023));
+        assert_eq!(
+            metadata.tracks[1].length,
+            Some(std::time::Duration::from_secs(164))
+        );
+
+        let json = json.replace("2023-07-12", "2023-00-00");
+        let album: DiscogsAlbum = serde_json::from_str::<ReleaseDetails>(&json)
+            .unwrap()
+            .into();
+        assert_eq!(album.released.map(|date| date.month), Some(None));
+        let json = json.replace("2023-00-00", "2023-07-00");
+        let album: DiscogsAlbum = serde_json::from_str::<ReleaseDetails>(&json)
+            .unwrap()
+            .into();
+        let released = album.released.unwrap();
+        assert_eq!((released.month, released.day), (Some(7), None));
+    }
+}
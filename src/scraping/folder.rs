@@ -0,0 +1,355 @@
+//! Album data from the tags of an album that's already on disk, so a bad rip can be downloaded
+//! again with the same tags (fixed up on the modify screen if they need it)
+
+use super::{AlbumMetadata, Confidence, FieldConfidence, MetadataSource, TrackMetadata};
+use crate::parsing::parse_release_date;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    time::Duration,
+};
+use thiserror::Error;
+
+/// What's counted as a track in the folder
+const AUDIO_EXTENSIONS: &[&str] = &[
+    "mp3", "flac", "opus", "m4a", "ogg", "oga", "wav", "aiff", "aif", "wma", "ape", "wv", "aac",
+];
+
+#[derive(Debug, Error)]
+pub enum FolderError {
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("ffprobe error reading the tags of {0}")]
+    FfprobeError(String),
+    #[error(r#"there aren't any audio files in "{0}""#)]
+    NoAudio(String),
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeOutput {
+    #[serde(default)]
+    streams: Vec<FfprobeTagged>,
+    #[serde(default)]
+    format: FfprobeTagged,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct FfprobeTagged {
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+/// One file's tags as ffprobe reads them, whatever kind of tags they are
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileTags {
+    /// Lowercase names, like `title` and `album_artist`
+    pub tags: HashMap<String, String>,
+    pub duration: Option<Duration>,
+}
+
+impl FileTags {
+    /// The first of `names` that's set and isn't empty
+    fn get(&self, names: &[&str]) -> Option<&str> {
+        names
+            .iter()
+            .filter_map(|name| self.tags.get(*name))
+            .map(|value| value.trim())
+            .find(|value| !value.is_empty())
+    }
+
+    /// The number before any "/", like 3 from "3/10"
+    fn number(&self, names: &[&str]) -> Option<u32> {
+        self.get(names)?.split('/').next()?.trim().parse().ok()
+    }
+
+    pub(crate) fn title(&self) -> Option<&str> {
+        self.get(&["title"])
+    }
+
+    fn artist(&self) -> Option<&str> {
+        self.get(&["artist"])
+    }
+
+    fn album_artist(&self) -> Option<&str> {
+        self.get(&["album_artist", "albumartist", "album artist"])
+    }
+
+    /// Disc then track number, so they can be sorted
+    pub(crate) fn position(&self) -> (Option<u32>, Option<u32>) {
+        (
+            self.number(&["disc", "discnumber"]),
+            self.number(&["track", "tracknumber"]),
+        )
+    }
+}
+
+/// An album folder, given as its path
+#[derive(Debug, Clone, Copy)]
+pub struct Folder;
+
+impl MetadataSource for Folder {
+    type Error = FolderError;
+
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error> {
+        read_folder(Path::new(url.trim()))
+    }
+}
+
+/// Reads the tags of every audio file in `dir` (not its subfolders). A file whose tags can't be
+/// read is still a track, named after the file.
+///
+/// # Errors
+/// - If `dir` can't be read
+/// - If there aren't any audio files in it
+pub fn read_folder(dir: &Path) -> Result<AlbumMetadata, FolderError> {
+    let files = find_audio(dir)?;
+    if files.is_empty() {
+        return Err(FolderError::NoAudio(dir.display().to_string()));
+    }
+    let tagged: Vec<(PathBuf, FileTags)> = files
+        .into_iter()
+        .map(|path| {
+            let tags = read_tags(&path).unwrap_or_else(|err| {
+                log::warn!(r#"couldn't read the tags of "{}": {err}"#, path.display());
+                FileTags::default()
+            });
+            (path, tags)
+        })
+        .collect();
+    Ok(album_from_tags(tagged))
+}
+
+/// The audio files in `dir`, sorted by name
+fn find_audio(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let is_audio = path.extension().map_or(false, |ext| {
+            AUDIO_EXTENSIONS
+                .iter()
+                .any(|audio| ext.eq_ignore_ascii_case(audio))
+        });
+        if path.is_file() && is_audio {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Runs ffprobe on the file for its tags and length
+///
+/// # Errors
+/// If ffprobe can't be run, fails, or its output can't be parsed
+pub fn read_tags(path: &Path) -> Result<FileTags, FolderError> {
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-select_streams",
+            "a:0",
+            "-show_entries",
+            "format=duration:format_tags:stream_tags",
+            "-of",
+            "json",
+        ])
+        .arg(path)
+        .output()?;
+    if !output.status.success() {
+        log::error!("{}", String::from_utf8_lossy(&output.stderr));
+        return Err(FolderError::FfprobeError(
+            path.to_string_lossy().to_string(),
+        ));
+    }
+    Ok(parse_ffprobe_tags(&String::from_utf8_lossy(
+        &output.stdout,
+    ))?)
+}
+
+/// Parses ffprobe's JSON output. Some formats keep their tags on the audio stream (like Opus)
+/// and others on the file, so both are used.
+///
+/// # Errors
+/// If it isn't ffprobe's JSON
+pub fn parse_ffprobe_tags(json: &str) -> Result<FileTags, serde_json::Error> {
+    let output: FfprobeOutput = serde_json::from_str(json)?;
+    let mut tags = HashMap::new();
+    let stream_tags = output.streams.into_iter().flat_map(|stream| stream.tags);
+    for (name, value) in output.format.tags.into_iter().chain(stream_tags) {
+        tags.entry(name.to_lowercase()).or_insert(value);
+    }
+    let duration = output
+        .format
+        .duration
+        .and_then(|secs| secs.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .map(Duration::from_secs_f64);
+    Ok(FileTags { tags, duration })
+}
+
+/// Puts the files in disc and track order (files without numbers go last, by name), and takes
+/// the album's details from whichever value most of the files have
+fn album_from_tags(mut files: Vec<(PathBuf, FileTags)>) -> AlbumMetadata {
+    files.sort_by_key(|(_, tags)| {
+        let (disc, track) = tags.position();
+        (disc.unwrap_or(1), track.is_none(), track)
+    });
+
+    let most_common = |get: &dyn Fn(&FileTags) -> Option<&str>| -> Option<String> {
+        let mut counts: Vec<(&str, usize)> = Vec::new();
+        for value in files.iter().filter_map(|(_, tags)| get(tags)) {
+            match counts.iter_mut().find(|(v, _)| *v == value) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((value, 1)),
+            }
+        }
+        // the first one wins a tie
+        counts
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(value, _)| value.to_string())
+    };
+    let mut confidence = FieldConfidence::default();
+    let artist = most_common(&|tags| tags.album_artist())
+        .or_else(|| {
+            // the tracks' artists can have guests in them
+            confidence.artist = Confidence::Guessed;
+            most_common(&FileTags::artist)
+        })
+        .unwrap_or_default();
+    if files.iter().any(|(_, tags)| tags.title().is_none()) {
+        confidence.tracks = Confidence::Guessed;
+    }
+
+    let tracks = files
+        .iter()
+        .map(|(path, tags)| TrackMetadata {
+            title: tags.title().map_or_else(
+                || {
+                    path.file_stem()
+                        .map(|stem| stem.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                },
+                str::to_string,
+            ),
+            artist: tags
+                .artist()
+                .filter(|track_artist| *track_artist != artist)
+                .map(str::to_string),
+            length: tags.duration,
+        })
+        .collect();
+
+    AlbumMetadata {
+        title: most_common(&|tags| tags.get(&["album"])).unwrap_or_default(),
+        genres: most_common(&|tags| tags.get(&["genre"]))
+            .map(|genre| {
+                genre
+                    .split(';')
+                    .map(str::trim)
+                    .filter(|genre| !genre.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default(),
+        year: None,
+        released: most_common(&|tags| tags.get(&["date", "year"]))
+            .and_then(|date| parse_release_date(&date)),
+        image: None,
+        artist,
+        tracks,
+        confidence,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str, tags: &[(&str, &str)]) -> (PathBuf, FileTags) {
+        let tags = tags
+            .iter()
+            .map(|(name, value)| ((*name).to_string(), (*value).to_string()))
+            .collect();
+        (
+            PathBuf::from(name),
+            FileTags {
+                tags,
+                duration: None,
+            },
+        )
+    }
+
+    #[test]
+    fn ffprobe_tags() {
+        let json = r#"{
+            "programs": [],
+            "streams": [{"tags": {"TITLE": "Song", "ARTIST": "Band", "track": "2"}}],
+            "format": {"duration": "185.500000", "tags": {"encoder": "Lavf60.3.100", "track": "3/10"}}
+        }"#;
+        let tags = parse_ffprobe_tags(json).unwrap();
+        assert_eq!(tags.title(), Some("Song"));
+        assert_eq!(tags.artist(), Some("Band"));
+        // the file's tags win
+        assert_eq!(tags.position(), (None, Some(3)));
+        assert_eq!(tags.duration, Some(Duration::from_secs_f64(185.5)));
+
+        let untagged = parse_ffprobe_tags(r#"{"format": {}}"#).unwrap();
+        assert_eq!(untagged, FileTags::default());
+    }
+
+    #[test]
+    fn album() {
+        let album = album_from_tags(vec![
+            file("bonus.mp3", &[("title", "Bonus")]),
+            file(
+                "b.mp3",
+                &[
+                    ("title", "Second"),
+                    ("artist", "Band"),
+                    ("album", "Album"),
+                    ("track", "2/3"),
+                    ("date", "2021-05-04"),
+                ],
+            ),
+            file(
+                "a.mp3",
+                &[
+                    ("title", "First"),
+                    ("artist", "Band"),
+                    ("album", "Album"),
+                    ("track", "1/3"),
+                    ("genre", "Rock; Pop"),
+                ],
+            ),
+            file(
+                "c.mp3",
+                &[
+                    ("artist", "Band feat. Guest"),
+                    ("album", "Album"),
+                    ("track", "3"),
+                ],
+            ),
+        ]);
+
+        assert_eq!(album.title, "Album");
+        assert_eq!(album.artist, "Band");
+        assert_eq!(album.genres, ["Rock", "Pop"]);
+        assert_eq!(album.year(), Some(2021));
+        let titles: Vec<&str> = album.tracks.iter().map(|t| t.title.as_str()).collect();
+        assert_eq!(titles, ["First", "Second", "c", "Bonus"]);
+        assert_eq!(album.tracks[2].artist.as_deref(), Some("Band feat. Guest"));
+        assert_eq!(album.tracks[0].artist, None);
+        // there's no album artist, and "c" and "Bonus" are named after their files
+        assert_eq!(album.confidence.artist, Confidence::Guessed);
+        assert_eq!(album.confidence.tracks, Confidence::Guessed);
+        assert_eq!(album.confidence().image, Confidence::Missing);
+    }
+}
@@ -0,0 +1,124 @@
+//! Albums from the [iTunes Search API](https://performance-partners.apple.com/search-api), just for
+//! their covers, which are usually clean, square and big
+
+use crate::network::download_identified;
+use serde::Deserialize;
+use thiserror::Error;
+use url::Url;
+
+const API_URL: &str = "https://itunes.apple.com/search";
+
+#[derive(Debug, Error)]
+pub enum ItunesError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("{0}")]
+    InvalidUrl(#[from] url::ParseError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ItunesAlbum {
+    pub artist_name: String,
+    pub collection_name: String,
+    /// A 100×100 version of the cover
+    pub artwork_url100: Option<String>,
+}
+
+impl ItunesAlbum {
+    /// The cover at `size`×`size`, which the API only gives a thumbnail of, but which can be
+    /// asked for at other sizes by changing the URL
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::scraping::ItunesAlbum;
+    ///
+    /// let album = ItunesAlbum {
+    ///     artist_name: String::from("Artist"),
+    ///     collection_name: String::from("Album"),
+    ///     artwork_url100: Some(String::from(
+    ///         "https://is1-ssl.mzstatic.com/image/thumb/Music/ab/cd/source/100x100bb.jpg",
+    ///     )),
+    /// };
+    /// assert_eq!(
+    ///     album.artwork_url(1000).as_deref(),
+    ///     Some("https://is1-ssl.mzstatic.com/image/thumb/Music/ab/cd/source/1000x1000bb.jpg")
+    /// );
+    /// ```
+    #[must_use]
+    pub fn artwork_url(&self, size: u32) -> Option<String> {
+        let url = self.artwork_url100.as_deref()?;
+        Some(url.replace("100x100bb", &format!("{size}x{size}bb")))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct SearchResults {
+    #[serde(default)]
+    results: Vec<ItunesAlbum>,
+}
+
+/// Searches the iTunes store for the album, best match first
+///
+/// # Errors
+/// - If the API can't be reached or responds with an error
+/// - If the response can't be parsed
+pub fn search_itunes(artist: &str, album: &str) -> Result<Vec<ItunesAlbum>, ItunesError> {
+    let url = Url::parse_with_params(
+        API_URL,
+        [
+            ("term", format!("{artist} {album}").as_str()),
+            ("entity", "album"),
+            ("limit", "5"),
+        ],
+    )?;
+    parse_itunes_search(&download_identified(url.as_str())?.text()?)
+}
+
+/// Parses a search from the API, leaving out any albums without a cover
+///
+/// # Errors
+/// If it isn't a search from the API
+pub fn parse_itunes_search(json: &str) -> Result<Vec<ItunesAlbum>, ItunesError> {
+    let search: SearchResults = serde_json::from_str(json)?;
+    Ok(search
+        .results
+        .into_iter()
+        .filter(|album| album.artwork_url100.is_some())
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn search() {
+        let json = r#"{
+            "resultCount": 2,
+            "results": [
+                {
+                    "wrapperType": "collection",
+                    "collectionType": "Album",
+                    "artistName": "Daft Punk",
+                    "collectionName": "Discovery",
+                    "artworkUrl60": "https://is1-ssl.mzstatic.com/image/thumb/Music/x/60x60bb.jpg",
+                    "artworkUrl100": "https://is1-ssl.mzstatic.com/image/thumb/Music/x/100x100bb.jpg"
+                },
+                {"wrapperType": "collection", "artistName": "Someone", "collectionName": "No Cover"}
+            ]
+        }"#;
+        let albums = parse_itunes_search(json).unwrap();
+        assert_eq!(albums.len(), 1);
+        assert_eq!(albums[0].collection_name, "Discovery");
+        assert_eq!(
+            albums[0].artwork_url(600).as_deref(),
+            Some("https://is1-ssl.mzstatic.com/image/thumb/Music/x/600x600bb.jpg")
+        );
+        assert!(parse_itunes_search(r#"{"resultCount": 0}"#)
+            .unwrap()
+            .is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! Lyrics from [LRCLIB](https://lrclib.net) (or another server with the same API), looked up by
+//! each track's artist, title, album and length so they can be embedded in its tags
+
+use crate::network::download_identified;
+use reqwest::StatusCode;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use thiserror::Error;
+use url::Url;
+
+const API_URL: &str = "https://lrclib.net/api";
+
+#[derive(Debug, Error)]
+pub enum LyricsError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("{0}")]
+    InvalidUrl(#[from] url::ParseError),
+    #[error("couldn't find any lyrics")]
+    NotFound,
+}
+
+/// Set under `[lyrics]` in the config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LyricsSettings {
+    /// Look up each track's lyrics and embed them
+    pub embed: bool,
+    /// Embed the timestamped (LRC) lyrics when there are some, for players that show them line by
+    /// line
+    pub synced: bool,
+    /// Save the timestamped lyrics in an `.lrc` file next to each track too, for players that
+    /// only read them from there
+    pub lrc_file: bool,
+    /// A server with LRCLIB's API to use instead, like a self-hosted one
+    pub api_url: Option<String>,
+}
+
+impl LyricsSettings {
+    fn api_url(&self) -> &str {
+        self.api_url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/'))
+            .filter(|url| !url.is_empty())
+            .unwrap_or(API_URL)
+    }
+}
+
+/// What's known about a track to look up its lyrics
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LyricsQuery<'a> {
+    pub artist: &'a str,
+    pub title: &'a str,
+    pub album: &'a str,
+    /// LRCLIB only matches tracks within a couple of seconds of this, so without it the lyrics
+    /// are searched for instead
+    pub duration: Option<Duration>,
+}
+
+/// A track's lyrics, as LRCLIB has them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lyrics {
+    pub plain_lyrics: Option<String>,
+    /// In LRC format, like `[00:17.12] First line`
+    pub synced_lyrics: Option<String>,
+    #[serde(default)]
+    pub instrumental: bool,
+}
+
+impl Lyrics {
+    /// The lyrics to embed: the synced ones if `synced` is set and there are some, otherwise the
+    /// plain ones (or the synced ones without their timestamps)
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::scraping::Lyrics;
+    ///
+    /// let lyrics = Lyrics {
+    ///     plain_lyrics: None,
+    ///     synced_lyrics: Some(String::from("[00:01.00] Hello\n[00:02.50] World")),
+    ///     instrumental: false,
+    /// };
+    /// assert_eq!(lyrics.text(false).as_deref(), Some("Hello\nWorld"));
+    /// assert_eq!(lyrics.text(true).as_deref(), Some("[00:01.00] Hello\n[00:02.50] World"));
+    /// ```
+    #[must_use]
+    pub fn text(&self, synced: bool) -> Option<String> {
+        let non_empty = |lyrics: &Option<String>| {
+            lyrics
+                .as_deref()
+                .map(str::trim)
+                .filter(|lyrics| !lyrics.is_empty())
+                .map(str::to_string)
+        };
+        let synced_lyrics = non_empty(&self.synced_lyrics);
+        match synced_lyrics {
+            Some(lyrics) if synced => Some(lyrics),
+            synced_lyrics => non_empty(&self.plain_lyrics)
+                .or_else(|| synced_lyrics.map(|lyrics| strip_timestamps(&lyrics))),
+        }
+    }
+
+    /// The timestamped lyrics in LRC format, if there are any
+    #[must_use]
+    pub fn lrc(&self) -> Option<&str> {
+        self.synced_lyrics
+            .as_deref()
+            .map(str::trim)
+            .filter(|lyrics| !parse_lrc(lyrics).is_empty())
+    }
+}
+
+/// Each line of LRC lyrics with the millisecond it starts at, in order. A line with several
+/// timestamps (like a chorus) is there once for each, and tags like `[ar: Artist]` are left out.
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::parse_lrc;
+///
+/// let lrc = "[ti: Song]\n[00:12.50] Chorus\n[00:03.10]First\n[01:02.345][00:20.00] Again";
+/// assert_eq!(
+///     parse_lrc(lrc),
+///     [
+///         (3100, String::from("First")),
+///         (12500, String::from("Chorus")),
+///         (20000, String::from("Again")),
+///         (62345, String::from("Again")),
+///     ]
+/// );
+/// ```
+#[must_use]
+pub fn parse_lrc(lrc: &str) -> Vec<(u32, String)> {
+    let mut lines = Vec::new();
+    for line in lrc.lines() {
+        let mut rest = line.trim();
+        let mut times = Vec::new();
+        while let Some((tag, after)) = rest.strip_prefix('[').and_then(|tag| tag.split_once(']')) {
+            match parse_timestamp(tag) {
+                Some(ms) => times.push(ms),
+                // metadata, or a timestamp that doesn't make sense
+                None => break,
+            }
+            rest = after.trim_start();
+        }
+        lines.extend(times.into_iter().map(|ms| (ms, rest.to_string())));
+    }
+    lines.sort_by_key(|(ms, _)| *ms);
+    lines
+}
+
+/// `mm:ss`, `mm:ss.xx` or `mm:ss.xxx` in milliseconds
+fn parse_timestamp(timestamp: &str) -> Option<u32> {
+    let (minutes, seconds) = timestamp.trim().split_once(':')?;
+    let (seconds, fraction) = seconds.split_once('.').unwrap_or((seconds, ""));
+    let number = |digits: &str| {
+        (!digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()))
+            .then(|| digits.parse::<u32>().ok())
+            .flatten()
+    };
+    let millis = match fraction {
+        "" => 0,
+        // usually hundredths, but some have tenths or thousandths
+        _ if fraction.bytes().all(|b| b.is_ascii_digit()) => {
+            format!("{:0<3}", &fraction[..fraction.len().min(3)])
+                .parse()
+                .ok()?
+        }
+        _ => return None,
+    };
+    let seconds = number(seconds).filter(|seconds| *seconds < 60)?;
+    Some((number(minutes)? * 60 + seconds) * 1000 + millis)
+}
+
+/// Removes the `[mm:ss.xx]` timestamps from the start of each line of LRC lyrics, and the lines
+/// that are only tags like `[ar: Artist]`
+fn strip_timestamps(lrc: &str) -> String {
+    lrc.lines()
+        .filter_map(|line| {
+            let mut line = line.trim();
+            while let Some(rest) = line.strip_prefix('[') {
+                let (tag, rest) = rest.split_once(']')?;
+                if !tag.starts_with(|c: char| c.is_ascii_digit()) {
+                    // metadata rather than a timestamp
+                    return None;
+                }
+                line = rest.trim_start();
+            }
+            Some(line)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Looks up the track's lyrics. With its length, LRCLIB is asked for the exact track first, then
+/// it's searched for by artist and title, using the first result that has lyrics.
+///
+/// # Errors
+/// - If the API can't be reached or responds with an error
+/// - If the response can't be parsed
+/// - If there aren't any lyrics for it, including if it's instrumental
+pub fn fetch_lyrics(
+    query: &LyricsQuery<'_>,
+    settings: &LyricsSettings,
+) -> Result<Lyrics, LyricsError> {
+    let api_url = settings.api_url();
+    if let Some(duration) = query.duration {
+        let url = Url::parse_with_params(
+            &format!("{api_url}/get"),
+            [
+                ("artist_name", query.artist),
+                ("track_name", query.title),
+                ("album_name", query.album),
+                ("duration", &duration.as_secs().to_string()),
+            ],
+        )?;
+        match get_json::<Lyrics>(&url) {
+            Ok(lyrics) => return has_lyrics(lyrics),
+            Err(LyricsError::NotFound) => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    let url = Url::parse_with_params(
+        &format!("{api_url}/search"),
+        [("artist_name", query.artist), ("track_name", query.title)],
+    )?;
+    get_json::<Vec<Lyrics>>(&url)?
+        .into_iter()
+        .find(|lyrics| lyrics.text(false).is_some())
+        .ok_or(LyricsError::NotFound)
+}
+
+/// 404s are [`LyricsError::NotFound`]
+fn get_json<T: for<'de> Deserialize<'de>>(url: &Url) -> Result<T, LyricsError> {
+    match download_identified(url.as_str()) {
+        Ok(resp) => Ok(serde_json::from_str(&resp.text()?)?),
+        Err(err) if err.status() == Some(StatusCode::NOT_FOUND) => Err(LyricsError::NotFound),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn has_lyrics(lyrics: Lyrics) -> Result<Lyrics, LyricsError> {
+    if lyrics.instrumental || lyrics.text(false).is_none() {
+        Err(LyricsError::NotFound)
+    } else {
+        Ok(lyrics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_lrclib() {
+        let json = r#"{
+            "id": 3396226,
+            "trackName": "I Want to Live",
+            "artistName": "Borislav Slavov",
+            "albumName": "Baldur's Gate 3 (Original Game Soundtrack)",
+            "duration": 233,
+            "instrumental": false,
+            "plainLyrics": "I feel your breath upon my neck\nA soft caress as cold as death",
+            "syncedLyrics": "[00:17.12] I feel your breath upon my neck\n[00:22.45] A soft caress as cold as death"
+        }"#;
+        let lyrics: Lyrics = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            lyrics.text(false).as_deref(),
+            Some("I feel your breath upon my neck\nA soft caress as cold as death")
+        );
+        assert!(lyrics.text(true).unwrap().starts_with("[00:17.12]"));
+
+        let instrumental: Lyrics = serde_json::from_str(
+            r#"{"instrumental": true, "plainLyrics": null, "syncedLyrics": null}"#,
+        )
+        .unwrap();
+        assert_eq!(instrumental.text(false), None);
+        assert!(matches!(
+            has_lyrics(instrumental),
+            Err(LyricsError::NotFound)
+        ));
+    }
+
+    #[test]
+    fn strips_timestamps() {
+        let lrc =
+            "[ar: Artist]\n[ti: Title]\n[00:01.00]First\n[00:02.00][00:09.00] Chorus\n\n[00:03.50]";
+        assert_eq!(strip_timestamps(lrc), "First\nChorus\n\n");
+    }
+
+    #[test]
+    fn lrc_timestamps() {
+        assert_eq!(parse_timestamp("01:02"), Some(62_000));
+        assert_eq!(parse_timestamp("00:01.5"), Some(1500));
+        assert_eq!(parse_timestamp("10:00.1234"), Some(600_123));
+        assert_eq!(parse_timestamp("00:75.00"), None);
+        assert_eq!(parse_timestamp("ar: Artist"), None);
+
+        let lyrics = Lyrics {
+            plain_lyrics: Some(String::from("First")),
+            synced_lyrics: Some(String::from("[ar: Artist]\n")),
+            instrumental: false,
+        };
+        assert_eq!(lyrics.lrc(), None);
+        assert!(parse_lrc("First\n[00:x1.00] Second").is_empty());
+    }
+
+    #[test]
+    fn custom_api() {
+        let settings = LyricsSettings {
+            api_url: Some(String::from("http://localhost:3000/api/ ")),
+            ..LyricsSettings::default()
+        };
+        assert_eq!(settings.api_url(), "http://localhost:3000/api");
+        assert_eq!(LyricsSettings::default().api_url(), API_URL);
+    }
+}
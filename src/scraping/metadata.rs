@@ -0,0 +1,210 @@
+//! Album data from any source, so the GUI doesn't need to know where it came from
+
+use super::{
+    BandcampError, DiscogsScrapeError, FolderError, MusicBrainzError, ScrapeYoutubePlaylistError,
+};
+use crate::urls::{classify, UrlKind};
+use id3::Timestamp;
+use std::{path::Path, time::Duration};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MetadataError {
+    #[error("{0}")]
+    Bandcamp(#[from] BandcampError),
+    #[error("{0}")]
+    Discogs(#[from] DiscogsScrapeError),
+    #[error("{0}")]
+    Folder(#[from] FolderError),
+    #[error("{0}")]
+    MusicBrainz(#[from] MusicBrainzError),
+    #[error("{0}")]
+    YoutubePlaylist(#[from] ScrapeYoutubePlaylistError),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TrackMetadata {
+    pub title: String,
+    /// Only if it's different to the album's artist
+    pub artist: Option<String>,
+    pub length: Option<Duration>,
+}
+
+/// How much a scraped field can be trusted, least first, so the ones worth checking can be
+/// pointed out before downloading
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Confidence {
+    /// The source didn't have it, so it's empty or a default like the current year
+    Missing,
+    /// Worked out from something that isn't meant for it, like a playlist's title
+    Guessed,
+    /// Read from the source's structured data, like a release schema, an API or file tags
+    #[default]
+    Parsed,
+    /// Typed in or picked by the user
+    Edited,
+}
+
+impl Confidence {
+    /// Whether the field should be double-checked
+    #[must_use]
+    pub fn is_low(self) -> bool {
+        self < Self::Parsed
+    }
+
+    /// Why the field should be double-checked, if it should
+    #[must_use]
+    pub const fn warning(self) -> Option<&'static str> {
+        match self {
+            Self::Missing => Some("not found"),
+            Self::Guessed => Some("guessed"),
+            Self::Parsed | Self::Edited => None,
+        }
+    }
+}
+
+/// The [`Confidence`] of each of an album's fields
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FieldConfidence {
+    pub title: Confidence,
+    pub artist: Confidence,
+    pub genres: Confidence,
+    pub year: Confidence,
+    pub image: Confidence,
+    /// The track titles
+    pub tracks: Confidence,
+}
+
+/// An album as scraped from a [`MetadataSource`]
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlbumMetadata {
+    pub title: String,
+    pub artist: String,
+    /// Most relevant first
+    pub genres: Vec<String>,
+    /// If it's known without the full release date
+    pub year: Option<i32>,
+    pub released: Option<Timestamp>,
+    /// Link to the cover
+    pub image: Option<String>,
+    pub tracks: Vec<TrackMetadata>,
+    /// How the scraper got each field. Empty fields don't need to be marked
+    /// [`Confidence::Missing`], since [`AlbumMetadata::confidence`] does that.
+    pub confidence: FieldConfidence,
+}
+
+impl AlbumMetadata {
+    /// [`AlbumMetadata::year`], or the year from [`AlbumMetadata::released`]
+    #[must_use]
+    pub fn year(&self) -> Option<i32> {
+        self.year.or(self.released.map(|released| released.year))
+    }
+
+    /// [`AlbumMetadata::confidence`], with the fields that weren't found as
+    /// [`Confidence::Missing`]
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::scraping::{AlbumMetadata, Confidence};
+    ///
+    /// let mut album = AlbumMetadata {
+    ///     title: String::from("Album"),
+    ///     artist: String::from("Artist"),
+    ///     ..AlbumMetadata::default()
+    /// };
+    /// album.confidence.artist = Confidence::Guessed;
+    /// let confidence = album.confidence();
+    /// assert_eq!(confidence.title, Confidence::Parsed);
+    /// assert_eq!(confidence.artist, Confidence::Guessed);
+    /// assert_eq!(confidence.year, Confidence::Missing);
+    /// ```
+    #[must_use]
+    pub fn confidence(&self) -> FieldConfidence {
+        let found = |found: bool, confidence: Confidence| {
+            if found {
+                confidence
+            } else {
+                Confidence::Missing
+            }
+        };
+        let confidence = self.confidence;
+        FieldConfidence {
+            title: found(!self.title.trim().is_empty(), confidence.title),
+            artist: found(!self.artist.trim().is_empty(), confidence.artist),
+            genres: found(!self.genres.is_empty(), confidence.genres),
+            year: found(self.year().is_some(), confidence.year),
+            image: found(self.image.is_some(), confidence.image),
+            tracks: found(!self.tracks.is_empty(), confidence.tracks),
+        }
+    }
+}
+
+/// Somewhere album data can be scraped from
+pub trait MetadataSource {
+    type Error: std::error::Error;
+
+    /// # Errors
+    /// If the album can't be scraped from `url`
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error>;
+}
+
+/// The name of the site [`scrape_metadata`] scrapes `url` from, for showing what it's doing
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::metadata_site;
+///
+/// assert_eq!(metadata_site("https://www.discogs.com/release/1234"), "Discogs");
+/// assert_eq!(metadata_site("https://artist.bandcamp.com/album/album"), "Bandcamp");
+/// ```
+#[must_use]
+pub fn metadata_site(url: &str) -> &'static str {
+    let kind = classify(url);
+    if Path::new(url.trim()).is_dir() {
+        "the folder"
+    } else if kind.is_musicbrainz() {
+        "Musicbrainz"
+    } else if kind == UrlKind::Bandcamp {
+        "Bandcamp"
+    } else if kind.is_youtube() {
+        "Youtube"
+    } else {
+        "Discogs"
+    }
+}
+
+/// Scrapes the album with whichever [`MetadataSource`] the link is for, Discogs if it isn't
+/// recognised. A path to a folder reads the tags of the album in it instead.
+///
+/// # Errors
+/// If the source can't scrape it
+pub fn scrape_metadata(url: &str) -> Result<AlbumMetadata, MetadataError> {
+    let kind = classify(url);
+    Ok(if Path::new(url.trim()).is_dir() {
+        super::Folder::scrape(url)?
+    } else if kind.is_musicbrainz() {
+        super::MusicBrainz::scrape(url)?
+    } else if kind == UrlKind::Bandcamp {
+        super::Bandcamp::scrape(url)?
+    } else if kind.is_youtube() {
+        super::YoutubePlaylist::scrape(url)?
+    } else {
+        super::Discogs::scrape(url)?
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn year_from_released() {
+        let mut album = AlbumMetadata {
+            released: "2023-07-12".parse().ok(),
+            ..AlbumMetadata::default()
+        };
+        assert_eq!(album.year(), Some(2023));
+        album.year = Some(2022);
+        assert_eq!(album.year(), Some(2022));
+    }
+}
@@ -1,8 +1,32 @@
+pub mod bandcamp;
+pub use bandcamp::*;
+
 pub mod discogs;
 pub use discogs::*;
 
+pub mod discogs_api;
+pub use discogs_api::*;
+
+pub mod folder;
+pub use folder::*;
+
+pub mod itunes;
+pub use itunes::*;
+
+pub mod lyrics;
+pub use lyrics::*;
+
+pub mod metadata;
+pub use metadata::*;
+
+pub mod musicbrainz;
+pub use musicbrainz::*;
+
 pub mod youtube;
 pub use youtube::*;
 
 pub mod youtube_playlist;
 pub use youtube_playlist::*;
+
+pub mod youtube_search;
+pub use youtube_search::*;
@@ -0,0 +1,396 @@
+//! Album data from the [MusicBrainz API](https://musicbrainz.org/doc/MusicBrainz_API), as an
+//! alternative to Discogs
+
+use super::{AlbumMetadata, FieldConfidence, MetadataSource, TrackMetadata};
+use crate::{
+    network::download_identified,
+    parsing::parse_release_date,
+    urls::{classify, UrlKind},
+};
+use serde::Deserialize;
+use std::{cmp::Reverse, thread, time::Duration};
+use thiserror::Error;
+
+const API_URL: &str = "https://musicbrainz.org/ws/2";
+/// Musicbrainz allows one request a second
+const RATE_LIMIT: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Error)]
+pub enum MusicBrainzError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error(r#""{0}" isn't a MusicBrainz release, release group or MBID"#)]
+    InvalidLink(String),
+    #[error("the release group doesn't have any releases")]
+    NoReleases,
+}
+
+/// Musicbrainz releases, release groups (which use their earliest official release) or bare
+/// release MBIDs
+#[derive(Debug, Clone, Copy)]
+pub struct MusicBrainz;
+
+impl MetadataSource for MusicBrainz {
+    type Error = MusicBrainzError;
+
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error> {
+        scrape_musicbrainz(url)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ArtistCredit {
+    name: String,
+    #[serde(default)]
+    joinphrase: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Genre {
+    name: String,
+    #[serde(default)]
+    count: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Track {
+    title: String,
+    /// In milliseconds
+    length: Option<u64>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Medium {
+    #[serde(default)]
+    tracks: Vec<Track>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct CoverArtArchive {
+    front: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Release {
+    id: String,
+    title: String,
+    date: Option<String>,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+    #[serde(default)]
+    media: Vec<Medium>,
+    #[serde(default)]
+    genres: Vec<Genre>,
+    #[serde(rename = "cover-art-archive")]
+    cover_art_archive: Option<CoverArtArchive>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseGroupRelease {
+    id: String,
+    date: Option<String>,
+    status: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseGroup {
+    #[serde(default)]
+    releases: Vec<ReleaseGroupRelease>,
+}
+
+/// A release group from a search, for its cover on the Cover Art Archive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseGroupMatch {
+    pub id: String,
+    pub title: String,
+    pub artist: String,
+}
+
+impl ReleaseGroupMatch {
+    /// The group's front cover, which the archive picks from its releases. Not every group has
+    /// one, so this can 404.
+    #[must_use]
+    pub fn cover_url(&self) -> String {
+        format!(
+            "https://coverartarchive.org/release-group/{}/front",
+            self.id
+        )
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct SearchedReleaseGroup {
+    id: String,
+    title: String,
+    #[serde(default)]
+    score: u8,
+    #[serde(rename = "artist-credit", default)]
+    artist_credit: Vec<ArtistCredit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct ReleaseGroupSearch {
+    #[serde(rename = "release-groups", default)]
+    release_groups: Vec<SearchedReleaseGroup>,
+}
+
+/// How sure Musicbrainz has to be of a search result, out of 100, for it to be used
+const MIN_SEARCH_SCORE: u8 = 90;
+
+/// Gets the release from a Musicbrainz release link, release group link (which uses its earliest
+/// official release) or a bare release MBID.
+///
+/// Genres are most voted first, the image is the front cover on the Cover Art Archive if it has
+/// one, and every medium's tracks are one after another.
+///
+/// # Errors
+/// - If it isn't one of those
+/// - If the API can't be reached or responds with an error (e.g. it doesn't exist)
+/// - If the response can't be parsed
+/// - If a release group doesn't have any releases
+pub fn scrape_musicbrainz(link: &str) -> Result<AlbumMetadata, MusicBrainzError> {
+    let release_id = match classify(link) {
+        UrlKind::MusicBrainzRelease(id) => id,
+        UrlKind::MusicBrainzReleaseGroup(id) => {
+            let id = first_release(&id)?;
+            thread::sleep(RATE_LIMIT);
+            id
+        }
+        _ => parse_mbid(link.trim())
+            .ok_or_else(|| MusicBrainzError::InvalidLink(link.to_string()))?
+            .to_string(),
+    };
+
+    let url =
+        format!("{API_URL}/release/{release_id}?inc=artist-credits+recordings+genres&fmt=json");
+    parse_release(&download_identified(&url)?.text()?)
+}
+
+/// The earliest official release in the group, or the earliest of any if none are official
+fn first_release(release_group_id: &str) -> Result<String, MusicBrainzError> {
+    let url = format!("{API_URL}/release-group/{release_group_id}?inc=releases&fmt=json");
+    let group: ReleaseGroup = serde_json::from_str(&download_identified(&url)?.text()?)?;
+    group
+        .releases
+        .into_iter()
+        .min_by_key(|release| {
+            let date = release.date.clone().unwrap_or_default();
+            // undated ones last
+            (
+                release.status.as_deref() != Some("Official"),
+                date.is_empty(),
+                date,
+            )
+        })
+        .map(|release| release.id)
+        .ok_or(MusicBrainzError::NoReleases)
+}
+
+/// Parses a release from the API
+///
+/// # Errors
+/// If it isn't a release from the API
+pub fn parse_release(json: &str) -> Result<AlbumMetadata, MusicBrainzError> {
+    let release: Release = serde_json::from_str(json)?;
+    let artist = join_credits(&release.artist_credit);
+
+    let tracks = release
+        .media
+        .iter()
+        .flat_map(|medium| &medium.tracks)
+        .map(|track| {
+            let track_artist = join_credits(&track.artist_credit);
+            TrackMetadata {
+                title: track.title.clone(),
+                artist: Some(track_artist).filter(|a| !a.is_empty() && *a != artist),
+                length: track.length.map(Duration::from_millis),
+            }
+        })
+        .collect();
+
+    let mut genres = release.genres;
+    genres.sort_by_key(|genre| Reverse(genre.count));
+
+    let has_front = release
+        .cover_art_archive
+        .map_or(false, |archive| archive.front);
+
+    Ok(AlbumMetadata {
+        title: release.title,
+        artist,
+        genres: genres.into_iter().map(|genre| genre.name).collect(),
+        year: None,
+        released: release.date.as_deref().and_then(parse_release_date),
+        image: has_front
+            .then(|| format!("https://coverartarchive.org/release/{}/front", release.id)),
+        tracks,
+        confidence: FieldConfidence::default(),
+    })
+}
+
+/// Searches for release groups with the album's title and artist, best match first
+///
+/// # Errors
+/// - If the API can't be reached or responds with an error
+/// - If the response can't be parsed
+pub fn search_release_groups(
+    artist: &str,
+    album: &str,
+) -> Result<Vec<ReleaseGroupMatch>, MusicBrainzError> {
+    // quoted so they're searched for as phrases
+    let quote = |s: &str| format!(r#""{}""#, s.replace('\\', r"\\").replace('"', r#"\""#));
+    let query = format!("releasegroup:{} AND artist:{}", quote(album), quote(artist));
+    let url = format!(
+        "{API_URL}/release-group/?query={}&limit=5&fmt=json",
+        url::form_urlencoded::byte_serialize(query.as_bytes()).collect::<String>()
+    );
+    parse_release_group_search(&download_identified(&url)?.text()?)
+}
+
+/// Parses a release group search from the API, leaving out the unlikely matches
+///
+/// # Errors
+/// If it isn't a search from the API
+pub fn parse_release_group_search(json: &str) -> Result<Vec<ReleaseGroupMatch>, MusicBrainzError> {
+    let search: ReleaseGroupSearch = serde_json::from_str(json)?;
+    Ok(search
+        .release_groups
+        .into_iter()
+        .filter(|group| group.score >= MIN_SEARCH_SCORE)
+        .map(|group| ReleaseGroupMatch {
+            artist: join_credits(&group.artist_credit),
+            id: group.id,
+            title: group.title,
+        })
+        .collect())
+}
+
+/// Like "Artist feat. Other"
+fn join_credits(credits: &[ArtistCredit]) -> String {
+    let mut joined = String::new();
+    for credit in credits {
+        joined.push_str(&credit.name);
+        joined.push_str(&credit.joinphrase);
+    }
+    joined
+}
+
+/// The MBID if it's shaped like one (`xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx` in hex)
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::parse_mbid;
+///
+/// assert!(parse_mbid("b84ee12a-09ef-421b-82de-0441a926375b").is_some());
+/// assert!(parse_mbid("B84EE12A-09EF-421B-82DE-0441A926375B").is_some());
+/// assert!(parse_mbid("b84ee12a09ef421b82de0441a926375b").is_none());
+/// assert!(parse_mbid("not-an-mbid").is_none());
+/// ```
+#[must_use]
+pub fn parse_mbid(s: &str) -> Option<&str> {
+    let groups: Vec<&str> = s.split('-').collect();
+    let lengths = [8, 4, 4, 4, 12];
+    let valid = groups.len() == lengths.len()
+        && groups
+            .iter()
+            .zip(lengths)
+            .all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()));
+    valid.then_some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn release() {
+        let json = r#"{
+            "id": "b84ee12a-09ef-421b-82de-0441a926375b",
+            "title": "Version Up",
+            "date": "2023-07-12",
+            "artist-credit": [
+                {"name": "ODD EYE CIRCLE", "joinphrase": ""}
+            ],
+            "genres": [
+                {"name": "pop", "count": 1},
+                {"name": "k-pop", "count": 3}
+            ],
+            "cover-art-archive": {"front": true, "count": 1},
+            "media": [
+                {"tracks": [
+                    {"title": "Did You Wait?", "length": 88000, "artist-credit": [
+                        {"name": "ODD EYE CIRCLE", "joinphrase": ""}
+                    ]},
+                    {"title": "Air Force One", "length": null, "artist-credit": [
+                        {"name": "ODD EYE CIRCLE", "joinphrase": " feat. "},
+                        {"name": "Someone"}
+                    ]}
+                ]},
+                {"tracks": [{"title": "Bonus", "length": 200500}]}
+            ]
+        }"#;
+        let album = parse_release(json).unwrap();
+        assert_eq!(album.title, "Version Up");
+        assert_eq!(album.artist, "ODD EYE CIRCLE");
+        assert_eq!(album.genres, ["k-pop", "pop"]);
+        assert_eq!(album.year(), Some(2023));
+        assert_eq!(
+            album.image.as_deref(),
+            Some("https://coverartarchive.org/release/b84ee12a-09ef-421b-82de-0441a926375b/front")
+        );
+        assert_eq!(
+            album.tracks,
+            [
+                TrackMetadata {
+                    title: String::from("Did You Wait?"),
+                    artist: None,
+                    length: Some(Duration::from_secs(88)),
+                },
+                TrackMetadata {
+                    title: String::from("Air Force One"),
+                    artist: Some(String::from("ODD EYE CIRCLE feat. Someone")),
+                    length: None,
+                },
+                TrackMetadata {
+                    title: String::from("Bonus"),
+                    artist: None,
+                    length: Some(Duration::from_millis(200_500)),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn release_group_search() {
+        let json = r#"{
+            "created": "2024-01-01T00:00:00.000Z",
+            "count": 2,
+            "release-groups": [
+                {
+                    "id": "0da580f2-6768-498f-af9d-2becaddf15e0",
+                    "score": 100,
+                    "title": "Discovery",
+                    "artist-credit": [{"name": "Daft Punk"}]
+                },
+                {"id": "b3d1b4a5-1b1c-4a3e-9e4f-1234567890ab", "score": 41, "title": "Discovery Live"}
+            ]
+        }"#;
+        let groups = parse_release_group_search(json).unwrap();
+        assert_eq!(
+            groups,
+            [ReleaseGroupMatch {
+                id: String::from("0da580f2-6768-498f-af9d-2becaddf15e0"),
+                title: String::from("Discovery"),
+                artist: String::from("Daft Punk"),
+            }]
+        );
+        assert_eq!(
+            groups[0].cover_url(),
+            "https://coverartarchive.org/release-group/0da580f2-6768-498f-af9d-2becaddf15e0/front"
+        );
+    }
+}
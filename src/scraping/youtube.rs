@@ -1,20 +1,40 @@
-use crate::utils::reduce_vec_of_results;
-use serde::Deserialize;
+use super::PlaylistItem;
+use crate::{
+    ids::VideoId,
+    network,
+    sources::Channel,
+    utils::{reduce_vec_of_results, serialize_display},
+};
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
-use std::{io, process::Command};
+use std::{io, time::Duration};
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+/// `--print` template for [`search_videos`], read by [`parse_search_results`]
+const SEARCH_TEMPLATE: &str = "%(id)s\t%(duration)s\t%(channel_id)s\t%(channel)s\t%(title)s";
+
+#[derive(Debug, Error, Serialize)]
 pub enum ScrapeYoutubeError {
     #[error("{0}")]
-    IoError(#[from] io::Error),
+    IoError(
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        io::Error,
+    ),
     #[error("{0}")]
-    SerdeJsonError(#[from] serde_json::Error),
+    SerdeJsonError(
+        #[from]
+        #[serde(serialize_with = "serialize_display")]
+        serde_json::Error,
+    ),
+    /// What was being done
+    #[error("{0} timed out")]
+    Timeout(String),
 }
 
 #[derive(Debug, Deserialize)]
 pub struct YoutubeVideo {
-    pub id: String,
+    pub id: VideoId,
     pub title: String,
     pub full_title: Option<String>,
     pub thumbnail: String,
@@ -36,6 +56,16 @@ pub struct YoutubeVideo {
     pub release_date: Option<Value>,
 }
 
+/// A chapter of a video, either set by the uploader or worked out from timestamps in the description
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct YoutubeChapter {
+    pub title: String,
+    /// In seconds
+    pub start_time: f64,
+    /// In seconds
+    pub end_time: f64,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct YoutubeThumbnail {
     pub url: String,
@@ -49,7 +79,7 @@ pub struct YoutubeThumbnail {
 /// - If the yt-dlp command fails
 /// - If it can't parse the returned JSON
 pub fn scrape_youtube(url: &str) -> Result<Vec<YoutubeVideo>, ScrapeYoutubeError> {
-    let output = Command::new("yt-dlp")
+    let output = network::ytdlp()
         .args(["--skip-download", "--dump-json", url])
         .output()?;
 
@@ -63,6 +93,102 @@ pub fn scrape_youtube(url: &str) -> Result<Vec<YoutubeVideo>, ScrapeYoutubeError
     reduce_vec_of_results(video_datas).map_err(ScrapeYoutubeError::SerdeJsonError)
 }
 
+/// Uses the yt-dlp CLI tool to get the titles of some Youtube videos.
+///
+/// Videos that yt-dlp couldn't get (e.g. if they're private) are left out of the returned list of
+/// `(id, title)`.
+///
+/// # Errors
+/// If the yt-dlp command fails to run
+pub fn scrape_titles(ids: &[VideoId]) -> Result<Vec<(VideoId, String)>, ScrapeYoutubeError> {
+    let output = network::ytdlp()
+        .args([
+            "--skip-download",
+            "--ignore-errors",
+            "--print",
+            "%(id)s\t%(title)s",
+        ])
+        .args(ids.iter().map(VideoId::url))
+        .output()?;
+
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(id, title)| Some((id.parse().ok()?, title.to_string())))
+        .collect())
+}
+
+/// Uses the yt-dlp CLI tool to search Youtube, returning up to `count` videos in the order Youtube
+/// ranks them
+///
+/// # Errors
+/// If the yt-dlp command fails to run
+pub fn search_videos(query: &str, count: usize) -> Result<Vec<PlaylistItem>, ScrapeYoutubeError> {
+    let output = network::ytdlp()
+        .args([
+            "--flat-playlist",
+            "--ignore-errors",
+            "--print",
+            SEARCH_TEMPLATE,
+            &format!("ytsearch{count}:{query}"),
+        ])
+        .output()?;
+
+    Ok(parse_search_results(&String::from_utf8_lossy(
+        &output.stdout,
+    )))
+}
+
+/// Each line is [`SEARCH_TEMPLATE`], with "NA" for anything yt-dlp doesn't know. Lines without a
+/// valid ID (like channels in the results) are skipped.
+fn parse_search_results(output: &str) -> Vec<PlaylistItem> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(5, '\t');
+            let id: VideoId = fields.next()?.trim().parse().ok()?;
+            let duration = fields
+                .next()?
+                .trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|secs| secs.is_finite() && *secs > 0.0)
+                .map(Duration::from_secs_f64);
+            let channel = Channel::parse(&format!("{}\t{}", fields.next()?, fields.next()?));
+            let title = fields.next().map(str::trim).filter(|t| !t.is_empty());
+            Some(PlaylistItem {
+                title: title.map(ToString::to_string),
+                id: Some(id),
+                duration,
+                channel,
+            })
+        })
+        .collect()
+}
+
+/// Uses the yt-dlp CLI tool to get the chapters of a video, which is empty if it doesn't have any.
+///
+/// # Errors
+/// - If the yt-dlp command fails
+/// - If it can't parse the returned JSON
+pub fn scrape_chapters(url: &str) -> Result<Vec<YoutubeChapter>, ScrapeYoutubeError> {
+    let output = network::ytdlp()
+        .args(["--skip-download", "--print", "%(chapters)j", url])
+        .output()?;
+
+    parse_chapters(&String::from_utf8_lossy(&output.stdout))
+}
+
+/// yt-dlp prints `null` (or `NA` in older versions) for videos without chapters
+fn parse_chapters(json: &str) -> Result<Vec<YoutubeChapter>, ScrapeYoutubeError> {
+    let json = json.trim();
+    if json.is_empty() || json == "NA" {
+        return Ok(Vec::new());
+    }
+    let chapters: Option<Vec<YoutubeChapter>> = serde_json::from_str(json)?;
+    Ok(chapters.unwrap_or_default())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -103,4 +229,34 @@ mod tests {
             assert!(track.thumbnail.starts_with("https://i.ytimg.com/vi"));
         }
     }
+
+    #[test]
+    fn chapters() {
+        let chapters = parse_chapters(
+            r#"[{"start_time": 0.0, "title": "Intro", "end_time": 70.0}, {"start_time": 70.0, "title": "Air Force One", "end_time": 234.5}]
+"#,
+        )
+        .unwrap();
+        assert_eq!(chapters.len(), 2);
+        assert_eq!(chapters[1].title, "Air Force One");
+        assert!((chapters[1].end_time - 234.5).abs() < f64::EPSILON);
+
+        assert!(parse_chapters("null\n").unwrap().is_empty());
+        assert!(parse_chapters("NA\n").unwrap().is_empty());
+    }
+
+    #[test]
+    fn search_results() {
+        let results = parse_search_results(
+            "dQw4w9WgXcQ\t213.0\tUCuAXFkgsw1L7xaCfnd5JJOw\tRick Astley\tNever Gonna Give You Up\n\
+             UCuAXFkgsw1L7xaCfnd5JJOw\tNA\tNA\tNA\tRick Astley\n\
+             oHg5SJYRHA0\tNA\tNA\tNA\tLive\tat the venue\n",
+        );
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].duration, Some(Duration::from_secs(213)));
+        assert_eq!(results[0].channel.as_ref().unwrap().name, "Rick Astley");
+        assert_eq!(results[1].duration, None);
+        assert_eq!(results[1].channel, None);
+        assert_eq!(results[1].title.as_deref(), Some("Live\tat the venue"));
+    }
 }
@@ -1,6 +1,11 @@
-use crate::utils::{download, selectors::SCRIPT};
+use super::{AlbumMetadata, Confidence, FieldConfidence, MetadataSource, TrackMetadata};
+use crate::{
+    ids::VideoId, network::download, parsing::find_json_object, playlist::page_url,
+    sources::Channel, titles::split_track_number, utils::selectors::SCRIPT,
+};
 use scraper::Html;
 use serde_json::Value;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Debug, Error)]
@@ -36,7 +41,11 @@ impl Playlist {
 #[derive(Debug, Clone, Default)]
 pub struct PlaylistItem {
     pub title: Option<String>,
-    pub id: Option<String>,
+    /// `None` if it's missing or malformed, e.g. for unavailable videos
+    pub id: Option<VideoId>,
+    pub duration: Option<Duration>,
+    /// Who uploaded it, to point out channels that have been marked as a bad source
+    pub channel: Option<Channel>,
 }
 
 fn extract_playlist_data(json: &Value) -> Option<&Value> {
@@ -64,18 +73,33 @@ fn extract_playlist_item(extracted_json: &Value) -> PlaylistItem {
             None
         }
     }
-    fn extract_id(j: &Value) -> Option<String> {
-        if let Some(Value::String(id)) = j.get("videoId") {
-            Some(id.clone())
-        } else {
-            None
-        }
+    fn extract_id(j: &Value) -> Option<VideoId> {
+        j.get("videoId")?.as_str()?.parse().ok()
+    }
+    fn extract_duration(j: &Value) -> Option<Duration> {
+        let secs = j.get("lengthSeconds")?.as_str()?.parse().ok()?;
+        Some(Duration::from_secs(secs))
+    }
+    fn extract_channel(j: &Value) -> Option<Channel> {
+        let run = j.get("shortBylineText")?.get("runs")?.get(0)?;
+        let id = run
+            .get("navigationEndpoint")?
+            .get("browseEndpoint")?
+            .get("browseId")?
+            .as_str()?;
+        let name = run.get("text")?.as_str()?;
+        Some(Channel {
+            id: id.to_string(),
+            name: name.to_string(),
+        })
     }
 
     if let Some(extracted_json) = extracted_json.get("playlistVideoRenderer") {
         PlaylistItem {
             title: extract_title(extracted_json),
             id: extract_id(extracted_json),
+            duration: extract_duration(extracted_json),
+            channel: extract_channel(extracted_json),
         }
     } else {
         PlaylistItem::default()
@@ -122,29 +146,90 @@ fn extract_artist(json: &Value) -> &str {
     extract_artist_opt(json).unwrap_or_default()
 }
 
+/// Pulls the `ytInitialData` JSON out of the contents of a script tag.
+///
+/// Doesn't care how it's assigned, so `var ytInitialData = {...};`, `window["ytInitialData"] = {...};`
+/// and the like all work, with or without whitespace and trailing code.
+fn parse_initial_data(script: &str) -> Option<Value> {
+    const NAME: &str = "ytInitialData";
+
+    script.match_indices(NAME).find_map(|(i, _)| {
+        let rest = script[i + NAME.len()..]
+            .trim_start_matches(['"', '\'', ']'])
+            .trim_start()
+            .strip_prefix('=')?
+            .trim_start();
+        if rest.starts_with('{') {
+            serde_json::from_str(find_json_object(rest)?).ok()
+        } else {
+            None
+        }
+    })
+}
+
 /// Attempts to scrape out playlist information from the given link.
 ///
 /// # Errors
 /// - If it can't actually download the request (via [reqwest])
-/// - If it can't find a valid script tag (whose contents should be `var ytInitialData = <...>;` where `<...>` is valid JSON)
+/// - If it can't find a valid script tag (one that assigns valid JSON to `ytInitialData`)
 pub fn scrape_playlist(url: &str) -> Result<Playlist, ScrapeYoutubePlaylistError> {
     let resp = download(url)?.text()?;
-    let doc = Html::parse_document(&resp);
+    parse_playlist_html(&resp)
+}
+
+/// Youtube (Music) playlists, for when there isn't anywhere better to get the album data from
+#[derive(Debug, Clone, Copy)]
+pub struct YoutubePlaylist;
+
+impl MetadataSource for YoutubePlaylist {
+    type Error = ScrapeYoutubePlaylistError;
+
+    fn scrape(url: &str) -> Result<AlbumMetadata, Self::Error> {
+        scrape_playlist(&page_url(url)).map(|playlist| AlbumMetadata::from(&playlist))
+    }
+}
+
+/// The tracks are named after the videos, without the track number if they start with one. All
+/// of it is [`Confidence::Guessed`], since playlists are often named and uploaded differently to
+/// the album.
+impl From<&Playlist> for AlbumMetadata {
+    fn from(playlist: &Playlist) -> Self {
+        Self {
+            title: playlist.title.clone(),
+            artist: playlist.artist.clone(),
+            tracks: playlist
+                .tracks
+                .iter()
+                .map(|video| TrackMetadata {
+                    title: split_track_number(video.title.as_deref().unwrap_or_default())
+                        .1
+                        .to_string(),
+                    artist: None,
+                    length: video.duration,
+                })
+                .collect(),
+            image: Some(playlist.thumbnail.clone()).filter(|thumbnail| !thumbnail.is_empty()),
+            confidence: FieldConfidence {
+                title: Confidence::Guessed,
+                artist: Confidence::Guessed,
+                image: Confidence::Guessed,
+                tracks: Confidence::Guessed,
+                ..FieldConfidence::default()
+            },
+            ..Self::default()
+        }
+    }
+}
+
+/// Parses the playlist information out of the HTML of a playlist page.
+///
+/// # Errors
+/// If it can't find a valid script tag (one that assigns valid JSON to `ytInitialData`)
+pub fn parse_playlist_html(html: &str) -> Result<Playlist, ScrapeYoutubePlaylistError> {
+    let doc = Html::parse_document(html);
 
     for script in doc.select(&SCRIPT) {
-        let inner = script.inner_html();
-
-        if let Some(Ok(json)) = inner
-            .strip_prefix("var ytInitialData = ")
-            .and_then(|s| {
-                if s.ends_with(';') {
-                    s.strip_suffix(';')
-                } else {
-                    Some(s)
-                }
-            })
-            .map(serde_json::from_str::<Value>)
-        {
+        if let Some(json) = parse_initial_data(&script.inner_html()) {
             if let Some(Value::Array(tracks)) = extract_playlist_data(&json) {
                 return Ok(Playlist {
                     title: extract_title(&json).to_string(),
@@ -176,4 +261,79 @@ mod tests {
             assert_ne!(track.id, None);
         }
     }
+
+    const INITIAL_DATA: &str = r#"{"header":{"playlistHeaderRenderer":{"title":{"simpleText":"Album – Version {Up}"}}},"contents":{"twoColumnBrowseResultsRenderer":{"tabs":[{"tabRenderer":{"content":{"sectionListRenderer":{"contents":[{"itemSectionRenderer":{"contents":[{"playlistVideoListRenderer":{"contents":[{"playlistVideoRenderer":{"videoId":"abcdefghijk","title":{"runs":[{"text":"Did You Wait? \"}\""}]},"shortBylineText":{"runs":[{"text":"ODD EYE CIRCLE"}]},"lengthSeconds":"70"}},{"playlistVideoRenderer":{"videoId":"bcdefghijkl","title":{"runs":[{"text":"Air Force One"}]}}}]}}]}}]}}}}]}}}"#;
+
+    fn check_fixture(html: &str) {
+        let playlist = parse_playlist_html(html).unwrap();
+        assert_eq!(playlist.title, "Version {Up}");
+        assert_eq!(playlist.artist, "ODD EYE CIRCLE");
+        assert_eq!(playlist.tracks.len(), 2);
+        assert_eq!(
+            playlist.tracks[0].title.as_deref(),
+            Some(r#"Did You Wait? "}""#)
+        );
+        assert_eq!(playlist.tracks[0].id.as_ref().unwrap(), "abcdefghijk");
+        assert_eq!(playlist.tracks[1].id.as_ref().unwrap(), "bcdefghijkl");
+        assert_eq!(playlist.tracks[0].duration, Some(Duration::from_secs(70)));
+        assert_eq!(playlist.tracks[1].duration, None);
+    }
+
+    #[test]
+    fn initial_data_var() {
+        check_fixture(&format!(
+            "<html><script>var ytInitialData = {INITIAL_DATA};</script></html>"
+        ));
+    }
+
+    #[test]
+    fn initial_data_no_whitespace_or_semicolon() {
+        check_fixture(&format!(
+            "<html><script>var ytInitialData={INITIAL_DATA}</script></html>"
+        ));
+    }
+
+    #[test]
+    fn initial_data_window_index() {
+        check_fixture(&format!(
+            r#"<html><script>window["ytInitialData"] = {INITIAL_DATA};</script></html>"#
+        ));
+        check_fixture(&format!(
+            "<html><script>window['ytInitialData']  =\n{INITIAL_DATA};</script></html>"
+        ));
+    }
+
+    #[test]
+    fn initial_data_surrounded_by_other_code() {
+        check_fixture(&format!(
+            "<html><script>if (window.ytInitialData) {{}} window.ytInitialData = {INITIAL_DATA}; window.ytcsi = {{}};</script></html>"
+        ));
+    }
+
+    #[test]
+    fn as_metadata() {
+        let playlist = parse_playlist_html(&format!(
+            "<html><script>var ytInitialData = {INITIAL_DATA};</script></html>"
+        ))
+        .unwrap();
+        let album = AlbumMetadata::from(&playlist);
+        assert_eq!(album.title, "Version {Up}");
+        assert_eq!(album.artist, "ODD EYE CIRCLE");
+        assert_eq!(album.image, None);
+        assert_eq!(album.tracks[1].title, "Air Force One");
+        assert_eq!(album.tracks[0].length, Some(Duration::from_secs(70)));
+
+        let confidence = album.confidence();
+        assert_eq!(confidence.title, Confidence::Guessed);
+        assert_eq!(confidence.image, Confidence::Missing);
+        assert_eq!(confidence.year, Confidence::Missing);
+    }
+
+    #[test]
+    fn initial_data_missing() {
+        assert!(matches!(
+            parse_playlist_html("<html><script>var ytInitialPlayerResponse = {};</script></html>"),
+            Err(ScrapeYoutubePlaylistError::MissingScript)
+        ));
+    }
 }
@@ -0,0 +1,172 @@
+//! Finding an album's playlist on Youtube from its artist and title, so only the release's link
+//! has to be given. Youtube Music's album playlists (the ones with IDs starting `OLAK5uy_`) show
+//! up in Youtube's playlist search, which yt-dlp can read.
+
+use super::ScrapeYoutubeError;
+use crate::{network, output_with_deadline, stage_deadline, DownloadError, Timeouts};
+use std::io;
+use url::Url;
+
+/// Youtube's search filter for playlists only
+const PLAYLISTS_FILTER: &str = "EgIQAw==";
+/// What Youtube Music album playlists' IDs start with
+const ALBUM_PREFIX: &str = "OLAK5uy_";
+/// `--print` template for the search, read by [`parse_album_results`]
+const ALBUM_TEMPLATE: &str = "%(id)s\t%(title)s";
+
+/// An album playlist in the search results
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlbumPlaylist {
+    /// Like `OLAK5uy_…`
+    pub id: String,
+    pub title: String,
+}
+
+impl AlbumPlaylist {
+    #[must_use]
+    pub fn url(&self) -> String {
+        format!("https://www.youtube.com/playlist?list={}", self.id)
+    }
+}
+
+/// The Youtube search for playlists of `artist`'s `album`
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::album_search_url;
+///
+/// assert_eq!(
+///     album_search_url("ODD EYE CIRCLE", "Version Up"),
+///     "https://www.youtube.com/results?search_query=ODD+EYE+CIRCLE+Version+Up&sp=EgIQAw%3D%3D"
+/// );
+/// ```
+#[must_use]
+pub fn album_search_url(artist: &str, album: &str) -> String {
+    let query = format!("{} {}", artist.trim(), album.trim());
+    Url::parse_with_params(
+        "https://www.youtube.com/results",
+        [("search_query", query.trim()), ("sp", PLAYLISTS_FILTER)],
+    )
+    .map(String::from)
+    .unwrap_or_default()
+}
+
+/// Each line is [`ALBUM_TEMPLATE`]. Playlists that aren't albums (like ones people have made) are
+/// skipped.
+fn parse_album_results(output: &str) -> Vec<AlbumPlaylist> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (id, title) = line.split_once('\t')?;
+            let id = id.trim();
+            id.starts_with(ALBUM_PREFIX).then(|| AlbumPlaylist {
+                id: id.to_string(),
+                title: title.trim().to_string(),
+            })
+        })
+        .collect()
+}
+
+/// The letters and numbers in `s`, lowercase, so punctuation and spacing don't stop titles
+/// matching
+fn simplify(s: &str) -> String {
+    s.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The first (so best ranked) result that has the album's title in its own, since the titles of
+/// album playlists are usually just the album's
+///
+/// # Examples
+/// ```
+/// use ytmdl::scraping::{best_album_match, AlbumPlaylist};
+///
+/// let playlist = |id: &str, title: &str| AlbumPlaylist {
+///     id: id.to_string(),
+///     title: title.to_string(),
+/// };
+/// let results = [
+///     playlist("OLAK5uy_a", "Version Down"),
+///     playlist("OLAK5uy_b", "Album - Version Up!"),
+///     playlist("OLAK5uy_c", "Version Up"),
+/// ];
+/// assert_eq!(best_album_match(&results, "version up").unwrap().id, "OLAK5uy_b");
+/// assert!(best_album_match(&results, "Another Album").is_none());
+/// ```
+#[must_use]
+pub fn best_album_match<'a>(
+    results: &'a [AlbumPlaylist],
+    album: &str,
+) -> Option<&'a AlbumPlaylist> {
+    let album = simplify(album);
+    if album.is_empty() {
+        return None;
+    }
+    results
+        .iter()
+        .find(|playlist| simplify(&playlist.title).contains(&album))
+}
+
+/// Searches Youtube for the album playlist of `artist`'s `album` with the yt-dlp CLI tool, or
+/// `None` if there isn't one with the album's title. yt-dlp is killed if it takes longer than the
+/// [download timeout](Timeouts::download).
+///
+/// # Errors
+/// - If yt-dlp can't be run
+/// - If it times out
+pub fn find_album_playlist(
+    artist: &str,
+    album: &str,
+) -> Result<Option<AlbumPlaylist>, ScrapeYoutubeError> {
+    log::info!("searching Youtube for the playlist of {artist} - {album}");
+    let mut command = network::ytdlp();
+    command.args([
+        "--flat-playlist",
+        "--ignore-errors",
+        "--playlist-end",
+        "20",
+        "--print",
+        ALBUM_TEMPLATE,
+        &album_search_url(artist, album),
+    ]);
+    let deadline = stage_deadline(Timeouts::from_env().download, None);
+    let output = output_with_deadline(
+        &mut command,
+        deadline,
+        "searching Youtube for the album's playlist",
+    )
+    .map_err(|err| match err {
+        DownloadError::IoError(err) => ScrapeYoutubeError::IoError(err),
+        DownloadError::Timeout(what) => ScrapeYoutubeError::Timeout(what),
+        err => ScrapeYoutubeError::IoError(io::Error::other(err.to_string())),
+    })?;
+    let results = parse_album_results(&String::from_utf8_lossy(&output.stdout));
+    Ok(best_album_match(&results, album).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn album_results() {
+        let output = "\
+            OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ\tVersion Up\n\
+            PLabcdefghijklmnop\tODD EYE CIRCLE full album playlist\n\
+            NA\tNA\n\
+            OLAK5uy_other\tVersion Up (Remixes)\n";
+        let results = parse_album_results(output);
+        assert_eq!(results.len(), 2);
+        assert_eq!(
+            results[0].url(),
+            "https://www.youtube.com/playlist?list=OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ"
+        );
+        assert_eq!(
+            best_album_match(&results, "Version Up").map(|playlist| playlist.title.as_str()),
+            Some("Version Up")
+        );
+        assert_eq!(best_album_match(&results, " ?! "), None);
+    }
+}
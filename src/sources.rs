@@ -0,0 +1,188 @@
+//! Remembering which channels tracks were downloaded from, and whether they were kept or marked as
+//! a bad source (like a re-upload with worse audio) on the results screen, so tracks from a channel
+//! that's been marked before can be pointed out next time
+
+use crate::history::History;
+use serde::{Deserialize, Serialize};
+
+/// The Youtube channel (or other uploader) a track came from
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Channel {
+    /// Like `UCxxxxxxxxxxxxxxxxxxxxxx` on Youtube
+    pub id: String,
+    pub name: String,
+}
+
+impl Channel {
+    /// yt-dlp `--print` template for [`Channel::parse`]
+    pub const TEMPLATE: &'static str = "%(channel_id)s\t%(channel)s";
+
+    /// What's printed for yt-dlp's `--print` [`Channel::TEMPLATE`], which has "NA" for anything
+    /// it doesn't know. The name falls back to the ID.
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::sources::Channel;
+    ///
+    /// let channel = Channel::parse("UCabc\tArtist - Topic").unwrap();
+    /// assert_eq!(channel.id, "UCabc");
+    /// assert_eq!(channel.name, "Artist - Topic");
+    /// assert_eq!(Channel::parse("UCabc\tNA").unwrap().name, "UCabc");
+    /// assert_eq!(Channel::parse("NA\tSomeone"), None);
+    /// ```
+    #[must_use]
+    pub fn parse(printed: &str) -> Option<Self> {
+        let mut fields = printed
+            .trim()
+            .split('\t')
+            .map(|field| Some(field.trim()).filter(|f| !f.is_empty() && *f != "NA"));
+        let id = fields.next()??.to_string();
+        let name = fields.next().flatten().unwrap_or(&id).to_string();
+        Some(Self { id, name })
+    }
+}
+
+/// How the tracks downloaded from a channel turned out, kept in the [`History`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SourceRecord {
+    pub channel: Channel,
+    /// Tracks that were downloaded and not marked as a bad source
+    pub kept: u32,
+    /// Tracks marked as a bad source after they were downloaded, to be replaced
+    pub flagged: u32,
+}
+
+impl SourceRecord {
+    /// Whether any of its tracks have been marked as a bad source
+    #[must_use]
+    pub fn is_flagged(&self) -> bool {
+        self.flagged > 0
+    }
+
+    /// Shown next to a track from the channel
+    #[must_use]
+    pub fn warning(&self) -> String {
+        format!(
+            "⚠ From {}, which you've marked as a bad source for {} of {} tracks",
+            self.channel.name,
+            self.flagged,
+            self.kept + self.flagged
+        )
+    }
+}
+
+impl History {
+    /// Counts a downloaded track from each of `channels` as kept
+    pub fn keep_sources<'a>(&mut self, channels: impl IntoIterator<Item = &'a Channel>) {
+        for channel in channels {
+            self.source_mut(channel).kept += 1;
+        }
+    }
+
+    /// Marks a downloaded track from `channel` as a bad source (or takes that back if `flagged`
+    /// is `false`), moving it between kept and flagged
+    pub fn flag_source(&mut self, channel: &Channel, flagged: bool) {
+        let record = self.source_mut(channel);
+        if flagged {
+            record.kept = record.kept.saturating_sub(1);
+            record.flagged += 1;
+        } else if record.flagged > 0 {
+            record.flagged -= 1;
+            record.kept += 1;
+        }
+    }
+
+    /// The channels that have been marked as a bad source
+    #[must_use]
+    pub fn flagged_sources(&self) -> Vec<SourceRecord> {
+        self.sources
+            .iter()
+            .filter(|record| record.is_flagged())
+            .cloned()
+            .collect()
+    }
+
+    /// Its record, added if there isn't one, with the name updated in case it's changed
+    fn source_mut(&mut self, channel: &Channel) -> &mut SourceRecord {
+        let existing = self
+            .sources
+            .iter()
+            .position(|record| record.channel.id == channel.id);
+        let index = if let Some(index) = existing {
+            index
+        } else {
+            self.sources.push(SourceRecord {
+                channel: channel.clone(),
+                kept: 0,
+                flagged: 0,
+            });
+            self.sources.len() - 1
+        };
+        let record = &mut self.sources[index];
+        record.channel.name.clone_from(&channel.name);
+        record
+    }
+}
+
+/// The channels that have been marked as a bad source in the saved history, or none if it can't be
+/// loaded
+#[must_use]
+pub fn flagged() -> Vec<SourceRecord> {
+    History::load().map_or_else(
+        |err| {
+            log::warn!("couldn't load the download history: {err}");
+            Vec::new()
+        },
+        |history| history.flagged_sources(),
+    )
+}
+
+/// Marks a downloaded track from `channel` as a bad source (or takes that back) in the saved
+/// history, just logging if that fails
+pub fn flag(channel: &Channel, flagged: bool) {
+    let result = History::load().and_then(|mut history| {
+        history.flag_source(channel, flagged);
+        history.save()
+    });
+    if let Err(err) = result {
+        log::warn!("couldn't save the source to the download history: {err}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn channel(id: &str, name: &str) -> Channel {
+        Channel {
+            id: id.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn kept_and_flagged() {
+        let topic = channel("UCtopic", "Artist - Topic");
+        let reupload = channel("UCreupload", "Some Reuploader");
+        let mut history = History::default();
+        history.keep_sources([&topic, &topic, &reupload]);
+        assert!(history.flagged_sources().is_empty());
+
+        history.flag_source(&channel("UCreupload", "Renamed Reuploader"), true);
+        let flagged = history.flagged_sources();
+        assert_eq!(flagged.len(), 1);
+        assert_eq!(flagged[0].kept, 0);
+        assert_eq!(flagged[0].flagged, 1);
+        assert_eq!(
+            flagged[0].warning(),
+            "⚠ From Renamed Reuploader, which you've marked as a bad source for 1 of 1 tracks"
+        );
+
+        history.flag_source(&reupload, false);
+        assert!(history.flagged_sources().is_empty());
+        assert_eq!(history.sources[1].kept, 1);
+        // taking it back again doesn't go below zero
+        history.flag_source(&reupload, false);
+        assert_eq!(history.sources[1].kept, 1);
+    }
+}
@@ -0,0 +1,320 @@
+//! Tags for each format. They're all made from the same ID3 [`Tag`], which MP3s and AIFFs are
+//! tagged with directly, and ffmpeg writes it for the rest as Vorbis comments (FLAC and Opus), MP4
+//! atoms (M4A) or a `LIST`/`INFO` chunk (WAV).
+
+use crate::config::OutputFormat;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use id3::{frame::Picture, Tag, TagLike, Version};
+use std::path::Path;
+
+/// ID3 text frames and the Vorbis comments they become
+const VORBIS_TEXT_FRAMES: &[(&str, &str)] = &[
+    ("TSOP", "ARTISTSORT"),
+    ("TSO2", "ALBUMARTISTSORT"),
+    ("TSOA", "ALBUMSORT"),
+    ("TSOT", "TITLESORT"),
+    ("TBPM", "BPM"),
+    ("TKEY", "INITIALKEY"),
+];
+
+/// The tag as ffmpeg metadata for `format`, leaving out anything that's empty.
+///
+/// M4A only gets the standard atoms ffmpeg writes (title, artists, album, date, track, genre and
+/// lyrics), and WAV only the ones its `INFO` chunk has (title, artist, album, date, track and
+/// genre).
+/// The cover isn't included, since it's attached as a stream, except for Opus where ffmpeg can't
+/// do that so it's a `METADATA_BLOCK_PICTURE` comment.
+#[must_use]
+pub fn ffmpeg_metadata(tag: &Tag, format: OutputFormat) -> Vec<(String, String)> {
+    let text = |id: &str| {
+        tag.get(id)
+            .and_then(|frame| frame.content().text())
+            .map(str::to_string)
+    };
+    let lyrics = tag.lyrics().next().map(|lyrics| lyrics.text.clone());
+    let date = tag
+        .date_released()
+        .map(|date| date.to_string())
+        .or_else(|| tag.year().map(|year| year.to_string()));
+
+    let metadata: Vec<(String, Option<String>)> = if format == OutputFormat::Wav {
+        // ffmpeg turns these into the `INFO` chunk's `INAM`, `IART` and so on
+        vec![
+            (String::from("title"), text("TIT2")),
+            (String::from("artist"), text("TPE1")),
+            (String::from("album"), text("TALB")),
+            (String::from("date"), date),
+            (
+                String::from("track"),
+                tag.track().map(|track| track.to_string()),
+            ),
+            (String::from("genre"), text("TCON")),
+        ]
+    } else if format == OutputFormat::M4a {
+        let track = tag.track().map(|track| match tag.total_tracks() {
+            Some(total) => format!("{track}/{total}"),
+            None => track.to_string(),
+        });
+        vec![
+            (String::from("title"), text("TIT2")),
+            (String::from("artist"), text("TPE1")),
+            (String::from("album_artist"), text("TPE2")),
+            (String::from("album"), text("TALB")),
+            (String::from("date"), date),
+            (String::from("track"), track),
+            (String::from("genre"), text("TCON")),
+            (String::from("lyrics"), lyrics),
+        ]
+    } else {
+        let mut metadata = vec![
+            (String::from("TITLE"), text("TIT2")),
+            (String::from("ARTIST"), text("TPE1")),
+            (String::from("ALBUMARTIST"), text("TPE2")),
+            (String::from("ALBUM"), text("TALB")),
+            (String::from("DATE"), date),
+            (
+                String::from("TRACKNUMBER"),
+                tag.track().map(|track| track.to_string()),
+            ),
+            (
+                String::from("TRACKTOTAL"),
+                tag.total_tracks().map(|total| total.to_string()),
+            ),
+            (String::from("GENRE"), text("TCON")),
+            (String::from("LYRICS"), lyrics),
+        ];
+        for (id, name) in VORBIS_TEXT_FRAMES {
+            metadata.push(((*name).to_string(), text(id)));
+        }
+        let rating = tag
+            .frames()
+            .find_map(|frame| frame.content().popularimeter())
+            .map(|popm| (u32::from(stars(popm.rating)) * 20).to_string());
+        metadata.push((String::from("RATING"), rating));
+        for extended in tag.extended_texts() {
+            metadata.push((
+                extended.description.to_uppercase(),
+                Some(extended.value.clone()),
+            ));
+        }
+        if format == OutputFormat::Opus {
+            let picture = tag
+                .pictures()
+                .next()
+                .map(|p| STANDARD.encode(picture_block(p)));
+            metadata.push((String::from("METADATA_BLOCK_PICTURE"), picture));
+        }
+        metadata
+    };
+
+    metadata
+        .into_iter()
+        .filter_map(|(key, value)| Some((key, value.filter(|value| !value.is_empty())?)))
+        .collect()
+}
+
+/// Stars out of 5 from a POPM rating, with the same ranges as Windows Media Player
+fn stars(rating: u8) -> u8 {
+    match rating {
+        0 => 0,
+        1..=31 => 1,
+        32..=95 => 2,
+        96..=159 => 3,
+        160..=223 => 4,
+        _ => 5,
+    }
+}
+
+/// A FLAC picture block, which is what Vorbis comments embed (base64 encoded) for the cover.
+///
+/// The width, height and colour depth are left as 0, which players work out themselves.
+#[must_use]
+pub fn picture_block(picture: &Picture) -> Vec<u8> {
+    #[allow(clippy::cast_possible_truncation)]
+    fn push_with_len(block: &mut Vec<u8>, bytes: &[u8]) {
+        block.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+        block.extend_from_slice(bytes);
+    }
+
+    let mut block = Vec::with_capacity(32 + picture.mime_type.len() + picture.data.len());
+    block.extend_from_slice(&u32::from(u8::from(picture.picture_type)).to_be_bytes());
+    push_with_len(&mut block, picture.mime_type.as_bytes());
+    push_with_len(&mut block, picture.description.as_bytes());
+    // width, height, colour depth and number of colours
+    block.extend_from_slice(&[0; 16]);
+    push_with_len(&mut block, &picture.data);
+    block
+}
+
+/// `metadata` as an ffmetadata file, which ffmpeg reads with `-f ffmetadata`. Using a file rather
+/// than `-metadata` arguments means a big cover doesn't go over the command line's length limit.
+///
+/// # Examples
+/// ```
+/// use ytmdl::tagging::ffmetadata;
+///
+/// let metadata = [(String::from("TITLE"), String::from("a=b; c"))];
+/// assert_eq!(ffmetadata(&metadata), ";FFMETADATA1\nTITLE=a\\=b\\; c\n");
+/// ```
+#[must_use]
+pub fn ffmetadata(metadata: &[(String, String)]) -> String {
+    let escape = |s: &str| {
+        let mut escaped = String::with_capacity(s.len());
+        for c in s.chars() {
+            if matches!(c, '=' | ';' | '#' | '\\' | '\n') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+        }
+        escaped
+    };
+    let mut file = String::from(";FFMETADATA1\n");
+    for (key, value) in metadata {
+        file.push_str(&escape(key));
+        file.push('=');
+        file.push_str(&escape(value));
+        file.push('\n');
+    }
+    file
+}
+
+/// Reads the ID3 tag of a file in a format that [uses ID3](OutputFormat::uses_id3). AIFFs have it
+/// in an `ID3 ` chunk rather than at the start of the file.
+///
+/// # Errors
+/// If the file can't be read or doesn't have a tag
+pub fn read_id3(path: &Path, format: OutputFormat) -> id3::Result<Tag> {
+    if format == OutputFormat::Aiff {
+        Tag::read_from_aiff_path(path)
+    } else {
+        Tag::read_from_path(path)
+    }
+}
+
+/// Writes the ID3 tag of a file in a format that [uses ID3](OutputFormat::uses_id3), in the same
+/// place [`read_id3`] reads it from
+///
+/// # Errors
+/// If the file can't be read or written
+pub fn write_id3(
+    tag: &Tag,
+    path: &Path,
+    format: OutputFormat,
+    version: Version,
+) -> id3::Result<()> {
+    if format == OutputFormat::Aiff {
+        tag.write_to_aiff_path(path, version)
+    } else {
+        tag.write_to_path(path, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use id3::frame::{ExtendedText, PictureType, Popularimeter};
+
+    fn tag() -> Tag {
+        let mut tag = Tag::new();
+        tag.set_title("Song");
+        tag.set_artist("The Band");
+        tag.set_album("Album");
+        tag.set_year(2023);
+        tag.set_track(3);
+        tag.set_total_tracks(10);
+        tag.set_text("TSOP", "Band, The");
+        tag.add_frame(Popularimeter {
+            user: String::new(),
+            rating: 196,
+            counter: 0,
+        });
+        tag.add_frame(ExtendedText {
+            description: String::from("favorite"),
+            value: String::from("1"),
+        });
+        tag.add_frame(Picture {
+            mime_type: String::from("image/jpeg"),
+            picture_type: PictureType::CoverFront,
+            description: String::new(),
+            data: vec![1, 2, 3],
+        });
+        tag.add_frame(id3::frame::Lyrics {
+            lang: String::from("eng"),
+            description: String::new(),
+            text: String::from("First line\nSecond line"),
+        });
+        tag
+    }
+
+    fn get<'a>(metadata: &'a [(String, String)], key: &str) -> Option<&'a str> {
+        metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    #[test]
+    fn vorbis_comments() {
+        let metadata = ffmpeg_metadata(&tag(), OutputFormat::Flac);
+        assert_eq!(get(&metadata, "TITLE"), Some("Song"));
+        assert_eq!(get(&metadata, "DATE"), Some("2023"));
+        assert_eq!(get(&metadata, "TRACKNUMBER"), Some("3"));
+        assert_eq!(get(&metadata, "TRACKTOTAL"), Some("10"));
+        assert_eq!(get(&metadata, "ARTISTSORT"), Some("Band, The"));
+        assert_eq!(get(&metadata, "RATING"), Some("80"));
+        assert_eq!(get(&metadata, "FAVORITE"), Some("1"));
+        assert_eq!(get(&metadata, "ALBUMARTIST"), None);
+        assert_eq!(get(&metadata, "LYRICS"), Some("First line\nSecond line"));
+        // FLAC has the cover attached instead
+        assert_eq!(get(&metadata, "METADATA_BLOCK_PICTURE"), None);
+
+        let metadata = ffmpeg_metadata(&tag(), OutputFormat::Opus);
+        let picture = get(&metadata, "METADATA_BLOCK_PICTURE").unwrap();
+        let block = STANDARD.decode(picture).unwrap();
+        assert_eq!(block[..4], [0, 0, 0, 3]);
+        assert_eq!(block[4..8], [0, 0, 0, 10]);
+        assert_eq!(&block[8..18], b"image/jpeg");
+        assert_eq!(block.len(), 8 + 10 + 4 + 16 + 4 + 3);
+        assert_eq!(block[block.len() - 7..], [0, 0, 0, 3, 1, 2, 3]);
+    }
+
+    #[test]
+    fn mp4_atoms() {
+        let metadata = ffmpeg_metadata(&tag(), OutputFormat::M4a);
+        assert_eq!(get(&metadata, "title"), Some("Song"));
+        assert_eq!(get(&metadata, "artist"), Some("The Band"));
+        assert_eq!(get(&metadata, "track"), Some("3/10"));
+        assert_eq!(get(&metadata, "date"), Some("2023"));
+        assert_eq!(get(&metadata, "lyrics"), Some("First line\nSecond line"));
+        assert_eq!(get(&metadata, "RATING"), None);
+    }
+
+    #[test]
+    fn riff_info() {
+        let metadata = ffmpeg_metadata(&tag(), OutputFormat::Wav);
+        assert_eq!(get(&metadata, "title"), Some("Song"));
+        assert_eq!(get(&metadata, "track"), Some("3"));
+        assert_eq!(get(&metadata, "date"), Some("2023"));
+        assert_eq!(get(&metadata, "lyrics"), None);
+        assert_eq!(get(&metadata, "METADATA_BLOCK_PICTURE"), None);
+    }
+
+    #[test]
+    fn aiff_id3_chunk() {
+        let dir = tempdir::TempDir::new("ytmdl-aiff").unwrap();
+        let path = dir.path().join("Song.aiff");
+        // just the FORM header and an empty COMM chunk, which is all the tag is written around
+        std::fs::write(&path, b"FORM\0\0\0\x0cAIFFCOMM\0\0\0\0").unwrap();
+
+        write_id3(&tag(), &path, OutputFormat::Aiff, Version::Id3v24).unwrap();
+        // still an AIFF, with the tag in a chunk rather than in front of it
+        assert!(std::fs::read(&path).unwrap().starts_with(b"FORM"));
+        assert_eq!(
+            Tag::read_from_aiff_path(&path).unwrap().title(),
+            Some("Song")
+        );
+        let read = read_id3(&path, OutputFormat::Aiff).unwrap();
+        assert_eq!(read.title(), Some("Song"));
+        assert_eq!(read.pictures().count(), 1);
+    }
+}
@@ -0,0 +1,203 @@
+//! Running the whole pipeline without the network, for integration tests (behind the
+//! `test-support` feature).
+//!
+//! [`FixtureServer`] serves recorded pages from the local machine in place of Discogs and
+//! Youtube, and [`Sandbox`] points ytmdl's dirs into a temp dir and puts stub `yt-dlp` and
+//! `ffmpeg` scripts first on the `PATH`, so [`download_album`](crate::download_album) can be run
+//! end to end.
+
+use std::{
+    collections::HashMap,
+    io::{self, BufRead, BufReader, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    thread::{self, JoinHandle},
+};
+
+#[cfg(unix)]
+mod sandbox;
+#[cfg(unix)]
+pub use sandbox::*;
+
+/// A Discogs release page for "Fixture Artist - Fixture Album" with two tracks, with its image
+/// at `{server}/cover.jpg` (replace `{server}` with [`FixtureServer::base_url`])
+pub const DISCOGS_RELEASE: &str = include_str!("fixtures/discogs_release.html");
+/// A Youtube playlist page with the two tracks from [`DISCOGS_RELEASE`]
+pub const YOUTUBE_PLAYLIST: &str = include_str!("fixtures/youtube_playlist.html");
+
+/// The smallest thing that passes for a JPEG
+#[must_use]
+pub fn cover_jpeg() -> Vec<u8> {
+    let mut img = vec![0xFF, 0xD8, 0xFF, 0xE0];
+    img.resize(crate::album_art::MIN_IMAGE_SIZE * 2, 0);
+    img.extend([0xFF, 0xD9]);
+    img
+}
+
+/// What's served at a path
+#[derive(Debug, Clone)]
+pub struct Fixture {
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Fixture {
+    #[must_use]
+    pub fn html(body: impl Into<String>) -> Self {
+        Self {
+            content_type: "text/html; charset=utf-8",
+            body: body.into().into_bytes(),
+        }
+    }
+
+    #[must_use]
+    pub fn jpeg(body: Vec<u8>) -> Self {
+        Self {
+            content_type: "image/jpeg",
+            body,
+        }
+    }
+}
+
+type Routes = Arc<Mutex<HashMap<String, Fixture>>>;
+
+/// A tiny HTTP server on `127.0.0.1`, serving [`Fixture`]s until it's dropped.
+///
+/// Paths are matched with their query string first, then without it. Anything else is a 404.
+#[derive(Debug)]
+pub struct FixtureServer {
+    addr: SocketAddr,
+    routes: Routes,
+    stop: Arc<AtomicBool>,
+    handle: Option<JoinHandle<()>>,
+}
+
+impl FixtureServer {
+    /// Starts serving on a free port, with nothing to serve yet
+    ///
+    /// # Errors
+    /// If it can't listen on the local machine
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?;
+        let routes = Routes::default();
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let handle = {
+            let routes = Arc::clone(&routes);
+            let stop = Arc::clone(&stop);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if stop.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let routes = Arc::clone(&routes);
+                    thread::spawn(move || {
+                        if let Err(err) = respond(stream, &routes) {
+                            log::warn!("fixture server: {err}");
+                        }
+                    });
+                }
+            })
+        };
+
+        Ok(Self {
+            addr,
+            routes,
+            stop,
+            handle: Some(handle),
+        })
+    }
+
+    /// Like `http://127.0.0.1:1234`
+    #[must_use]
+    pub fn base_url(&self) -> String {
+        format!("http://{}", self.addr)
+    }
+
+    /// `path` (starting with `/`) on this server
+    #[must_use]
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.base_url())
+    }
+
+    /// Serves `fixture` at `path` (starting with `/`), replacing anything already there
+    pub fn serve(&self, path: &str, fixture: Fixture) {
+        self.routes
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(path.to_string(), fixture);
+    }
+}
+
+impl Drop for FixtureServer {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        // wakes the listener up so it sees it's been stopped
+        let _ = TcpStream::connect(self.addr);
+        if let Some(handle) = self.handle.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+fn respond(stream: TcpStream, routes: &Mutex<HashMap<String, Fixture>>) -> io::Result<()> {
+    let mut reader = BufReader::new(stream);
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // the headers don't matter, but they have to be read before responding
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 0 && !header.trim_end().is_empty() {
+        header.clear();
+    }
+
+    let mut parts = request_line.split_whitespace();
+    let method = parts.next().unwrap_or_default();
+    let target = parts.next().unwrap_or_default();
+    let fixture = {
+        let routes = routes.lock().unwrap_or_else(PoisonError::into_inner);
+        routes
+            .get(target)
+            .or_else(|| routes.get(target.split('?').next().unwrap_or_default()))
+            .cloned()
+    };
+
+    let mut stream = reader.into_inner();
+    let (status, content_type, body) = match fixture {
+        Some(fixture) => ("200 OK", fixture.content_type, fixture.body),
+        None => ("404 Not Found", "text/plain", b"not found".to_vec()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        body.len()
+    )?;
+    if method != "HEAD" {
+        stream.write_all(&body)?;
+    }
+    stream.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::download;
+
+    #[test]
+    fn serves_fixtures() {
+        let server = FixtureServer::start().unwrap();
+        server.serve("/page", Fixture::html("<html></html>"));
+        assert_eq!(
+            download(&server.url("/page?a=b")).unwrap().text().unwrap(),
+            "<html></html>"
+        );
+        assert_eq!(
+            download(&server.url("/missing")).unwrap().status(),
+            reqwest::StatusCode::NOT_FOUND
+        );
+    }
+}
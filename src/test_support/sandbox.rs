@@ -0,0 +1,139 @@
+use std::{
+    env,
+    ffi::OsString,
+    fs, io,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+use tempdir::TempDir;
+
+/// Stands in for yt-dlp: prints a length (`YTDLP_STUB_DURATION` seconds, or the fixture's first
+/// track's 3:05), an Opus format, a channel and the file name for `--print`, otherwise writes a few
+/// bytes there, logging each call to `yt-dlp.log` next to it
+const YTDLP_STUB: &str = r#"#!/bin/sh
+echo "$@" >> "$(dirname "$0")/yt-dlp.log"
+get_filename=
+dir=.
+out=
+url=
+while [ $# -gt 0 ]; do
+    case "$1" in
+        --print) shift; get_filename=1 ;;
+        -P) shift; dir="$1" ;;
+        -o) shift; out="$1" ;;
+        -f|--download-sections|--proxy|--compat-options) shift ;;
+        -*) ;;
+        *) url="$1" ;;
+    esac
+    shift
+done
+path="$dir/$(echo "$out" | sed 's/%(ext)s/webm/')"
+if [ -n "$get_filename" ]; then
+    printf '%s\t251\topus\t129.478\tUCstub\tStub Channel\n' "${YTDLP_STUB_DURATION:-185}"
+    echo "$path"
+else
+    echo "stub audio from $url" > "$path"
+fi
+"#;
+
+/// Stands in for ffmpeg: copies the `-i` input to the output (the last argument, `-` for stdout)
+const FFMPEG_STUB: &str = r#"#!/bin/sh
+echo "$@" >> "$(dirname "$0")/ffmpeg.log"
+for out; do :; done
+in=
+while [ $# -gt 0 ]; do
+    case "$1" in
+        -i) shift; in="$1" ;;
+    esac
+    shift
+done
+if [ "$out" = "-" ]; then
+    cat "$in"
+else
+    cp "$in" "$out"
+fi
+"#;
+
+/// Points ytmdl's config, cache and data dirs and `YTMDL_OUT_DIR` into a temp dir, and puts stub
+/// `yt-dlp` and `ffmpeg` scripts first on the `PATH`. Everything's put back when it's dropped.
+///
+/// These are process-wide, so there should only be one at a time, and it has to be made before
+/// anything reads the config (which is only loaded once).
+#[derive(Debug)]
+pub struct Sandbox {
+    dir: TempDir,
+    previous: Vec<(&'static str, Option<OsString>)>,
+}
+
+impl Sandbox {
+    /// # Errors
+    /// If the temp dir or the stubs can't be made
+    pub fn new() -> io::Result<Self> {
+        let dir = TempDir::new("ytmdl-sandbox")?;
+        let bin = dir.path().join("bin");
+        fs::create_dir(&bin)?;
+        for (name, script) in [("yt-dlp", YTDLP_STUB), ("ffmpeg", FFMPEG_STUB)] {
+            let path = bin.join(name);
+            fs::write(&path, script)?;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o755))?;
+        }
+
+        let mut path = OsString::from(&bin);
+        if let Some(old_path) = env::var_os("PATH") {
+            path.push(":");
+            path.push(old_path);
+        }
+        let vars = [
+            ("HOME", dir.path().join("home").into_os_string()),
+            (
+                "XDG_CONFIG_HOME",
+                dir.path().join("config").into_os_string(),
+            ),
+            ("XDG_CACHE_HOME", dir.path().join("cache").into_os_string()),
+            ("XDG_DATA_HOME", dir.path().join("data").into_os_string()),
+            ("YTMDL_OUT_DIR", dir.path().join("out").into_os_string()),
+            ("YTMDL_MIN_FREE_SPACE", OsString::from("0")),
+            ("PATH", path),
+        ];
+        let previous = vars
+            .iter()
+            .map(|(key, _)| (*key, env::var_os(key)))
+            .collect();
+        for (key, value) in vars {
+            env::set_var(key, value);
+        }
+
+        Ok(Self { dir, previous })
+    }
+
+    /// The temp dir everything's in
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        self.dir.path()
+    }
+
+    /// Where the downloads go
+    #[must_use]
+    pub fn out_dir(&self) -> PathBuf {
+        self.dir.path().join("out")
+    }
+
+    /// Each time the stub was run (`yt-dlp` or `ffmpeg`), as its arguments joined by spaces
+    #[must_use]
+    pub fn calls(&self, tool: &str) -> Vec<String> {
+        fs::read_to_string(self.dir.path().join("bin").join(format!("{tool}.log")))
+            .map(|log| log.lines().map(str::to_string).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Drop for Sandbox {
+    fn drop(&mut self) {
+        for (key, value) in self.previous.drain(..) {
+            match value {
+                Some(value) => env::set_var(key, value),
+                None => env::remove_var(key),
+            }
+        }
+    }
+}
@@ -1,4 +1,155 @@
+//! Running tracks in parallel. Each track runs on [`POOL`] (through [`run_tracks`]), and its
+//! downloads and conversions are limited separately by [`NETWORK`] and [`CPU`], so slow downloads
+//! don't hold up conversions of the tracks that are already downloaded, and the other way round.
+//!
+//! The limits are shared by every album being downloaded, so the queue downloading a few at once
+//! (see [`albums_at_once`]) doesn't run any more yt-dlp or ffmpeg processes than one album would.
+
+use crate::config;
 use once_cell::sync::Lazy;
-use rayon::{ThreadPool, ThreadPoolBuilder};
+use rayon::{
+    prelude::{IntoParallelIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
+use std::{
+    num::NonZeroUsize,
+    sync::{Condvar, Mutex, PoisonError},
+    thread,
+};
+
+/// Big enough for every download and conversion to run at once, with as many tracks again waiting
+/// for a turn for each album that can be downloaded at once, so one album's tracks can't take all
+/// the threads while they wait
+pub static POOL: Lazy<ThreadPool> = Lazy::new(|| {
+    ThreadPoolBuilder::new()
+        .num_threads((NETWORK.size() + CPU.size()) * albums_at_once())
+        .build()
+        .unwrap()
+});
+
+/// yt-dlp calls, sized by the config's `download_threads`
+pub static NETWORK: Lazy<Semaphore> =
+    Lazy::new(|| Semaphore::new(threads_or_cores(config::current().download_threads)));
+
+/// Analysis, conversion and tagging, sized by the config's `threads`
+pub static CPU: Lazy<Semaphore> =
+    Lazy::new(|| Semaphore::new(threads_or_cores(config::current().threads)));
+
+/// Read once like the thread counts, since [`POOL`] is sized by it
+static ALBUMS_AT_ONCE: Lazy<usize> =
+    Lazy::new(|| usize::from(config::current().albums_at_once.max(1)));
+
+/// How many albums the queue downloads at once, from the config's `albums_at_once` (which is one
+/// at a time if it's 0) when it was first asked for, so it applies after restarting
+#[must_use]
+pub fn albums_at_once() -> usize {
+    *ALBUMS_AT_ONCE
+}
+
+/// Runs each of an album's tracks with `run` on [`POOL`], alongside any other albums' tracks,
+/// giving back what they returned in the same order. Blocks until they've all finished.
+pub fn run_tracks<T, R>(tracks: Vec<T>, run: impl Fn(T) -> R + Send + Sync) -> Vec<R>
+where
+    T: Send,
+    R: Send,
+{
+    POOL.install(|| tracks.into_par_iter().map(run).collect())
+}
+
+/// `threads`, or one per CPU core if it's 0
+fn threads_or_cores(threads: u8) -> usize {
+    match threads {
+        0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+        threads => usize::from(threads),
+    }
+}
+
+/// Limits how many of something can happen at once
+#[derive(Debug)]
+pub struct Semaphore {
+    size: usize,
+    available: Mutex<usize>,
+    released: Condvar,
+}
+
+impl Semaphore {
+    /// At least 1, so [`Semaphore::acquire`] can't block forever
+    #[must_use]
+    pub fn new(size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            size,
+            available: Mutex::new(size),
+            released: Condvar::new(),
+        }
+    }
+
+    /// How many [`Permit`]s there can be at once
+    #[must_use]
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Blocks until there's a free permit, which is released when it's dropped
+    pub fn acquire(&self) -> Permit<'_> {
+        // the count is always valid, so a panic while it was locked doesn't matter
+        let mut available = self
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        while *available == 0 {
+            available = self
+                .released
+                .wait(available)
+                .unwrap_or_else(PoisonError::into_inner);
+        }
+        *available -= 1;
+        Permit(self)
+    }
+}
+
+/// From [`Semaphore::acquire`]
+#[derive(Debug)]
+#[must_use = "the permit is released as soon as it's dropped"]
+pub struct Permit<'a>(&'a Semaphore);
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        let mut available = self
+            .0
+            .available
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        *available += 1;
+        self.0.released.notify_one();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::Duration,
+    };
 
-pub static POOL: Lazy<ThreadPool> = Lazy::new(|| ThreadPoolBuilder::new().build().unwrap());
+    #[test]
+    fn semaphore_limits() {
+        let semaphore = Semaphore::new(2);
+        let running = AtomicUsize::new(0);
+        let most = AtomicUsize::new(0);
+        thread::scope(|scope| {
+            for _ in 0..6 {
+                scope.spawn(|| {
+                    let _permit = semaphore.acquire();
+                    let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                    most.fetch_max(now, Ordering::SeqCst);
+                    thread::sleep(Duration::from_millis(20));
+                    running.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        assert!(most.load(Ordering::SeqCst) <= 2);
+        assert_eq!(Semaphore::new(0).size(), 1);
+    }
+}
@@ -0,0 +1,460 @@
+//! Cleaning up video titles so they can be used as track names
+
+use serde::{Deserialize, Serialize};
+use std::fmt::{self, Display, Formatter};
+
+/// Characters that can go after a track number at the start of a video title
+const TRACK_NUMBER_SEPARATORS: &[char] = &['-', '–', '—', '|', '.', ')', ':', '_'];
+
+/// Splits a leading track number off a video title, like "03 - Song Name" or "Track 3: Song Name".
+///
+/// The number needs a separator after it (or to be in brackets, or after "Track"), so titles
+/// that start with a number like "7 Rings" or "1999" are left alone.
+///
+/// # Examples
+/// ```
+/// use ytmdl::titles::split_track_number;
+///
+/// assert_eq!(split_track_number("03 - Song Name"), (Some(3), "Song Name"));
+/// assert_eq!(split_track_number("12. Song Name"), (Some(12), "Song Name"));
+/// assert_eq!(split_track_number("[04] Song Name"), (Some(4), "Song Name"));
+/// assert_eq!(split_track_number("Track 5 Song Name"), (Some(5), "Song Name"));
+/// assert_eq!(split_track_number("7 Rings"), (None, "7 Rings"));
+/// assert_eq!(split_track_number("1999 - Remastered"), (None, "1999 - Remastered"));
+/// ```
+#[must_use]
+pub fn split_track_number(title: &str) -> (Option<usize>, &str) {
+    let trimmed = title.trim();
+    split_track_number_opt(trimmed).map_or((None, trimmed), |(number, rest)| (Some(number), rest))
+}
+
+fn split_track_number_opt(title: &str) -> Option<(usize, &str)> {
+    // at most 3 digits, so years aren't track numbers
+    const MAX_DIGITS: usize = 3;
+
+    let mut rest = title;
+    let mut needs_separator = true;
+    if let Some(after) = rest
+        .get(..5)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("track"))
+        .map(|_| &rest[5..])
+    {
+        rest = after.trim_start_matches([' ', '#']);
+        needs_separator = false;
+    }
+    let closing = match rest.chars().next()? {
+        '[' => Some(']'),
+        '(' => Some(')'),
+        _ => None,
+    };
+    if closing.is_some() {
+        rest = &rest[1..];
+    }
+
+    let digits = rest
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(rest.len());
+    if digits == 0 || digits > MAX_DIGITS {
+        return None;
+    }
+    let number: usize = rest[..digits].parse().ok().filter(|n| *n > 0)?;
+    rest = &rest[digits..];
+
+    if let Some(closing) = closing {
+        rest = rest.strip_prefix(closing)?;
+    } else {
+        let after_spaces = rest.trim_start();
+        if after_spaces.starts_with(TRACK_NUMBER_SEPARATORS) {
+            // "1.5" is a number, not track 1
+            if after_spaces.starts_with('.')
+                && after_spaces[1..].starts_with(|c: char| c.is_ascii_digit())
+            {
+                return None;
+            }
+            rest = after_spaces.trim_start_matches(TRACK_NUMBER_SEPARATORS);
+        } else if needs_separator || !rest.starts_with(char::is_whitespace) {
+            return None;
+        }
+    }
+
+    let rest = rest.trim_start();
+    (!rest.is_empty()).then_some((number, rest))
+}
+
+/// Where each video goes in the tracklist (0-based), going by the track numbers from
+/// [`split_track_number`].
+///
+/// Returns `None` unless every video has a different number that fits in the tracklist, since
+/// otherwise the numbers can't be trusted.
+///
+/// # Examples
+/// ```
+/// use ytmdl::titles::order_by_track_number;
+///
+/// assert_eq!(order_by_track_number(&[Some(2), Some(3), Some(1)]), Some(vec![1, 2, 0]));
+/// assert_eq!(order_by_track_number(&[Some(2), None, Some(1)]), None);
+/// assert_eq!(order_by_track_number(&[Some(1), Some(1)]), None);
+/// assert_eq!(order_by_track_number(&[Some(1), Some(3)]), None);
+/// ```
+#[must_use]
+pub fn order_by_track_number(numbers: &[Option<usize>]) -> Option<Vec<usize>> {
+    let mut seen = vec![false; numbers.len()];
+    numbers
+        .iter()
+        .map(|number| {
+            let index = (*number)?.checked_sub(1)?;
+            if std::mem::replace(seen.get_mut(index)?, true) {
+                None
+            } else {
+                Some(index)
+            }
+        })
+        .collect()
+}
+
+/// Words that make a bracketed part of a title a version of the track, like "(Extended Mix)"
+const VERSION_WORDS: &[&str] = &[
+    "remix",
+    "mix",
+    "edit",
+    "version",
+    "live",
+    "acoustic",
+    "instrumental",
+    "remaster",
+    "remastered",
+    "demo",
+    "reprise",
+    "rework",
+    "bootleg",
+    "unplugged",
+    "extended",
+    "radio",
+    "original",
+    "club",
+    "dub",
+    "vip",
+];
+
+/// How featured artists are introduced, longest first so "feat." is found rather than "feat"
+const FEAT_WORDS: &[&str] = &["featuring", "feat.", "feat", "ft.", "ft"];
+
+/// Which brackets a part of a title goes in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BracketStyle {
+    /// Whatever brackets it already has
+    #[default]
+    Keep,
+    Round,
+    Square,
+}
+
+impl BracketStyle {
+    pub const ALL: &'static [Self] = &[Self::Keep, Self::Round, Self::Square];
+}
+
+impl Display for BracketStyle {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Keep => "Leave as they are",
+            Self::Round => "(Round)",
+            Self::Square => "[Square]",
+        })
+    }
+}
+
+/// Rules for making the track titles of an album consistent, set under `[titles]` in the config
+/// and changed for just one album on the modify screen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TitleRules {
+    /// Brackets around featured artists. A "feat." that isn't in brackets gets them too.
+    pub feat_brackets: BracketStyle,
+    /// Write "ft.", "feat" and "featuring" as "feat."
+    pub standardize_feat: bool,
+    /// Brackets around versions, like "(Remix)" or "(Live)"
+    pub version_brackets: BracketStyle,
+    /// Capitalize the words that make it a version, like "(extended mix)" to "(Extended Mix)"
+    pub capitalize_versions: bool,
+}
+
+/// A title split up by its brackets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TitlePart<'a> {
+    Text(&'a str),
+    /// The opening bracket and what's inside, or no bracket for a "feat." that wasn't in any
+    Bracketed(Option<char>, &'a str),
+}
+
+impl TitleRules {
+    /// Whether any rule is turned on, i.e. whether [`TitleRules::apply`] can change anything
+    #[must_use]
+    pub fn any(self) -> bool {
+        self.feat_brackets != BracketStyle::Keep
+            || self.standardize_feat
+            || self.version_brackets != BracketStyle::Keep
+            || self.capitalize_versions
+    }
+
+    /// `title` with the rules applied. Brackets that aren't a featured artist or a version (like
+    /// "(2019)") are left alone, and so is the title if there's nothing to change.
+    ///
+    /// # Examples
+    /// ```
+    /// use ytmdl::titles::{BracketStyle, TitleRules};
+    ///
+    /// let rules = TitleRules {
+    ///     feat_brackets: BracketStyle::Round,
+    ///     standardize_feat: true,
+    ///     version_brackets: BracketStyle::Square,
+    ///     capitalize_versions: true,
+    /// };
+    /// assert_eq!(rules.apply("Song [ft. Someone] (club remix)"), "Song (feat. Someone) [Club Remix]");
+    /// assert_eq!(rules.apply("Song featuring Someone"), "Song (feat. Someone)");
+    /// assert_eq!(rules.apply("Song (Live) [2019]"), "Song [Live] [2019]");
+    /// assert_eq!(rules.apply("Song (1999)"), "Song (1999)");
+    /// ```
+    #[must_use]
+    pub fn apply(self, title: &str) -> String {
+        if !self.any() {
+            return title.to_string();
+        }
+        let mut changed = false;
+        let mut pieces = Vec::new();
+        for part in self.split(title) {
+            match part {
+                TitlePart::Text(text) => pieces.push(text.trim().to_string()),
+                TitlePart::Bracketed(open, inner) => {
+                    let restyled = self.restyle(open, inner);
+                    changed |= restyled != with_brackets(open, inner);
+                    pieces.push(restyled);
+                }
+            }
+        }
+        if !changed {
+            return title.to_string();
+        }
+        pieces.retain(|piece| !piece.is_empty());
+        pieces.join(" ")
+    }
+
+    /// Splits out each matching pair of brackets, and a "feat." that isn't in brackets (up to a
+    /// " - " or the next brackets) if there's a rule for it
+    fn split(self, title: &str) -> Vec<TitlePart<'_>> {
+        fn push_text<'a>(parts: &mut Vec<TitlePart<'a>>, text: &'a str, split_feat: bool) {
+            let feat = split_feat
+                .then(|| {
+                    text.char_indices()
+                        .map(|(i, _)| i)
+                        .find(|&i| text[..i].ends_with(' ') && feat_word(&text[i..]).is_some())
+                })
+                .flatten();
+            if let Some(start) = feat {
+                let end = text[start..]
+                    .find(" - ")
+                    .map_or(text.len(), |len| start + len);
+                parts.push(TitlePart::Text(&text[..start]));
+                parts.push(TitlePart::Bracketed(None, text[start..end].trim_end()));
+                parts.push(TitlePart::Text(&text[end..]));
+            } else {
+                parts.push(TitlePart::Text(text));
+            }
+        }
+
+        let split_feat = self.feat_brackets != BracketStyle::Keep || self.standardize_feat;
+        let mut parts = Vec::new();
+        let mut rest = title;
+        while let Some(start) = rest.find(['(', '[']) {
+            let open = if rest[start..].starts_with('(') {
+                '('
+            } else {
+                '['
+            };
+            let close = if open == '(' { ')' } else { ']' };
+            let inner = &rest[start + 1..];
+            let mut depth = 0;
+            let len = inner.char_indices().find_map(|(i, c)| {
+                if c == open {
+                    depth += 1;
+                } else if c == close {
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                    depth -= 1;
+                }
+                None
+            });
+            // an unclosed bracket is just text
+            let Some(len) = len else {
+                break;
+            };
+            push_text(&mut parts, &rest[..start], split_feat);
+            parts.push(TitlePart::Bracketed(Some(open), &inner[..len]));
+            rest = &inner[len + 1..];
+        }
+        push_text(&mut parts, rest, split_feat);
+        parts
+    }
+
+    /// What's inside brackets, in the brackets and with the words the rules say
+    fn restyle(self, open: Option<char>, inner: &str) -> String {
+        let (style, inner) = if let Some(word) = feat_word(inner) {
+            let inner = if self.standardize_feat {
+                format!("feat.{}", &inner[word.len()..])
+            } else {
+                inner.to_string()
+            };
+            (self.feat_brackets, inner)
+        } else if inner
+            .split(|c: char| !c.is_alphanumeric())
+            .any(is_version_word)
+        {
+            let inner = if self.capitalize_versions {
+                capitalize_version_words(inner)
+            } else {
+                inner.to_string()
+            };
+            (self.version_brackets, inner)
+        } else {
+            (BracketStyle::Keep, inner.to_string())
+        };
+        match style {
+            BracketStyle::Keep => with_brackets(open, &inner),
+            BracketStyle::Round => with_brackets(Some('('), &inner),
+            BracketStyle::Square => with_brackets(Some('['), &inner),
+        }
+    }
+}
+
+fn with_brackets(open: Option<char>, inner: &str) -> String {
+    match open {
+        Some('(') => format!("({inner})"),
+        Some(_) => format!("[{inner}]"),
+        None => inner.to_string(),
+    }
+}
+
+/// The word `text` starts with if it introduces a featured artist, as it's written
+fn feat_word(text: &str) -> Option<&str> {
+    FEAT_WORDS.iter().find_map(|word| {
+        let found = text
+            .get(..word.len())
+            .filter(|found| found.eq_ignore_ascii_case(word))?;
+        // a whole word, so "Feathers" isn't a featured artist
+        text[word.len()..].starts_with(' ').then_some(found)
+    })
+}
+
+fn is_version_word(word: &str) -> bool {
+    VERSION_WORDS
+        .iter()
+        .any(|version| word.eq_ignore_ascii_case(version))
+}
+
+/// Only the version words, so names like "deadmau5" in "(deadmau5 remix)" keep their case
+fn capitalize_version_words(text: &str) -> String {
+    text.split_inclusive(|c: char| !c.is_alphanumeric())
+        .map(|piece| {
+            let word = piece.trim_end_matches(|c: char| !c.is_alphanumeric());
+            if is_version_word(word) {
+                format!(
+                    "{}{}{}",
+                    word[..1].to_ascii_uppercase(),
+                    word[1..].to_ascii_lowercase(),
+                    &piece[word.len()..]
+                )
+            } else {
+                piece.to_string()
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separators() {
+        for title in [
+            "1 - Song",
+            "01 – Song",
+            "1—Song",
+            "1 | Song",
+            "1) Song",
+            "1: Song",
+            "01_Song",
+            "(1) Song",
+            "Track #1 - Song",
+            "TRACK 01. Song",
+        ] {
+            assert_eq!(split_track_number(title), (Some(1), "Song"), "{title}");
+        }
+    }
+
+    #[test]
+    fn not_track_numbers() {
+        for title in [
+            "99 Luftballons",
+            "1.5 Degrees",
+            "0 - Zero",
+            "(1)",
+            "[1 Song",
+            "Trackless - Song",
+            "22",
+            "トラック 1 - Song",
+            "",
+        ] {
+            assert_eq!(split_track_number(title).0, None, "{title}");
+        }
+    }
+
+    #[test]
+    fn keeps_the_rest_of_the_title() {
+        assert_eq!(
+            split_track_number("  02. Song - Live (2019)  "),
+            (Some(2), "Song - Live (2019)")
+        );
+    }
+
+    #[test]
+    fn title_rules() {
+        let round = TitleRules {
+            feat_brackets: BracketStyle::Round,
+            version_brackets: BracketStyle::Round,
+            ..TitleRules::default()
+        };
+        assert_eq!(round.apply("Song [feat. A & B]"), "Song (feat. A & B)");
+        assert_eq!(
+            round.apply("Song ft. Someone - Live"),
+            "Song (ft. Someone) - Live"
+        );
+        assert_eq!(
+            round.apply("Song [VIP Mix] [Explicit]"),
+            "Song (VIP Mix) [Explicit]"
+        );
+        // nested brackets stay with their group
+        assert_eq!(round.apply("Song [feat. A (UK)]"), "Song (feat. A (UK))");
+        // not features
+        assert_eq!(round.apply("Feathers [Demo]"), "Feathers (Demo)");
+        assert_eq!(round.apply("Song (Aftermath)"), "Song (Aftermath)");
+        // unchanged titles aren't touched at all
+        assert_eq!(round.apply("Song  (feat. A)"), "Song  (feat. A)");
+        assert_eq!(round.apply("Song (unclosed"), "Song (unclosed");
+
+        let words = TitleRules {
+            standardize_feat: true,
+            capitalize_versions: true,
+            ..TitleRules::default()
+        };
+        assert_eq!(words.apply("Song (FT. A)"), "Song (feat. A)");
+        assert_eq!(words.apply("Song featuring A"), "Song feat. A");
+        assert_eq!(
+            words.apply("Song [deadmau5 REMIX] (radio-edit)"),
+            "Song [deadmau5 Remix] (Radio-Edit)"
+        );
+        assert!(!TitleRules::default().any());
+        assert_eq!(TitleRules::default().apply("song (remix)"), "song (remix)");
+    }
+}
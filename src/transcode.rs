@@ -0,0 +1,354 @@
+//! Converting tracks with libav (ffmpeg's libraries) in-process instead of running the ffmpeg CLI,
+//! built with the `libav` feature. Tagging and shrinking covers still use the CLI.
+//!
+//! Based on ffmpeg-next's `transcode-audio` example: the best audio stream is decoded, resampled
+//! to what the encoder takes with a filter graph (which is just `anull` unless there are
+//! [post-processing](crate::postprocess) filters), and encoded.
+
+use crate::report::EncodeSettings;
+use ffmpeg_next::{
+    channel_layout::ChannelLayout,
+    codec::{self, flag::Flags},
+    encoder, filter, format, frame, media, Packet, Rational,
+};
+use std::{path::Path, time::Instant};
+use thiserror::Error;
+
+/// What the ffmpeg CLI leaves lossy encoders at if there's no bitrate
+const DEFAULT_BITRATE: usize = 128_000;
+/// libav's `FF_QP2LAMBDA`, which VBR levels are scaled by like `-q:a` does
+const QP2LAMBDA: usize = 118;
+
+#[derive(Debug, Error)]
+pub enum TranscodeError {
+    #[error("{0}")]
+    Libav(#[from] ffmpeg_next::Error),
+    #[error("there's no audio in it")]
+    NoAudio,
+    #[error("libav was built without the {0} encoder")]
+    MissingEncoder(&'static str),
+    #[error("it timed out")]
+    Timeout,
+}
+
+/// Converts the best audio stream in `input` to `output` as `settings` say, with the same sample
+/// rate, through the ffmpeg audio `filter` if there is one. Gives up once it's past `deadline`,
+/// checking between packets.
+///
+/// # Errors
+/// - If `input` can't be read or has no audio
+/// - If libav doesn't have the encoder for the format
+/// - If decoding or encoding fails, with libav's error
+/// - If it's still going at `deadline`
+pub fn convert(
+    input: &Path,
+    output: &Path,
+    settings: &EncodeSettings,
+    filter: Option<&str>,
+    deadline: Option<Instant>,
+) -> Result<(), TranscodeError> {
+    ffmpeg_next::init()?;
+    let mut ictx = format::input(input)?;
+    let mut octx = format::output(output)?;
+    let mut transcoder = Transcoder::new(&mut ictx, &mut octx, settings, filter)?;
+
+    octx.set_metadata(ictx.metadata().to_owned());
+    octx.write_header()?;
+    // the muxer can pick another time base for the stream when it writes the header
+    transcoder.out_time_base = octx
+        .stream(0)
+        .ok_or(ffmpeg_next::Error::StreamNotFound)?
+        .time_base();
+
+    for (stream, mut packet) in ictx.packets() {
+        if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+            return Err(TranscodeError::Timeout);
+        }
+        if stream.index() == transcoder.stream {
+            packet.rescale_ts(stream.time_base(), transcoder.in_time_base);
+            transcoder.decoder.send_packet(&packet)?;
+            transcoder.decode(&mut octx)?;
+        }
+    }
+
+    transcoder.decoder.send_eof()?;
+    transcoder.decode(&mut octx)?;
+    transcoder.flush_filter()?;
+    transcoder.filter(&mut octx)?;
+    transcoder.encoder.send_eof()?;
+    transcoder.encode(&mut octx)?;
+
+    octx.write_trailer()?;
+    Ok(())
+}
+
+/// The decoder, filter graph and encoder for one stream
+struct Transcoder {
+    /// Index of the audio stream in the input
+    stream: usize,
+    filter: filter::Graph,
+    decoder: codec::decoder::Audio,
+    /// Opened
+    encoder: codec::encoder::Audio,
+    in_time_base: Rational,
+    /// The output stream's, which is only settled once [`convert`] has written the header
+    out_time_base: Rational,
+}
+
+impl Transcoder {
+    fn new(
+        ictx: &mut format::context::Input,
+        octx: &mut format::context::Output,
+        settings: &EncodeSettings,
+        filter: Option<&str>,
+    ) -> Result<Self, TranscodeError> {
+        let input = ictx
+            .streams()
+            .best(media::Type::Audio)
+            .ok_or(TranscodeError::NoAudio)?;
+        let context = codec::context::Context::from_parameters(input.parameters())?;
+        let mut decoder = context.decoder().audio()?;
+        decoder.set_parameters(input.parameters())?;
+
+        let name = settings.format.encoder();
+        let codec = encoder::find_by_name(name)
+            .ok_or(TranscodeError::MissingEncoder(name))?
+            .audio()?;
+        let global_header = octx
+            .format()
+            .flags()
+            .contains(format::flag::Flags::GLOBAL_HEADER);
+
+        let mut output = octx.add_stream(codec)?;
+        let context = codec::context::Context::from_parameters(output.parameters())?;
+        let mut encoder = context.encoder().audio()?;
+
+        let channel_layout = codec
+            .channel_layouts()
+            .map_or(ChannelLayout::STEREO, |layouts| {
+                layouts.best(decoder.channel_layout().channels())
+            });
+        let rate = i32::try_from(decoder.rate()).map_err(|_| ffmpeg_next::Error::InvalidData)?;
+        let sample_format = codec
+            .formats()
+            .and_then(|mut formats| formats.next())
+            .ok_or(ffmpeg_next::Error::InvalidData)?;
+
+        let mut flags = Flags::empty();
+        if global_header {
+            flags |= Flags::GLOBAL_HEADER;
+        }
+        if !settings.format.is_lossless() {
+            match (settings.vbr_quality, settings.bitrate) {
+                (Some(quality), _) => {
+                    flags |= Flags::QSCALE;
+                    encoder.set_quality(usize::from(quality) * QP2LAMBDA);
+                }
+                (None, Some(kbps)) => encoder.set_bit_rate(kbps as usize * 1000),
+                (None, None) => encoder.set_bit_rate(DEFAULT_BITRATE),
+            }
+        }
+        encoder.set_flags(flags);
+        encoder.set_rate(rate);
+        encoder.set_channel_layout(channel_layout);
+        encoder.set_format(sample_format);
+        encoder.set_time_base((1, rate));
+        output.set_time_base((1, rate));
+
+        let encoder = encoder.open_as(codec)?;
+        output.set_parameters(&encoder);
+        let filter = filter_graph(&decoder, &encoder, filter)?;
+
+        Ok(Self {
+            stream: input.index(),
+            filter,
+            in_time_base: decoder.time_base(),
+            out_time_base: Rational::new(1, rate),
+            decoder,
+            encoder,
+        })
+    }
+
+    /// Tells the filter graph there's nothing more to come
+    fn flush_filter(&mut self) -> Result<(), TranscodeError> {
+        // both ends are added by `filter_graph`
+        self.filter
+            .get("in")
+            .expect("no filter input")
+            .source()
+            .flush()?;
+        Ok(())
+    }
+
+    /// Sends everything that's been decoded through the filter graph
+    fn decode(&mut self, octx: &mut format::context::Output) -> Result<(), TranscodeError> {
+        let mut decoded = frame::Audio::empty();
+        while self.decoder.receive_frame(&mut decoded).is_ok() {
+            let timestamp = decoded.timestamp();
+            decoded.set_pts(timestamp);
+            self.filter
+                .get("in")
+                .expect("no filter input")
+                .source()
+                .add(&decoded)?;
+            self.filter(octx)?;
+        }
+        Ok(())
+    }
+
+    /// Sends everything that's come out of the filter graph to the encoder
+    fn filter(&mut self, octx: &mut format::context::Output) -> Result<(), TranscodeError> {
+        let mut filtered = frame::Audio::empty();
+        while self
+            .filter
+            .get("out")
+            .expect("no filter output")
+            .sink()
+            .frame(&mut filtered)
+            .is_ok()
+        {
+            self.encoder.send_frame(&filtered)?;
+            self.encode(octx)?;
+        }
+        Ok(())
+    }
+
+    /// Writes everything that's been encoded
+    fn encode(&mut self, octx: &mut format::context::Output) -> Result<(), TranscodeError> {
+        let mut encoded = Packet::empty();
+        while self.encoder.receive_packet(&mut encoded).is_ok() {
+            encoded.set_stream(0);
+            encoded.rescale_ts(self.in_time_base, self.out_time_base);
+            encoded.write_interleaved(octx)?;
+        }
+        Ok(())
+    }
+}
+
+/// Converts the decoder's samples to the encoder's format, channel layout and frame size, after
+/// `filter` if there is one
+fn filter_graph(
+    decoder: &codec::decoder::Audio,
+    encoder: &codec::encoder::Audio,
+    filter: Option<&str>,
+) -> Result<filter::Graph, TranscodeError> {
+    let mut graph = filter::Graph::new();
+    let args = format!(
+        "time_base={}:sample_rate={}:sample_fmt={}:channel_layout=0x{:x}",
+        decoder.time_base(),
+        decoder.rate(),
+        decoder.format().name(),
+        decoder.channel_layout().bits()
+    );
+    let find = |name: &str| filter::find(name).ok_or(ffmpeg_next::Error::FilterNotFound);
+    graph.add(&find("abuffer")?, "in", &args)?;
+    graph.add(&find("abuffersink")?, "out", "")?;
+
+    {
+        let mut out = graph.get("out").ok_or(ffmpeg_next::Error::FilterNotFound)?;
+        out.set_sample_format(encoder.format());
+        out.set_channel_layout(encoder.channel_layout());
+        out.set_sample_rate(encoder.rate());
+    }
+
+    graph
+        .output("in", 0)?
+        .input("out", 0)?
+        .parse(filter.unwrap_or("anull"))?;
+    graph.validate()?;
+
+    let variable_frame_size = encoder.codec().is_some_and(|codec| {
+        codec
+            .capabilities()
+            .contains(codec::capabilities::Capabilities::VARIABLE_FRAME_SIZE)
+    });
+    if !variable_frame_size {
+        if let Some(mut out) = graph.get("out") {
+            out.sink().set_frame_size(encoder.frame_size());
+        }
+    }
+    Ok(graph)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OutputFormat;
+    use std::fs;
+    use tempdir::TempDir;
+
+    /// A second of a 440 Hz tone as a 48 kHz mono 16-bit WAV, a rate every encoder takes
+    fn tone_wav() -> Vec<u8> {
+        const RATE: u32 = 48_000;
+        let samples: Vec<u8> = (0..RATE)
+            .flat_map(|i| {
+                let phase = f64::from(i) / f64::from(RATE) * 440.0 * std::f64::consts::TAU;
+                #[allow(clippy::cast_possible_truncation)]
+                let sample = (phase.sin() * 8000.0) as i16;
+                sample.to_le_bytes()
+            })
+            .collect();
+        let data_len = u32::try_from(samples.len()).unwrap();
+
+        let mut wav = b"RIFF".to_vec();
+        wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+        wav.extend_from_slice(b"WAVEfmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        // PCM, mono
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&RATE.to_le_bytes());
+        // bytes per second, bytes per sample and bits per sample
+        wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&data_len.to_le_bytes());
+        wav.extend(samples);
+        wav
+    }
+
+    /// How long the packets' timestamps say the file is, in seconds
+    #[allow(clippy::cast_precision_loss)]
+    fn length(path: &Path) -> f64 {
+        let mut ictx = format::input(path).unwrap();
+        let mut end = 0.0_f64;
+        for (stream, packet) in ictx.packets() {
+            if let Some(pts) = packet.pts() {
+                let time_base = f64::from(stream.time_base());
+                end = end.max((pts + packet.duration()) as f64 * time_base);
+            }
+        }
+        end
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::new("ytmdl-transcode-test").unwrap();
+        let input = dir.path().join("tone.wav");
+        fs::write(&input, tone_wav()).unwrap();
+
+        for &format in OutputFormat::ALL {
+            let output = dir.path().join(format!("tone.{}", format.extension()));
+            let settings = EncodeSettings {
+                format,
+                bitrate: Some(192),
+                vbr_quality: None,
+            };
+            match convert(&input, &output, &settings, None, None) {
+                // LAME and libopus are optional in libav builds, the rest are built in
+                Err(TranscodeError::MissingEncoder(_))
+                    if matches!(format, OutputFormat::Mp3 | OutputFormat::Opus) =>
+                {
+                    continue
+                }
+                result => result.unwrap(),
+            }
+            let length = length(&output);
+            assert!(
+                (length - 1.0).abs() < 0.1,
+                "{format:?} came out {length}s long"
+            );
+        }
+    }
+}
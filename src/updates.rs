@@ -0,0 +1,145 @@
+//! Checking Github for a newer release of ytmdl at startup
+
+use crate::network::download_identified;
+use once_cell::sync::Lazy;
+use serde::Deserialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    str::FromStr,
+    sync::RwLock,
+};
+use thiserror::Error;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/Breadinator/ytmdl/releases/latest";
+/// This version of ytmdl
+pub const CURRENT: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Error)]
+pub enum UpdateError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error(r#"couldn't read the version "{0}""#)]
+    InvalidVersion(String),
+}
+
+/// A release on Github
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct Release {
+    /// Like "v0.4.0"
+    pub tag_name: String,
+    /// The release's page
+    pub html_url: String,
+}
+
+/// Like "1.2.3", optionally with a "v" in front. Anything after a `-` or `+` is ignored.
+///
+/// # Examples
+/// ```
+/// use ytmdl::updates::Version;
+///
+/// let version: Version = "v0.10.2".parse().unwrap();
+/// assert!(version > "0.9.12".parse().unwrap());
+/// assert_eq!("1.0.0-beta.1".parse::<Version>().unwrap().to_string(), "1.0.0");
+/// assert!("1.0".parse::<Version>().is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl FromStr for Version {
+    type Err = UpdateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || UpdateError::InvalidVersion(s.to_string());
+        let trimmed = s.trim();
+        let trimmed = trimmed.strip_prefix('v').unwrap_or(trimmed);
+        let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+        let parts: Vec<u64> = core
+            .split('.')
+            .map(str::parse)
+            .collect::<Result<_, _>>()
+            .map_err(|_| invalid())?;
+        match parts[..] {
+            [major, minor, patch] => Ok(Self {
+                major,
+                minor,
+                patch,
+            }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Display for Version {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+/// A newer release than this one that's been found, unless it's been dismissed. Kept outside of
+/// the GUI state so it stays when switching screens.
+static AVAILABLE: Lazy<RwLock<Option<Release>>> = Lazy::new(RwLock::default);
+
+#[must_use]
+pub fn available() -> Option<Release> {
+    AVAILABLE.read().ok().and_then(|release| release.clone())
+}
+
+/// `None` to dismiss it
+pub fn set_available(release: Option<Release>) {
+    if let Ok(mut available) = AVAILABLE.write() {
+        *available = release;
+    }
+}
+
+/// The latest release on Github, if it's newer than this version
+///
+/// # Errors
+/// - If Github can't be reached or responds with an error
+/// - If the response can't be parsed, or its version can't be read
+pub fn check() -> Result<Option<Release>, UpdateError> {
+    let json = download_identified(LATEST_RELEASE_URL)?.text()?;
+    newer_release(&json, CURRENT)
+}
+
+/// The release from the Github API's JSON, if it's newer than `current`
+///
+/// # Errors
+/// If it isn't a release, or either version can't be read
+pub fn newer_release(json: &str, current: &str) -> Result<Option<Release>, UpdateError> {
+    let release: Release = serde_json::from_str(json)?;
+    let newer = release.tag_name.parse::<Version>()? > current.parse()?;
+    Ok(newer.then_some(release))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newer() {
+        let json = r#"{
+            "tag_name": "v0.4.0",
+            "name": "0.4.0",
+            "html_url": "https://github.com/Breadinator/ytmdl/releases/tag/v0.4.0",
+            "draft": false,
+            "prerelease": false
+        }"#;
+        let release = newer_release(json, "0.3.4").unwrap().unwrap();
+        assert_eq!(
+            release.html_url,
+            "https://github.com/Breadinator/ytmdl/releases/tag/v0.4.0"
+        );
+        assert_eq!(newer_release(json, "0.4.0").unwrap(), None);
+        assert_eq!(newer_release(json, "0.10.0").unwrap(), None);
+        assert!(matches!(
+            newer_release(r#"{"tag_name": "latest", "html_url": ""}"#, "0.3.4"),
+            Err(UpdateError::InvalidVersion(_))
+        ));
+    }
+}
@@ -0,0 +1,243 @@
+//! Working out what a pasted link points to, so it can be put in the right place
+
+use crate::{
+    ids::{PlaylistId, VideoId},
+    playlist::parse_youtube_url,
+    scraping::parse_mbid,
+};
+use url::Url;
+
+/// What a link points to, from [`classify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlKind {
+    /// One of the playlists Youtube makes for albums (`OLAK5uy_...`), on either Youtube or
+    /// Youtube Music
+    YoutubeMusicAlbum(PlaylistId),
+    YoutubePlaylist(PlaylistId),
+    YoutubeVideo(VideoId),
+    /// With the release's number
+    DiscogsRelease(u64),
+    /// With the master's number
+    DiscogsMaster(u64),
+    /// With the release's MBID
+    MusicBrainzRelease(String),
+    /// With the release group's MBID
+    MusicBrainzReleaseGroup(String),
+    Bandcamp,
+    Soundcloud,
+    Unknown,
+}
+
+impl UrlKind {
+    #[must_use]
+    pub fn is_youtube(&self) -> bool {
+        matches!(
+            self,
+            Self::YoutubeMusicAlbum(_) | Self::YoutubePlaylist(_) | Self::YoutubeVideo(_)
+        )
+    }
+
+    #[must_use]
+    pub fn is_discogs(&self) -> bool {
+        matches!(self, Self::DiscogsRelease(_) | Self::DiscogsMaster(_))
+    }
+
+    #[must_use]
+    pub fn is_musicbrainz(&self) -> bool {
+        matches!(
+            self,
+            Self::MusicBrainzRelease(_) | Self::MusicBrainzReleaseGroup(_)
+        )
+    }
+
+    /// Whether the album data can be got from it, i.e. Discogs, Musicbrainz or Bandcamp
+    #[must_use]
+    pub fn is_metadata(&self) -> bool {
+        self.is_discogs() || self.is_musicbrainz() || *self == Self::Bandcamp
+    }
+}
+
+/// Works out what a link points to. Links without `https://` are fine.
+///
+/// # Examples
+/// ```
+/// use ytmdl::urls::{classify, UrlKind};
+///
+/// assert!(matches!(
+///     classify("https://music.youtube.com/playlist?list=OLAK5uy_mZcxjzRvOZAUa2H6Pf8LVvyLDGeBSdmJQ"),
+///     UrlKind::YoutubeMusicAlbum(_),
+/// ));
+/// assert!(matches!(
+///     classify("https://www.youtube.com/playlist?list=PLabcdefghijklmnop"),
+///     UrlKind::YoutubePlaylist(_),
+/// ));
+/// assert!(matches!(classify("youtu.be/dQw4w9WgXcQ"), UrlKind::YoutubeVideo(_)));
+/// assert_eq!(
+///     classify("https://www.discogs.com/release/27651927-Odd-Eye-Circle-Version-Up"),
+///     UrlKind::DiscogsRelease(27_651_927),
+/// );
+/// assert_eq!(
+///     classify("https://www.discogs.com/master/3166419-Odd-Eye-Circle-Version-Up"),
+///     UrlKind::DiscogsMaster(3_166_419),
+/// );
+/// assert!(matches!(
+///     classify("https://musicbrainz.org/release/b84ee12a-09ef-421b-82de-0441a926375b"),
+///     UrlKind::MusicBrainzRelease(_),
+/// ));
+/// assert_eq!(classify("https://artist.bandcamp.com/album/name"), UrlKind::Bandcamp);
+/// assert_eq!(classify("not a link"), UrlKind::Unknown);
+/// ```
+#[must_use]
+pub fn classify(url: &str) -> UrlKind {
+    let url = url.trim();
+    let with_scheme;
+    let url = if url.contains("://") {
+        url
+    } else {
+        with_scheme = format!("https://{url}");
+        &with_scheme
+    };
+    let Ok(parsed) = Url::parse(url) else {
+        return UrlKind::Unknown;
+    };
+    let Some(host) = parsed.host_str() else {
+        return UrlKind::Unknown;
+    };
+    let host = host.strip_prefix("www.").unwrap_or(host);
+
+    if host == "youtu.be" || host == "youtube.com" || host.ends_with(".youtube.com") {
+        classify_youtube(url)
+    } else if host == "discogs.com" {
+        classify_discogs(&parsed)
+    } else if host == "musicbrainz.org" || host.ends_with(".musicbrainz.org") {
+        classify_musicbrainz(&parsed)
+    } else if host == "bandcamp.com" || host.ends_with(".bandcamp.com") {
+        UrlKind::Bandcamp
+    } else if host == "soundcloud.com" || host.ends_with(".soundcloud.com") {
+        UrlKind::Soundcloud
+    } else {
+        UrlKind::Unknown
+    }
+}
+
+/// A video that's in a playlist counts as the playlist, unless it's a mix
+fn classify_youtube(url: &str) -> UrlKind {
+    let ids = parse_youtube_url(url);
+    match (ids.video, ids.playlist) {
+        (_, Some(id)) if id.is_album() => UrlKind::YoutubeMusicAlbum(id),
+        (None, Some(id)) => UrlKind::YoutubePlaylist(id),
+        (_, Some(id)) if !id.is_mix() => UrlKind::YoutubePlaylist(id),
+        (Some(id), _) => UrlKind::YoutubeVideo(id),
+        _ => UrlKind::Unknown,
+    }
+}
+
+/// Both `/release/123-Artist-Title` and the older `/Artist-Title/release/123`
+fn classify_discogs(url: &Url) -> UrlKind {
+    let Some(segments) = url.path_segments() else {
+        return UrlKind::Unknown;
+    };
+    let segments: Vec<&str> = segments.collect();
+    let number = |segment: &str| -> Option<u64> {
+        let digits = segment
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(segment.len());
+        segment[..digits].parse().ok()
+    };
+
+    segments
+        .windows(2)
+        .find_map(|pair| match pair {
+            ["release", id] => number(id).map(UrlKind::DiscogsRelease),
+            ["master", id] => number(id).map(UrlKind::DiscogsMaster),
+            _ => None,
+        })
+        .unwrap_or(UrlKind::Unknown)
+}
+
+/// `/release/<mbid>` and `/release-group/<mbid>`, including their subpages
+fn classify_musicbrainz(url: &Url) -> UrlKind {
+    let mut segments = url.path_segments().into_iter().flatten();
+    match (segments.next(), segments.next().and_then(parse_mbid)) {
+        (Some("release"), Some(id)) => UrlKind::MusicBrainzRelease(id.to_lowercase()),
+        (Some("release-group"), Some(id)) => UrlKind::MusicBrainzReleaseGroup(id.to_lowercase()),
+        _ => UrlKind::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn discogs_shapes() {
+        for url in [
+            "https://www.discogs.com/release/123",
+            "discogs.com/release/123-Artist-Album",
+            "https://www.discogs.com/Artist-Album/release/123",
+            "https://www.discogs.com/release/123-Artist-Album?ev=rr",
+        ] {
+            assert_eq!(classify(url), UrlKind::DiscogsRelease(123), "{url}");
+        }
+        assert_eq!(
+            classify("https://www.discogs.com/artist/123-Artist"),
+            UrlKind::Unknown
+        );
+    }
+
+    #[test]
+    fn musicbrainz_shapes() {
+        let id = "b84ee12a-09ef-421b-82de-0441a926375b";
+        for url in [
+            format!("https://musicbrainz.org/release/{id}"),
+            format!("musicbrainz.org/release/{id}/cover-art"),
+            format!("https://beta.musicbrainz.org/release/{}", id.to_uppercase()),
+        ] {
+            assert_eq!(
+                classify(&url),
+                UrlKind::MusicBrainzRelease(id.to_string()),
+                "{url}"
+            );
+        }
+        assert_eq!(
+            classify(&format!("https://musicbrainz.org/release-group/{id}")),
+            UrlKind::MusicBrainzReleaseGroup(id.to_string())
+        );
+        assert!(classify(&format!("https://musicbrainz.org/release/{id}")).is_metadata());
+        assert!(classify("https://artist.bandcamp.com/album/name").is_metadata());
+        assert_eq!(
+            classify(&format!("https://musicbrainz.org/artist/{id}")),
+            UrlKind::Unknown
+        );
+        assert_eq!(
+            classify("https://musicbrainz.org/release/123"),
+            UrlKind::Unknown
+        );
+    }
+
+    #[test]
+    fn youtube_shapes() {
+        assert!(classify("https://www.youtube.com/watch?v=dQw4w9WgXcQ").is_youtube());
+        assert!(matches!(
+            classify("https://youtu.be/dQw4w9WgXcQ?list=OLAK5uy_abc"),
+            UrlKind::YoutubeMusicAlbum(_)
+        ));
+        assert!(matches!(
+            classify("https://www.youtube.com/watch?v=dQw4w9WgXcQ&list=RDdQw4w9WgXcQ"),
+            UrlKind::YoutubeVideo(_)
+        ));
+        assert!(matches!(
+            classify("https://youtube.com/playlist?list=OLAK5uy_abc"),
+            UrlKind::YoutubeMusicAlbum(_)
+        ));
+        assert_eq!(
+            classify("https://www.youtube.com/@artist"),
+            UrlKind::Unknown
+        );
+        assert_eq!(
+            classify("https://soundcloud.com/artist/song"),
+            UrlKind::Soundcloud
+        );
+        assert!(!classify("https://soundcloud.com/artist/song").is_youtube());
+    }
+}
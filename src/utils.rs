@@ -1,6 +1,7 @@
-use reqwest::blocking::{Client, Response};
-use std::{borrow::Cow, ffi::OsStr};
-use url::Url;
+use serde::Serializer;
+use std::{borrow::Cow, ffi::OsStr, fmt::Display, str::FromStr};
+use unicode_normalization::{char::is_combining_mark, UnicodeNormalization};
+use url::{Host, Url};
 
 /// If all given results are `Ok`, returns `Ok(vec![ok_values])`,
 /// else it returns the first error in the `Vec`
@@ -42,11 +43,176 @@ pub fn sanitize_file_name(name: &str) -> Cow<str> {
     }
 }
 
-/// Makes a get request via [reqwest] using a fake user agent
+/// Makes a name ASCII for devices that can't show anything else: accents are dropped, a few
+/// letters are spelled out and anything else becomes `_`.
+///
+/// # Examples
+/// ```
+/// use ytmdl::utils::ascii_file_name;
+///
+/// assert_eq!(ascii_file_name("01 Café Straße"), "01 Cafe Strasse");
+/// assert_eq!(ascii_file_name("Sigur Rós - Ágætis byrjun"), "Sigur Ros - Agaetis byrjun");
+/// assert_eq!(ascii_file_name("東京"), "__");
+/// assert_eq!(ascii_file_name("Plain"), "Plain");
+/// ```
+#[must_use]
+pub fn ascii_file_name(name: &str) -> Cow<str> {
+    if name.is_ascii() {
+        return Cow::Borrowed(name);
+    }
+
+    let mut out = String::with_capacity(name.len());
+    for ch in name.nfd().filter(|&ch| !is_combining_mark(ch)) {
+        match ch {
+            ch if ch.is_ascii() => out.push(ch),
+            'ß' => out.push_str("ss"),
+            'æ' => out.push_str("ae"),
+            'Æ' => out.push_str("AE"),
+            'œ' => out.push_str("oe"),
+            'Œ' => out.push_str("OE"),
+            'ø' => out.push('o'),
+            'Ø' => out.push('O'),
+            'đ' | 'ð' => out.push('d'),
+            'Đ' | 'Ð' => out.push('D'),
+            'ł' => out.push('l'),
+            'Ł' => out.push('L'),
+            'þ' => out.push_str("th"),
+            'Þ' => out.push_str("Th"),
+            '‘' | '’' => out.push('\''),
+            '“' | '”' => out.push('"'),
+            '–' | '—' => out.push('-'),
+            _ => out.push('_'),
+        }
+    }
+    Cow::Owned(out)
+}
+
+/// Replaces each `{key}` in the template with its value, in one pass, so a value with something
+/// like `{title}` in it is left as it is. Anything in braces that isn't a key is kept too.
+///
+/// # Examples
+/// ```
+/// use ytmdl::utils::fill_template;
+///
+/// let filled = fill_template("{a} - {b} ({a})", &[("a", "foo"), ("b", "bar")]);
+/// assert_eq!(filled, "foo - bar (foo)");
+/// let filled = fill_template("{a} - {b} {c}", &[("a", "{b}"), ("b", "bar")]);
+/// assert_eq!(filled, "{b} - bar {c}");
+/// ```
+#[must_use]
+pub fn fill_template(template: &str, values: &[(&str, &str)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let value = rest.find('}').and_then(|close| {
+            let (_, value) = values.iter().find(|(key, _)| *key == &rest[1..close])?;
+            Some((value, close))
+        });
+        if let Some((value, close)) = value {
+            out.push_str(value);
+            rest = &rest[close + 1..];
+        } else {
+            out.push('{');
+            rest = &rest[1..];
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The name to sort by, with a leading "The", "A" or "An" moved to the end.
+///
+/// Returns `None` if it doesn't start with one, since then it sorts by its own name.
+///
+/// # Examples
+/// ```
+/// use ytmdl::utils::sort_name;
+///
+/// assert_eq!(sort_name("The Beatles").as_deref(), Some("Beatles, The"));
+/// assert_eq!(sort_name("a tribe called quest").as_deref(), Some("tribe called quest, a"));
+/// assert_eq!(sort_name("Theory of a Deadman"), None);
+/// assert_eq!(sort_name("The"), None);
+/// ```
+#[must_use]
+pub fn sort_name(name: &str) -> Option<String> {
+    const ARTICLES: &[&str] = &["the", "a", "an"];
+
+    let name = name.trim();
+    let (article, rest) = name.split_once(' ')?;
+    let rest = rest.trim_start();
+    if rest.is_empty() || !ARTICLES.contains(&article.to_lowercase().as_str()) {
+        return None;
+    }
+    Some(format!("{rest}, {article}"))
+}
+
+/// Quotes a CSV field if it needs it, doubling any quotes inside, so it reads back the same with
+/// [`parse_csv`](crate::parsing::parse_csv).
+///
+/// # Examples
+/// ```
+/// use ytmdl::utils::csv_field;
+///
+/// assert_eq!(csv_field("Song"), "Song");
+/// assert_eq!(csv_field("Hello, World"), r#""Hello, World""#);
+/// assert_eq!(csv_field(r#"The "Band""#), r#""The ""Band""""#);
+/// ```
+#[must_use]
+pub fn csv_field(field: &str) -> Cow<str> {
+    if field.contains([',', ';', '\t', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// For `#[serde(serialize_with = "...")]`, serializes a value as its [`Display`] string.
+///
+/// Mostly for errors from other crates that don't implement [`serde::Serialize`].
 #[allow(clippy::missing_errors_doc)]
-pub fn download(url: &str) -> Result<Response, reqwest::Error> {
-    let client = Client::builder().user_agent("Chrome/116.0.0.0").build()?; // lol
-    client.get(url).send()
+pub fn serialize_display<T: Display, S: Serializer>(
+    value: &T,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// For `#[serde(with = "...")]` on an `Option` of something that's saved as its [`Display`]
+/// string and loaded with [`FromStr`], like [`id3::Timestamp`].
+pub mod option_display_from_str {
+    use super::{Display, FromStr};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[allow(clippy::missing_errors_doc, clippy::ref_option)]
+    pub fn serialize<T: Display, S: Serializer>(
+        value: &Option<T>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match value {
+            Some(value) => serializer.serialize_some(&value.to_string()),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    #[allow(clippy::missing_errors_doc)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<T>, D::Error>
+    where
+        T: FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| s.parse().map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Seconds since the Unix epoch, from the [`SystemClock`](crate::clock::SystemClock)
+#[must_use]
+pub fn unix_time() -> u64 {
+    crate::clock::Clock::unix_time(&crate::clock::SystemClock)
 }
 
 /// Wrapper around a `*const T` that allows it to be sent across threads.
@@ -104,24 +270,44 @@ pub mod selectors {
     selector!(VERSIONS_TABLE_LINK, "section#versions table a.link_1ctor");
     selector!(SCRIPT, "script");
     selector!(TIME, "time");
+    selector!(TRALBUM, "[data-tralbum]");
 }
 
-#[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+/// Whether the URL points at the local machine (`localhost` or a loopback IP)
+///
+/// # Examples
+/// ```
+/// use url::Url;
+/// use ytmdl::utils::is_loopback;
+///
+/// assert!(is_loopback(&Url::parse("http://127.0.0.1:8080/page").unwrap()));
+/// assert!(is_loopback(&Url::parse("http://localhost/page").unwrap()));
+/// assert!(!is_loopback(&Url::parse("https://www.discogs.com/").unwrap()));
+/// ```
 #[must_use]
-pub fn current_year() -> i32 {
-    const SECONDS_PER_YEAR: f64 = 365.25 * 24.0 * 60.0 * 60.0;
+pub fn is_loopback(url: &Url) -> bool {
+    match url.host() {
+        Some(Host::Domain(domain)) => domain == "localhost",
+        Some(Host::Ipv4(ip)) => ip.is_loopback(),
+        Some(Host::Ipv6(ip)) => ip.is_loopback(),
+        None => false,
+    }
+}
 
-    std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .map_or(2023, |since_epoch| {
-            (since_epoch.as_secs_f64() / SECONDS_PER_YEAR) as i32 + 1970
-        })
+/// Whether the URL is for the [fixture server](crate::test_support::FixtureServer), which links
+/// are left pointing at rather than being moved to the real sites. Never, without the
+/// `test-support` feature.
+#[must_use]
+pub fn is_test_server(url: &Url) -> bool {
+    cfg!(feature = "test-support") && is_loopback(url)
 }
 
+/// Moves a link to www.youtube.com, except links to the [test server](is_test_server)
 #[must_use]
 pub fn music_to_www(url: &str) -> Cow<str> {
     if let Ok(mut parsed_url) = Url::parse(url) {
         if parsed_url.host_str() != Some("www.youtube.com")
+            && !is_test_server(&parsed_url)
             && parsed_url.set_host(Some("www.youtube.com")).is_ok()
         {
             Cow::Owned(parsed_url.to_string())
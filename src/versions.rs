@@ -0,0 +1,125 @@
+//! Spotting tracks whose video is probably a different version of the song (like a live
+//! recording or an extended mix) by how far its length is from the release's, and searching
+//! Youtube for an upload that's closer
+
+use crate::{
+    parsing::format_duration,
+    scraping::{search_videos, PlaylistItem, ScrapeYoutubeError},
+};
+use serde::Serialize;
+use std::{
+    fmt::{self, Display, Formatter},
+    time::Duration,
+};
+
+/// Videos can be this much longer or shorter than the track without it counting, since intros,
+/// outros and silence at the end add a bit
+const MIN_DIFFERENCE: Duration = Duration::from_secs(45);
+/// Or this much of the track's length, if that's more
+const MAX_DIFFERENCE_PERCENT: u32 = 30;
+/// How many search results to pick from
+const SEARCH_RESULTS: usize = 8;
+
+/// A video that's much longer or shorter than the track it's for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LengthMismatch {
+    /// From the release
+    pub track: Duration,
+    pub video: Duration,
+}
+
+impl LengthMismatch {
+    /// `None` if the video is close enough to the track's length to be the same version
+    ///
+    /// # Examples
+    /// ```
+    /// use std::time::Duration;
+    /// use ytmdl::versions::LengthMismatch;
+    ///
+    /// let minutes = |m: f64| Duration::from_secs_f64(m * 60.0);
+    /// assert_eq!(LengthMismatch::check(minutes(3.0), minutes(3.5)), None);
+    /// assert_eq!(LengthMismatch::check(minutes(8.0), minutes(10.0)), None);
+    /// assert!(LengthMismatch::check(minutes(3.0), minutes(10.0)).is_some());
+    /// assert!(LengthMismatch::check(minutes(7.0), minutes(3.5)).is_some());
+    /// ```
+    #[must_use]
+    pub fn check(track: Duration, video: Duration) -> Option<Self> {
+        let allowed = (track * MAX_DIFFERENCE_PERCENT / 100).max(MIN_DIFFERENCE);
+        (track.abs_diff(video) > allowed).then_some(Self { track, video })
+    }
+}
+
+/// Like "the video is 10:02, but the track is 3:30"
+impl Display for LengthMismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the video is {}, but the track is {}",
+            format_duration(self.video),
+            format_duration(self.track)
+        )
+    }
+}
+
+/// The search result closest to `length` that isn't a [`LengthMismatch`], which is the first of
+/// them if they're equally close
+#[must_use]
+pub fn closest(results: &[PlaylistItem], length: Duration) -> Option<&PlaylistItem> {
+    results
+        .iter()
+        .filter(|video| video.id.is_some())
+        .filter_map(|video| Some((video, video.duration?)))
+        .filter(|(_, duration)| LengthMismatch::check(length, *duration).is_none())
+        .min_by_key(|(_, duration)| length.abs_diff(*duration))
+        .map(|(video, _)| video)
+}
+
+/// Searches Youtube for `artist - title` and picks the video closest to `length`, or `None` if
+/// none of them are close enough
+///
+/// # Errors
+/// If yt-dlp can't be run
+pub fn find_alternative(
+    artist: &str,
+    title: &str,
+    length: Duration,
+) -> Result<Option<PlaylistItem>, ScrapeYoutubeError> {
+    let query = if artist.trim().is_empty() {
+        title.trim().to_string()
+    } else {
+        format!("{} - {}", artist.trim(), title.trim())
+    };
+    log::info!("searching Youtube for {query}");
+    let results = search_videos(&query, SEARCH_RESULTS)?;
+    Ok(closest(&results, length).cloned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn video(id: &str, secs: Option<u64>) -> PlaylistItem {
+        PlaylistItem {
+            id: id.parse().ok(),
+            duration: secs.map(Duration::from_secs),
+            ..PlaylistItem::default()
+        }
+    }
+
+    #[test]
+    fn closest_result() {
+        let results = [
+            video("aaaaaaaaaaa", Some(600)),
+            video("not an id", Some(210)),
+            video("bbbbbbbbbbb", None),
+            video("ccccccccccc", Some(240)),
+            video("ddddddddddd", Some(205)),
+        ];
+        let length = Duration::from_secs(210);
+        assert_eq!(
+            closest(&results, length).and_then(|video| video.id.clone()),
+            "ddddddddddd".parse().ok()
+        );
+        assert!(closest(&results[..3], length).is_none());
+    }
+}
@@ -0,0 +1,237 @@
+//! Checking watched channels and artist playlists for albums that haven't been downloaded yet
+
+use crate::{
+    gui::view_modifying_data::StateModifyingData, history::History, ids::PlaylistId, jobs::Job,
+    network, queue, scraping::ScrapeYoutubeError, utils::unix_time,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, TryLockError};
+use url::Url;
+
+/// Held while the watched channels are being checked, since the next tick can come before a slow
+/// check is done and would queue the same albums again
+static CHECKING: Mutex<()> = Mutex::new(());
+
+/// Under `[watch]` in the config
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WatchSettings {
+    /// Channels (e.g. `https://www.youtube.com/@artist`) or playlists of an artist's albums
+    pub urls: Vec<String>,
+    /// How often to check while the app is open, or 0 to only check when asked
+    pub interval_hours: u16,
+}
+
+impl WatchSettings {
+    pub const INTERVAL_RANGE: std::ops::RangeInclusive<u16> = 0..=48;
+
+    /// Whether it's time to check again, going by when it was last checked
+    #[must_use]
+    pub fn is_due(&self, last_checked: u64, now: u64) -> bool {
+        !self.urls.is_empty()
+            && self.interval_hours > 0
+            && now.saturating_sub(last_checked) >= u64::from(self.interval_hours) * 3600
+    }
+}
+
+/// An album found on a watched channel or playlist
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// The album's playlist ID, which is also its key in the [`History`]
+    pub id: PlaylistId,
+    pub title: String,
+}
+
+impl Release {
+    #[must_use]
+    pub fn url(&self) -> String {
+        self.id.url()
+    }
+}
+
+/// Channel links go to the channel's releases tab, since that's where its albums are. Anything
+/// else is left alone.
+///
+/// # Examples
+/// ```
+/// use ytmdl::watch::releases_url;
+///
+/// assert_eq!(releases_url("https://www.youtube.com/@artist"), "https://www.youtube.com/@artist/releases");
+/// assert_eq!(
+///     releases_url("https://youtube.com/channel/UCabc/"),
+///     "https://youtube.com/channel/UCabc/releases",
+/// );
+/// assert_eq!(
+///     releases_url("https://youtube.com/playlist?list=OLAK5uy_abc"),
+///     "https://youtube.com/playlist?list=OLAK5uy_abc",
+/// );
+/// ```
+#[must_use]
+pub fn releases_url(url: &str) -> String {
+    let url = url.trim();
+    let Ok(parsed) = Url::parse(url) else {
+        return url.to_string();
+    };
+    let segments: Vec<&str> = parsed
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect())
+        .unwrap_or_default();
+    let is_channel = match segments.as_slice() {
+        [handle] => handle.starts_with('@'),
+        ["channel" | "c" | "user", _] => true,
+        _ => false,
+    };
+
+    if is_channel {
+        format!("{}/releases", url.trim_end_matches('/'))
+    } else {
+        url.to_string()
+    }
+}
+
+/// Uses the yt-dlp CLI tool to list the albums on a channel or playlist
+///
+/// # Errors
+/// If the yt-dlp command fails to run
+pub fn scrape_releases(url: &str) -> Result<Vec<Release>, ScrapeYoutubeError> {
+    let output = network::ytdlp()
+        .args([
+            "--flat-playlist",
+            "--ignore-errors",
+            "--print",
+            "%(id)s\t%(title)s",
+            &releases_url(url),
+        ])
+        .output()?;
+
+    Ok(parse_releases(&String::from_utf8_lossy(&output.stdout)))
+}
+
+/// Parses yt-dlp's output, skipping anything that isn't an album playlist (e.g. single videos)
+fn parse_releases(output: &str) -> Vec<Release> {
+    output
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter_map(|(id, title)| {
+            Some(Release {
+                id: id.parse().ok().filter(PlaylistId::is_album)?,
+                title: title.to_string(),
+            })
+        })
+        .collect()
+}
+
+/// Albums on the watched channels and playlists that aren't in the [`History`].
+///
+/// Channels that can't be checked are skipped.
+#[must_use]
+pub fn new_releases(settings: &WatchSettings, history: &History) -> Vec<Release> {
+    let mut releases: Vec<Release> = Vec::new();
+    for url in &settings.urls {
+        match scrape_releases(url) {
+            Ok(found) => {
+                for release in found {
+                    if !history.contains(release.id.as_str())
+                        && !releases.iter().any(|other| other.id == release.id)
+                    {
+                        releases.push(release);
+                    }
+                }
+            }
+            Err(err) => log::warn!("couldn't check {url} for new releases: {err}"),
+        }
+    }
+    releases
+}
+
+/// Checks the watched channels and playlists, and adds any new albums to the
+/// [`queue`](crate::queue) with their tracks filled in from the playlist.
+///
+/// Returns the albums that were queued, which is none if another check is still going.
+#[must_use]
+pub fn queue_new_releases(settings: &WatchSettings) -> Vec<Release> {
+    let _checking = match CHECKING.try_lock() {
+        Ok(guard) => guard,
+        Err(TryLockError::Poisoned(err)) => err.into_inner(),
+        Err(TryLockError::WouldBlock) => {
+            log::info!("the watched channels are already being checked");
+            return Vec::new();
+        }
+    };
+    let mut history = History::load().unwrap_or_else(|err| {
+        log::warn!("couldn't load the download history: {err}");
+        History::default()
+    });
+
+    let mut queued = Vec::new();
+    for release in new_releases(settings, &history) {
+        match StateModifyingData::new_without_discogs(release.url()) {
+            Ok(mut state) => {
+                if state.album_data.name.is_empty() {
+                    state.album_data.name.clone_from(&release.title);
+                }
+                log::info!(r#"new release "{}", adding it to the queue"#, release.title);
+                queue::add(Job::new(&state));
+                history.seen.push(release.id.to_string());
+                queued.push(release);
+            }
+            Err(err) => log::warn!(r#"couldn't scrape new release "{}": {err}"#, release.title),
+        }
+    }
+
+    history.watch_checked = unix_time();
+    if let Err(err) = history.save() {
+        log::warn!("couldn't save the download history: {err}");
+    }
+    queued
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_album_playlists() {
+        let releases = parse_releases(
+            "OLAK5uy_abc\tFirst Album\ndQw4w9WgXcQ\tA Video\nOLAK5uy_def\tSecond Album\n",
+        );
+        assert_eq!(
+            releases,
+            [
+                Release {
+                    id: "OLAK5uy_abc".parse().unwrap(),
+                    title: String::from("First Album"),
+                },
+                Release {
+                    id: "OLAK5uy_def".parse().unwrap(),
+                    title: String::from("Second Album"),
+                },
+            ]
+        );
+        assert_eq!(
+            releases[0].url(),
+            "https://www.youtube.com/playlist?list=OLAK5uy_abc"
+        );
+    }
+
+    #[test]
+    fn due_after_interval() {
+        let settings = WatchSettings {
+            urls: vec![String::from("https://www.youtube.com/@artist")],
+            interval_hours: 2,
+        };
+        assert!(!settings.is_due(1000, 1000 + 7199));
+        assert!(settings.is_due(1000, 1000 + 7200));
+
+        let never = WatchSettings {
+            interval_hours: 0,
+            ..settings.clone()
+        };
+        assert!(!never.is_due(0, u64::MAX));
+        let nothing_watched = WatchSettings {
+            urls: Vec::new(),
+            ..settings
+        };
+        assert!(!nothing_watched.is_due(0, u64::MAX));
+    }
+}
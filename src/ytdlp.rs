@@ -0,0 +1,207 @@
+//! A copy of yt-dlp that ytmdl downloads from yt-dlp's Github releases into its data directory and
+//! keeps up to date, for people who'd rather not install it themselves. When there's a copy it's
+//! used instead of the one on the `PATH`.
+
+use crate::{
+    deps::tool_version,
+    network::{download_identified, download_large},
+    paths,
+};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    env,
+    fmt::{self, Display, Formatter},
+    fs, io,
+    path::PathBuf,
+};
+use thiserror::Error;
+
+const LATEST_RELEASE_URL: &str = "https://api.github.com/repos/yt-dlp/yt-dlp/releases/latest";
+const DOWNLOAD_URL: &str = "https://github.com/yt-dlp/yt-dlp/releases/download";
+/// Published with each release, with a line for each of its files
+const CHECKSUMS: &str = "SHA2-256SUMS";
+
+#[derive(Debug, Error)]
+pub enum YtdlpError {
+    #[error("{0}")]
+    ReqwestError(#[from] reqwest::Error),
+    #[error("{0}")]
+    SerdeError(#[from] serde_json::Error),
+    #[error("{0}")]
+    IoError(#[from] io::Error),
+    #[error("couldn't determine the data directory")]
+    NoDataDir,
+    #[error("yt-dlp doesn't have a build for {0}, so it has to be installed by hand")]
+    UnsupportedPlatform(String),
+    #[error("{0} isn't in the release's checksums")]
+    MissingChecksum(String),
+    #[error("the download doesn't match its checksum, so it wasn't saved")]
+    ChecksumMismatch,
+}
+
+/// The latest release on Github
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+struct Release {
+    /// yt-dlp's version, like "2023.11.16"
+    tag_name: String,
+}
+
+/// What [`update`] did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum YtdlpUpdate {
+    /// With the version that was downloaded
+    Installed(String),
+    /// With the version there already was
+    UpToDate(String),
+}
+
+impl Display for YtdlpUpdate {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Installed(version) => write!(f, "Downloaded yt-dlp {version}"),
+            Self::UpToDate(version) => write!(f, "yt-dlp {version} is the latest version"),
+        }
+    }
+}
+
+/// Which of the files in a yt-dlp release runs on `os` and `arch` (as in [`env::consts`]) without
+/// needing Python
+///
+/// # Examples
+/// ```
+/// use ytmdl::ytdlp::release_file;
+///
+/// assert_eq!(release_file("windows", "x86_64"), Some("yt-dlp.exe"));
+/// assert_eq!(release_file("linux", "aarch64"), Some("yt-dlp_linux_aarch64"));
+/// assert_eq!(release_file("freebsd", "x86_64"), None);
+/// ```
+#[must_use]
+pub fn release_file(os: &str, arch: &str) -> Option<&'static str> {
+    match (os, arch) {
+        ("windows", "x86_64" | "aarch64") => Some("yt-dlp.exe"),
+        ("windows", "x86") => Some("yt-dlp_x86.exe"),
+        ("macos", _) => Some("yt-dlp_macos"),
+        ("linux", "x86_64") => Some("yt-dlp_linux"),
+        ("linux", "aarch64") => Some("yt-dlp_linux_aarch64"),
+        ("linux", "arm") => Some("yt-dlp_linux_armv7l"),
+        _ => None,
+    }
+}
+
+/// Where ytmdl's copy goes, whether or not there is one yet
+#[must_use]
+pub fn local_path() -> Option<PathBuf> {
+    let mut path = paths::data_dir()?;
+    path.push("bin");
+    path.push(format!("yt-dlp{}", env::consts::EXE_SUFFIX));
+    Some(path)
+}
+
+/// ytmdl's copy if there is one, otherwise `yt-dlp` to run the one on the `PATH`
+#[must_use]
+pub fn program() -> PathBuf {
+    local_path()
+        .filter(|path| path.is_file())
+        .unwrap_or_else(|| PathBuf::from("yt-dlp"))
+}
+
+/// Whether ytmdl has its own copy
+#[must_use]
+pub fn is_installed() -> bool {
+    local_path().is_some_and(|path| path.is_file())
+}
+
+/// The version of ytmdl's copy, if there is one
+#[must_use]
+pub fn local_version() -> Option<String> {
+    local_path()
+        .filter(|path| path.is_file())
+        .and_then(|path| tool_version(path, "--version"))
+}
+
+/// The checksum of `file` from a release's `SHA2-256SUMS`, which has lines like
+/// `<hex>  yt-dlp_linux`
+///
+/// # Examples
+/// ```
+/// use ytmdl::ytdlp::find_checksum;
+///
+/// let sums = "0123abcd  yt-dlp\n4567ef01  yt-dlp.exe\n";
+/// assert_eq!(find_checksum(sums, "yt-dlp.exe"), Some("4567ef01"));
+/// assert_eq!(find_checksum(sums, "yt-dlp_macos"), None);
+/// ```
+#[must_use]
+pub fn find_checksum<'a>(checksums: &'a str, file: &str) -> Option<&'a str> {
+    checksums.lines().find_map(|line| {
+        let (hash, name) = line.trim().split_once(char::is_whitespace)?;
+        // `sha256sum` marks binary files with a `*`
+        let name = name.trim_start().trim_start_matches('*');
+        (name == file).then_some(hash)
+    })
+}
+
+/// Downloads the latest yt-dlp for this platform into the data directory, unless the copy there is
+/// already the latest. The download is checked against the release's checksums before it
+/// replaces the old copy.
+///
+/// # Errors
+/// - If there isn't a build of yt-dlp for this platform
+/// - If Github can't be reached or responds with an error
+/// - If the download doesn't match its checksum
+/// - If it can't be saved
+pub fn update() -> Result<YtdlpUpdate, YtdlpError> {
+    let file = release_file(env::consts::OS, env::consts::ARCH).ok_or_else(|| {
+        YtdlpError::UnsupportedPlatform(format!("{} {}", env::consts::OS, env::consts::ARCH))
+    })?;
+    let path = local_path().ok_or(YtdlpError::NoDataDir)?;
+
+    let release: Release = serde_json::from_str(&download_identified(LATEST_RELEASE_URL)?.text()?)?;
+    let latest = release.tag_name;
+    // versions are dates, and nightlies have the time after them
+    if let Some(current) = local_version().filter(|current| *current >= latest) {
+        return Ok(YtdlpUpdate::UpToDate(current));
+    }
+
+    let checksums = download_identified(&format!("{DOWNLOAD_URL}/{latest}/{CHECKSUMS}"))?.text()?;
+    let expected = find_checksum(&checksums, file)
+        .ok_or_else(|| YtdlpError::MissingChecksum(file.to_string()))?
+        .to_lowercase();
+    log::info!("downloading yt-dlp {latest} ({file})");
+    let bytes = download_large(&format!("{DOWNLOAD_URL}/{latest}/{file}"))?.bytes()?;
+    let mut hasher = Sha256::new();
+    hasher.update(&bytes);
+    if format!("{:x}", hasher.finalize()) != expected {
+        return Err(YtdlpError::ChecksumMismatch);
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // written next to it first, so a failed write doesn't leave half of it behind
+    let partial = path.with_extension("part");
+    fs::write(&partial, &bytes)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&partial, fs::Permissions::from_mode(0o755))?;
+    }
+    fs::rename(&partial, &path)?;
+    Ok(YtdlpUpdate::Installed(latest))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        let sums = "\
+            a1b2c3  yt-dlp\n\
+            d4e5f6 *yt-dlp_linux\n\
+            070809  yt-dlp_linux.zip\n";
+        assert_eq!(find_checksum(sums, "yt-dlp_linux"), Some("d4e5f6"));
+        assert_eq!(find_checksum(sums, "yt-dlp"), Some("a1b2c3"));
+        assert_eq!(find_checksum("", "yt-dlp"), None);
+    }
+}
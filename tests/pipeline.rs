@@ -0,0 +1,120 @@
+//! The whole download, from scraping Discogs and the playlist to the tagged files, against
+//! [`ytmdl::test_support`]'s fixture server and stub tools
+#![cfg(unix)]
+
+use id3::{Tag, TagLike};
+use std::time::Duration;
+use ytmdl::{
+    album_art::{find_covers, CoverQuery, CoverSource},
+    download_album,
+    full_album::Section,
+    gui::view_modifying_data::StateModifyingData,
+    scraping::{Discogs, MetadataSource},
+    test_support::{
+        cover_jpeg, Fixture, FixtureServer, Sandbox, DISCOGS_RELEASE, YOUTUBE_PLAYLIST,
+    },
+};
+
+#[test]
+fn download_album_end_to_end() {
+    let sandbox = Sandbox::new().unwrap();
+    let server = FixtureServer::start().unwrap();
+    server.serve(
+        "/release/1-Fixture-Artist-Fixture-Album",
+        Fixture::html(DISCOGS_RELEASE.replace("{server}", &server.base_url())),
+    );
+    server.serve("/playlist", Fixture::html(YOUTUBE_PLAYLIST));
+    server.serve("/cover.jpg", Fixture::jpeg(cover_jpeg()));
+
+    let album = Discogs::scrape(&server.url("/release/1-Fixture-Artist-Fixture-Album")).unwrap();
+    assert_eq!(album.title, "Fixture Album");
+    let state = StateModifyingData::new(server.url("/playlist"), &album);
+    assert_eq!(state.track_data.len(), 2);
+
+    // the release's cover is offered, without searching for others since there's no album name
+    let covers = find_covers(&CoverQuery {
+        release_image: state.release_image.as_deref(),
+        ..CoverQuery::default()
+    });
+    assert_eq!(covers.len(), 1);
+    assert_eq!(covers[0].source, CoverSource::Release);
+    assert_eq!(covers[0].info.content_type, "image/jpeg");
+    assert_eq!(
+        covers[0].thumbnail.as_deref(),
+        Some(cover_jpeg().as_slice())
+    );
+
+    let report = download_album(&state).unwrap();
+    assert_eq!(report.tracks.len(), 2);
+    assert_eq!(sandbox.calls("yt-dlp").len(), 4);
+
+    let mut tracks = report.tracks;
+    tracks.sort_by_key(|track| track.index);
+    for (track, title) in tracks.iter().zip(["First Track", "Second Track"]) {
+        let [path] = track.outputs.as_slice() else {
+            panic!("expected one output, got {:?}", track.outputs);
+        };
+        assert!(path.starts_with(sandbox.out_dir()));
+        let tag = Tag::read_from_path(path).unwrap();
+        assert_eq!(tag.title(), Some(title));
+        assert_eq!(tag.artist(), Some("Fixture Artist"));
+        assert_eq!(tag.album(), Some("Fixture Album"));
+        assert_eq!(tag.track(), Some(track.index as u32 + 1));
+        assert_eq!(
+            track.source_format.as_ref().unwrap().to_string(),
+            "251 (opus, 129 kbps)"
+        );
+        assert_eq!(track.channel.as_ref().unwrap().name, "Stub Channel");
+        // 3:05 is close enough to both tracks' lengths
+        assert_eq!(track.length_mismatch, None);
+        assert_eq!(tag.pictures().next().unwrap().data, cover_jpeg());
+    }
+
+    // the tracks are already there, so running it again doesn't download them
+    std::env::set_var("YTMDL_OVERWRITE", "false");
+    let again = download_album(&state);
+    std::env::remove_var("YTMDL_OVERWRITE");
+    let again = again.unwrap();
+    assert_eq!(again.tracks.len(), 2);
+    assert!(again.tracks.iter().all(|track| track.already_saved));
+    assert_eq!(sandbox.calls("yt-dlp").len(), 4);
+
+    // tracks from one full album upload are cut out of it after it's downloaded once
+    let mut split = state.clone();
+    for (track, start) in split.track_data.iter_mut().zip([0, 70]) {
+        track.source = Some(String::from("https://youtu.be/dQw4w9WgXcQ"));
+        track.section = Some(Section {
+            start: Duration::from_secs(start),
+            end: (start == 0).then_some(Duration::from_secs(70)),
+        });
+    }
+    std::env::set_var("YTMDL_OVERWRITE", "true");
+    let report = download_album(&split);
+    std::env::remove_var("YTMDL_OVERWRITE");
+    assert_eq!(report.unwrap().tracks.len(), 2);
+    let downloads = sandbox.calls("yt-dlp");
+    assert_eq!(downloads.len(), 6);
+    assert!(downloads[4..]
+        .iter()
+        .all(|call| !call.contains("--download-sections")));
+    let cuts: Vec<String> = sandbox
+        .calls("ffmpeg")
+        .into_iter()
+        .filter(|call| call.contains("-c:a copy"))
+        .collect();
+    assert_eq!(cuts.len(), 2);
+    assert!(cuts.iter().any(|cut| cut.contains("-ss 70 -i")));
+
+    // videos much longer than the tracks are pointed out
+    std::env::set_var("YTMDL_OVERWRITE", "true");
+    std::env::set_var("YTDLP_STUB_DURATION", "600");
+    let report = download_album(&state);
+    std::env::remove_var("YTMDL_OVERWRITE");
+    std::env::remove_var("YTDLP_STUB_DURATION");
+    let report = report.unwrap();
+    let mismatches = report.length_mismatches();
+    assert_eq!(mismatches.len(), 2);
+    assert!(mismatches
+        .iter()
+        .all(|(_, mismatch)| mismatch.video == Duration::from_secs(600)));
+}